@@ -0,0 +1,122 @@
+//! Derive macros for the `FromRow` and `IntoRow` traits of the
+//! [`noria`](https://docs.rs/noria) crate.
+//!
+//! You should not need to depend on this crate directly, as `noria` re-exports both macros.
+//!
+//! Both macros only work on structs with named fields. Each field is mapped to the column with
+//! the same name, unless the field is annotated with `#[noria(rename = "column")]`.
+#![deny(missing_docs)]
+#![warn(rust_2018_idioms)]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+/// Derive `noria::FromRow`, which builds a struct from a row of a view.
+#[proc_macro_derive(FromRow, attributes(noria))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match columns(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let inits = fields.iter().map(|(field, column)| {
+        quote! { #field: ::noria::take_column(columns, &mut row, #column)? }
+    });
+
+    let expanded = quote! {
+        impl #impl_generics ::noria::FromRow for #name #ty_generics #where_clause {
+            fn from_row(
+                columns: &[::std::string::String],
+                mut row: ::std::vec::Vec<::noria::DataType>,
+            ) -> ::std::result::Result<Self, ::noria::error::RowError> {
+                ::std::result::Result::Ok(#name { #(#inits),* })
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Derive `noria::IntoRow`, which turns a struct into a row for a table.
+#[proc_macro_derive(IntoRow, attributes(noria))]
+pub fn derive_into_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let fields = match columns(&input) {
+        Ok(fields) => fields,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let values = fields.iter().map(|(field, column)| {
+        quote! { (#column, ::noria::DataType::from(self.#field)) }
+    });
+
+    let expanded = quote! {
+        impl #impl_generics ::noria::IntoRow for #name #ty_generics #where_clause {
+            fn into_row(self) -> ::std::vec::Vec<(&'static str, ::noria::DataType)> {
+                vec![#(#values),*]
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Find the column name of every field of the struct being derived for.
+fn columns(input: &DeriveInput) -> syn::Result<Vec<(Ident, String)>> {
+    let fields = match input.data {
+        Data::Struct(ref s) => match s.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "rows can only be mapped to structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "rows can only be mapped to structs",
+            ))
+        }
+    };
+
+    let mut columns = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.clone().unwrap();
+        let mut column = ident.to_string();
+        for attr in field.attrs.iter().filter(|a| a.path.is_ident("noria")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(syn::Error::new_spanned(meta, "expected #[noria(...)]")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(ref nv)) if nv.path.is_ident("rename") => {
+                        match nv.lit {
+                            Lit::Str(ref s) => column = s.value(),
+                            ref lit => {
+                                return Err(syn::Error::new_spanned(lit, "expected a column name"))
+                            }
+                        }
+                    }
+                    nested => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "unknown noria attribute; expected `rename = \"column\"`",
+                        ))
+                    }
+                }
+            }
+        }
+        columns.push((ident, column));
+    }
+    Ok(columns)
+}
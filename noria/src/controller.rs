@@ -1,16 +1,17 @@
-use crate::consensus::{self, Authority};
+use crate::consensus::{self, Authority, Epoch};
 use crate::debug::stats;
 use crate::table::{Table, TableBuilder, TableRpc};
+use crate::transaction::Transaction;
 use crate::view::{View, ViewBuilder, ViewRpc};
 use crate::ActivationResult;
 use failure::{self, ResultExt};
-use futures_util::future;
+use futures_util::future::{self, BoxFuture};
 use petgraph::graph::NodeIndex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     future::Future,
     task::{Context, Poll},
@@ -31,26 +32,62 @@ pub struct ControllerDescriptor {
     pub nonce: u64,
 }
 
+/// How long an idempotent request keeps being retried while the controller is unreachable.
+///
+/// This needs to be long enough for a new controller to be elected if the current one fails.
+const LEADER_CHANGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fetches the current builder for a base table or view from the controller, so that a handle
+/// can reconnect to its shards if they move.
+pub(crate) type Refresh<B> =
+    Arc<dyn Fn() -> BoxFuture<'static, Result<Option<B>, failure::Error>> + Send + Sync>;
+
 struct Controller<A> {
     authority: Arc<A>,
     client: hyper::Client<hyper::client::HttpConnector>,
+    /// The epoch and address of the controller that we last reached.
+    leader: Arc<Mutex<Option<(Epoch, SocketAddr)>>>,
 }
 
 #[derive(Debug)]
 struct ControllerRequest {
     path: &'static str,
     request: Vec<u8>,
+    idempotent: bool,
 }
 
 impl ControllerRequest {
     fn new<Q: Serialize>(path: &'static str, r: Q) -> Result<Self, serde_json::Error> {
+        // these only read controller state (or, for prepare, only change it the first time), so
+        // it is safe to send them again if we do not know whether the controller got to handle
+        // them before it failed.
+        let idempotent = match path {
+            "inputs" | "outputs" | "view_builder" | "table_builder" | "get_statistics"
+            | "graphviz" | "simple_graphviz" | "prepare" => true,
+            _ => false,
+        };
+
         Ok(ControllerRequest {
             path,
             request: serde_json::to_vec(&r)?,
+            idempotent,
         })
     }
 }
 
+/// Find the epoch and address of the current controller, waiting for one if there is none.
+async fn current_leader<A>(authority: Arc<A>) -> Result<(Epoch, SocketAddr), failure::Error>
+where
+    A: 'static + Authority,
+{
+    let (epoch, descriptor) = tokio::task::spawn_blocking(move || authority.get_leader())
+        .await?
+        .context("failed to get current leader")?;
+    let descriptor: ControllerDescriptor =
+        serde_json::from_slice(&descriptor).context("failed to deserialize authority reply")?;
+    Ok((epoch, descriptor.external_addr))
+}
+
 impl<A> Service<ControllerRequest> for Controller<A>
 where
     A: 'static + Authority,
@@ -70,32 +107,53 @@ where
     fn call(&mut self, req: ControllerRequest) -> Self::Future {
         let client = self.client.clone();
         let auth = self.authority.clone();
+        let leader = self.leader.clone();
         let path = req.path;
         let body = req.request;
+        let idempotent = req.idempotent;
 
         async move {
-            let mut url = None;
+            let give_up = Instant::now() + LEADER_CHANGE_TIMEOUT;
+
+            // forget about a controller that has failed us, unless someone else has already
+            // found its replacement.
+            let forget = |epoch| {
+                let mut current = leader.lock().unwrap();
+                if current.map(|(e, _)| e) == Some(epoch) {
+                    *current = None;
+                }
+            };
 
             loop {
-                if url.is_none() {
-                    // TODO: don't do blocking things here...
-                    // TODO: cache this value?
-                    let descriptor: ControllerDescriptor = serde_json::from_slice(
-                        &auth.get_leader().context("failed to get current leader")?.1,
-                    )
-                    .context("failed to deserialize authority reply")?;
-
-                    url = Some(format!("http://{}/{}", descriptor.external_addr, path));
-                }
+                let cached = *leader.lock().unwrap();
+                let (epoch, addr) = match cached {
+                    Some(l) => l,
+                    None => {
+                        let l = current_leader(auth.clone()).await?;
+                        *leader.lock().unwrap() = Some(l);
+                        l
+                    }
+                };
 
-                let r = hyper::Request::post(url.as_ref().unwrap())
+                let url = format!("http://{}/{}", addr, path);
+                let r = hyper::Request::post(&url)
                     .body(hyper::Body::from(body.clone()))
                     .unwrap();
 
-                let res = client
-                    .request(r)
-                    .await
-                    .map_err(|he| failure::Error::from(he).context("hyper request failed"))?;
+                let res = match client.request(r).await {
+                    Ok(res) => res,
+                    Err(he) => {
+                        // the controller may have failed, so look it up again next time.
+                        forget(epoch);
+                        if idempotent && Instant::now() < give_up {
+                            tokio::time::delay_for(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                        return Err(failure::Error::from(he)
+                            .context("hyper request failed")
+                            .into());
+                    }
+                };
 
                 let status = res.status();
                 let body = hyper::body::to_bytes(res.into_body())
@@ -111,7 +169,7 @@ where
                     ),
                     s => {
                         if s == hyper::StatusCode::SERVICE_UNAVAILABLE {
-                            url = None;
+                            forget(epoch);
                         }
 
                         tokio::time::delay_for(Duration::from_millis(100)).await;
@@ -132,6 +190,11 @@ where
 /// appropriate `Authority`. In the likely case that you are using Zookeeper, use
 /// `ControllerHandle::from_zk`.
 ///
+/// If the controller fails, the handle finds the newly elected controller through the
+/// `Authority`. Requests that only read controller state, such as `view`, `table`, `inputs`,
+/// `outputs`, and `statistics`, are retried against the new controller for a while, and so is
+/// `prepare`. Other requests fail, since they may already have taken effect.
+///
 /// Note that whatever Tokio Runtime you use to execute the `Future` that resolves into the
 /// `ControllerHandle` will also be the one that executes all your reads and writes through `View`
 /// and `Table`. Make sure that that `Runtime` stays alive, and continues to be driven, otherwise
@@ -209,6 +272,7 @@ impl<A: Authority + 'static> ControllerHandle<A> {
                 Controller {
                     authority,
                     client: hyper::Client::new(),
+                    leader: Default::default(),
                 },
                 1,
             ),
@@ -305,6 +369,7 @@ impl<A: Authority + 'static> ControllerHandle<A> {
 
         let views = self.views.clone();
         let name = name.to_string();
+        let refresh = self.refresher("view_builder", name.clone());
         let fut = self
             .handle
             .call(ControllerRequest::new("view_builder", &name).unwrap());
@@ -315,7 +380,7 @@ impl<A: Authority + 'static> ControllerHandle<A> {
                 .context("failed to fetch view builder")?;
 
             match serde_json::from_slice::<Option<ViewBuilder>>(&body) {
-                Ok(Some(vb)) => Ok(vb.build(views)?),
+                Ok(Some(vb)) => Ok(vb.build(views, Some(refresh))?),
                 Ok(None) => Err(failure::err_msg("view does not exist")),
                 Err(e) => Err(failure::Error::from(e)),
             }
@@ -323,6 +388,36 @@ impl<A: Authority + 'static> ControllerHandle<A> {
         }
     }
 
+    /// Obtain a `View` for the parameterized SELECT query `sql`, installing the query if needed.
+    ///
+    /// If an equivalent query is already installed, whether through a recipe or an earlier call
+    /// to `prepare`, its view is reused. Otherwise, the recipe is extended with the query, and
+    /// the returned view starts out empty, with its results filled in as they are looked up.
+    ///
+    /// ```rust,no_run
+    /// # async fn f(mut c: noria::ControllerHandle<noria::ZookeeperAuthority>) {
+    /// let mut story = c.prepare("SELECT * FROM Story WHERE id = ?").await.unwrap();
+    /// let rows = story.lookup(&[42.into()], true).await.unwrap();
+    /// # }
+    /// ```
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn prepare(&mut self, sql: &str) -> impl Future<Output = Result<View, failure::Error>> {
+        let fut = self.rpc::<_, String>("prepare", sql, "failed to prepare query");
+        let mut handle = self.clone();
+        async move {
+            let name = fut.await?;
+            handle.view(&name).await
+        }
+    }
+
+    /// Start a transaction that applies operations on several base tables as a single unit.
+    ///
+    /// See [`Transaction`] for details.
+    pub fn transaction(&self) -> Transaction {
+        Transaction::default()
+    }
+
     /// Obtain a `Table` that allows you to perform writes, deletes, and other operations on the
     /// given base table.
     ///
@@ -335,6 +430,7 @@ impl<A: Authority + 'static> ControllerHandle<A> {
 
         let domains = self.domains.clone();
         let name = name.to_string();
+        let refresh = self.refresher("table_builder", name.clone());
         let fut = self
             .handle
             .call(ControllerRequest::new("table_builder", &name).unwrap());
@@ -346,7 +442,7 @@ impl<A: Authority + 'static> ControllerHandle<A> {
                 .context("failed to fetch table builder")?;
 
             match serde_json::from_slice::<Option<TableBuilder>>(&body) {
-                Ok(Some(tb)) => Ok(tb.build(domains)?),
+                Ok(Some(tb)) => Ok(tb.build(domains, Some(refresh))?),
                 Ok(None) => Err(failure::err_msg("view table not exist")),
                 Err(e) => Err(failure::Error::from(e)),
             }
@@ -354,6 +450,31 @@ impl<A: Authority + 'static> ControllerHandle<A> {
         }
     }
 
+    /// Fetch the builder for the table or view called `name` through `path` whenever the
+    /// returned function is called.
+    fn refresher<B>(&self, path: &'static str, name: String) -> Refresh<B>
+    where
+        for<'de> B: Deserialize<'de>,
+        B: Send + 'static,
+    {
+        let handle = self.handle.clone();
+        Arc::new(move || {
+            let mut handle = handle.clone();
+            let name = name.clone();
+            let fut: BoxFuture<'static, Result<Option<B>, failure::Error>> = Box::pin(async move {
+                future::poll_fn(|cx| handle.poll_ready(cx))
+                    .await
+                    .map_err(failure::Error::from_boxed_compat)?;
+                let body = handle
+                    .call(ControllerRequest::new(path, &name).unwrap())
+                    .await
+                    .map_err(failure::Error::from_boxed_compat)?;
+                Ok(serde_json::from_slice(&body)?)
+            });
+            fut
+        })
+    }
+
     #[doc(hidden)]
     pub fn rpc<Q: Serialize, R: 'static>(
         &mut self,
@@ -1,16 +1,24 @@
 use arccstr::ArcCStr;
 
-use chrono::{self, NaiveDate, NaiveDateTime};
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+use chrono::{self, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use num_bigint::BigInt;
 
-use nom_sql::Literal;
+use nom_sql::{Literal, SqlType};
+
+use crate::JsonPath;
 
 use std::convert::TryFrom;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+use std::sync::Arc;
 
 const FLOAT_PRECISION: f64 = 1_000_000_000.0;
 const TINYTEXT_WIDTH: usize = 15;
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_DAY: i64 = 24 * 60 * 60 * MICROS_PER_SECOND;
 
 /// The main type used for user data throughout the codebase.
 ///
@@ -20,11 +28,15 @@ const TINYTEXT_WIDTH: usize = 15;
 /// Note that cloning a `DataType` using the `Clone` trait is possible, but may result in cache
 /// contention on the reference counts for de-duplicated strings. Use `DataType::deep_clone` to
 /// clone the *value* of a `DataType` without danger of contention.
-#[derive(Eq, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[warn(variant_size_differences)]
 pub enum DataType {
     /// An empty value.
     None,
+    /// A boolean value.
+    ///
+    /// Like in MySQL, booleans are interchangeable with the integers 0 and 1.
+    Bool(bool),
     /// A signed 32-bit numeric value.
     Int(i32),
     /// An unsigned 32-bit numeric value.
@@ -36,18 +48,42 @@ pub enum DataType {
     /// A fixed point real value. The first field is the integer part, while the second is the
     /// fractional and must be between -999999999 and 999999999.
     Real(i64, i32),
+    /// A double-precision floating point value.
+    ///
+    /// Unlike for `f64` itself, `NaN` is equal to itself and sorts after all other values, and
+    /// `-0.0` is equal to `0.0`.
+    Double(f64),
+    /// An exact decimal number of arbitrary precision, for `DECIMAL` columns.
+    ///
+    /// Decimals that differ only in trailing fractional zeros (like `1.5` and `1.50`) are equal.
+    Decimal(Arc<BigDecimal>),
     /// A reference-counted string-like value.
     Text(ArcCStr),
     /// A tiny string that fits in a pointer
     TinyText([u8; TINYTEXT_WIDTH]),
     /// A timestamp for date/time types.
     Timestamp(NaiveDateTime),
+    /// A calendar date, for `DATE` columns.
+    Date(NaiveDate),
+    /// A time of day, for `TIME` columns.
+    Time(NaiveTime),
+    /// A span of time, as given by `INTERVAL` expressions. The first field is a number of months
+    /// and the second a number of microseconds, since the length of a month depends on which
+    /// month it is.
+    Interval(i32, i64),
+    /// A JSON document.
+    ///
+    /// Documents are compared as (compact) JSON text, with object members sorted by name.
+    Json(#[serde(with = "json_text")] Arc<serde_json::Value>),
+    /// A reference-counted byte string, for binary and blob types.
+    ByteArray(Arc<Vec<u8>>),
 }
 
 impl fmt::Display for DataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             DataType::None => write!(f, "NULL"),
+            DataType::Bool(b) => write!(f, "{}", b),
             DataType::Text(..) | DataType::TinyText(..) => {
                 let text: &str = self.into();
                 // TODO: do we really want to produce quoted strings?
@@ -65,7 +101,22 @@ impl fmt::Display for DataType {
                     write!(f, "{}.{:09}", i, frac.abs())
                 }
             }
+            DataType::Double(d) => write!(f, "{}", d),
+            DataType::Decimal(ref d) => write!(f, "{}", d),
             DataType::Timestamp(ts) => write!(f, "{}", ts.format("%c")),
+            DataType::Date(d) => write!(f, "{}", d),
+            DataType::Time(t) => write!(f, "{}", t),
+            DataType::Interval(months, micros) => {
+                write!(f, "{} MONTH {} MICROSECOND", months, micros)
+            }
+            DataType::Json(ref doc) => write!(f, "{}", doc),
+            DataType::ByteArray(ref bytes) => {
+                write!(f, "0x")?;
+                for b in bytes.iter() {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -74,6 +125,7 @@ impl fmt::Debug for DataType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             DataType::None => write!(f, "None"),
+            DataType::Bool(b) => write!(f, "Bool({})", b),
             DataType::Text(..) => {
                 let text: &str = self.into();
                 write!(f, "Text({:?})", text)
@@ -83,7 +135,14 @@ impl fmt::Debug for DataType {
                 write!(f, "TinyText({:?})", text)
             }
             DataType::Timestamp(ts) => write!(f, "Timestamp({:?})", ts),
+            DataType::Date(d) => write!(f, "Date({:?})", d),
+            DataType::Time(t) => write!(f, "Time({:?})", t),
+            DataType::Interval(months, micros) => write!(f, "Interval({}, {})", months, micros),
+            DataType::Json(ref doc) => write!(f, "Json({})", doc),
             DataType::Real(..) => write!(f, "Real({})", self),
+            DataType::Double(d) => write!(f, "Double({:?})", d),
+            DataType::Decimal(ref d) => write!(f, "Decimal({})", d),
+            DataType::ByteArray(ref bytes) => write!(f, "ByteArray({:?})", bytes),
             DataType::Int(n) => write!(f, "Int({})", n),
             DataType::UnsignedInt(n) => write!(f, "UnsignedInt({})", n),
             DataType::BigInt(n) => write!(f, "BigInt({})", n),
@@ -100,6 +159,9 @@ impl DataType {
     pub fn deep_clone(&self) -> Self {
         match *self {
             DataType::Text(ref cstr) => DataType::Text(ArcCStr::from(&**cstr)),
+            DataType::ByteArray(ref bytes) => DataType::ByteArray(Arc::new((**bytes).clone())),
+            DataType::Decimal(ref d) => DataType::Decimal(Arc::new((**d).clone())),
+            DataType::Json(ref doc) => DataType::Json(Arc::new((**doc).clone())),
             ref dt => dt.clone(),
         }
     }
@@ -123,7 +185,41 @@ impl DataType {
     /// Checks if this value is of a real data type (i.e., can be converted into `f64`).
     pub fn is_real(&self) -> bool {
         match *self {
-            DataType::Real(_, _) => true,
+            DataType::Real(_, _) | DataType::Double(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the decimal data type (i.e., can be converted into
+    /// `BigDecimal`).
+    pub fn is_decimal(&self) -> bool {
+        match *self {
+            DataType::Decimal(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the boolean data type.
+    pub fn is_bool(&self) -> bool {
+        match *self {
+            DataType::Bool(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the byte string data type (i.e., can be converted into
+    /// `&[u8]`).
+    pub fn is_bytes(&self) -> bool {
+        match *self {
+            DataType::ByteArray(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is a JSON document.
+    pub fn is_json(&self) -> bool {
+        match *self {
+            DataType::Json(_) => true,
             _ => false,
         }
     }
@@ -144,6 +240,539 @@ impl DataType {
             _ => false,
         }
     }
+
+    /// Convert `lit` into a value for a column of SQL type `ty`.
+    ///
+    /// SQL has no literals for booleans, doubles or byte strings, so for columns of those types
+    /// this converts integer, fixed-point and string literals into `Bool`, `Double` and
+    /// `ByteArray` values respectively. Numbers for `DECIMAL` columns are rounded to the column's
+    /// scale, and strings for `DATE`, `DATETIME` and `TIMESTAMP` columns are parsed. All other
+    /// literals convert as they would with `From`.
+    pub fn from_literal(lit: &Literal, ty: &SqlType) -> Self {
+        match (ty, lit) {
+            (&SqlType::Bool, &Literal::Integer(n)) => DataType::Bool(n != 0),
+            (&SqlType::Bool, &Literal::UnsignedInteger(n)) => DataType::Bool(n != 0),
+            (&SqlType::Double, &Literal::Integer(n)) | (&SqlType::Float, &Literal::Integer(n)) => {
+                DataType::Double(n as f64)
+            }
+            (&SqlType::Double, &Literal::UnsignedInteger(n))
+            | (&SqlType::Float, &Literal::UnsignedInteger(n)) => DataType::Double(n as f64),
+            (&SqlType::Double, &Literal::FixedPoint(ref r))
+            | (&SqlType::Float, &Literal::FixedPoint(ref r)) => {
+                let real = DataType::Real(i64::from(r.integral), r.fractional as i32);
+                DataType::Double((&real).into())
+            }
+            (&SqlType::Decimal(precision, scale), _) => DataType::from(lit)
+                .to_decimal(precision, scale)
+                .unwrap_or_else(|| lit.into()),
+            (&SqlType::Blob, &Literal::String(ref s))
+            | (&SqlType::Longblob, &Literal::String(ref s))
+            | (&SqlType::Mediumblob, &Literal::String(ref s))
+            | (&SqlType::Tinyblob, &Literal::String(ref s))
+            | (&SqlType::Binary(_), &Literal::String(ref s))
+            | (&SqlType::Varbinary(_), &Literal::String(ref s)) => {
+                DataType::from(s.as_bytes().to_vec())
+            }
+            (&SqlType::Date, &Literal::String(_))
+            | (&SqlType::Date, &Literal::CurrentTimestamp) => match DataType::from(lit).date() {
+                DataType::None => lit.into(),
+                date => date,
+            },
+            (&SqlType::DateTime(_), &Literal::String(_))
+            | (&SqlType::Timestamp, &Literal::String(_)) => {
+                match DataType::from(lit).datetime_value() {
+                    Some(ts) => DataType::Timestamp(ts),
+                    None => lit.into(),
+                }
+            }
+            _ => lit.into(),
+        }
+    }
+
+    /// Construct the value of the SQL expression `INTERVAL n unit`.
+    ///
+    /// Panics if the interval is too long to represent.
+    pub fn interval(n: i64, unit: TimeUnit) -> Self {
+        let micros = |per: i64| match n.checked_mul(per) {
+            Some(micros) => DataType::Interval(0, micros),
+            None => panic!("can't fit INTERVAL {} {} in a DataType::Interval", n, unit),
+        };
+        let months = |per: i64| match n.checked_mul(per).and_then(|m| i32::try_from(m).ok()) {
+            Some(months) => DataType::Interval(months, 0),
+            None => panic!("can't fit INTERVAL {} {} in a DataType::Interval", n, unit),
+        };
+        match unit {
+            TimeUnit::Microsecond => micros(1),
+            TimeUnit::Second => micros(MICROS_PER_SECOND),
+            TimeUnit::Minute => micros(60 * MICROS_PER_SECOND),
+            TimeUnit::Hour => micros(60 * 60 * MICROS_PER_SECOND),
+            TimeUnit::Day => micros(MICROS_PER_DAY),
+            TimeUnit::Week => micros(7 * MICROS_PER_DAY),
+            TimeUnit::Month => months(1),
+            TimeUnit::Quarter => months(3),
+            TimeUnit::Year => months(12),
+        }
+    }
+
+    /// The date part of this date or timestamp, like SQL's `DATE()`.
+    ///
+    /// Strings are parsed as timestamps or dates, and `NULL` is returned for anything else.
+    pub fn date(&self) -> DataType {
+        match self.datetime_value() {
+            Some(ts) => DataType::Date(ts.date()),
+            None => DataType::None,
+        }
+    }
+
+    /// The given part of this date, time or timestamp, like SQL's `EXTRACT(unit FROM ...)`.
+    ///
+    /// Weeks are numbered as by MySQL's `WEEK()`: they start on Sunday, and the days before the
+    /// first Sunday of the year are in week 0. Strings are parsed as timestamps or dates, and
+    /// `NULL` is returned for anything else, as well as for date parts of times.
+    pub fn extract(&self, unit: TimeUnit) -> DataType {
+        let ts = self.datetime_value();
+        let time = match *self {
+            DataType::Time(t) => Some(t),
+            _ => ts.map(|ts| ts.time()),
+        };
+        let date = ts.map(|ts| ts.date());
+        let part = match unit {
+            // leap seconds are stored as nanoseconds beyond one second
+            TimeUnit::Microsecond => time.map(|t| t.nanosecond() % 1_000_000_000 / 1000),
+            TimeUnit::Second => time.map(|t| t.second()),
+            TimeUnit::Minute => time.map(|t| t.minute()),
+            TimeUnit::Hour => time.map(|t| t.hour()),
+            TimeUnit::Day => date.map(|d| d.day()),
+            TimeUnit::Week => date.map(week_of_year),
+            TimeUnit::Month => date.map(|d| d.month()),
+            TimeUnit::Quarter => date.map(|d| d.month0() / 3 + 1),
+            TimeUnit::Year => {
+                return date.map_or(DataType::None, |d| i64::from(d.year()).into());
+            }
+        };
+        part.map_or(DataType::None, |n| i64::from(n).into())
+    }
+
+    /// The start of the `unit`-long period that this date or timestamp falls in, such as the
+    /// day, week or month.
+    ///
+    /// Grouping rows by this value groups them into daily (or weekly, monthly, ...) buckets.
+    /// Weeks start on Sunday, as for [`DataType::extract`]. Dates stay dates, while everything
+    /// else becomes a timestamp. Strings are parsed as timestamps or dates, and `NULL` is
+    /// returned for anything else.
+    pub fn bucket(&self, unit: TimeUnit) -> DataType {
+        let ts = match self.datetime_value() {
+            Some(ts) => ts,
+            None => return DataType::None,
+        };
+        let (date, time) = (ts.date(), ts.time());
+        let start = match unit {
+            TimeUnit::Microsecond => date.and_hms_micro(
+                time.hour(),
+                time.minute(),
+                time.second(),
+                time.nanosecond() / 1000,
+            ),
+            TimeUnit::Second => date.and_hms(time.hour(), time.minute(), time.second()),
+            TimeUnit::Minute => date.and_hms(time.hour(), time.minute(), 0),
+            TimeUnit::Hour => date.and_hms(time.hour(), 0, 0),
+            TimeUnit::Day => date.and_hms(0, 0, 0),
+            TimeUnit::Week => {
+                let days = date.weekday().num_days_from_sunday();
+                (date - Duration::days(days.into())).and_hms(0, 0, 0)
+            }
+            TimeUnit::Month => NaiveDate::from_ymd(date.year(), date.month(), 1).and_hms(0, 0, 0),
+            TimeUnit::Quarter => {
+                NaiveDate::from_ymd(date.year(), date.month0() / 3 * 3 + 1, 1).and_hms(0, 0, 0)
+            }
+            TimeUnit::Year => NaiveDate::from_ymd(date.year(), 1, 1).and_hms(0, 0, 0),
+        };
+        match *self {
+            DataType::Date(_) => DataType::Date(start.date()),
+            _ => DataType::Timestamp(start),
+        }
+    }
+
+    /// Parse `text` as a JSON document.
+    ///
+    /// Tables have no JSON column type, since our SQL parser does not support one, and so they
+    /// store whatever text they are given. Write documents built with this function to make sure
+    /// that only valid documents are stored.
+    pub fn json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text).map(|doc| DataType::Json(Arc::new(doc)))
+    }
+
+    /// The value at `path` in this JSON document, like SQL's `JSON_EXTRACT(doc, path)`.
+    ///
+    /// Strings are parsed as JSON documents. `NULL` is returned if there is no value at `path`,
+    /// and for values that are not JSON documents.
+    pub fn json_extract(&self, path: &JsonPath) -> DataType {
+        self.with_json(|doc| {
+            path.extract(doc)
+                .map(|v| DataType::Json(Arc::new(v.clone())))
+        })
+    }
+
+    /// The value at `path` in this JSON document, like SQL's `doc->>path`.
+    ///
+    /// Unlike in MySQL, JSON strings, numbers and booleans become values of the matching type
+    /// rather than strings, so that they compare with other values as they would in the
+    /// document. JSON `null` becomes `NULL`, and arrays and objects stay JSON documents.
+    pub fn json_extract_unquoted(&self, path: &JsonPath) -> DataType {
+        self.with_json(|doc| {
+            path.extract(doc).map(|v| match *v {
+                serde_json::Value::Null => DataType::None,
+                serde_json::Value::Bool(b) => DataType::Bool(b),
+                serde_json::Value::Number(ref n) => n
+                    .as_i64()
+                    .map(DataType::from)
+                    .or_else(|| n.as_u64().map(DataType::from))
+                    .or_else(|| n.as_f64().map(DataType::Double))
+                    .unwrap_or(DataType::None),
+                serde_json::Value::String(ref s) => s.as_str().into(),
+                _ => DataType::Json(Arc::new(v.clone())),
+            })
+        })
+    }
+
+    /// Apply `f` to this JSON document, parsing strings first, or return `NULL` if this is not
+    /// a document or `f` returns `None`.
+    fn with_json<F>(&self, f: F) -> DataType
+    where
+        F: FnOnce(&serde_json::Value) -> Option<DataType>,
+    {
+        let result = match *self {
+            DataType::Json(ref doc) => f(doc),
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = self.into();
+                serde_json::from_str(s).ok().and_then(|doc| f(&doc))
+            }
+            _ => None,
+        };
+        result.unwrap_or(DataType::None)
+    }
+
+    /// This date or timestamp as a timestamp, with dates at midnight.
+    ///
+    /// Strings are parsed as timestamps (with an optional fractional second) or dates.
+    fn datetime_value(&self) -> Option<NaiveDateTime> {
+        match *self {
+            DataType::Timestamp(ts) => Some(ts),
+            DataType::Date(d) => Some(d.and_hms(0, 0, 0)),
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = self.into();
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                    .or_else(|_| {
+                        NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_hms(0, 0, 0))
+                    })
+                    .ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if this value can be converted into `i128`, which booleans can.
+    fn is_integral(&self) -> bool {
+        match *self {
+            DataType::Bool(_)
+            | DataType::Int(_)
+            | DataType::UnsignedInt(_)
+            | DataType::BigInt(_)
+            | DataType::UnsignedBigInt(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is a number (i.e., can be converted into `f64`), which includes
+    /// booleans.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integral() || self.is_real() || self.is_decimal()
+    }
+
+    /// Convert this number into a `DECIMAL(precision, scale)` value, the way MySQL does when
+    /// storing it in a column of that type.
+    ///
+    /// The number is rounded half away from zero to `scale` fractional digits, and numbers with
+    /// too many digits for `precision` are clamped to the largest (or smallest) value that fits.
+    /// `NULL` stays `NULL`, and `None` is returned for values that are not finite numbers.
+    pub fn to_decimal(&self, precision: u8, scale: u8) -> Option<DataType> {
+        if self.is_none() {
+            return Some(DataType::None);
+        }
+        let d = self.decimal_value()?;
+        let (digits, exp) = round_to_scale(&d, i64::from(scale)).into_bigint_and_exponent();
+        let max = pow10(u64::from(precision)) - BigInt::from(1);
+        let digits = if digits.abs() > max {
+            if digits.is_negative() {
+                -max
+            } else {
+                max
+            }
+        } else {
+            digits
+        };
+        Some(DataType::from(BigDecimal::new(digits, exp)))
+    }
+
+    /// Convert this value for storage in a column of SQL type `ty`, or return `None` if it does
+    /// not fit in such a column.
+    ///
+    /// Values whose type already suits the column are kept as they are, except that numbers for
+    /// `DECIMAL` columns are rounded as by [`DataType::to_decimal`]. Beyond that, strings are
+    /// parsed for numeric and date columns, non-integral numbers are rounded for integer columns,
+    /// numbers are written out for text columns, and strings become byte strings for binary
+    /// columns. Numbers outside the range of an integer column do not fit it. `NULL` fits in any
+    /// column, and columns of other types accept any value.
+    pub fn coerce_to(&self, ty: &SqlType) -> Option<DataType> {
+        if self.is_none() {
+            return Some(DataType::None);
+        }
+        match *ty {
+            SqlType::Bool
+            | SqlType::Tinyint(_)
+            | SqlType::Int(_)
+            | SqlType::Bigint(_)
+            | SqlType::UnsignedTinyint(_)
+            | SqlType::UnsignedInt(_)
+            | SqlType::UnsignedBigint(_) => {
+                let (min, max): (i64, u64) = match *ty {
+                    SqlType::Bool | SqlType::Tinyint(_) => {
+                        (i8::min_value().into(), i8::max_value() as u64)
+                    }
+                    SqlType::Int(_) => (i32::min_value().into(), i32::max_value() as u64),
+                    SqlType::Bigint(_) => (i64::min_value(), i64::max_value() as u64),
+                    SqlType::UnsignedTinyint(_) => (0, u8::max_value().into()),
+                    SqlType::UnsignedInt(_) => (0, u32::max_value().into()),
+                    _ => (0, u64::max_value()),
+                };
+                let n = self.number_value()?;
+                let d = round_to_scale(&n.decimal_value()?, 0);
+                if d < BigDecimal::from(min) || d > BigDecimal::from(max) {
+                    None
+                } else if n.is_integral() {
+                    Some(n)
+                } else {
+                    d.to_i64()
+                        .map(DataType::from)
+                        .or_else(|| d.to_u64().map(DataType::from))
+                }
+            }
+            SqlType::Double | SqlType::Float | SqlType::Real => match self.number_value()? {
+                ref n if self.is_string() => Some(DataType::Double(n.into())),
+                n => Some(n),
+            },
+            SqlType::Decimal(precision, scale) => self.number_value()?.to_decimal(precision, scale),
+            SqlType::Char(_)
+            | SqlType::Varchar(_)
+            | SqlType::Tinytext
+            | SqlType::Mediumtext
+            | SqlType::Longtext
+            | SqlType::Text => {
+                if self.is_string() || self.is_json() {
+                    Some(self.clone())
+                } else {
+                    self.decimal_value().map(|d| d.to_string().into())
+                }
+            }
+            SqlType::Blob
+            | SqlType::Longblob
+            | SqlType::Mediumblob
+            | SqlType::Tinyblob
+            | SqlType::Binary(_)
+            | SqlType::Varbinary(_) => match *self {
+                DataType::ByteArray(_) => Some(self.clone()),
+                DataType::Text(..) | DataType::TinyText(..) => {
+                    let s: &str = self.into();
+                    Some(s.as_bytes().to_vec().into())
+                }
+                _ => None,
+            },
+            SqlType::Date => match *self {
+                DataType::Date(_) => Some(self.clone()),
+                _ => self.datetime_value().map(|ts| DataType::Date(ts.date())),
+            },
+            SqlType::DateTime(_) | SqlType::Timestamp => {
+                self.datetime_value().map(DataType::Timestamp)
+            }
+            _ => Some(self.clone()),
+        }
+    }
+
+    /// This number, or the number in this string, if there is one.
+    ///
+    /// Strings holding an integer become `BigInt`s, and other numeric strings become
+    /// `Decimal`s.
+    fn number_value(&self) -> Option<DataType> {
+        match *self {
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = self.into();
+                let s = s.trim();
+                s.parse::<i64>()
+                    .map(DataType::from)
+                    .or_else(|_| BigDecimal::from_str(s).map(DataType::from))
+                    .ok()
+            }
+            _ if self.is_numeric() => Some(self.clone()),
+            _ => None,
+        }
+    }
+
+    /// The exact decimal value of this number, if it is one.
+    ///
+    /// `Double`s are converted through their shortest decimal representation.
+    fn decimal_value(&self) -> Option<BigDecimal> {
+        match *self {
+            DataType::Decimal(ref d) => Some((**d).clone()),
+            DataType::Bool(b) => Some(BigDecimal::from(u8::from(b))),
+            DataType::Int(n) => Some(BigDecimal::from(n)),
+            DataType::UnsignedInt(n) => Some(BigDecimal::from(n)),
+            DataType::BigInt(n) => Some(BigDecimal::from(n)),
+            DataType::UnsignedBigInt(n) => Some(BigDecimal::from(n)),
+            DataType::Real(i, f) => {
+                let digits = BigInt::from(i) * BigInt::from(1_000_000_000) + BigInt::from(f);
+                Some(strip_zeros(BigDecimal::new(digits, 9)))
+            }
+            DataType::Double(d) if d.is_finite() => BigDecimal::from_str(&d.to_string()).ok(),
+            _ => None,
+        }
+    }
+
+    /// The position of this value's type in the order of values of different types.
+    ///
+    /// `NULL` sorts first, followed by numbers (and booleans), strings, byte strings, dates,
+    /// timestamps, times, intervals, and finally JSON documents.
+    fn type_rank(&self) -> u8 {
+        match *self {
+            DataType::None => 0,
+            DataType::Bool(_)
+            | DataType::Int(_)
+            | DataType::UnsignedInt(_)
+            | DataType::BigInt(_)
+            | DataType::UnsignedBigInt(_)
+            | DataType::Real(..)
+            | DataType::Decimal(_)
+            | DataType::Double(_) => 1,
+            DataType::Text(_) | DataType::TinyText(_) => 2,
+            DataType::ByteArray(_) => 3,
+            DataType::Date(_) => 4,
+            DataType::Timestamp(_) => 5,
+            DataType::Time(_) => 6,
+            DataType::Interval(..) => 7,
+            DataType::Json(_) => 8,
+        }
+    }
+
+    /// Hash a number so that it hashes like every number of another type that it is equal to.
+    fn hash_number<H: Hasher>(&self, state: &mut H) {
+        let d = match self.decimal_value() {
+            Some(d) => strip_zeros(d),
+            None => {
+                // only infinite doubles and NaN have no decimal value
+                return canonical_bits(self.into()).hash(state);
+            }
+        };
+        if d.as_bigint_and_exponent().1 <= 0 {
+            // integers hash like they do when they are integer types
+            if let Some(n) = d.to_i64() {
+                return n.hash(state);
+            } else if let Some(n) = d.to_u64() {
+                return n.hash(state);
+            }
+        }
+        d.hash(state)
+    }
+}
+
+/// The bits of `f`, with all zeroes and all `NaN`s mapped to the same bits.
+///
+/// Used to make equality and hashing of `DataType::Double` agree with its ordering.
+fn canonical_bits(f: f64) -> u64 {
+    if f == 0.0 {
+        0
+    } else if f.is_nan() {
+        std::f64::NAN.to_bits()
+    } else {
+        f.to_bits()
+    }
+}
+
+/// `10^n` as a big integer.
+fn pow10(n: u64) -> BigInt {
+    (0..n).fold(BigInt::from(1), |p, _| p * BigInt::from(10))
+}
+
+/// Divide `num` by `den`, rounding half away from zero like MySQL does for decimals.
+fn div_round(num: &BigInt, den: &BigInt) -> BigInt {
+    let q = num / den;
+    let r = num % den;
+    if r.abs() * BigInt::from(2) >= den.abs() {
+        if num.is_negative() == den.is_negative() {
+            q + BigInt::from(1)
+        } else {
+            q - BigInt::from(1)
+        }
+    } else {
+        q
+    }
+}
+
+/// `d` with exactly `scale` fractional digits, rounded half away from zero if it had more.
+fn round_to_scale(d: &BigDecimal, scale: i64) -> BigDecimal {
+    let (digits, exp) = d.as_bigint_and_exponent();
+    if exp <= scale {
+        d.with_scale(scale)
+    } else {
+        BigDecimal::new(div_round(&digits, &pow10((exp - scale) as u64)), scale)
+    }
+}
+
+/// `d` without trailing fractional zeros.
+fn strip_zeros(d: BigDecimal) -> BigDecimal {
+    let (mut digits, mut exp) = d.into_bigint_and_exponent();
+    let ten = BigInt::from(10);
+    while exp > 0 && !digits.is_zero() && (&digits % &ten).is_zero() {
+        digits = digits / &ten;
+        exp -= 1;
+    }
+    BigDecimal::new(digits, exp)
+}
+
+/// Divide two decimals the way MySQL does: the result has four more fractional digits than the
+/// dividend, and dividing by zero gives `NULL`.
+fn decimal_div(a: BigDecimal, b: &BigDecimal) -> DataType {
+    if b.is_zero() {
+        return DataType::None;
+    }
+    let (a_digits, a_exp) = a.into_bigint_and_exponent();
+    let (b_digits, b_exp) = b.as_bigint_and_exponent();
+    let scale = a_exp.max(0) + 4;
+    // a / b * 10^scale == a_digits * 10^(b_exp + scale - a_exp) / b_digits
+    let shift = b_exp + scale - a_exp;
+    let digits = if shift >= 0 {
+        div_round(&(a_digits * pow10(shift as u64)), &b_digits)
+    } else {
+        div_round(&a_digits, &(b_digits * pow10((-shift) as u64)))
+    };
+    DataType::from(BigDecimal::new(digits, scale))
+}
+
+/// Compare two doubles, with `NaN` equal to itself and greater than all other values.
+fn cmp_doubles(a: f64, b: f64) -> Ordering {
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+/// Compare two numbers, of any numeric types, by their exact values.
+///
+/// Doubles are compared by their shortest decimal representation, which orders them the same
+/// way their binary values do. Infinite doubles and `NaN` are ordered as by [`cmp_doubles`].
+fn cmp_numbers(a: &DataType, b: &DataType) -> Ordering {
+    match (a.decimal_value(), b.decimal_value()) {
+        (Some(ad), Some(bd)) => ad.cmp(&bd),
+        (Some(_), None) => cmp_doubles(0.0, b.into()),
+        (None, Some(_)) => cmp_doubles(a.into(), 0.0),
+        (None, None) => cmp_doubles(a.into(), b.into()),
+    }
 }
 
 impl PartialEq for DataType {
@@ -189,15 +818,36 @@ impl PartialEq for DataType {
                 let b: i128 = other.into();
                 a == b
             }
+            (&DataType::Bool(a), &DataType::Bool(b)) => a == b,
+            (&DataType::Bool(..), _) | (_, &DataType::Bool(..))
+                if self.is_integral() && other.is_integral() =>
+            {
+                let a: i128 = self.into();
+                let b: i128 = other.into();
+                a == b
+            }
             (&DataType::Real(ai, af), &DataType::Real(bi, bf)) => ai == bi && af == bf,
+            (&DataType::Double(a), &DataType::Double(b)) => canonical_bits(a) == canonical_bits(b),
             (&DataType::Timestamp(tsa), &DataType::Timestamp(tsb)) => tsa == tsb,
+            (&DataType::Date(a), &DataType::Date(b)) => a == b,
+            (&DataType::Time(a), &DataType::Time(b)) => a == b,
+            (&DataType::Interval(am, aus), &DataType::Interval(bm, bus)) => am == bm && aus == bus,
+            (&DataType::Json(ref a), &DataType::Json(ref b)) => a.to_string() == b.to_string(),
+            (&DataType::ByteArray(ref a), &DataType::ByteArray(ref b)) => a == b,
+            (&DataType::Decimal(ref a), &DataType::Decimal(ref b)) => a == b,
             (&DataType::None, &DataType::None) => true,
+            // numbers of different types are equal if their values are
+            _ if self.is_numeric() && other.is_numeric() => {
+                cmp_numbers(self, other) == Ordering::Equal
+            }
 
             _ => false,
         }
     }
 }
 
+impl Eq for DataType {}
+
 use std::cmp::Ordering;
 impl PartialOrd for DataType {
     fn partial_cmp(&self, other: &DataType) -> Option<Ordering> {
@@ -236,21 +886,41 @@ impl Ord for DataType {
                 let b: i128 = other.into();
                 a.cmp(&b)
             }
+            (&DataType::Bool(a), &DataType::Bool(ref b)) => a.cmp(b),
+            (&DataType::Bool(..), _) | (_, &DataType::Bool(..))
+                if self.is_integral() && other.is_integral() =>
+            {
+                let a: i128 = self.into();
+                let b: i128 = other.into();
+                a.cmp(&b)
+            }
             (&DataType::Real(ai, af), &DataType::Real(ref bi, ref bf)) => {
                 ai.cmp(bi).then_with(|| af.cmp(bf))
             }
+            (&DataType::Double(a), &DataType::Double(b)) => cmp_doubles(a, b),
             (&DataType::Timestamp(tsa), &DataType::Timestamp(ref tsb)) => tsa.cmp(tsb),
+            (&DataType::Date(a), &DataType::Date(ref b)) => a.cmp(b),
+            (&DataType::Time(a), &DataType::Time(ref b)) => a.cmp(b),
+            (&DataType::Interval(am, aus), &DataType::Interval(bm, bus)) => {
+                am.cmp(&bm).then_with(|| aus.cmp(&bus))
+            }
+            (&DataType::Json(ref a), &DataType::Json(ref b)) => a.to_string().cmp(&b.to_string()),
+            // dates are ordered by their midnight among timestamps, but are never equal to them.
+            (&DataType::Date(..), &DataType::Timestamp(..))
+            | (&DataType::Timestamp(..), &DataType::Date(..)) => {
+                let a = self.datetime_value().unwrap();
+                let b = other.datetime_value().unwrap();
+                a.cmp(&b)
+                    .then_with(|| self.type_rank().cmp(&other.type_rank()))
+            }
+            (&DataType::ByteArray(ref a), &DataType::ByteArray(ref b)) => a.cmp(b),
+            (&DataType::Decimal(ref a), &DataType::Decimal(ref b)) => a.cmp(b),
             (&DataType::None, &DataType::None) => Ordering::Equal,
 
-            // order Ints, Reals, Text, Timestamps, None
-            (&DataType::Int(..), _)
-            | (&DataType::UnsignedInt(..), _)
-            | (&DataType::BigInt(..), _)
-            | (&DataType::UnsignedBigInt(..), _) => Ordering::Greater,
-            (&DataType::Real(..), _) => Ordering::Greater,
-            (&DataType::Text(..), _) | (&DataType::TinyText(..), _) => Ordering::Greater,
-            (&DataType::Timestamp(..), _) => Ordering::Greater,
-            (&DataType::None, _) => Ordering::Greater,
+            // numbers of different types are ordered by their exact values, so that for example
+            // a DECIMAL column can be compared with a literal that parsed as another type.
+            _ if self.is_numeric() && other.is_numeric() => cmp_numbers(self, other),
+            _ => self.type_rank().cmp(&other.type_rank()),
         }
     }
 }
@@ -262,6 +932,7 @@ impl Hash for DataType {
         // collisions, but the decreased overhead is worth it.
         match *self {
             DataType::None => {}
+            DataType::Bool(b) => i64::from(b).hash(state),
             DataType::Int(..) | DataType::BigInt(..) => {
                 let n: i64 = self.into();
                 n.hash(state)
@@ -270,15 +941,25 @@ impl Hash for DataType {
                 let n: u64 = self.into();
                 n.hash(state)
             }
-            DataType::Real(i, f) => {
-                i.hash(state);
-                f.hash(state);
+            // numbers that are equal to an integer hash like it does
+            DataType::Real(i, 0) => i.hash(state),
+            DataType::Double(d) if d.fract() == 0.0 && d.abs() < 9.0e15 => (d as i64).hash(state),
+            DataType::Real(..) | DataType::Double(..) | DataType::Decimal(..) => {
+                self.hash_number(state)
             }
             DataType::Text(..) | DataType::TinyText(..) => {
                 let t: &str = self.into();
                 t.hash(state)
             }
             DataType::Timestamp(ts) => ts.hash(state),
+            DataType::Date(d) => d.hash(state),
+            DataType::Time(t) => t.hash(state),
+            DataType::Interval(months, micros) => {
+                months.hash(state);
+                micros.hash(state);
+            }
+            DataType::Json(ref doc) => doc.to_string().hash(state),
+            DataType::ByteArray(ref bytes) => bytes.hash(state),
         }
     }
 }
@@ -335,6 +1016,24 @@ impl From<usize> for DataType {
     }
 }
 
+impl From<bool> for DataType {
+    fn from(b: bool) -> Self {
+        DataType::Bool(b)
+    }
+}
+
+impl From<BigDecimal> for DataType {
+    fn from(d: BigDecimal) -> Self {
+        DataType::Decimal(Arc::new(d))
+    }
+}
+
+impl From<Vec<u8>> for DataType {
+    fn from(bytes: Vec<u8>) -> Self {
+        DataType::ByteArray(Arc::new(bytes))
+    }
+}
+
 impl From<f32> for DataType {
     fn from(f: f32) -> Self {
         Self::from(f as f64)
@@ -377,9 +1076,12 @@ impl<'a> From<&'a Literal> for DataType {
                 let ts = chrono::Local::now().naive_local();
                 DataType::Timestamp(ts)
             }
+            Literal::CurrentDate => DataType::Date(chrono::Local::now().naive_local().date()),
+            Literal::CurrentTime => DataType::Time(chrono::Local::now().naive_local().time()),
             Literal::FixedPoint(ref r) => {
                 DataType::Real(i64::from(r.integral), r.fractional as i32)
             }
+            Literal::Blob(ref b) => DataType::from(b.clone()),
             _ => unimplemented!(),
         }
     }
@@ -397,6 +1099,24 @@ impl From<NaiveDateTime> for DataType {
     }
 }
 
+impl From<serde_json::Value> for DataType {
+    fn from(doc: serde_json::Value) -> Self {
+        DataType::Json(Arc::new(doc))
+    }
+}
+
+impl From<NaiveDate> for DataType {
+    fn from(d: NaiveDate) -> Self {
+        DataType::Date(d)
+    }
+}
+
+impl From<NaiveTime> for DataType {
+    fn from(t: NaiveTime) -> Self {
+        DataType::Time(t)
+    }
+}
+
 // This conversion has many unwraps, but all of them are expected to be safe,
 // because DataType variants (i.e. `Text` and `TinyText`) constructors are all
 // generated from valid UTF-8 strings, or the constructor fails (e.g. TryFrom &[u8]).
@@ -439,6 +1159,7 @@ impl From<DataType> for i64 {
 impl From<&'_ DataType> for i128 {
     fn from(data: &'_ DataType) -> Self {
         match *data {
+            DataType::Bool(b) => i128::from(b),
             DataType::BigInt(s) => i128::from(s),
             DataType::UnsignedBigInt(s) => i128::from(s),
             DataType::Int(s) => i128::from(s),
@@ -451,6 +1172,7 @@ impl From<&'_ DataType> for i128 {
 impl From<&'_ DataType> for i64 {
     fn from(data: &'_ DataType) -> Self {
         match *data {
+            DataType::Bool(b) => i64::from(b),
             DataType::BigInt(s) => s,
             DataType::Int(s) => i64::from(s),
             DataType::UnsignedInt(s) => i64::from(s),
@@ -517,13 +1239,65 @@ impl From<&'_ DataType> for f64 {
     fn from(data: &'_ DataType) -> Self {
         match *data {
             DataType::Real(i, f) => i as f64 + f64::from(f) / FLOAT_PRECISION,
+            DataType::Double(d) => d,
+            DataType::Decimal(ref d) => d.to_f64().unwrap(),
+            DataType::Bool(b) => f64::from(u8::from(b)),
             DataType::Int(i) => f64::from(i),
+            DataType::UnsignedInt(i) => f64::from(i),
             DataType::BigInt(i) => i as f64,
+            DataType::UnsignedBigInt(i) => i as f64,
             _ => panic!("attempted to convert a {:?} to an f64", data),
         }
     }
 }
 
+impl From<DataType> for BigDecimal {
+    fn from(data: DataType) -> Self {
+        (&data).into()
+    }
+}
+
+impl From<&'_ DataType> for BigDecimal {
+    fn from(data: &'_ DataType) -> Self {
+        data.decimal_value()
+            .unwrap_or_else(|| panic!("attempted to convert a {:?} to a decimal", data))
+    }
+}
+
+impl From<DataType> for bool {
+    fn from(data: DataType) -> Self {
+        (&data).into()
+    }
+}
+
+impl From<&'_ DataType> for bool {
+    fn from(data: &'_ DataType) -> Self {
+        match *data {
+            DataType::Bool(b) => b,
+            ref n if n.is_integral() => i128::from(n) != 0,
+            _ => panic!("attempted to convert a {:?} to a bool", data),
+        }
+    }
+}
+
+impl<'a> From<&'a DataType> for &'a [u8] {
+    fn from(data: &'a DataType) -> Self {
+        match *data {
+            DataType::ByteArray(ref bytes) => &bytes[..],
+            _ => panic!("attempted to convert a {:?} to a byte string", data),
+        }
+    }
+}
+
+impl From<DataType> for Vec<u8> {
+    fn from(data: DataType) -> Self {
+        match data {
+            DataType::ByteArray(bytes) => Arc::try_unwrap(bytes).unwrap_or_else(|b| (*b).clone()),
+            _ => panic!("attempted to convert a {:?} to a byte string", data),
+        }
+    }
+}
+
 impl From<String> for DataType {
     fn from(s: String) -> Self {
         DataType::try_from(s.as_bytes()).unwrap()
@@ -569,8 +1343,8 @@ impl TryFrom<mysql_common::value::Value> for DataType {
             Value::Bytes(v) => DataType::try_from(&v[..]),
             Value::Int(v) => Ok(v.into()),
             Value::UInt(v) => Ok(v.into()),
-            Value::Float(v) => Ok(v.into()),
-            Value::Double(v) => Ok(v.into()),
+            Value::Float(v) => Ok(DataType::Double(v.into())),
+            Value::Double(v) => Ok(DataType::Double(v)),
             Value::Date(year, month, day, hour, minutes, seconds, micros) => {
                 Ok(DataType::Timestamp(
                     NaiveDate::from_ymd(year.into(), month.into(), day.into()).and_hms_micro(
@@ -581,17 +1355,136 @@ impl TryFrom<mysql_common::value::Value> for DataType {
                     ),
                 ))
             }
-            Value::Time(..) => Err("`mysql_common::value::Value::time` is not supported in Noria"),
+            Value::Time(false, 0, hour, minutes, seconds, micros) => Ok(DataType::Time(
+                NaiveTime::from_hms_micro(hour.into(), minutes.into(), seconds.into(), micros),
+            )),
+            Value::Time(..) => {
+                Err("`mysql_common::value::Value::time` outside of a day is not supported in Noria")
+            }
+        }
+    }
+}
+
+/// (De)serialization of JSON documents as JSON text, since `serde_json::Value` can only be
+/// deserialized from self-describing formats, which bincode is not.
+mod json_text {
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::Serializer;
+    use std::sync::Arc;
+
+    pub(super) fn serialize<S>(
+        doc: &Arc<serde_json::Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&doc.to_string())
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<Arc<serde_json::Value>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        serde_json::from_str(&text)
+            .map(Arc::new)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Add the interval `b` to (or, if `subtract` is set, subtract it from) the date, time,
+/// timestamp or interval `a`, like SQL's `a + INTERVAL ...`. Intervals can also be added to the
+/// left of a value.
+///
+/// Months are added first, keeping the day of the month unless the new month is too short.
+/// Dates stay dates unless the interval has a part shorter than a day, and times wrap around
+/// midnight. Returns `None` if neither value is an interval, and `NULL` if the result is out of
+/// range or the other value is not a date, time or timestamp.
+fn interval_arithmetic(a: &DataType, b: &DataType, subtract: bool) -> Option<DataType> {
+    let sign = if subtract { -1 } else { 1 };
+    let (value, months, micros) = match (a, b) {
+        (_, &DataType::Interval(m, us)) => {
+            (a, i64::from(m) * sign, i128::from(us) * i128::from(sign))
+        }
+        (&DataType::Interval(m, us), _) if !subtract => (b, i64::from(m), i128::from(us)),
+        _ => return None,
+    };
+    Some(add_interval(value, months, micros).unwrap_or(DataType::None))
+}
+
+fn add_interval(value: &DataType, months: i64, micros: i128) -> Option<DataType> {
+    let micros = i64::try_from(micros).ok()?;
+    match *value {
+        DataType::Interval(m, us) => Some(DataType::Interval(
+            i32::try_from(i64::from(m) + months).ok()?,
+            us.checked_add(micros)?,
+        )),
+        DataType::Time(t) if months == 0 => Some(DataType::Time(
+            t.overflowing_add_signed(Duration::microseconds(micros)).0,
+        )),
+        DataType::Date(d) if micros % MICROS_PER_DAY == 0 => Some(DataType::Date(
+            add_months(d, months)?.checked_add_signed(Duration::days(micros / MICROS_PER_DAY))?,
+        )),
+        _ => {
+            let ts = value.datetime_value()?;
+            let ts = add_months(ts.date(), months)?.and_time(ts.time());
+            Some(DataType::Timestamp(
+                ts.checked_add_signed(Duration::microseconds(micros))?,
+            ))
         }
     }
 }
 
+/// Add `months` months to `d`, moving to the last day of the new month if it is too short.
+fn add_months(d: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let month0 = i64::from(d.year()) * 12 + i64::from(d.month0()) + months;
+    let year = i32::try_from(month0.div_euclid(12)).ok()?;
+    let month = month0.rem_euclid(12) as u32 + 1;
+    (1..=d.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+}
+
+/// The week of the year that `d` falls in, like MySQL's `WEEK(d)`.
+fn week_of_year(d: NaiveDate) -> u32 {
+    let jan1 = NaiveDate::from_ymd(d.year(), 1, 1);
+    let first_sunday = (7 - jan1.weekday().num_days_from_sunday()) % 7;
+    match d.ordinal0().checked_sub(first_sunday) {
+        Some(days) => days / 7 + 1,
+        None => 0,
+    }
+}
+
 // Performs an arithmetic operation on two numeric DataTypes,
 // returning a new DataType as the result.
 macro_rules! arithmetic_operation (
-    ($op:tt, $first:ident, $second:ident) => (
+    ($op:tt, $first:ident, $second:ident, $decimal:expr) => (
         match ($first, $second) {
             (&DataType::None, _) | (_, &DataType::None) => DataType::None,
+            (first @ &DataType::Double(..), second) | (first, second @ &DataType::Double(..))
+                if first.is_numeric() && second.is_numeric() =>
+            {
+                let a: f64 = first.into();
+                let b: f64 = second.into();
+                DataType::Double(a $op b)
+            }
+            (first, second)
+                if (first.is_decimal() || second.is_decimal())
+                    && first.is_numeric()
+                    && second.is_numeric() =>
+            {
+                let a: BigDecimal = first.into();
+                let b: BigDecimal = second.into();
+                ($decimal)(a, &b)
+            }
+            (first @ &DataType::Bool(..), second) | (first, second @ &DataType::Bool(..))
+                if first.is_integral() && second.is_integral() =>
+            {
+                let a: i128 = first.into();
+                let b: i128 = second.into();
+                (a $op b).into()
+            }
             (&DataType::Int(a), &DataType::Int(b)) => (a $op b).into(),
             (&DataType::UnsignedInt(a), &DataType::UnsignedInt(b)) => (a $op b).into(),
             (&DataType::BigInt(a), &DataType::BigInt(b)) => (a $op b).into(),
@@ -635,7 +1528,12 @@ impl<'a, 'b> Add<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn add(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(+, self, other)
+        if let Some(dt) = interval_arithmetic(self, other, false) {
+            return dt;
+        }
+        arithmetic_operation!(+, self, other, |a: BigDecimal, b: &BigDecimal| {
+            DataType::from(a + b)
+        })
     }
 }
 
@@ -643,7 +1541,12 @@ impl<'a, 'b> Sub<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn sub(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(-, self, other)
+        if let Some(dt) = interval_arithmetic(self, other, true) {
+            return dt;
+        }
+        arithmetic_operation!(-, self, other, |a: BigDecimal, b: &BigDecimal| {
+            DataType::from(a - b)
+        })
     }
 }
 
@@ -651,7 +1554,9 @@ impl<'a, 'b> Mul<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn mul(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(*, self, other)
+        arithmetic_operation!(*, self, other, |a: BigDecimal, b: &BigDecimal| {
+            DataType::from(a * b)
+        })
     }
 }
 
@@ -659,7 +1564,47 @@ impl<'a, 'b> Div<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn div(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(/, self, other)
+        arithmetic_operation!(/, self, other, decimal_div)
+    }
+}
+
+/// A unit of time, as used in SQL `INTERVAL` and `EXTRACT` expressions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum TimeUnit {
+    /// A microsecond.
+    Microsecond,
+    /// A second.
+    Second,
+    /// A minute.
+    Minute,
+    /// An hour.
+    Hour,
+    /// A day.
+    Day,
+    /// A week, starting on Sunday.
+    Week,
+    /// A month.
+    Month,
+    /// A quarter of a year, starting in January, April, July or October.
+    Quarter,
+    /// A year.
+    Year,
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match *self {
+            TimeUnit::Microsecond => "MICROSECOND",
+            TimeUnit::Second => "SECOND",
+            TimeUnit::Minute => "MINUTE",
+            TimeUnit::Hour => "HOUR",
+            TimeUnit::Day => "DAY",
+            TimeUnit::Week => "WEEK",
+            TimeUnit::Month => "MONTH",
+            TimeUnit::Quarter => "QUARTER",
+            TimeUnit::Year => "YEAR",
+        };
+        write!(f, "{}", unit)
     }
 }
 
@@ -717,6 +1662,18 @@ pub enum TableOperation {
         /// The key used to identify the row to update.
         key: Vec<DataType>,
     },
+    /// Delete every row that matches `filter`.
+    DeleteWhere {
+        /// Pairs of a column index and the value that column must hold for a row to match.
+        filter: Vec<(usize, DataType)>,
+    },
+    /// Update every row that matches `filter`.
+    UpdateWhere {
+        /// Pairs of a column index and the value that column must hold for a row to match.
+        filter: Vec<(usize, DataType)>,
+        /// The modifications to make to each column of the matching rows.
+        set: Vec<Modification>,
+    },
 }
 
 impl TableOperation {
@@ -728,6 +1685,15 @@ impl TableOperation {
             _ => None,
         }
     }
+
+    #[doc(hidden)]
+    pub fn filter(&self) -> Option<&[(usize, DataType)]> {
+        match *self {
+            TableOperation::DeleteWhere { ref filter }
+            | TableOperation::UpdateWhere { ref filter, .. } => Some(filter),
+            _ => None,
+        }
+    }
 }
 
 impl From<Vec<DataType>> for TableOperation {
@@ -911,6 +1877,357 @@ mod tests {
         assert_eq!(format!("{}", big_int), "5");
     }
 
+    #[test]
+    fn bool_double_and_bytes() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |dt: &DataType| {
+            let mut hasher = DefaultHasher::new();
+            dt.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // booleans are interchangeable with 0 and 1
+        assert_eq!(DataType::Bool(true), DataType::Int(1));
+        assert_eq!(DataType::BigInt(0), DataType::Bool(false));
+        assert_eq!(hash(&DataType::Bool(true)), hash(&DataType::Int(1)));
+        assert!(DataType::Bool(true) > DataType::Bool(false));
+        assert!(DataType::Bool(true) < DataType::Int(2));
+        assert_eq!(&DataType::Bool(true) + &DataType::Int(2), 3.into());
+        assert!(bool::from(DataType::Int(5)));
+
+        // doubles are totally ordered
+        let nan = DataType::Double(std::f64::NAN);
+        assert_eq!(nan, nan);
+        assert_eq!(
+            nan.cmp(&DataType::Double(std::f64::INFINITY)),
+            Ordering::Greater
+        );
+        assert_eq!(DataType::Double(0.0), DataType::Double(-0.0));
+        assert_eq!(hash(&DataType::Double(0.0)), hash(&DataType::Double(-0.0)));
+        assert_eq!(DataType::Double(1.0), DataType::Int(1));
+        assert_eq!(hash(&DataType::Double(1.0)), hash(&DataType::Int(1)));
+        assert!(DataType::Double(1.5) < DataType::Int(2));
+        assert!(DataType::Double(1.5) > DataType::from(1.25));
+        assert_eq!(
+            &DataType::Double(1.5) * &DataType::Int(2),
+            DataType::Double(3.0)
+        );
+        assert_eq!(
+            &DataType::from(0.5) + &DataType::Double(1.0),
+            DataType::Double(1.5)
+        );
+
+        let bytes = DataType::from(vec![0u8, 255]);
+        assert_eq!(bytes, DataType::from(vec![0u8, 255]));
+        assert!(bytes < DataType::from(vec![1u8]));
+        assert_eq!(<&[u8]>::from(&bytes), &[0, 255]);
+        assert_eq!(format!("{}", bytes), "0x00ff");
+        assert_eq!(format!("{:?}", bytes), "ByteArray([0, 255])");
+        assert_eq!(Vec::<u8>::from(bytes), vec![0, 255]);
+    }
+
+    #[test]
+    fn decimals() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |dt: &DataType| {
+            let mut hasher = DefaultHasher::new();
+            dt.hash(&mut hasher);
+            hasher.finish()
+        };
+        let d = |s: &str| DataType::from(BigDecimal::from_str(s).unwrap());
+
+        // trailing zeros don't matter, except for display
+        assert_eq!(d("1.5"), d("1.50"));
+        assert_eq!(hash(&d("1.5")), hash(&d("1.50")));
+        assert_eq!(d("1.50").to_string(), "1.50");
+
+        // and numbers of different types are equal if their values are
+        assert_eq!(d("1.00"), DataType::from(1));
+        assert_eq!(hash(&d("1.00")), hash(&DataType::from(1)));
+        assert_eq!(d("9.99"), DataType::from(9.99));
+        assert_eq!(hash(&d("9.99")), hash(&DataType::from(9.99)));
+        assert_eq!(d("0.5"), DataType::Double(0.5));
+        assert_eq!(hash(&d("0.5")), hash(&DataType::Double(0.5)));
+        assert_eq!(
+            hash(&d("18446744073709551615")),
+            hash(&DataType::UnsignedBigInt(std::u64::MAX))
+        );
+
+        // arithmetic is exact
+        assert_eq!(&d("0.1") + &d("0.2"), d("0.3"));
+        assert_eq!(&d("19.99") * &DataType::from(3), d("59.97"));
+        assert_eq!(&d("1.10") - &DataType::from(1.1), d("0"));
+        assert_eq!(&d("0.5") + &DataType::Double(0.25), DataType::Double(0.75));
+
+        // division gives four more digits than the dividend, and NULL when dividing by zero
+        assert_eq!((&d("2") / &d("3")).to_string(), "0.6667");
+        assert_eq!((&d("-2.0") / &d("3")).to_string(), "-0.66667");
+        assert_eq!(&d("1") / &d("0"), DataType::None);
+
+        // numbers are rounded half away from zero, and clamped to the precision
+        let rounded = |dt: DataType| dt.to_decimal(5, 2).unwrap().to_string();
+        assert_eq!(rounded(d("1.005")), "1.01");
+        assert_eq!(rounded(d("-1.005")), "-1.01");
+        assert_eq!(rounded(d("1.004")), "1.00");
+        assert_eq!(rounded(DataType::from(7)), "7.00");
+        assert_eq!(rounded(DataType::Double(0.125)), "0.13");
+        assert_eq!(rounded(d("12345.678")), "999.99");
+        assert_eq!(rounded(d("-12345.678")), "-999.99");
+        assert_eq!(DataType::None.to_decimal(5, 2), Some(DataType::None));
+        assert_eq!(DataType::from("1.5").to_decimal(5, 2), None);
+
+        // decimals are ordered exactly among other numbers
+        assert!(d("1.5") > DataType::from(1));
+        assert!(d("1.5") < DataType::from(1.75));
+        assert!(d("0.1") < d("0.10000000000000000001"));
+        assert_eq!(d("9.99").cmp(&DataType::from(9.99)), Ordering::Equal);
+        assert!(d("10.00") <= DataType::from(10));
+        assert!(d("10.01") > DataType::from(10));
+        assert!(d("1e400") < DataType::Double(std::f64::INFINITY));
+    }
+
+    #[test]
+    fn dates_times_and_intervals() {
+        let date = |y, m, d| DataType::Date(NaiveDate::from_ymd(y, m, d));
+        let ts = |y, m, d, h| DataType::Timestamp(NaiveDate::from_ymd(y, m, d).and_hms(h, 0, 0));
+        let time = |h, m| DataType::Time(NaiveTime::from_hms(h, m, 0));
+        let interval = DataType::interval;
+
+        assert_eq!(date(2020, 2, 29).to_string(), "2020-02-29");
+        assert_eq!(time(9, 5).to_string(), "09:05:00");
+        assert_eq!(
+            format!("{:?}", interval(2, TimeUnit::Quarter)),
+            "Interval(6, 0)"
+        );
+
+        // dates sort among timestamps, but aren't equal to them
+        assert_ne!(date(2020, 1, 1), ts(2020, 1, 1, 0));
+        assert!(date(2020, 1, 1) < ts(2020, 1, 1, 0));
+        assert!(date(2020, 1, 2) > ts(2020, 1, 1, 23));
+        assert!(interval(1, TimeUnit::Month) > interval(31, TimeUnit::Day));
+
+        // months keep the day of the month where they can
+        assert_eq!(
+            &date(2020, 1, 31) + &interval(1, TimeUnit::Month),
+            date(2020, 2, 29)
+        );
+        assert_eq!(
+            &date(2020, 3, 31) - &interval(1, TimeUnit::Quarter),
+            date(2019, 12, 31)
+        );
+        assert_eq!(
+            &interval(1, TimeUnit::Year) + &date(2020, 2, 29),
+            date(2021, 2, 28)
+        );
+        // dates only become timestamps for intervals shorter than a day
+        assert_eq!(
+            &date(2020, 12, 31) + &interval(1, TimeUnit::Week),
+            date(2021, 1, 7)
+        );
+        assert_eq!(
+            &date(2020, 1, 1) - &interval(1, TimeUnit::Hour),
+            ts(2019, 12, 31, 23)
+        );
+        assert_eq!(
+            &ts(2020, 1, 1, 23) + &interval(2, TimeUnit::Hour),
+            ts(2020, 1, 2, 1)
+        );
+        assert_eq!(&time(23, 30) + &interval(45, TimeUnit::Minute), time(0, 15));
+        assert_eq!(
+            &interval(1, TimeUnit::Day) - &interval(1, TimeUnit::Hour),
+            interval(23, TimeUnit::Hour)
+        );
+        assert_eq!(
+            &DataType::None + &interval(1, TimeUnit::Day),
+            DataType::None
+        );
+        assert_eq!(&time(1, 0) + &interval(1, TimeUnit::Month), DataType::None);
+
+        // functions
+        let t = DataType::Timestamp(NaiveDate::from_ymd(2020, 1, 4).and_hms_micro(13, 14, 15, 16));
+        assert_eq!(t.date(), date(2020, 1, 4));
+        assert_eq!(
+            DataType::from("2020-01-04 13:14:15").date(),
+            date(2020, 1, 4)
+        );
+        assert_eq!(DataType::from("2020-01-04").date(), date(2020, 1, 4));
+        assert_eq!(DataType::from("yesterday").date(), DataType::None);
+        assert_eq!(t.extract(TimeUnit::Year), 2020.into());
+        assert_eq!(t.extract(TimeUnit::Quarter), 1.into());
+        assert_eq!(t.extract(TimeUnit::Day), 4.into());
+        assert_eq!(t.extract(TimeUnit::Minute), 14.into());
+        assert_eq!(t.extract(TimeUnit::Microsecond), 16.into());
+        assert_eq!(time(9, 5).extract(TimeUnit::Hour), 9.into());
+        assert_eq!(time(9, 5).extract(TimeUnit::Day), DataType::None);
+        // 2020 starts on a Wednesday, so its first Sunday starts week 1
+        assert_eq!(t.extract(TimeUnit::Week), 0.into());
+        assert_eq!(date(2020, 1, 5).extract(TimeUnit::Week), 1.into());
+        assert_eq!(date(2020, 12, 31).extract(TimeUnit::Week), 52.into());
+
+        // buckets
+        assert_eq!(t.bucket(TimeUnit::Hour), ts(2020, 1, 4, 13));
+        assert_eq!(t.bucket(TimeUnit::Day), ts(2020, 1, 4, 0));
+        assert_eq!(t.bucket(TimeUnit::Week), ts(2019, 12, 29, 0));
+        assert_eq!(
+            date(2020, 8, 17).bucket(TimeUnit::Quarter),
+            date(2020, 7, 1)
+        );
+        assert_eq!(date(2020, 8, 17).bucket(TimeUnit::Year), date(2020, 1, 1));
+        assert_eq!(DataType::from(1).bucket(TimeUnit::Day), DataType::None);
+    }
+
+    #[test]
+    fn json_documents() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |dt: &DataType| {
+            let mut hasher = DefaultHasher::new();
+            dt.hash(&mut hasher);
+            hasher.finish()
+        };
+        let doc = |text| DataType::json(text).unwrap();
+        let path = |p: &str| p.parse::<JsonPath>().unwrap();
+
+        assert!(DataType::json("{\"a\": ").is_err());
+        assert!(doc("[1]").is_json());
+
+        // documents are the same regardless of whitespace and the order of members
+        let d = doc(r#"{"b": [1, 2.5, null], "a": {"c": true}}"#);
+        assert_eq!(d, doc(r#"{"a":{"c":true},"b":[1,2.5,null]}"#));
+        assert_eq!(hash(&d), hash(&doc(r#"{"a":{"c":true},"b":[1,2.5,null]}"#)));
+        assert_eq!(d.to_string(), r#"{"a":{"c":true},"b":[1,2.5,null]}"#);
+        assert_ne!(doc("1"), doc("1.0"));
+        assert!(doc("[1]") < doc("[2]"));
+
+        // documents survive serialization
+        let bytes = bincode::serialize(&d).unwrap();
+        assert_eq!(bincode::deserialize::<DataType>(&bytes).unwrap(), d);
+
+        assert_eq!(d.json_extract(&path("$.b[1]")), doc("2.5"));
+        assert_eq!(d.json_extract(&path("$.a")), doc(r#"{"c": true}"#));
+        assert_eq!(d.json_extract(&path("$.x")), DataType::None);
+        assert_eq!(d.json_extract_unquoted(&path("$.b[0]")), 1.into());
+        assert_eq!(
+            d.json_extract_unquoted(&path("$.b[1]")),
+            DataType::Double(2.5)
+        );
+        assert_eq!(d.json_extract_unquoted(&path("$.b[2]")), DataType::None);
+        assert_eq!(
+            d.json_extract_unquoted(&path("$.a.c")),
+            DataType::Bool(true)
+        );
+        assert_eq!(
+            DataType::from(r#"{"name": "alice"}"#).json_extract_unquoted(&path("$.name")),
+            "alice".into()
+        );
+        assert_eq!(
+            DataType::from("not json").json_extract(&path("$")),
+            DataType::None
+        );
+        assert_eq!(DataType::from(1).json_extract(&path("$")), DataType::None);
+    }
+
+    #[test]
+    fn literals_for_column_types() {
+        assert_eq!(
+            DataType::from_literal(&Literal::Integer(1), &SqlType::Bool),
+            DataType::Bool(true)
+        );
+        assert_eq!(
+            DataType::from_literal(&Literal::Integer(2), &SqlType::Double),
+            DataType::Double(2.0)
+        );
+        assert_eq!(
+            DataType::from_literal(&Literal::String("ab".into()), &SqlType::Blob),
+            DataType::from(b"ab".to_vec())
+        );
+        assert_eq!(
+            DataType::from_literal(&Literal::Integer(7), &SqlType::Decimal(12, 2)).to_string(),
+            "7.00"
+        );
+        assert_eq!(
+            DataType::from_literal(&Literal::Integer(1), &SqlType::Int(32)),
+            DataType::Int(1)
+        );
+        assert_eq!(
+            DataType::from_literal(&Literal::String("2020-01-02".into()), &SqlType::Date),
+            DataType::Date(NaiveDate::from_ymd(2020, 1, 2))
+        );
+        assert_eq!(
+            DataType::from_literal(
+                &Literal::String("2020-01-02 03:04:05".into()),
+                &SqlType::Timestamp
+            ),
+            DataType::Timestamp(NaiveDate::from_ymd(2020, 1, 2).and_hms(3, 4, 5))
+        );
+    }
+
+    #[test]
+    fn coercion_to_column_types() {
+        let int = SqlType::Int(32);
+        assert_eq!(DataType::from(5).coerce_to(&int), Some(5.into()));
+        assert_eq!(DataType::from(" 5").coerce_to(&int), Some(5.into()));
+        assert_eq!(DataType::Double(2.5).coerce_to(&int), Some(3.into()));
+        assert_eq!(DataType::from("five").coerce_to(&int), None);
+        assert_eq!(DataType::None.coerce_to(&int), Some(DataType::None));
+        assert_eq!(
+            DataType::from(-1).coerce_to(&SqlType::UnsignedInt(32)),
+            None
+        );
+        assert_eq!(DataType::from(1i64 << 40).coerce_to(&int), None);
+        assert_eq!(DataType::from(128).coerce_to(&SqlType::Tinyint(8)), None);
+        assert_eq!(
+            DataType::from(-128).coerce_to(&SqlType::Tinyint(8)),
+            Some((-128).into())
+        );
+        assert_eq!(
+            DataType::from(256).coerce_to(&SqlType::UnsignedTinyint(8)),
+            None
+        );
+        assert_eq!(
+            DataType::from("18446744073709551615").coerce_to(&SqlType::UnsignedBigint(64)),
+            Some(u64::max_value().into())
+        );
+        assert_eq!(
+            DataType::from("18446744073709551616").coerce_to(&SqlType::UnsignedBigint(64)),
+            None
+        );
+
+        assert_eq!(
+            DataType::from("1.5").coerce_to(&SqlType::Double),
+            Some(DataType::Double(1.5))
+        );
+        assert_eq!(
+            DataType::from("1.005")
+                .coerce_to(&SqlType::Decimal(12, 2))
+                .unwrap()
+                .to_string(),
+            "1.01"
+        );
+        assert_eq!(
+            DataType::from(42).coerce_to(&SqlType::Varchar(255)),
+            Some("42".into())
+        );
+        assert_eq!(
+            DataType::Date(NaiveDate::from_ymd(2020, 1, 2)).coerce_to(&SqlType::Text),
+            None
+        );
+        assert_eq!(
+            DataType::from("ab").coerce_to(&SqlType::Blob),
+            Some(b"ab".to_vec().into())
+        );
+        assert_eq!(
+            DataType::from("2020-01-02 03:04:05").coerce_to(&SqlType::Date),
+            Some(DataType::Date(NaiveDate::from_ymd(2020, 1, 2)))
+        );
+        assert_eq!(
+            DataType::Date(NaiveDate::from_ymd(2020, 1, 2)).coerce_to(&SqlType::Timestamp),
+            Some(DataType::Timestamp(
+                NaiveDate::from_ymd(2020, 1, 2).and_hms(0, 0, 0)
+            ))
+        );
+        assert_eq!(DataType::from("soon").coerce_to(&SqlType::Timestamp), None);
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn data_type_fungibility() {
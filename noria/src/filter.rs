@@ -0,0 +1,216 @@
+use crate::data::DataType;
+use crate::JsonPath;
+use std::fmt;
+
+pub use nom_sql::Operator;
+
+/// The value that a column is compared against in a [`FilterCondition::Comparison`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Value {
+    /// A fixed value.
+    Constant(DataType),
+    /// The value of the given column of the same row.
+    Column(usize),
+    /// The query parameter at the given position of the lookup key.
+    ///
+    /// The reader of a query fills these in with [`FilterCondition::bind`] when the query
+    /// compares against its parameters in ways that cannot be looked up by key, like inside an
+    /// `OR`. They cannot be used in the filters given to
+    /// [`View::lookup_filtered`](crate::View::lookup_filtered).
+    Parameter(usize),
+}
+
+impl From<DataType> for Value {
+    fn from(dt: DataType) -> Self {
+        Value::Constant(dt)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Constant(ref c) => write!(f, "{}", c),
+            Value::Column(ref ci) => write!(f, "col: {}", ci),
+            Value::Parameter(ref pi) => write!(f, "?{}", pi),
+        }
+    }
+}
+
+/// A condition on the value of a single column of a row, or a combination of such conditions.
+///
+/// Used to filter the results of [`View::lookup_filtered`](crate::View::lookup_filtered), where
+/// each condition is paired with the index of the column it applies to.
+///
+/// Conditions follow SQL's rules for `NULL`: comparing `NULL` to anything, including another
+/// `NULL`, is never true, so a column holding `NULL` satisfies neither `= x` nor `!= x`. Use
+/// [`FilterCondition::IsNull`] and [`FilterCondition::IsNotNull`] to test for `NULL`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FilterCondition {
+    /// The column compares to the given value as given by the operator.
+    ///
+    /// Only `=`, `!=`, `>`, `>=`, `<` and `<=` are supported. The condition does not hold if
+    /// either side is `NULL`.
+    Comparison(Operator, Value),
+    /// The column holds one of the given values, none of which match `NULL`.
+    In(Vec<DataType>),
+    /// The column is `NULL`.
+    IsNull,
+    /// The column is not `NULL`.
+    IsNotNull,
+    /// The column holds a JSON document whose value at the given path satisfies the given
+    /// condition, like `col->>'$.path' = 'x'` does in SQL.
+    ///
+    /// The value is extracted with [`DataType::json_extract_unquoted`].
+    Json(JsonPath, Box<FilterCondition>),
+    /// All of the given conditions hold, each on the column it is paired with.
+    ///
+    /// The column that this condition is itself paired with is not used.
+    And(Vec<(usize, FilterCondition)>),
+    /// At least one of the given conditions holds, each on the column it is paired with.
+    ///
+    /// The column that this condition is itself paired with is not used.
+    Or(Vec<(usize, FilterCondition)>),
+}
+
+impl FilterCondition {
+    /// Returns true if column `col` of `row` satisfies this condition.
+    ///
+    /// Panics if the condition compares using an operator that is not supported.
+    pub fn matches(&self, row: &[DataType], col: usize) -> bool {
+        self.matches_value(&row[col], row)
+    }
+
+    /// Returns true if `d`, a value computed from `row`, satisfies this condition.
+    fn matches_value(&self, d: &DataType, row: &[DataType]) -> bool {
+        match *self {
+            FilterCondition::Comparison(ref op, ref f) => {
+                let v = match *f {
+                    Value::Constant(ref dt) => dt,
+                    Value::Column(c) => &row[c],
+                    Value::Parameter(i) => panic!("parameter {} was never bound", i),
+                };
+                if d.is_none() || v.is_none() {
+                    return false;
+                }
+                match *op {
+                    Operator::Equal => d == v,
+                    Operator::NotEqual => d != v,
+                    Operator::Greater => d > v,
+                    Operator::GreaterOrEqual => d >= v,
+                    Operator::Less => d < v,
+                    Operator::LessOrEqual => d <= v,
+                    Operator::In => unreachable!(),
+                    _ => unimplemented!(),
+                }
+            }
+            FilterCondition::In(ref fs) => !d.is_none() && fs.contains(d),
+            FilterCondition::IsNull => d.is_none(),
+            FilterCondition::IsNotNull => !d.is_none(),
+            FilterCondition::Json(ref path, ref cond) => {
+                cond.matches_value(&d.json_extract_unquoted(path), row)
+            }
+            // NOT has been pushed down to the comparisons by the time a condition is built, so
+            // treating an unknown result as false here gives the same answer as SQL's
+            // three-valued logic would
+            FilterCondition::And(ref conds) => conds.iter().all(|(c, cond)| cond.matches(row, *c)),
+            FilterCondition::Or(ref conds) => conds.iter().any(|(c, cond)| cond.matches(row, *c)),
+        }
+    }
+
+    /// Replace the query parameters that this condition compares against with their values in
+    /// `key`.
+    ///
+    /// A parameter that `key` has no value for is replaced with `NULL`, which nothing matches.
+    pub fn bind(&self, key: &[DataType]) -> FilterCondition {
+        let bind_all = |conds: &[(usize, FilterCondition)]| {
+            conds.iter().map(|(c, cond)| (*c, cond.bind(key))).collect()
+        };
+        match *self {
+            FilterCondition::Comparison(ref op, Value::Parameter(i)) => {
+                FilterCondition::Comparison(
+                    op.clone(),
+                    Value::Constant(key.get(i).cloned().unwrap_or(DataType::None)),
+                )
+            }
+            FilterCondition::Json(ref path, ref cond) => {
+                FilterCondition::Json(path.clone(), Box::new(cond.bind(key)))
+            }
+            FilterCondition::And(ref conds) => FilterCondition::And(bind_all(conds)),
+            FilterCondition::Or(ref conds) => FilterCondition::Or(bind_all(conds)),
+            ref cond => cond.clone(),
+        }
+    }
+
+    /// Check that this condition can be evaluated against the rows of a view with `ncols`
+    /// columns, and describe the problem if not.
+    pub(crate) fn check(&self, ncols: usize) -> Result<(), String> {
+        match *self {
+            FilterCondition::Comparison(ref op, ref f) => {
+                match *op {
+                    Operator::Equal
+                    | Operator::NotEqual
+                    | Operator::Greater
+                    | Operator::GreaterOrEqual
+                    | Operator::Less
+                    | Operator::LessOrEqual => {}
+                    ref op => return Err(format!("unsupported operator {}", op)),
+                }
+                match *f {
+                    Value::Column(c) if c >= ncols => Err(format!("no column {}", c)),
+                    Value::Parameter(i) => Err(format!("unbound parameter {}", i)),
+                    _ => Ok(()),
+                }
+            }
+            FilterCondition::In(..) | FilterCondition::IsNull | FilterCondition::IsNotNull => {
+                Ok(())
+            }
+            FilterCondition::Json(_, ref cond) => cond.check(ncols),
+            FilterCondition::And(ref conds) | FilterCondition::Or(ref conds) => {
+                for (c, cond) in conds {
+                    if *c >= ncols {
+                        return Err(format!("no column {}", c));
+                    }
+                    cond.check(ncols)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for FilterCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FilterCondition::Comparison(ref op, ref x) => write!(f, "{} {}", op, x),
+            FilterCondition::In(ref xs) => {
+                let xs: Vec<_> = xs.iter().map(ToString::to_string).collect();
+                write!(f, "IN ({})", xs.join(", "))
+            }
+            FilterCondition::IsNull => write!(f, "IS NULL"),
+            FilterCondition::IsNotNull => write!(f, "IS NOT NULL"),
+            FilterCondition::Json(ref path, ref cond) => write!(f, "->>'{}' {}", path, cond),
+            FilterCondition::And(ref conds) => write_all(f, conds, " AND "),
+            FilterCondition::Or(ref conds) => write_all(f, conds, " OR "),
+        }
+    }
+}
+
+/// Write `conds` joined by `sep` in brackets, naming each condition's column the way filter
+/// descriptions do.
+fn write_all(
+    f: &mut fmt::Formatter<'_>,
+    conds: &[(usize, FilterCondition)],
+    sep: &str,
+) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, (c, cond)) in conds.iter().enumerate() {
+        if i != 0 {
+            write!(f, "{}", sep)?;
+        }
+        match *cond {
+            FilterCondition::Json(..) => write!(f, "f{}{}", c, cond)?,
+            _ => write!(f, "f{} {}", c, cond)?,
+        }
+    }
+    write!(f, ")")
+}
@@ -0,0 +1,157 @@
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum Leg {
+    Member(String),
+    Index(usize),
+}
+
+/// A path to a value inside a JSON document, written in MySQL's syntax (like `$.tags[0]`).
+///
+/// Paths start with `$`, the whole document, followed by any number of object members (`.name`
+/// or `."some name"`) and array elements (`[2]`). Wildcards are not supported.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonPath {
+    legs: Vec<Leg>,
+}
+
+/// A [`JsonPath`] that could not be parsed.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid JSON path {:?} at offset {}", path, offset)]
+pub struct JsonPathError {
+    path: String,
+    offset: usize,
+}
+
+impl JsonPath {
+    /// The value at this path in `doc`, if there is one.
+    ///
+    /// Like in MySQL, a value that is not an array is treated as an array holding just that
+    /// value, so `[0]` picks it.
+    pub fn extract<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.legs.iter().try_fold(doc, |v, leg| match *leg {
+            Leg::Member(ref name) => v.get(name.as_str()),
+            Leg::Index(i) => match *v {
+                Value::Array(ref elements) => elements.get(i),
+                _ if i == 0 => Some(v),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let err = |offset| JsonPathError {
+            path: path.to_owned(),
+            offset,
+        };
+
+        // all delimiters are ASCII, so it is fine to look for them byte by byte
+        let bytes = path.as_bytes();
+        if bytes.first() != Some(&b'$') {
+            return Err(err(0));
+        }
+        let mut legs = Vec::new();
+        let mut i = 1;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'.' if bytes.get(i + 1) == Some(&b'"') => {
+                    let mut end = i + 2;
+                    while end < bytes.len() && bytes[end] != b'"' {
+                        end += if bytes[end] == b'\\' { 2 } else { 1 };
+                    }
+                    if end >= bytes.len() {
+                        return Err(err(i));
+                    }
+                    let name = serde_json::from_str(&path[i + 1..=end]).map_err(|_| err(i))?;
+                    legs.push(Leg::Member(name));
+                    i = end + 1;
+                }
+                b'.' => {
+                    let end = bytes[i + 1..]
+                        .iter()
+                        .position(|&b| b == b'.' || b == b'[')
+                        .map_or(bytes.len(), |n| i + 1 + n);
+                    let name = &path[i + 1..end];
+                    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '*') {
+                        return Err(err(i));
+                    }
+                    legs.push(Leg::Member(name.to_owned()));
+                    i = end;
+                }
+                b'[' => {
+                    let end = match bytes[i..].iter().position(|&b| b == b']') {
+                        Some(n) => i + n,
+                        None => return Err(err(i)),
+                    };
+                    let index = path[i + 1..end].trim().parse().map_err(|_| err(i))?;
+                    legs.push(Leg::Index(index));
+                    i = end + 1;
+                }
+                _ => return Err(err(i)),
+            }
+        }
+        Ok(JsonPath { legs })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for leg in &self.legs {
+            match *leg {
+                Leg::Member(ref name)
+                    if !name.starts_with(|c: char| c.is_ascii_digit())
+                        && name.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                {
+                    write!(f, ".{}", name)?
+                }
+                Leg::Member(ref name) => write!(f, ".{}", Value::from(name.as_str()))?,
+                Leg::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for path in &["$", "$.a", "$.a.b_2[3]", "$[0][1]", "$.\"two words\".c"] {
+            assert_eq!(JsonPath::from_str(path).unwrap().to_string(), *path);
+        }
+        assert_eq!(
+            JsonPath::from_str("$.\"plain\"[ 1 ]").unwrap().to_string(),
+            "$.plain[1]"
+        );
+        for path in &[
+            "", "a", "$.", "$..a", "$.a b", "$.*", "$[*]", "$[1", "$.\"a",
+        ] {
+            assert!(JsonPath::from_str(path).is_err(), "{:?} parsed", path);
+        }
+    }
+
+    #[test]
+    fn extract() {
+        let doc: Value =
+            serde_json::from_str(r#"{"a": {"b": [1, {"c": "x"}]}, "d e": 2}"#).unwrap();
+        let extract = |path: &str| JsonPath::from_str(path).unwrap().extract(&doc).cloned();
+
+        assert_eq!(extract("$"), Some(doc.clone()));
+        assert_eq!(extract("$.a.b[0]"), Some(Value::from(1)));
+        assert_eq!(extract("$.a.b[1].c"), Some(Value::from("x")));
+        assert_eq!(extract("$.\"d e\""), Some(Value::from(2)));
+        assert_eq!(extract("$.\"d e\"[0]"), Some(Value::from(2)));
+        assert_eq!(extract("$.a.b[2]"), None);
+        assert_eq!(extract("$.a.c"), None);
+        assert_eq!(extract("$.a.b.c"), None);
+    }
+}
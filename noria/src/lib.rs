@@ -206,9 +206,15 @@ use tokio_tower::multiplex;
 
 mod controller;
 mod data;
+mod json;
 mod table;
+mod transaction;
+mod typed;
 mod view;
 
+/// Blocking wrappers for use outside of an asynchronous context.
+pub mod sync;
+
 #[doc(hidden)]
 pub mod channel;
 #[doc(hidden)]
@@ -245,7 +251,9 @@ pub mod results {
 
 /// Noria errors.
 pub mod error {
+    pub use crate::json::JsonPathError;
     pub use crate::table::TableError;
+    pub use crate::typed::RowError;
     pub use crate::view::ViewError;
 }
 
@@ -296,15 +304,24 @@ impl<T> From<T> for Tagged<T> {
 }
 
 pub use crate::controller::{ControllerDescriptor, ControllerHandle};
-pub use crate::data::{DataType, Modification, Operation, TableOperation};
-pub use crate::table::Table;
-pub use crate::view::View;
+pub use crate::data::{DataType, Modification, Operation, TableOperation, TimeUnit};
+pub use crate::json::JsonPath;
+pub use crate::table::{Table, WriteOutcome, WriteToken};
+pub use crate::transaction::Transaction;
+pub use crate::typed::{FromRow, FromValue, IntoRow};
+pub use crate::view::{Delta, Pages, Subscription, View};
+pub use bigdecimal::BigDecimal;
+pub use noria_derive::{FromRow, IntoRow};
 
 #[doc(hidden)]
-pub use crate::table::Input;
+pub use crate::table::{Input, WriteAck};
+#[doc(hidden)]
+pub use crate::transaction::Txn;
+#[doc(hidden)]
+pub use crate::typed::take_column;
 
 #[doc(hidden)]
-pub use crate::view::{ReadQuery, ReadReply, ReadReplyBatch};
+pub use crate::view::{cmp_rows, KeyRange, ReadQuery, ReadReply, ReadReplyBatch};
 
 #[doc(hidden)]
 pub mod builders {
@@ -315,6 +332,9 @@ pub mod builders {
 /// Types used when debugging Noria.
 pub mod debug;
 
+/// Conditions for filtering the rows of a view.
+pub mod filter;
+
 /// Represents the result of a recipe activation.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ActivationResult {
@@ -332,6 +352,7 @@ pub struct ActivationResult {
 #[inline]
 pub fn shard_by(dt: &DataType, shards: usize) -> usize {
     match *dt {
+        DataType::Bool(b) => b as usize % shards,
         DataType::Int(n) => n as usize % shards,
         DataType::UnsignedInt(n) => n as usize % shards,
         DataType::BigInt(n) => n as usize % shards,
@@ -343,6 +364,19 @@ pub fn shard_by(dt: &DataType, shards: usize) -> usize {
             hasher.write(s.as_bytes());
             hasher.finish() as usize % shards
         }
+        DataType::Double(..)
+        | DataType::Decimal(..)
+        | DataType::ByteArray(..)
+        | DataType::Timestamp(..)
+        | DataType::Date(..)
+        | DataType::Time(..)
+        | DataType::Interval(..)
+        | DataType::Json(..) => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+            dt.hash(&mut hasher);
+            hasher.finish() as usize % shards
+        }
         // a bit hacky: send all NULL values to the first shard
         DataType::None => 0,
         ref x => {
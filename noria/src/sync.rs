@@ -0,0 +1,530 @@
+//! Blocking wrappers around the asynchronous Noria client API.
+//!
+//! The types in this module are for code that does not run on a Tokio runtime of its own. A
+//! [`SyncControllerHandle`] starts a background runtime that drives all the connections to Noria,
+//! and the [`SyncTable`]s and [`SyncView`]s obtained from it share that runtime. Every method
+//! blocks the calling thread until the corresponding operation completes:
+//!
+//! ```rust,no_run
+//! let mut c = noria::sync::SyncControllerHandle::from_zk("127.0.0.1:2181/hello").unwrap();
+//! let mut article = c.table("Article").unwrap();
+//! article.insert(vec![1.into(), "Hello world".into()]).unwrap();
+//!
+//! let mut awvc = c.view("ArticleWithVoteCount").unwrap();
+//! let rows = awvc.lookup(&[1.into()], true).unwrap();
+//! ```
+//!
+//! These methods must not be called from within an asynchronous context, since they would block
+//! the executor that is running it. Use the asynchronous API there instead.
+
+use crate::consensus::{self, Authority};
+use crate::data::*;
+use crate::debug::stats;
+use crate::error::{TableError, ViewError};
+use crate::filter::FilterCondition;
+use crate::results::{Results, Row};
+use crate::{
+    ActivationResult, ControllerHandle, FromRow, IntoRow, Pages, Table, Transaction, View,
+    WriteOutcome, WriteToken,
+};
+use futures_util::stream::StreamExt;
+use petgraph::graph::NodeIndex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::ops::RangeBounds;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Run `fut` to completion on the calling thread, with `rt` providing the reactor, timers, and
+/// executor for any background work it sets up.
+fn block_on<F: Future>(rt: &Runtime, fut: F) -> F::Output {
+    rt.enter(|| futures_executor::block_on(fut))
+}
+
+/// A blocking version of [`ControllerHandle`].
+///
+/// The handle owns the background runtime that executes all operations, and shares it with every
+/// [`SyncTable`] and [`SyncView`] created through it. The runtime is shut down once the handle
+/// and all of those are dropped.
+pub struct SyncControllerHandle<A>
+where
+    A: 'static + Authority,
+{
+    rt: Arc<Runtime>,
+    handle: ControllerHandle<A>,
+}
+
+impl<A> Clone for SyncControllerHandle<A>
+where
+    A: 'static + Authority,
+{
+    fn clone(&self) -> Self {
+        SyncControllerHandle {
+            rt: self.rt.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<A> fmt::Debug for SyncControllerHandle<A>
+where
+    A: 'static + Authority,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncControllerHandle")
+            .field("rt", &self.rt)
+            .finish()
+    }
+}
+
+impl SyncControllerHandle<consensus::ZookeeperAuthority> {
+    /// Fetch information about the current Soup controller from Zookeeper running at the given
+    /// address, and create a `SyncControllerHandle` from that.
+    pub fn from_zk(zookeeper_address: &str) -> Result<Self, failure::Error> {
+        let auth = consensus::ZookeeperAuthority::new(zookeeper_address)?;
+        SyncControllerHandle::new(auth)
+    }
+}
+
+impl<A: Authority + 'static> SyncControllerHandle<A> {
+    /// Start a background runtime, and use it to bootstrap a connection to Noria via the
+    /// configuration stored in the given `authority`.
+    pub fn new(authority: A) -> Result<Self, failure::Error>
+    where
+        A: Send + 'static,
+    {
+        Self::make(Arc::new(authority))
+    }
+
+    #[doc(hidden)]
+    pub fn make(authority: Arc<A>) -> Result<Self, failure::Error> {
+        let rt = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .thread_name("noria-sync")
+            .build()?;
+        let handle = block_on(&rt, ControllerHandle::make(authority))?;
+        Ok(SyncControllerHandle {
+            rt: Arc::new(rt),
+            handle,
+        })
+    }
+
+    /// Run `f` with the underlying asynchronous handle once it is ready to accept a request, and
+    /// wait for the future it returns.
+    fn run<F, Fut, R>(&mut self, f: F) -> Result<R, failure::Error>
+    where
+        F: FnOnce(&mut ControllerHandle<A>) -> Fut,
+        Fut: Future<Output = Result<R, failure::Error>>,
+    {
+        let handle = &mut self.handle;
+        block_on(&self.rt, async move {
+            handle.ready().await?;
+            f(handle).await
+        })
+    }
+
+    /// Enumerate all known base tables.
+    ///
+    /// See [`ControllerHandle::inputs`].
+    pub fn inputs(&mut self) -> Result<BTreeMap<String, NodeIndex>, failure::Error> {
+        self.run(|h| h.inputs())
+    }
+
+    /// Enumerate all known external views.
+    ///
+    /// See [`ControllerHandle::outputs`].
+    pub fn outputs(&mut self) -> Result<BTreeMap<String, NodeIndex>, failure::Error> {
+        self.run(|h| h.outputs())
+    }
+
+    /// Obtain a `SyncView` that allows you to query the given external view.
+    pub fn view(&mut self, name: &str) -> Result<SyncView, failure::Error> {
+        let view = self.run(|h| h.view(name))?;
+        Ok(SyncView {
+            rt: self.rt.clone(),
+            view,
+        })
+    }
+
+    /// Obtain a `SyncView` for the given `SELECT` statement, installing it first if needed.
+    ///
+    /// See [`ControllerHandle::prepare`].
+    pub fn prepare(&mut self, sql: &str) -> Result<SyncView, failure::Error> {
+        let view = self.run(|h| h.prepare(sql))?;
+        Ok(SyncView {
+            rt: self.rt.clone(),
+            view,
+        })
+    }
+
+    /// Obtain a `SyncTable` that allows you to perform writes, deletes, and other operations on
+    /// the given base table.
+    pub fn table(&mut self, name: &str) -> Result<SyncTable, failure::Error> {
+        let table = self.run(|h| h.table(name))?;
+        Ok(SyncTable {
+            rt: self.rt.clone(),
+            table,
+        })
+    }
+
+    /// Start a transaction that applies operations on several base tables as a single unit.
+    ///
+    /// Operations are added using [`SyncTable::as_async`], and the transaction is applied with
+    /// [`SyncControllerHandle::commit`].
+    pub fn transaction(&self) -> Transaction {
+        self.handle.transaction()
+    }
+
+    /// Apply all the operations in the given transaction.
+    ///
+    /// See [`Transaction::commit`].
+    pub fn commit(&self, txn: Transaction) -> Result<WriteToken, TableError> {
+        block_on(&self.rt, txn.commit())
+    }
+
+    /// Get statistics about the time spent processing different parts of the graph.
+    pub fn statistics(&mut self) -> Result<stats::GraphStats, failure::Error> {
+        self.run(|h| h.statistics())
+    }
+
+    /// Flush all partial state, evicting all rows present.
+    pub fn flush_partial(&mut self) -> Result<(), failure::Error> {
+        self.run(|h| h.flush_partial())
+    }
+
+    /// Extend the existing recipe with the given set of queries.
+    pub fn extend_recipe(
+        &mut self,
+        recipe_addition: &str,
+    ) -> Result<ActivationResult, failure::Error> {
+        self.run(|h| h.extend_recipe(recipe_addition))
+    }
+
+    /// Replace the existing recipe with this one.
+    pub fn install_recipe(&mut self, new_recipe: &str) -> Result<ActivationResult, failure::Error> {
+        self.run(|h| h.install_recipe(new_recipe))
+    }
+
+    /// Fetch a graphviz description of the dataflow graph.
+    pub fn graphviz(&mut self) -> Result<String, failure::Error> {
+        self.run(|h| h.graphviz())
+    }
+
+    /// Fetch a simplified graphviz description of the dataflow graph.
+    pub fn simple_graphviz(&mut self) -> Result<String, failure::Error> {
+        self.run(|h| h.simple_graphviz())
+    }
+
+    /// Remove the given external view from the graph.
+    pub fn remove_node(&mut self, view: NodeIndex) -> Result<(), failure::Error> {
+        self.run(|h| h.remove_node(view))
+    }
+
+    /// The asynchronous handle this handle wraps.
+    pub fn as_async(&self) -> &ControllerHandle<A> {
+        &self.handle
+    }
+}
+
+/// A blocking version of [`Table`].
+#[derive(Clone)]
+pub struct SyncTable {
+    rt: Arc<Runtime>,
+    table: Table,
+}
+
+impl fmt::Debug for SyncTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SyncTable").field(&self.table).finish()
+    }
+}
+
+impl SyncTable {
+    /// Get the name of this base table.
+    pub fn table_name(&self) -> &str {
+        self.table.table_name()
+    }
+
+    /// Get the list of columns in this base table.
+    pub fn columns(&self) -> &[String] {
+        self.table.columns()
+    }
+
+    /// Insert a single row of data into this base table.
+    pub fn insert<V>(&mut self, u: V) -> Result<WriteToken, TableError>
+    where
+        V: Into<Vec<DataType>>,
+    {
+        block_on(&self.rt, self.table.insert(u))
+    }
+
+    /// Insert a value into this base table as a single row.
+    ///
+    /// See [`Table::insert_typed`].
+    pub fn insert_typed<T>(&mut self, row: T) -> Result<WriteToken, TableError>
+    where
+        T: IntoRow,
+    {
+        block_on(&self.rt, self.table.insert_typed(row))
+    }
+
+    /// Perform multiple operation on this base table.
+    pub fn perform_all<I, V>(&mut self, i: I) -> Result<WriteToken, TableError>
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<TableOperation>,
+    {
+        block_on(&self.rt, self.table.perform_all(i))
+    }
+
+    /// Delete the row with the given key from this base table.
+    pub fn delete<I>(&mut self, key: I) -> Result<WriteToken, TableError>
+    where
+        I: Into<Vec<DataType>>,
+    {
+        block_on(&self.rt, self.table.delete(key))
+    }
+
+    /// Update the row with the given key in this base table.
+    ///
+    /// See [`Table::update`].
+    pub fn update<V>(&mut self, key: Vec<DataType>, u: V) -> Result<WriteToken, TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        block_on(&self.rt, self.table.update(key, u))
+    }
+
+    /// Delete every row of this base table that matches `filter`.
+    ///
+    /// See [`Table::delete_where`].
+    pub fn delete_where<F>(&mut self, filter: F) -> Result<WriteToken, TableError>
+    where
+        F: IntoIterator<Item = (usize, DataType)>,
+    {
+        block_on(&self.rt, self.table.delete_where(filter))
+    }
+
+    /// Update every row of this base table that matches `filter`.
+    ///
+    /// See [`Table::update_where`].
+    pub fn update_where<F, V>(&mut self, filter: F, u: V) -> Result<WriteToken, TableError>
+    where
+        F: IntoIterator<Item = (usize, DataType)>,
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        block_on(&self.rt, self.table.update_where(filter, u))
+    }
+
+    /// Perform a insert-or-update on this base table.
+    ///
+    /// See [`Table::insert_or_update`].
+    pub fn insert_or_update<V>(
+        &mut self,
+        insert: Vec<DataType>,
+        update: V,
+    ) -> Result<WriteToken, TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        block_on(&self.rt, self.table.insert_or_update(insert, update))
+    }
+
+    /// Perform multiple operations on this base table, and report what each of them did.
+    ///
+    /// See [`Table::perform_all_acked`].
+    pub fn perform_all_acked<I, V>(
+        &mut self,
+        i: I,
+    ) -> Result<(Vec<WriteOutcome>, WriteToken), TableError>
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<TableOperation>,
+    {
+        block_on(&self.rt, self.table.perform_all_acked(i))
+    }
+
+    /// Delete the row with the given key from this base table, and report whether it existed.
+    pub fn delete_acked<I>(&mut self, key: I) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        I: Into<Vec<DataType>>,
+    {
+        block_on(&self.rt, self.table.delete_acked(key))
+    }
+
+    /// Update the row with the given key in this base table, and report whether it existed.
+    ///
+    /// See [`Table::update_acked`].
+    pub fn update_acked<V>(
+        &mut self,
+        key: Vec<DataType>,
+        u: V,
+    ) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        block_on(&self.rt, self.table.update_acked(key, u))
+    }
+
+    /// Perform a insert-or-update on this base table, and report which of the two happened.
+    pub fn insert_or_update_acked<V>(
+        &mut self,
+        insert: Vec<DataType>,
+        update: V,
+    ) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        block_on(&self.rt, self.table.insert_or_update_acked(insert, update))
+    }
+
+    /// The asynchronous table this table wraps.
+    ///
+    /// This is mostly useful for adding operations on this table to a [`Transaction`].
+    pub fn as_async(&self) -> &Table {
+        &self.table
+    }
+}
+
+/// A blocking version of [`View`].
+#[derive(Clone)]
+pub struct SyncView {
+    rt: Arc<Runtime>,
+    view: View,
+}
+
+impl fmt::Debug for SyncView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SyncView").field(&self.view).finish()
+    }
+}
+
+impl SyncView {
+    /// Get the list of columns in this view.
+    pub fn columns(&self) -> &[String] {
+        self.view.columns()
+    }
+
+    /// Set how long lookups on this view wait for missing state before giving up.
+    ///
+    /// See [`View::set_timeout`].
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.view.set_timeout(timeout)
+    }
+
+    /// How long lookups on this view wait for missing state before giving up.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.view.timeout()
+    }
+
+    /// Get the current size of this view.
+    pub fn len(&mut self) -> Result<usize, ViewError> {
+        block_on(&self.rt, self.view.len())
+    }
+
+    /// Retrieve the query results for the given parameter values.
+    ///
+    /// See [`View::multi_lookup`].
+    pub fn multi_lookup(
+        &mut self,
+        keys: Vec<Vec<DataType>>,
+        block: bool,
+    ) -> Result<Vec<Results>, ViewError> {
+        block_on(&self.rt, self.view.multi_lookup(keys, block))
+    }
+
+    /// Retrieve the query results for all keys that fall within the given range.
+    ///
+    /// See [`View::lookup_range`].
+    pub fn lookup_range<R>(&mut self, range: R, block: bool) -> Result<Results, ViewError>
+    where
+        R: RangeBounds<Vec<DataType>>,
+    {
+        block_on(&self.rt, self.view.lookup_range(range, block))
+    }
+
+    /// Retrieve the query results for the given parameter value.
+    ///
+    /// The method will block on missing state only when `block` is `true`.
+    pub fn lookup(&mut self, key: &[DataType], block: bool) -> Result<Results, ViewError> {
+        block_on(&self.rt, self.view.lookup(key, block))
+    }
+
+    /// Retrieve the query results for the given parameter value as values of type `T`.
+    ///
+    /// See [`View::lookup_typed`].
+    pub fn lookup_typed<T>(&mut self, key: &[DataType], block: bool) -> Result<Vec<T>, ViewError>
+    where
+        T: FromRow,
+    {
+        block_on(&self.rt, self.view.lookup_typed(key, block))
+    }
+
+    /// Retrieve the first query result for the given parameter value.
+    pub fn lookup_first(
+        &mut self,
+        key: &[DataType],
+        block: bool,
+    ) -> Result<Option<Row>, ViewError> {
+        block_on(&self.rt, self.view.lookup_first(key, block))
+    }
+
+    /// Retrieve the query results for the given parameter value that match `filter`.
+    ///
+    /// See [`View::lookup_filtered`].
+    pub fn lookup_filtered(
+        &mut self,
+        key: &[DataType],
+        filter: &[(usize, FilterCondition)],
+        block: bool,
+    ) -> Result<Results, ViewError> {
+        block_on(&self.rt, self.view.lookup_filtered(key, filter, block))
+    }
+
+    /// Retrieve the query results for the given parameter value once they reflect the writes
+    /// identified by `token`.
+    ///
+    /// See [`View::lookup_after`].
+    pub fn lookup_after(
+        &mut self,
+        key: &[DataType],
+        token: &WriteToken,
+    ) -> Result<Results, ViewError> {
+        block_on(&self.rt, self.view.lookup_after(key, token))
+    }
+
+    /// Retrieve the query results for the given parameter value one page at a time.
+    ///
+    /// See [`View::lookup_paged`].
+    pub fn lookup_paged(&mut self, key: &[DataType], page_size: usize) -> SyncPages {
+        SyncPages {
+            rt: self.rt.clone(),
+            pages: self.view.lookup_paged(key, page_size),
+        }
+    }
+
+    /// The asynchronous view this view wraps.
+    pub fn as_async(&self) -> &View {
+        &self.view
+    }
+}
+
+/// A blocking version of [`Pages`].
+///
+/// Created by [`SyncView::lookup_paged`]. Each call to `next` fetches one page.
+#[derive(Debug)]
+pub struct SyncPages {
+    rt: Arc<Runtime>,
+    pages: Pages,
+}
+
+impl Iterator for SyncPages {
+    type Item = Result<Results, ViewError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(&self.rt, self.pages.next())
+    }
+}
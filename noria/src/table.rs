@@ -1,6 +1,9 @@
 use crate::channel::CONNECTION_FROM_BASE;
+use crate::controller::Refresh;
 use crate::data::*;
 use crate::internal::*;
+use crate::transaction::Txn;
+use crate::typed::{IntoRow, RowError};
 use crate::LocalOrNot;
 use crate::{Tagged, Tagger};
 use async_bincode::{AsyncBincodeStream, AsyncDestination};
@@ -8,9 +11,9 @@ use futures_util::{
     future, future::TryFutureExt, ready, stream::futures_unordered::FuturesUnordered,
     stream::TryStreamExt,
 };
-use nom_sql::CreateTableStatement;
+use nom_sql::{ColumnConstraint, CreateTableStatement, SqlType};
 use petgraph::graph::NodeIndex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
@@ -27,7 +30,7 @@ use vec_map::VecMap;
 
 type Transport = AsyncBincodeStream<
     tokio::net::TcpStream,
-    Tagged<()>,
+    Tagged<WriteAck>,
     Tagged<LocalOrNot<Input>>,
     AsyncDestination,
 >;
@@ -35,8 +38,8 @@ type Transport = AsyncBincodeStream<
 /// Create a new row for insertion into a [`Table`] using column names.
 ///
 /// If the schema of the given table is known, column defaults and `NOT NULL` restrictions will
-/// also be respected. In the future, this method will also check that the provided `DataType`
-/// matches the expected data type for each column.
+/// also be respected. The values are checked against the types of their columns when the row is
+/// written to the table.
 ///
 /// Values are automatically converted to `DataType` as necessary.
 ///
@@ -49,7 +52,8 @@ type Transport = AsyncBincodeStream<
 ///     "created_at" => chrono::Local::now().naive_local(),
 ///     "logins" => 0,
 ///   );
-///   users.insert(user).await
+///   users.insert(user).await?;
+///   Ok(())
 /// }
 /// ```
 #[macro_export]
@@ -91,7 +95,6 @@ macro_rules! row {
         for (coli, col) in $tbl.columns().iter().enumerate() {
             match &**col {
                 $($k => {
-                    // TODO: check row[coli] against schema.fields[coli].sql_type ?
                     row[coli] = vals[$idx].take().expect("field name appears twice -- should be caught by match");
                     if let Some(ref schema) = schema {
                         if schema.fields[coli].constraints.iter().any(|c| c == &$crate::ColumnConstraint::NotNull) {
@@ -146,13 +149,14 @@ async fn add_user(users: &mut Table) -> Result<(), TableError> {
       "not an ident" => s,
       "logins" => 0,
     );
-    users.insert(user).await
+    users.insert(user).await?;
+    Ok(())
 }
 
 /// Create an update for a given [`Table`] using column names.
 ///
-/// In the future, this method will also check that the provided `DataType`
-/// matches the expected data type for each column if the schema is known.
+/// If the schema of the given table is known, the new values are checked against the types of
+/// their columns when the update is written to the table.
 ///
 /// Values are automatically converted to `DataType` as necessary.
 ///
@@ -163,7 +167,8 @@ async fn add_user(users: &mut Table) -> Result<(), TableError> {
 ///     "password" => "hunter3",
 ///     "logins" => noria::Modification::Apply(noria::Operation::Add, 1.into()),
 ///   );
-///   users.update(vec!["jonhoo".into()], user).await
+///   users.update(vec!["jonhoo".into()], user).await?;
+///   Ok(())
 /// }
 /// ```
 #[macro_export]
@@ -181,7 +186,6 @@ macro_rules! update {
         for (coli, col) in $tbl.columns().iter().enumerate() {
             match &**col {
                 $($k => {
-                    // TODO: check set[$idx].1 against schema.fields[coli].sql_type ?
                     set[$idx].0 = coli;
                 },)|+
                 _ => { /* column value not updated */ }
@@ -198,7 +202,8 @@ async fn update_user(users: &mut Table) -> Result<(), TableError> {
       "password" => "hunter3",
       "logins" => crate::Modification::Apply(crate::Operation::Add, 1.into()),
     );
-    users.update(vec!["jonhoo".into()], user).await
+    users.update(vec!["jonhoo".into()], user).await?;
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -289,6 +294,37 @@ pub enum TableError {
     )]
     WrongKeyColumnCount(usize, usize),
 
+    /// A value could not be turned into a row of this table.
+    #[fail(display = "{}", _0)]
+    RowError(#[cause] RowError),
+
+    /// A value does not fit the type of the column it was written to.
+    #[fail(
+        display = "column '{}' has type {}, but was given {:?}",
+        column, expected, got
+    )]
+    TypeMismatch {
+        /// The name of the column.
+        column: String,
+        /// The declared type of the column.
+        expected: SqlType,
+        /// The value that was written to the column.
+        got: DataType,
+    },
+
+    /// `NULL` was written to a column declared `NOT NULL`.
+    #[fail(display = "column '{}' is declared NOT NULL, but was given NULL", _0)]
+    NullValue(String),
+
+    /// A filter that selects rows to delete or update was empty.
+    #[fail(display = "filters must match on at least one column")]
+    EmptyFilter,
+
+    /// A filter that selects rows to delete or update does not match on all the columns of any
+    /// index of the table, so the matching rows could only be found by scanning the whole table.
+    #[fail(display = "no index covers the filtered columns {:?}", _0)]
+    NoIndexForFilter(Vec<usize>),
+
     /// The underlying connection to Noria produced an error.
     #[fail(display = "{}", _0)]
     TransportError(#[cause] failure::Error),
@@ -305,6 +341,9 @@ impl From<Box<dyn std::error::Error + Send + Sync>> for TableError {
 pub struct Input {
     pub dst: LocalNodeIndex,
     pub data: Vec<TableOperation>,
+    pub txns: Vec<Txn>,
+    /// Whether the ack for this input should report the outcome of each operation.
+    pub outcomes: bool,
 }
 
 impl fmt::Debug for Input {
@@ -312,10 +351,128 @@ impl fmt::Debug for Input {
         fmt.debug_struct("Input")
             .field("dst", &self.dst)
             .field("data", &self.data)
+            .field("txns", &self.txns)
+            .field("outcomes", &self.outcomes)
             .finish()
     }
 }
 
+/// Identifies a set of writes to base tables.
+///
+/// Every successful write to a [`Table`] returns a `WriteToken` for that write. Passing it to
+/// [`View::lookup_after`](crate::View::lookup_after) makes the lookup wait until the view reflects
+/// the write, so that a client can always read its own writes. The tokens of several writes can
+/// be combined with [`WriteToken::merge`] to wait for all of them at once.
+///
+/// Internally, a token holds the sequence number of the latest covered write to each shard of each
+/// base table. Sequence numbers start over whenever a base table shard is restarted, so each is
+/// paired with the epoch in which the shard assigned it, and writes from a later epoch always
+/// order after those from an earlier one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteToken {
+    seqs: BTreeMap<(NodeIndex, usize), (u64, u64)>,
+}
+
+impl WriteToken {
+    /// Identify the `seq`th write in epoch `epoch` to the given shard of the base table `base`.
+    #[doc(hidden)]
+    pub fn new(base: NodeIndex, shard: usize, epoch: u64, seq: u64) -> Self {
+        let mut seqs = BTreeMap::new();
+        seqs.insert((base, shard), (epoch, seq));
+        WriteToken { seqs }
+    }
+
+    /// Extend this token to also cover all the writes covered by `other`.
+    pub fn merge(&mut self, other: &WriteToken) {
+        for (&k, &seq) in &other.seqs {
+            let e = self.seqs.entry(k).or_insert(seq);
+            if *e < seq {
+                *e = seq;
+            }
+        }
+    }
+
+    /// Returns true if every write covered by `other` is also covered by this token.
+    pub fn covers(&self, other: &WriteToken) -> bool {
+        other
+            .seqs
+            .iter()
+            .all(|(k, &seq)| self.seqs.get(k).map(|&s| s >= seq).unwrap_or(false))
+    }
+
+    /// Returns true if this token covers any writes to the given shard of the base table `base`.
+    #[doc(hidden)]
+    pub fn contains(&self, base: NodeIndex, shard: usize) -> bool {
+        self.seqs.contains_key(&(base, shard))
+    }
+
+    /// Returns true if this token and `other` both cover writes to some shard of a base table.
+    #[doc(hidden)]
+    pub fn overlaps(&self, other: &WriteToken) -> bool {
+        other.seqs.keys().any(|k| self.seqs.contains_key(k))
+    }
+
+    /// Returns true if this token does not cover any writes.
+    pub fn is_empty(&self) -> bool {
+        self.seqs.is_empty()
+    }
+
+    /// Only keep the writes to the base table shards for which `f` returns true.
+    pub(crate) fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(NodeIndex, usize) -> bool,
+    {
+        self.seqs = std::mem::take(&mut self.seqs)
+            .into_iter()
+            .filter(|&((base, shard), _)| f(base, shard))
+            .collect();
+    }
+}
+
+/// What a single operation did to the base table it was applied to.
+///
+/// Returned by the `_acked` write methods on [`Table`], such as [`Table::update_acked`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WriteOutcome {
+    /// A new row was inserted.
+    Inserted,
+    /// An existing row was changed.
+    Updated,
+    /// An existing row was deleted.
+    Deleted,
+    /// A row with the given key exists, but the operation left it as it was.
+    ///
+    /// This is the outcome of inserting a row whose key is already present, and of updates that
+    /// leave every column with the value it already had.
+    NoOp,
+    /// No row has the key that the operation refers to, or no row matches its filter.
+    NotFound,
+}
+
+impl WriteOutcome {
+    /// Combine the outcomes of applying a single operation to several rows, or on several shards.
+    ///
+    /// The result is the outcome that says the most about what happened, so for example a
+    /// [`Table::delete_where`] that deleted rows on any shard is `Deleted`.
+    #[doc(hidden)]
+    pub fn combine(self, other: WriteOutcome) -> WriteOutcome {
+        match (self, other) {
+            (WriteOutcome::NotFound, o) | (o, WriteOutcome::NotFound) => o,
+            (WriteOutcome::NoOp, o) | (o, WriteOutcome::NoOp) => o,
+            (o, _) => o,
+        }
+    }
+}
+
+/// The reply to an [`Input`] once it has been applied.
+#[doc(hidden)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteAck {
+    pub token: WriteToken,
+    /// The outcome of each operation in the input, if it asked for them.
+    pub outcomes: Vec<WriteOutcome>,
+}
+
 #[doc(hidden)]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TableBuilder {
@@ -325,6 +482,8 @@ pub struct TableBuilder {
     pub key_is_primary: bool,
     pub key: Vec<usize>,
     pub dropped: VecMap<DataType>,
+    /// The columns of each index on the base table.
+    pub indices: Vec<Vec<usize>>,
 
     pub table_name: String,
     pub columns: Vec<String>,
@@ -335,6 +494,7 @@ impl TableBuilder {
     pub(crate) fn build(
         self,
         rpcs: Arc<Mutex<HashMap<(SocketAddr, usize), TableRpc>>>,
+        refresh: Option<Refresh<TableBuilder>>,
     ) -> Result<Table, io::Error> {
         let mut addrs = Vec::with_capacity(self.txs.len());
         let mut conns = Vec::with_capacity(self.txs.len());
@@ -378,6 +538,7 @@ impl TableBuilder {
             key_is_primary: self.key_is_primary,
             columns: self.columns,
             dropped: self.dropped,
+            indices: self.indices,
             table_name: self.table_name,
             schema: self.schema,
             dst_is_local: false,
@@ -385,6 +546,9 @@ impl TableBuilder {
             shard_addrs: addrs,
             shards: conns,
 
+            rpcs,
+            refresh,
+
             dispatch,
         })
     }
@@ -396,6 +560,10 @@ impl TableBuilder {
 /// connections to the Soup workers. For this reason, `Table` is *not* `Send` or `Sync`. To get a
 /// handle that can be sent to a different thread (i.e., one with its own dedicated connections),
 /// call `Table::into_exclusive`.
+///
+/// Every write returns once it has been applied to the base table, but before it has necessarily
+/// propagated to any views. The [`WriteToken`] it returns can be used to wait for that to happen
+/// using [`View::lookup_after`](crate::View::lookup_after).
 #[derive(Clone)]
 pub struct Table {
     ni: NodeIndex,
@@ -404,6 +572,7 @@ pub struct Table {
     key: Vec<usize>,
     columns: Vec<String>,
     dropped: VecMap<DataType>,
+    indices: Vec<Vec<usize>>,
     table_name: String,
     schema: Option<CreateTableStatement>,
     dst_is_local: bool,
@@ -411,6 +580,9 @@ pub struct Table {
     shards: Vec<TableRpc>,
     shard_addrs: Vec<SocketAddr>,
 
+    rpcs: Arc<Mutex<HashMap<(SocketAddr, usize), TableRpc>>>,
+    refresh: Option<Refresh<TableBuilder>>,
+
     dispatch: tracing::Dispatch,
 }
 
@@ -436,7 +608,7 @@ impl Table {
     fn input(
         &mut self,
         mut i: Input,
-    ) -> impl Future<Output = Result<Tagged<()>, TableError>> + Send {
+    ) -> impl Future<Output = Result<Tagged<WriteAck>, TableError>> + Send {
         let span = if crate::trace_next_op() {
             Some(tracing::trace_span!(
                 "table-request",
@@ -446,54 +618,7 @@ impl Table {
             None
         };
 
-        // NOTE: this is really just a try block
-        let immediate_err = || {
-            let ncols = self.columns.len() + self.dropped.len();
-            for op in &i.data {
-                match op {
-                    TableOperation::Insert(ref row) => {
-                        if row.len() != ncols {
-                            return Err(TableError::WrongColumnCount(ncols, row.len()));
-                        }
-                    }
-                    TableOperation::Delete { ref key } => {
-                        if key.len() != self.key.len() {
-                            return Err(TableError::WrongKeyColumnCount(self.key.len(), key.len()));
-                        }
-                    }
-                    TableOperation::InsertOrUpdate {
-                        ref row,
-                        ref update,
-                    } => {
-                        if row.len() != ncols {
-                            return Err(TableError::WrongColumnCount(ncols, row.len()));
-                        }
-                        if update.len() > self.columns.len() {
-                            // NOTE: < is okay to allow dropping tailing no-ops
-                            return Err(TableError::WrongColumnCount(
-                                self.columns.len(),
-                                update.len(),
-                            ));
-                        }
-                    }
-                    TableOperation::Update { ref set, ref key } => {
-                        if key.len() != self.key.len() {
-                            return Err(TableError::WrongKeyColumnCount(self.key.len(), key.len()));
-                        }
-                        if set.len() > self.columns.len() {
-                            // NOTE: < is okay to allow dropping tailing no-ops
-                            return Err(TableError::WrongColumnCount(
-                                self.columns.len(),
-                                set.len(),
-                            ));
-                        }
-                    }
-                }
-            }
-            Ok(())
-        };
-
-        if let Err(e) = immediate_err() {
+        if let Err(e) = self.check(&i.data) {
             return future::Either::Left(async move { Err(e) });
         }
 
@@ -510,45 +635,44 @@ impl Table {
                 self.shards[0].call(request).map_err(TableError::from),
             ))
         } else {
-            if self.key.is_empty() {
-                unreachable!("sharded base without a key?");
-            }
-            if self.key.len() != 1 {
-                // base sharded by complex key
-                unimplemented!();
-            }
-            let key_col = self.key[0];
-
             let _guard = span.as_ref().map(tracing::Span::enter);
             tracing::trace!("shard request");
-            let mut shard_writes = vec![Vec::new(); self.shards.len()];
-            for r in i.data.drain(..) {
-                let shard = {
-                    let key = match r {
-                        TableOperation::Insert(ref r) => &r[key_col],
-                        TableOperation::Delete { ref key } => &key[0],
-                        TableOperation::Update { ref key, .. } => &key[0],
-                        TableOperation::InsertOrUpdate { ref row, .. } => &row[key_col],
-                    };
-                    crate::shard_by(key, self.shards.len())
-                };
-                shard_writes[shard].push(r);
+            // for each shard, the writes that go to it, and where in `i` they came from
+            let mut shard_writes = vec![(Vec::new(), Vec::new()); self.shards.len()];
+            let nops = i.data.len();
+            for (opi, r) in i.data.drain(..).enumerate() {
+                match self.shard_of(&r) {
+                    Some(shard) => {
+                        shard_writes[shard].0.push(r);
+                        shard_writes[shard].1.push(opi);
+                    }
+                    None => {
+                        for (rs, opis) in &mut shard_writes {
+                            rs.push(r.clone());
+                            opis.push(opi);
+                        }
+                    }
+                }
             }
 
             let wait_for = FuturesUnordered::new();
-            for (s, rs) in shard_writes.drain(..).enumerate() {
+            for (s, (rs, opis)) in shard_writes.drain(..).enumerate() {
                 if !rs.is_empty() {
                     let p = if self.dst_is_local {
                         unsafe {
                             LocalOrNot::for_local_transfer(Input {
                                 dst: i.dst,
                                 data: rs,
+                                txns: i.txns.clone(),
+                                outcomes: i.outcomes,
                             })
                         }
                     } else {
                         LocalOrNot::new(Input {
                             dst: i.dst,
                             data: rs,
+                            txns: i.txns.clone(),
+                            outcomes: i.outcomes,
                         })
                     };
                     let request = Tagged::from(p);
@@ -562,7 +686,11 @@ impl Table {
                     let _guard = span.as_ref().map(tracing::Span::enter);
                     tracing::trace!("submit request shard");
 
-                    wait_for.push(self.shards[s].call(request));
+                    wait_for.push(
+                        self.shards[s]
+                            .call(request)
+                            .map_ok(move |ack| (opis, ack.v)),
+                    );
                 } else {
                     // poll_ready reserves a sender slot which we have to release
                     // we do that by dropping the old handle and replacing it with a clone
@@ -571,9 +699,24 @@ impl Table {
                 }
             }
 
+            let outcomes = if i.outcomes {
+                vec![WriteOutcome::NotFound; nops]
+            } else {
+                Vec::new()
+            };
+            let merged = WriteAck {
+                token: WriteToken::default(),
+                outcomes,
+            };
             future::Either::Right(future::Either::Right(
                 wait_for
-                    .try_for_each(|_| async { Ok(()) })
+                    .try_fold(merged, |mut merged, (opis, ack)| async move {
+                        merged.token.merge(&ack.token);
+                        for (opi, outcome) in opis.into_iter().zip(ack.outcomes) {
+                            merged.outcomes[opi] = merged.outcomes[opi].combine(outcome);
+                        }
+                        Ok(merged)
+                    })
                     .map_err(TableError::from)
                     .map_ok(Tagged::from),
             ))
@@ -586,9 +729,9 @@ impl Service<Vec<TableOperation>> for Table {
     type Response = <TableRpc as Service<Tagged<LocalOrNot<Input>>>>::Response;
 
     #[cfg(not(doc))]
-    type Future = impl Future<Output = Result<Tagged<()>, TableError>> + Send;
+    type Future = impl Future<Output = Result<Tagged<WriteAck>, TableError>> + Send;
     #[cfg(doc)]
-    type Future = crate::doc_mock::Future<Result<Tagged<()>, TableError>>;
+    type Future = crate::doc_mock::Future<Result<Tagged<WriteAck>, TableError>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         for s in &mut self.shards {
@@ -598,8 +741,10 @@ impl Service<Vec<TableOperation>> for Table {
     }
 
     fn call(&mut self, ops: Vec<TableOperation>) -> Self::Future {
-        let i = self.prep_records(ops);
-        self.input(i)
+        match self.prep_records(ops) {
+            Ok(i) => future::Either::Left(self.input(i)),
+            Err(e) => future::Either::Right(future::ready(Err(e))),
+        }
     }
 }
 
@@ -630,6 +775,149 @@ impl Table {
         self.schema.as_ref()
     }
 
+    /// Check that the given operations are well-formed for this base table.
+    pub(crate) fn check(&self, ops: &[TableOperation]) -> Result<(), TableError> {
+        let ncols = self.columns.len() + self.dropped.len();
+        for op in ops {
+            match op {
+                TableOperation::Insert(ref row) => {
+                    if row.len() != ncols {
+                        return Err(TableError::WrongColumnCount(ncols, row.len()));
+                    }
+                }
+                TableOperation::Delete { ref key } => {
+                    if key.len() != self.key.len() {
+                        return Err(TableError::WrongKeyColumnCount(self.key.len(), key.len()));
+                    }
+                }
+                TableOperation::InsertOrUpdate {
+                    ref row,
+                    ref update,
+                } => {
+                    if row.len() != ncols {
+                        return Err(TableError::WrongColumnCount(ncols, row.len()));
+                    }
+                    if update.len() > self.columns.len() {
+                        // NOTE: < is okay to allow dropping tailing no-ops
+                        return Err(TableError::WrongColumnCount(
+                            self.columns.len(),
+                            update.len(),
+                        ));
+                    }
+                }
+                TableOperation::Update { ref set, ref key } => {
+                    if key.len() != self.key.len() {
+                        return Err(TableError::WrongKeyColumnCount(self.key.len(), key.len()));
+                    }
+                    if set.len() > self.columns.len() {
+                        // NOTE: < is okay to allow dropping tailing no-ops
+                        return Err(TableError::WrongColumnCount(self.columns.len(), set.len()));
+                    }
+                }
+                TableOperation::DeleteWhere { ref filter } => {
+                    self.check_filter(filter)?;
+                }
+                TableOperation::UpdateWhere {
+                    ref filter,
+                    ref set,
+                } => {
+                    self.check_filter(filter)?;
+                    if set.len() > self.columns.len() {
+                        // NOTE: < is okay to allow dropping tailing no-ops
+                        return Err(TableError::WrongColumnCount(self.columns.len(), set.len()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_filter(&self, filter: &[(usize, DataType)]) -> Result<(), TableError> {
+        match filter.iter().map(|&(coli, _)| coli).max() {
+            None => return Err(TableError::EmptyFilter),
+            Some(coli) if coli >= self.columns.len() => {
+                return Err(TableError::WrongColumnCount(self.columns.len(), coli + 1));
+            }
+            Some(_) => {}
+        }
+
+        // the base table looks the matching rows up through an index, and won't scan itself
+        let filtered = |col: &usize| filter.iter().any(|(coli, _)| coli == col);
+        if !self.indices.iter().any(|cols| cols.iter().all(filtered)) {
+            let mut cols: Vec<_> = filter.iter().map(|&(coli, _)| coli).collect();
+            cols.sort_unstable();
+            cols.dedup();
+            return Err(TableError::NoIndexForFilter(cols));
+        }
+        Ok(())
+    }
+
+    /// The shard of this base table that the given operation applies to, or `None` if it may
+    /// apply to all of them.
+    fn shard_of(&self, op: &TableOperation) -> Option<usize> {
+        if self.shards.len() == 1 {
+            return Some(0);
+        }
+        if self.key.is_empty() {
+            unreachable!("sharded base without a key?");
+        }
+        if self.key.len() != 1 {
+            // base sharded by complex key
+            unimplemented!();
+        }
+        let key_col = self.key[0];
+
+        let key = match *op {
+            TableOperation::Insert(ref r) => &r[key_col],
+            TableOperation::Delete { ref key } => &key[0],
+            TableOperation::Update { ref key, .. } => &key[0],
+            TableOperation::InsertOrUpdate { ref row, .. } => &row[key_col],
+            TableOperation::DeleteWhere { ref filter }
+            | TableOperation::UpdateWhere { ref filter, .. } => {
+                // only the rows with the given key can match, and they all live on one shard
+                let (_, key) = filter.iter().find(|&&(coli, _)| coli == key_col)?;
+                key
+            }
+        };
+        Some(crate::shard_by(key, self.shards.len()))
+    }
+
+    /// Identifies the base table this handle writes to.
+    pub(crate) fn base(&self) -> NodeIndex {
+        self.ni
+    }
+
+    /// The shards of this base table that the given operations apply to.
+    pub(crate) fn shards_for(&self, ops: &[TableOperation]) -> Vec<usize> {
+        let nshards = self.shards.len();
+        let mut shards: Vec<_> = ops
+            .iter()
+            .flat_map(|op| match self.shard_of(op) {
+                Some(shard) => shard..shard + 1,
+                None => 0..nshards,
+            })
+            .collect();
+        shards.sort_unstable();
+        shards.dedup();
+        shards
+    }
+
+    /// Perform the given operations on this base table as part of the transaction `txn`.
+    pub(crate) async fn perform_in(
+        &mut self,
+        ops: Vec<TableOperation>,
+        txn: Txn,
+    ) -> Result<WriteToken, TableError> {
+        let res: Result<_, TableError> = async {
+            future::poll_fn(|cx| self.poll_ready(cx)).await?;
+            let mut i = self.prep_records(ops)?;
+            i.txns.push(txn);
+            Ok(self.input(i).await?.v.token)
+        }
+        .await;
+        self.refresh_on_failure(res).await
+    }
+
     fn inject_dropped_cols(&self, r: &mut TableOperation) {
         use std::mem;
         let ndropped = self.dropped.len();
@@ -716,31 +1004,236 @@ impl Table {
         }
     }
 
-    fn prep_records(&self, mut ops: Vec<TableOperation>) -> Input {
+    /// Convert the values written by `op` to the types of the columns they are written to, as
+    /// given by the schema of this base table, if it is known. The keys and filter values that
+    /// pick out the rows to update or delete are converted to the types of their columns too.
+    ///
+    /// Values are converted with [`DataType::coerce_to`], which among other things rounds the
+    /// values written to `DECIMAL` columns the way MySQL does when not in strict mode. Values
+    /// that do not fit their column, and `NULL`s written to `NOT NULL` columns, are rejected.
+    pub(crate) fn coerce(&self, op: &mut TableOperation) -> Result<(), TableError> {
+        let schema = match self.schema {
+            Some(ref schema) => schema,
+            None => return Ok(()),
+        };
+        // keys and filter values are converted like the values they are compared against, but
+        // since they are never stored, they may be NULL even for NOT NULL columns
+        let convert = |coli: usize, v: &mut DataType, stored: bool| {
+            let field = match schema.fields.get(coli) {
+                Some(field) => field,
+                None => return Ok(()),
+            };
+            if stored && v.is_none() && field.constraints.contains(&ColumnConstraint::NotNull) {
+                return Err(TableError::NullValue(field.column.name.clone()));
+            }
+            *v = v
+                .coerce_to(&field.sql_type)
+                .ok_or_else(|| TableError::TypeMismatch {
+                    column: field.column.name.clone(),
+                    expected: field.sql_type.clone(),
+                    got: v.clone(),
+                })?;
+            Ok(())
+        };
+        let coerce = |coli: usize, v: &mut DataType| convert(coli, v, true);
+        let coerce_key = |key: &mut [DataType]| -> Result<(), TableError> {
+            for (&coli, v) in self.key.iter().zip(key.iter_mut()) {
+                convert(coli, v, false)?;
+            }
+            Ok(())
+        };
+        let coerce_filter = |filter: &mut [(usize, DataType)]| -> Result<(), TableError> {
+            for (coli, v) in filter.iter_mut() {
+                convert(*coli, v, false)?;
+            }
+            Ok(())
+        };
+        let coerce_modification = |coli: usize, m: &mut Modification| match *m {
+            Modification::Set(ref mut v) => coerce(coli, v),
+            Modification::Apply(_, ref mut v) => {
+                // operands need not have the column's type (think intervals added to dates), but
+                // those for DECIMAL columns are rounded so that the result is too
+                if let Some(&SqlType::Decimal(precision, scale)) =
+                    schema.fields.get(coli).map(|f| &f.sql_type)
+                {
+                    if let Some(d) = v.to_decimal(precision, scale) {
+                        *v = d;
+                    }
+                }
+                Ok(())
+            }
+            Modification::None => Ok(()),
+        };
+
+        match *op {
+            TableOperation::Insert(ref mut row) => {
+                for (coli, v) in row.iter_mut().enumerate() {
+                    coerce(coli, v)?;
+                }
+            }
+            TableOperation::InsertOrUpdate {
+                ref mut row,
+                ref mut update,
+            } => {
+                for (coli, v) in row.iter_mut().enumerate() {
+                    coerce(coli, v)?;
+                }
+                for (coli, m) in update.iter_mut().enumerate() {
+                    coerce_modification(coli, m)?;
+                }
+            }
+            TableOperation::Update {
+                ref mut set,
+                ref mut key,
+            } => {
+                coerce_key(key)?;
+                for (coli, m) in set.iter_mut().enumerate() {
+                    coerce_modification(coli, m)?;
+                }
+            }
+            TableOperation::UpdateWhere {
+                ref mut set,
+                ref mut filter,
+            } => {
+                coerce_filter(filter)?;
+                for (coli, m) in set.iter_mut().enumerate() {
+                    coerce_modification(coli, m)?;
+                }
+            }
+            TableOperation::Delete { ref mut key } => coerce_key(key)?,
+            TableOperation::DeleteWhere { ref mut filter } => coerce_filter(filter)?,
+        }
+        Ok(())
+    }
+
+    fn prep_records(&self, mut ops: Vec<TableOperation>) -> Result<Input, TableError> {
         for r in &mut ops {
+            self.coerce(r)?;
             self.inject_dropped_cols(r);
         }
 
-        Input {
+        Ok(Input {
             dst: self.node,
             data: ops,
-        }
+            txns: Vec::new(),
+            outcomes: false,
+        })
     }
 
-    async fn quick_n_dirty<Request, R>(
-        &mut self,
-        r: Request,
-    ) -> Result<R, <Self as Service<Request>>::Error>
+    async fn quick_n_dirty<Request>(&mut self, r: Request) -> Result<WriteToken, TableError>
     where
         Request: Send + 'static,
-        Self: Service<Request, Response = Tagged<R>>,
+        Self: Service<Request, Response = Tagged<WriteAck>, Error = TableError>,
     {
-        future::poll_fn(|cx| self.poll_ready(cx)).await?;
-        Ok(self.call(r).await?.v)
+        let res: Result<_, TableError> = async {
+            future::poll_fn(|cx| self.poll_ready(cx)).await?;
+            Ok(self.call(r).await?.v.token)
+        }
+        .await;
+        self.refresh_on_failure(res).await
+    }
+
+    /// Perform `ops`, and report the outcome of each one.
+    async fn perform_acked(
+        &mut self,
+        ops: Vec<TableOperation>,
+    ) -> Result<(Vec<WriteOutcome>, WriteToken), TableError> {
+        let res: Result<_, TableError> = async {
+            future::poll_fn(|cx| self.poll_ready(cx)).await?;
+            let mut i = self.prep_records(ops)?;
+            i.outcomes = true;
+            let ack = self.input(i).await?.v;
+            Ok((ack.outcomes, ack.token))
+        }
+        .await;
+        self.refresh_on_failure(res).await
+    }
+
+    /// Reconnect to the shards of this base table if `res` shows that the connection to them
+    /// broke, so that later writes reach the table wherever it now lives.
+    ///
+    /// The failed write is not retried, since it may already have been applied.
+    async fn refresh_on_failure<R>(&mut self, res: Result<R, TableError>) -> Result<R, TableError> {
+        if let Err(TableError::TransportError(_)) = res {
+            // the original error is more useful to the caller than any error from refreshing
+            let _ = self.refresh().await;
+        }
+        res
+    }
+
+    /// Reconnect to the shards of this base table, wherever they currently are.
+    ///
+    /// The shards of a base table move if the worker that holds them fails. Writes already call
+    /// this method if the connection to a shard breaks, so it is rarely necessary to call it
+    /// directly.
+    pub async fn refresh(&mut self) -> Result<(), TableError> {
+        let refresh = match self.refresh {
+            Some(ref refresh) => Arc::clone(refresh),
+            None => return Ok(()),
+        };
+        let builder = refresh()
+            .await
+            .map_err(TableError::TransportError)?
+            .ok_or_else(|| TableError::TransportError(failure::err_msg("the table went away")))?;
+
+        // the connections we have may be broken, so don't let anyone pick them up again
+        {
+            let mut rpcs = self.rpcs.lock().unwrap();
+            for (shardi, &addr) in self.shard_addrs.iter().enumerate() {
+                rpcs.remove(&(addr, shardi));
+            }
+        }
+
+        let dst_is_local = self.dst_is_local;
+        *self = builder
+            .build(Arc::clone(&self.rpcs), Some(refresh))
+            .map_err(|e| TableError::TransportError(e.into()))?;
+        self.dst_is_local = dst_is_local;
+        Ok(())
+    }
+
+    fn typed_row<T: IntoRow>(&self, row: T) -> Result<Vec<DataType>, RowError> {
+        let mut values = vec![None; self.columns.len()];
+        for (column, value) in row.into_row() {
+            let coli = self
+                .columns
+                .iter()
+                .position(|c| c == column)
+                .ok_or_else(|| RowError::NoSuchColumn(column.to_owned()))?;
+            values[coli] = Some(value);
+        }
+
+        let schema = self.schema.as_ref();
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(coli, value)| {
+                let spec = match schema {
+                    Some(schema) => &schema.fields[coli],
+                    None => return Ok(value.unwrap_or(DataType::None)),
+                };
+
+                let mut not_null = false;
+                let mut default = DataType::None;
+                for c in &spec.constraints {
+                    match c {
+                        ColumnConstraint::NotNull => not_null = true,
+                        ColumnConstraint::DefaultValue(ref literal) => default = literal.into(),
+                        _ => {}
+                    }
+                }
+
+                let value = value.unwrap_or(default);
+                if not_null && value.is_none() {
+                    return Err(RowError::MissingValue(self.columns[coli].clone()));
+                }
+                Ok(value)
+            })
+            .collect()
     }
 
     /// Insert a single row of data into this base table.
-    pub async fn insert<V>(&mut self, u: V) -> Result<(), TableError>
+    pub async fn insert<V>(&mut self, u: V) -> Result<WriteToken, TableError>
     where
         V: Into<Vec<DataType>>,
     {
@@ -748,8 +1241,21 @@ impl Table {
             .await
     }
 
+    /// Insert a value into this base table as a single row.
+    ///
+    /// The fields of `row` are matched up with the columns of this table by name using
+    /// [`IntoRow`]. If the schema of the table is known, columns that `row` does not provide are
+    /// given their default values, as with [`row!`].
+    pub async fn insert_typed<T>(&mut self, row: T) -> Result<WriteToken, TableError>
+    where
+        T: IntoRow,
+    {
+        let row = self.typed_row(row).map_err(TableError::RowError)?;
+        self.insert(row).await
+    }
+
     /// Perform multiple operation on this base table.
-    pub async fn perform_all<I, V>(&mut self, i: I) -> Result<(), TableError>
+    pub async fn perform_all<I, V>(&mut self, i: I) -> Result<WriteToken, TableError>
     where
         I: IntoIterator<Item = V>,
         V: Into<TableOperation>,
@@ -759,7 +1265,7 @@ impl Table {
     }
 
     /// Delete the row with the given key from this base table.
-    pub async fn delete<I>(&mut self, key: I) -> Result<(), TableError>
+    pub async fn delete<I>(&mut self, key: I) -> Result<WriteToken, TableError>
     where
         I: Into<Vec<DataType>>,
     {
@@ -771,7 +1277,15 @@ impl Table {
     ///
     /// `u` is a set of column-modification pairs, where for each pair `(i, m)`, the modification
     /// `m` will be applied to column `i` of the record with key `key`.
-    pub async fn update<V>(&mut self, key: Vec<DataType>, u: V) -> Result<(), TableError>
+    pub async fn update<V>(&mut self, key: Vec<DataType>, u: V) -> Result<WriteToken, TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        let op = self.update_op(key, u)?;
+        self.quick_n_dirty(vec![op]).await
+    }
+
+    fn update_op<V>(&self, key: Vec<DataType>, u: V) -> Result<TableOperation, TableError>
     where
         V: IntoIterator<Item = (usize, Modification)>,
     {
@@ -788,8 +1302,61 @@ impl Table {
             set[coli] = m;
         }
 
-        self.quick_n_dirty(vec![TableOperation::Update { key, set }])
-            .await
+        Ok(TableOperation::Update { key, set })
+    }
+
+    /// Delete every row of this base table that matches `filter`.
+    ///
+    /// `filter` is a list of column-value pairs, and a row matches if it holds the given value in
+    /// every listed column. The matching rows are found by the base table itself, through one of
+    /// its indices, so this is much cheaper than reading them out through a view and deleting them
+    /// one by one.
+    ///
+    /// Fails with [`TableError::EmptyFilter`] if `filter` is empty, and with
+    /// [`TableError::NoIndexForFilter`] unless the filtered columns include all the columns of
+    /// some index of the table, such as its primary key.
+    pub async fn delete_where<F>(&mut self, filter: F) -> Result<WriteToken, TableError>
+    where
+        F: IntoIterator<Item = (usize, DataType)>,
+    {
+        assert!(
+            !self.key.is_empty() && self.key_is_primary,
+            "delete operations can only be applied to base nodes with key columns"
+        );
+
+        self.quick_n_dirty(vec![TableOperation::DeleteWhere {
+            filter: filter.into_iter().collect(),
+        }])
+        .await
+    }
+
+    /// Update every row of this base table that matches `filter`.
+    ///
+    /// Rows are matched as in [`Table::delete_where`], and the modifications in `u` are applied to
+    /// each matching row as documented in [`Table::update`].
+    pub async fn update_where<F, V>(&mut self, filter: F, u: V) -> Result<WriteToken, TableError>
+    where
+        F: IntoIterator<Item = (usize, DataType)>,
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        assert!(
+            !self.key.is_empty() && self.key_is_primary,
+            "update operations can only be applied to base nodes with key columns"
+        );
+
+        let mut set = vec![Modification::None; self.columns.len()];
+        for (coli, m) in u {
+            if coli >= self.columns.len() {
+                return Err(TableError::WrongColumnCount(self.columns.len(), coli + 1));
+            }
+            set[coli] = m;
+        }
+
+        self.quick_n_dirty(vec![TableOperation::UpdateWhere {
+            filter: filter.into_iter().collect(),
+            set,
+        }])
+        .await
     }
 
     /// Perform a insert-or-update on this base table.
@@ -800,7 +1367,19 @@ impl Table {
         &mut self,
         insert: Vec<DataType>,
         update: V,
-    ) -> Result<(), TableError>
+    ) -> Result<WriteToken, TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        let op = self.insert_or_update_op(insert, update)?;
+        self.quick_n_dirty(vec![op]).await
+    }
+
+    fn insert_or_update_op<V>(
+        &self,
+        insert: Vec<DataType>,
+        update: V,
+    ) -> Result<TableOperation, TableError>
     where
         V: IntoIterator<Item = (usize, Modification)>,
     {
@@ -817,10 +1396,76 @@ impl Table {
             set[coli] = m;
         }
 
-        self.quick_n_dirty(vec![TableOperation::InsertOrUpdate {
+        Ok(TableOperation::InsertOrUpdate {
             row: insert,
             update: set,
-        }])
-        .await
+        })
+    }
+
+    /// Perform multiple operations on this base table, and report what each of them did.
+    ///
+    /// The returned outcomes are in the same order as the operations. Operations that are applied
+    /// to the same row see the effects of the ones that precede them, so for example an update
+    /// that follows an insert of the same key is `Updated`.
+    pub async fn perform_all_acked<I, V>(
+        &mut self,
+        i: I,
+    ) -> Result<(Vec<WriteOutcome>, WriteToken), TableError>
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<TableOperation>,
+    {
+        self.perform_acked(i.into_iter().map(Into::into).collect())
+            .await
+    }
+
+    /// Delete the row with the given key from this base table, and report whether it existed.
+    ///
+    /// The outcome is either [`WriteOutcome::Deleted`] or [`WriteOutcome::NotFound`].
+    pub async fn delete_acked<I>(
+        &mut self,
+        key: I,
+    ) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        I: Into<Vec<DataType>>,
+    {
+        let (mut outcomes, token) = self
+            .perform_acked(vec![TableOperation::Delete { key: key.into() }])
+            .await?;
+        Ok((outcomes.swap_remove(0), token))
+    }
+
+    /// Update the row with the given key in this base table, and report whether it existed.
+    ///
+    /// Unlike [`Table::update`], this tells an update of a missing row
+    /// ([`WriteOutcome::NotFound`]) apart from one that took effect ([`WriteOutcome::Updated`]),
+    /// which makes it possible to update a row only if it exists without reading it first.
+    pub async fn update_acked<V>(
+        &mut self,
+        key: Vec<DataType>,
+        u: V,
+    ) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        let op = self.update_op(key, u)?;
+        let (mut outcomes, token) = self.perform_acked(vec![op]).await?;
+        Ok((outcomes.swap_remove(0), token))
+    }
+
+    /// Perform a insert-or-update on this base table, and report which of the two happened.
+    ///
+    /// See [`Table::insert_or_update`].
+    pub async fn insert_or_update_acked<V>(
+        &mut self,
+        insert: Vec<DataType>,
+        update: V,
+    ) -> Result<(WriteOutcome, WriteToken), TableError>
+    where
+        V: IntoIterator<Item = (usize, Modification)>,
+    {
+        let op = self.insert_or_update_op(insert, update)?;
+        let (mut outcomes, token) = self.perform_acked(vec![op]).await?;
+        Ok((outcomes.swap_remove(0), token))
     }
 }
@@ -0,0 +1,129 @@
+use crate::data::*;
+use crate::table::{Table, TableError, WriteToken};
+use futures_util::stream::{futures_unordered::FuturesUnordered, TryStreamExt};
+use petgraph::graph::NodeIndex;
+use std::fmt;
+
+/// Identifies a transaction, and every shard of a base table that it writes to.
+#[doc(hidden)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Txn {
+    pub id: u64,
+    pub parts: Vec<(NodeIndex, usize)>,
+}
+
+/// A batch of operations across one or more base tables that is applied as a single unit.
+///
+/// Created by [`ControllerHandle::transaction`](crate::ControllerHandle::transaction). Once the
+/// transaction is committed, each view either reflects all of its operations, or none of them;
+/// this holds even for views that join several of the tables it writes to. To achieve this, a view
+/// holds back the rows that a transaction changes while it waits for its remaining parts, along
+/// with any later changes to rows with the same keys. Changes to other keys become visible as
+/// usual.
+///
+/// The operations on each base table are applied separately, so a transaction is only atomic as
+/// far as views are concerned. If committing fails part-way through (for example because a
+/// connection to one of the tables is lost), the parts that did make it stay applied at their base
+/// tables. Views never show them: they keep holding back the keys that the transaction changed,
+/// since the remaining parts will never arrive. A view that evicts one of those keys, and later
+/// reads it back in from the base tables, does see the parts that were applied.
+///
+/// ```rust,no_run
+/// # async fn post(mut c: noria::ControllerHandle<noria::ZookeeperAuthority>)
+/// #     -> Result<(), noria::error::TableError> {
+/// let stories = c.table("Story").await.unwrap();
+/// let comments = c.table("Comment").await.unwrap();
+///
+/// let mut txn = c.transaction();
+/// txn.insert(&stories, vec![1.into(), "Hello world".into()]);
+/// txn.insert(&comments, vec![1.into(), 1.into(), "First!".into()]);
+/// txn.commit().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Transaction {
+    ops: Vec<(Table, Vec<TableOperation>)>,
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.ops.iter().map(|(t, ops)| (t.table_name(), ops)))
+            .finish()
+    }
+}
+
+impl Transaction {
+    /// Add an operation on `table` to this transaction.
+    pub fn perform<O>(&mut self, table: &Table, op: O) -> &mut Self
+    where
+        O: Into<TableOperation>,
+    {
+        let base = table.base();
+        match self.ops.iter_mut().find(|(t, _)| t.base() == base) {
+            Some((_, ops)) => ops.push(op.into()),
+            None => self.ops.push((table.clone(), vec![op.into()])),
+        }
+        self
+    }
+
+    /// Add the insertion of a single row into `table` to this transaction.
+    pub fn insert<V>(&mut self, table: &Table, row: V) -> &mut Self
+    where
+        V: Into<Vec<DataType>>,
+    {
+        self.perform(table, TableOperation::Insert(row.into()))
+    }
+
+    /// Add the deletion of the row with the given key from `table` to this transaction.
+    pub fn delete<I>(&mut self, table: &Table, key: I) -> &mut Self
+    where
+        I: Into<Vec<DataType>>,
+    {
+        self.perform(table, TableOperation::Delete { key: key.into() })
+    }
+
+    /// Apply all the operations in this transaction.
+    ///
+    /// The returned [`WriteToken`] covers every operation in the transaction. Nothing is written
+    /// if any of the operations is malformed.
+    ///
+    /// Any other error means that the transaction may have been applied in part: the operations
+    /// on some of its tables may have been applied even though those on others were not. See the
+    /// [type-level documentation](Transaction) for what views show in that case.
+    pub async fn commit(mut self) -> Result<WriteToken, TableError> {
+        for (table, ops) in &mut self.ops {
+            for op in ops.iter_mut() {
+                table.coerce(op)?;
+            }
+            table.check(ops)?;
+        }
+
+        let parts = self
+            .ops
+            .iter()
+            .flat_map(|(table, ops)| {
+                let base = table.base();
+                table
+                    .shards_for(ops)
+                    .into_iter()
+                    .map(move |shard| (base, shard))
+            })
+            .collect();
+        let txn = Txn {
+            id: rand::random(),
+            parts,
+        };
+
+        self.ops
+            .iter_mut()
+            .map(|(table, ops)| table.perform_in(std::mem::take(ops), txn.clone()))
+            .collect::<FuturesUnordered<_>>()
+            .try_fold(WriteToken::default(), |mut token, t| async move {
+                token.merge(&t);
+                Ok(token)
+            })
+            .await
+    }
+}
@@ -0,0 +1,217 @@
+use crate::data::*;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// A failed conversion between a Rust type and the rows of a [`View`](crate::View) or
+/// [`Table`](crate::Table).
+#[derive(Debug, Fail)]
+pub enum RowError {
+    /// The Rust type has a field for a column that does not exist.
+    #[fail(display = "no column named '{}'", _0)]
+    NoSuchColumn(String),
+
+    /// A `NOT NULL` column without a default value was not given a value.
+    #[fail(
+        display = "column '{}' is declared NOT NULL, but was given no value",
+        _0
+    )]
+    MissingValue(String),
+
+    /// The value of a column could not be converted into the type of the corresponding field.
+    #[fail(
+        display = "column '{}' holds {:?}, which cannot be converted to {}",
+        column, value, expected
+    )]
+    TypeMismatch {
+        /// The name of the column.
+        column: String,
+        /// The value the column held.
+        value: DataType,
+        /// The name of the Rust type the value was to be converted into.
+        expected: &'static str,
+    },
+}
+
+/// A Rust type that can be constructed from a single row of a [`View`](crate::View).
+///
+/// Fields are matched up with the view's columns by name, so the mapping keeps working if the
+/// columns of the view are reordered. This trait is usually derived:
+///
+/// ```rust
+/// #[derive(noria::FromRow)]
+/// struct Story {
+///     id: i32,
+///     title: String,
+///     #[noria(rename = "user_id")]
+///     author: i32,
+/// }
+/// ```
+pub trait FromRow: Sized {
+    /// Construct a value from `row`, whose columns are named by `columns`.
+    fn from_row(columns: &[String], row: Vec<DataType>) -> Result<Self, RowError>;
+}
+
+/// A Rust type that can be turned into a row of a [`Table`](crate::Table).
+///
+/// Like [`FromRow`], this trait is usually derived, and maps fields to columns by name. Columns
+/// without a corresponding field are given their default value, or `NULL` if they have none.
+pub trait IntoRow {
+    /// The name and value of each column this value provides.
+    fn into_row(self) -> Vec<(&'static str, DataType)>;
+}
+
+/// A Rust type that a single [`DataType`] value can be converted into without loss.
+///
+/// Unlike the `From<DataType>` conversions, this conversion gives back the original value instead
+/// of panicking if the value does not fit the type.
+pub trait FromValue: Sized {
+    /// Convert `value` into `Self`, or return it unchanged if that is not possible.
+    fn from_value(value: DataType) -> Result<Self, DataType>;
+}
+
+impl FromValue for DataType {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        Ok(value)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        if value.is_none() {
+            Ok(None)
+        } else {
+            T::from_value(value).map(Some)
+        }
+    }
+}
+
+macro_rules! integer_from_value {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: DataType) -> Result<Self, DataType> {
+                let v = match value {
+                    DataType::Int(v) => <$t>::try_from(v).ok(),
+                    DataType::UnsignedInt(v) => <$t>::try_from(v).ok(),
+                    DataType::BigInt(v) => <$t>::try_from(v).ok(),
+                    DataType::UnsignedBigInt(v) => <$t>::try_from(v).ok(),
+                    _ => None,
+                };
+                v.ok_or(value)
+            }
+        }
+    )*};
+}
+
+integer_from_value!(i32, u32, i64, u64);
+
+impl FromValue for f64 {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Real(..)
+            | DataType::Double(..)
+            | DataType::Int(..)
+            | DataType::BigInt(..) => Ok((&value).into()),
+            _ => Err(value),
+        }
+    }
+}
+
+impl FromValue for BigDecimal {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        if value.is_decimal() || value.is_integer() {
+            Ok(value.into())
+        } else {
+            Err(value)
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Bool(b) => Ok(b),
+            DataType::Int(0) | DataType::BigInt(0) => Ok(false),
+            DataType::Int(1) | DataType::BigInt(1) => Ok(true),
+            _ => Err(value),
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        if value.is_bytes() {
+            Ok(value.into())
+        } else {
+            Err(value)
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        if value.is_string() {
+            Ok(<&str>::from(&value).to_owned())
+        } else {
+            Err(value)
+        }
+    }
+}
+
+impl FromValue for NaiveDateTime {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Timestamp(ts) => Ok(ts),
+            _ => Err(value),
+        }
+    }
+}
+
+impl FromValue for serde_json::Value {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Json(doc) => Ok(Arc::try_unwrap(doc).unwrap_or_else(|doc| (*doc).clone())),
+            _ => Err(value),
+        }
+    }
+}
+
+impl FromValue for NaiveDate {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Date(d) => Ok(d),
+            _ => Err(value),
+        }
+    }
+}
+
+impl FromValue for NaiveTime {
+    fn from_value(value: DataType) -> Result<Self, DataType> {
+        match value {
+            DataType::Time(t) => Ok(t),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Take the value of the column named `column` out of `row`, and convert it into a `T`.
+///
+/// Used by `#[derive(FromRow)]`.
+#[doc(hidden)]
+pub fn take_column<T: FromValue>(
+    columns: &[String],
+    row: &mut [DataType],
+    column: &str,
+) -> Result<T, RowError> {
+    let coli = columns
+        .iter()
+        .position(|c| c == column)
+        .ok_or_else(|| RowError::NoSuchColumn(column.to_owned()))?;
+    let value = std::mem::replace(&mut row[coli], DataType::None);
+    T::from_value(value).map_err(|value| RowError::TypeMismatch {
+        column: column.to_owned(),
+        value,
+        expected: std::any::type_name::<T>(),
+    })
+}
@@ -1,19 +1,27 @@
+use crate::controller::Refresh;
 use crate::data::*;
+use crate::filter::FilterCondition;
+use crate::table::WriteToken;
+use crate::typed::{FromRow, RowError};
 use crate::{Tagged, Tagger};
 use async_bincode::{AsyncBincodeStream, AsyncDestination};
 use futures_util::{
     future, future::TryFutureExt, ready, stream::futures_unordered::FuturesUnordered,
     stream::StreamExt, stream::TryStreamExt,
 };
-use nom_sql::ColumnSpecification;
+use nom_sql::{ColumnSpecification, OrderType};
 use petgraph::graph::NodeIndex;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
+use std::ops::{Bound, RangeBounds};
+use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio_tower::multiplex;
 use tower_balance::p2c::Balance;
 use tower_buffer::Buffer;
@@ -101,6 +109,27 @@ pub enum ViewError {
     /// The given view is not yet available.
     #[fail(display = "the view is not yet available")]
     NotYetAvailable,
+    /// The server ended a [`Subscription`].
+    ///
+    /// This happens if the subscriber fell too far behind, if the subscribed key was evicted, or
+    /// if the view went away.
+    #[fail(display = "the subscription was ended by the server")]
+    SubscriptionEnded,
+    /// The lookup did not complete within the timeout set with [`View::set_timeout`].
+    #[fail(display = "the lookup timed out")]
+    Timeout,
+    /// A filter given to [`View::lookup_filtered`] cannot be evaluated against this view.
+    #[fail(display = "invalid filter: {}", _0)]
+    InvalidFilter(String),
+    /// [`View::lookup_range`] was called on a view that cannot look up ranges of keys.
+    ///
+    /// Only views whose query compares a parameter with `<`, `<=`, `>` or `>=` can, and only if
+    /// their results can be computed from a single, unsharded, fully materialized source.
+    #[fail(display = "the view cannot look up ranges of keys")]
+    RangesUnsupported,
+    /// A row of the results could not be converted into the requested type.
+    #[fail(display = "{}", _0)]
+    RowError(#[cause] RowError),
     /// A lower-level error occurred while communicating with Soup.
     #[fail(display = "{}", _0)]
     TransportError(#[cause] failure::Error),
@@ -112,6 +141,10 @@ impl From<Box<dyn std::error::Error + Send + Sync>> for ViewError {
     }
 }
 
+/// A range of keys in a view, given as a (start, end) pair of bounds.
+#[doc(hidden)]
+pub type KeyRange = (Bound<Vec<DataType>>, Bound<Vec<DataType>>);
+
 #[doc(hidden)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ReadQuery {
@@ -123,12 +156,79 @@ pub enum ReadQuery {
         keys: Vec<Vec<DataType>>,
         /// Whether to block if a partial replay is triggered
         block: bool,
+        /// How long the read may block for
+        timeout: Option<Duration>,
+    },
+    /// Read all keys that fall within the given ranges from a leaf view
+    Range {
+        /// Where to read from
+        target: (NodeIndex, usize),
+        /// Key ranges to read
+        ranges: Vec<KeyRange>,
+        /// Whether to block if a partial replay is triggered
+        block: bool,
+        /// How long the read may block for
+        timeout: Option<Duration>,
+    },
+    /// Read from a leaf view once it reflects the given writes
+    After {
+        /// Where to read from
+        target: (NodeIndex, usize),
+        /// Keys to read with
+        keys: Vec<Vec<DataType>>,
+        /// The writes the view must reflect before it is read
+        token: WriteToken,
+        /// How long the read may block for
+        timeout: Option<Duration>,
+    },
+    /// Read one page of the rows for a key from a leaf view, always blocking on a miss
+    Page {
+        /// Where to read from
+        target: (NodeIndex, usize),
+        /// Key to read
+        key: Vec<DataType>,
+        /// The last row of the previous page, and how many copies of it have been read
+        after: Option<(Vec<DataType>, usize)>,
+        /// The most rows to return
+        limit: usize,
+        /// How long the read may block for
+        timeout: Option<Duration>,
+    },
+    /// Read the rows for a key that match a filter from a leaf view
+    Filtered {
+        /// Where to read from
+        target: (NodeIndex, usize),
+        /// Key to read
+        key: Vec<DataType>,
+        /// Conditions on the columns of the view that the returned rows must all satisfy
+        filter: Vec<(usize, FilterCondition)>,
+        /// Whether to block if a partial replay is triggered
+        block: bool,
+        /// How long the read may block for
+        timeout: Option<Duration>,
     },
     /// Read the size of a leaf view
     Size {
         /// Where to read from
         target: (NodeIndex, usize),
     },
+    /// Subscribe to the changes to a key in a leaf view
+    Subscribe {
+        /// Where to subscribe
+        target: (NodeIndex, usize),
+        /// Key to subscribe to
+        key: Vec<DataType>,
+    },
+    /// Wait for the next batch of changes for a subscription on this connection
+    Deltas {
+        /// Subscription to wait on
+        subscription: usize,
+    },
+    /// End a subscription on this connection
+    Unsubscribe {
+        /// Subscription to end
+        subscription: usize,
+    },
 }
 
 #[doc(hidden)]
@@ -138,6 +238,23 @@ pub enum ReadReply<D = ReadReplyBatch> {
     Normal(Result<Vec<D>, ()>),
     /// Read size of view
     Size(usize),
+    /// Identifies the new subscription, or errors if view isn't ready yet.
+    Subscribed(Result<usize, ()>),
+    /// The next batch of changes, or an error if the subscription has ended.
+    Deltas(Result<Vec<Delta>, ()>),
+    /// The subscription has been ended.
+    Unsubscribed,
+    /// A blocking read did not complete before its timeout.
+    TimedOut,
+}
+
+/// A single change to the results for a key that a [`Subscription`] is subscribed to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delta {
+    /// The row was added to the results.
+    Positive(Vec<DataType>),
+    /// The row was removed from the results.
+    Negative(Vec<DataType>),
 }
 
 #[doc(hidden)]
@@ -147,6 +264,31 @@ pub struct ViewBuilder {
     pub columns: Vec<String>,
     pub schema: Option<Vec<ColumnSpecification>>,
     pub shards: Vec<SocketAddr>,
+    /// The base tables the view is computed from, and whether each is sharded such that its
+    /// writes only reach the matching shard of the view.
+    pub bases: Vec<(NodeIndex, bool)>,
+    /// The columns of the query's `ORDER BY` clause, in the order in which rows are returned.
+    pub order: Vec<(usize, OrderType)>,
+    /// Whether the view can look up ranges of keys.
+    pub ranges: bool,
+}
+
+/// Compare two rows of a view by the view's `ORDER BY` columns.
+///
+/// Rows that compare equal on all of those columns are compared as a whole, so that any two
+/// distinct rows are ordered the same way on every shard and for every page.
+#[doc(hidden)]
+pub fn cmp_rows(order: &[(usize, OrderType)], a: &[DataType], b: &[DataType]) -> Ordering {
+    for &(c, ref order_type) in order {
+        let result = match *order_type {
+            OrderType::OrderAscending => a[c].cmp(&b[c]),
+            OrderType::OrderDescending => b[c].cmp(&a[c]),
+        };
+        if result != Ordering::Equal {
+            return result;
+        }
+    }
+    a.cmp(b)
 }
 
 impl ViewBuilder {
@@ -155,11 +297,15 @@ impl ViewBuilder {
     pub fn build(
         &self,
         rpcs: Arc<Mutex<HashMap<(SocketAddr, usize), ViewRpc>>>,
+        refresh: Option<Refresh<ViewBuilder>>,
     ) -> Result<View, io::Error> {
         let node = self.node;
         let columns = self.columns.clone();
         let shards = self.shards.clone();
         let schema = self.schema.clone();
+        let bases = self.bases.clone();
+        let order = self.order.clone();
+        let ranges = self.ranges;
 
         let mut addrs = Vec::with_capacity(shards.len());
         let mut conns = Vec::with_capacity(shards.len());
@@ -203,6 +349,12 @@ impl ViewBuilder {
             columns,
             shard_addrs: addrs,
             shards: conns,
+            bases,
+            order,
+            ranges,
+            rpcs,
+            refresh,
+            timeout: None,
             tracer,
         })
     }
@@ -220,6 +372,13 @@ pub struct View {
 
     shards: Vec<ViewRpc>,
     shard_addrs: Vec<SocketAddr>,
+    bases: Vec<(NodeIndex, bool)>,
+    order: Vec<(usize, OrderType)>,
+    ranges: bool,
+
+    rpcs: Arc<Mutex<HashMap<(SocketAddr, usize), ViewRpc>>>,
+    refresh: Option<Refresh<ViewBuilder>>,
+    timeout: Option<Duration>,
 
     tracer: tracing::Dispatch,
 }
@@ -230,6 +389,7 @@ impl fmt::Debug for View {
             .field("node", &self.node)
             .field("columns", &self.columns)
             .field("shard_addrs", &self.shard_addrs)
+            .field("timeout", &self.timeout)
             .finish()
     }
 }
@@ -237,6 +397,20 @@ impl fmt::Debug for View {
 pub(crate) mod results;
 use self::results::{Results, Row};
 
+// Run an operation on a view, and if it fails because the connection to the view broke, reconnect
+// to wherever the view now lives and run the operation once more.
+macro_rules! refreshing {
+    ($view:ident, $op:expr) => {
+        match $op {
+            Err(ViewError::TransportError(_)) if $view.refresh.is_some() => {
+                $view.refresh().await?;
+                $op
+            }
+            res => res,
+        }
+    };
+}
+
 impl Service<(Vec<Vec<DataType>>, bool)> for View {
     type Response = Vec<Results>;
     type Error = ViewError;
@@ -270,6 +444,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                 target: (self.node, 0),
                 keys,
                 block,
+                timeout: self.timeout,
             });
 
             let _guard = span.as_ref().map(tracing::Span::enter);
@@ -286,6 +461,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                                 .map(|rows| Results::new(rows.into(), Arc::clone(&columns)))
                                 .collect()),
                             ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+                            ReadReply::TimedOut => Err(ViewError::Timeout),
                             _ => unreachable!(),
                         }
                     }),
@@ -303,6 +479,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
         }
 
         let node = self.node;
+        let timeout = self.timeout;
         future::Either::Right(
             self.shards
                 .iter_mut()
@@ -324,6 +501,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                         target: (node, shardi),
                         keys: shard_queries,
                         block,
+                        timeout,
                     });
 
                     let _guard = span.as_ref().map(tracing::Span::enter);
@@ -343,6 +521,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                             match reply.v {
                                 ReadReply::Normal(Ok(rows)) => Ok(rows),
                                 ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+                                ReadReply::TimedOut => Err(ViewError::Timeout),
                                 _ => unreachable!(),
                             }
                         })
@@ -370,10 +549,28 @@ impl View {
         self.schema.as_deref()
     }
 
+    /// Set how long lookups on this view may block while waiting for missing state.
+    ///
+    /// A lookup that is still waiting once `timeout` has passed fails with
+    /// [`ViewError::Timeout`], and the server stops retrying it. With `None`, which is the
+    /// default, lookups wait for as long as it takes.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Get how long lookups on this view may block while waiting for missing state.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
     /// Get the current size of this view.
     ///
     /// Note that you must also continue to poll this `View` for the returned future to resolve.
     pub async fn len(&mut self) -> Result<usize, ViewError> {
+        refreshing!(self, self.try_len().await)
+    }
+
+    async fn try_len(&mut self) -> Result<usize, ViewError> {
         future::poll_fn(|cx| self.poll_ready(cx)).await?;
 
         let node = self.node;
@@ -405,17 +602,142 @@ impl View {
     /// The method will block if the results are not yet available only when `block` is `true`.
     /// If `block` is false, misses will be returned as empty results. Any requested keys that have
     /// missing state will be backfilled (asynchronously if `block` is `false`).
+    ///
+    /// If the connection to a shard breaks while the lookup is in flight, it fails with
+    /// [`ViewError::TransportError`], and can simply be retried.
     pub async fn multi_lookup(
         &mut self,
         keys: Vec<Vec<DataType>>,
         block: bool,
+    ) -> Result<Vec<Results>, ViewError> {
+        // the keys are moved into the request, so the lookup can only be retried if the broken
+        // connection is noticed before the request is sent, which is where it usually is. a
+        // lookup that is in flight when the connection breaks fails, but the view is still
+        // refreshed so that later lookups find its new shards.
+        if let Err(e) = future::poll_fn(|cx| self.poll_ready(cx)).await {
+            match e {
+                ViewError::TransportError(_) if self.refresh.is_some() => {
+                    self.refresh().await?;
+                    future::poll_fn(|cx| self.poll_ready(cx)).await?;
+                }
+                e => return Err(e),
+            }
+        }
+
+        match self.call((keys, block)).await {
+            Err(e @ ViewError::TransportError(_)) if self.refresh.is_some() => {
+                self.refresh().await?;
+                Err(e)
+            }
+            res => res,
+        }
+    }
+
+    /// Retrieve the query results for all keys that fall within each of the given ranges.
+    ///
+    /// Every shard of the view is queried for every range. If the view's query has an `ORDER BY`
+    /// clause, the rows for each range are returned in that order. Otherwise, the rows of each
+    /// shard's results are ordered by key, but rows from different shards are simply
+    /// concatenated.
+    ///
+    /// The method will block if the results are not yet available only when `block` is `true`.
+    /// If `block` is false, ranges that are not yet materialized will be returned as empty
+    /// results, and will be backfilled asynchronously.
+    ///
+    /// Fails with [`ViewError::RangesUnsupported`] if the view cannot look up ranges of keys.
+    pub async fn multi_lookup_range(
+        &mut self,
+        ranges: Vec<KeyRange>,
+        block: bool,
+    ) -> Result<Vec<Results>, ViewError> {
+        if !self.ranges {
+            return Err(ViewError::RangesUnsupported);
+        }
+        refreshing!(self, self.try_multi_lookup_range(&ranges, block).await)
+    }
+
+    async fn try_multi_lookup_range(
+        &mut self,
+        ranges: &[KeyRange],
+        block: bool,
     ) -> Result<Vec<Results>, ViewError> {
         future::poll_fn(|cx| self.poll_ready(cx)).await?;
-        self.call((keys, block)).await
+
+        let node = self.node;
+        let timeout = self.timeout;
+        let nranges = ranges.len();
+        let mut rsps = self
+            .shards
+            .iter_mut()
+            .enumerate()
+            .map(|(shardi, shard)| {
+                shard.call(Tagged::from(ReadQuery::Range {
+                    target: (node, shardi),
+                    ranges: ranges.to_vec(),
+                    block,
+                    timeout,
+                }))
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut rows = vec![Vec::new(); nranges];
+        while let Some(reply) = rsps.next().await.transpose()? {
+            match reply.v {
+                ReadReply::Normal(Ok(batches)) => {
+                    assert_eq!(batches.len(), nranges);
+                    for (rows, batch) in rows.iter_mut().zip(batches) {
+                        rows.extend(batch);
+                    }
+                }
+                ReadReply::Normal(Err(())) => return Err(ViewError::NotYetAvailable),
+                ReadReply::TimedOut => return Err(ViewError::Timeout),
+                _ => unreachable!(),
+            }
+        }
+
+        if self.shards.len() > 1 && !self.order.is_empty() {
+            // every shard's rows are already ordered, but they still need to be merged
+            let order = &self.order;
+            for rows in &mut rows {
+                rows.sort_by(|a, b| cmp_rows(order, a, b));
+            }
+        }
+
+        let columns = Arc::from(&self.columns[..]);
+        Ok(rows
+            .into_iter()
+            .map(|rows| Results::new(rows, Arc::clone(&columns)))
+            .collect())
+    }
+
+    /// Retrieve the query results for all keys that fall within the given range.
+    ///
+    /// For a view created from `SELECT * FROM Story WHERE created_at > ?`, the range
+    /// `(Bound::Excluded(vec![ts]), Bound::Unbounded)` yields all stories created after `ts`.
+    /// Fails with [`ViewError::RangesUnsupported`] if the view cannot look up ranges of keys.
+    ///
+    /// The method will block if the results are not yet available only when `block` is `true`.
+    pub async fn lookup_range<R>(&mut self, range: R, block: bool) -> Result<Results, ViewError>
+    where
+        R: RangeBounds<Vec<DataType>>,
+    {
+        fn owned(b: Bound<&Vec<DataType>>) -> Bound<Vec<DataType>> {
+            match b {
+                Bound::Included(k) => Bound::Included(k.clone()),
+                Bound::Excluded(k) => Bound::Excluded(k.clone()),
+                Bound::Unbounded => Bound::Unbounded,
+            }
+        }
+
+        let range = (owned(range.start_bound()), owned(range.end_bound()));
+        let rs = self.multi_lookup_range(vec![range], block).await?;
+        Ok(rs.into_iter().next().unwrap())
     }
 
     /// Retrieve the query results for the given parameter value.
     ///
+    /// If the view's query has an `ORDER BY` clause, the rows are returned in that order.
+    ///
     /// The method will block if the results are not yet available only when `block` is `true`.
     pub async fn lookup(&mut self, key: &[DataType], block: bool) -> Result<Results, ViewError> {
         // TODO: Optimized version of this function?
@@ -423,8 +745,31 @@ impl View {
         Ok(rs.into_iter().next().unwrap())
     }
 
+    /// Retrieve the query results for the given parameter value as values of type `T`.
+    ///
+    /// Each row is converted using [`FromRow`], which matches up the columns of this view with the
+    /// fields of `T` by name.
+    ///
+    /// The method will block if the results are not yet available only when `block` is `true`.
+    pub async fn lookup_typed<T>(
+        &mut self,
+        key: &[DataType],
+        block: bool,
+    ) -> Result<Vec<T>, ViewError>
+    where
+        T: FromRow,
+    {
+        let rs: Vec<Vec<DataType>> = self.lookup(key, block).await?.into();
+        rs.into_iter()
+            .map(|row| T::from_row(&self.columns, row))
+            .collect::<Result<_, _>>()
+            .map_err(ViewError::RowError)
+    }
+
     /// Retrieve the first query result for the given parameter value.
     ///
+    /// For a view whose query has an `ORDER BY` clause, this is the first row in that order.
+    ///
     /// The method will block if the results are not yet available only when `block` is `true`.
     pub async fn lookup_first(
         &mut self,
@@ -435,6 +780,433 @@ impl View {
         let rs = self.multi_lookup(vec![Vec::from(key)], block).await?;
         Ok(rs.into_iter().next().unwrap().into_iter().next())
     }
+
+    /// Retrieve the query results for the given parameter value that match `filter`.
+    ///
+    /// `filter` pairs the index of a column of this view with a condition on that column, and a
+    /// row is returned only if it satisfies every condition. The conditions are evaluated by the
+    /// view before the results are sent back, so only the matching rows are transferred. This
+    /// makes it possible to narrow down the results of an existing view without installing a new
+    /// query for every variation of the condition.
+    ///
+    /// ```rust,no_run
+    /// # async fn open_tickets(tickets: &mut noria::View) {
+    /// use noria::filter::{FilterCondition, Operator, Value};
+    /// // the view is SELECT id, status FROM Ticket WHERE project = ?
+    /// let open = FilterCondition::Comparison(Operator::Equal, Value::Constant("open".into()));
+    /// let rows = tickets
+    ///     .lookup_filtered(&[42.into()], &[(1, open)], true)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// The method will block if the results are not yet available only when `block` is `true`.
+    pub async fn lookup_filtered(
+        &mut self,
+        key: &[DataType],
+        filter: &[(usize, FilterCondition)],
+        block: bool,
+    ) -> Result<Results, ViewError> {
+        for (col, cond) in filter {
+            if *col >= self.columns.len() {
+                return Err(ViewError::InvalidFilter(format!("no column {}", col)));
+            }
+            cond.check(self.columns.len())
+                .map_err(ViewError::InvalidFilter)?;
+        }
+
+        refreshing!(self, self.try_lookup_filtered(key, filter, block).await)
+    }
+
+    async fn try_lookup_filtered(
+        &mut self,
+        key: &[DataType],
+        filter: &[(usize, FilterCondition)],
+        block: bool,
+    ) -> Result<Results, ViewError> {
+        let shard = if self.shards.len() == 1 {
+            0
+        } else {
+            assert_eq!(key.len(), 1);
+            crate::shard_by(&key[0], self.shards.len())
+        };
+
+        let node = self.node;
+        let timeout = self.timeout;
+        let rpc = &mut self.shards[shard];
+        future::poll_fn(|cx| rpc.poll_ready(cx)).await?;
+        let reply = rpc
+            .call(Tagged::from(ReadQuery::Filtered {
+                target: (node, shard),
+                key: Vec::from(key),
+                filter: Vec::from(filter),
+                block,
+                timeout,
+            }))
+            .await?;
+
+        match reply.v {
+            ReadReply::Normal(Ok(rows)) => {
+                let columns = Arc::from(&self.columns[..]);
+                let rows = rows.into_iter().next().unwrap();
+                Ok(Results::new(rows.into(), columns))
+            }
+            ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+            ReadReply::TimedOut => Err(ViewError::Timeout),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Retrieve the query results for the given parameter value once they reflect the writes
+    /// identified by `token`.
+    ///
+    /// The lookup waits until every write covered by `token` has propagated to the view. Writes to
+    /// base tables that this view does not depend on are ignored. The results always block on
+    /// missing state, like [`View::lookup`] does when `block` is `true`.
+    ///
+    /// Note that if a write reaches the view along more than one path through the data-flow, the
+    /// view may already reflect it once it has arrived along any one of them.
+    pub async fn lookup_after(
+        &mut self,
+        key: &[DataType],
+        token: &WriteToken,
+    ) -> Result<Results, ViewError> {
+        refreshing!(self, self.try_lookup_after(key, token).await)
+    }
+
+    async fn try_lookup_after(
+        &mut self,
+        key: &[DataType],
+        token: &WriteToken,
+    ) -> Result<Results, ViewError> {
+        let shard = if self.shards.len() == 1 {
+            0
+        } else {
+            assert_eq!(key.len(), 1);
+            crate::shard_by(&key[0], self.shards.len())
+        };
+
+        // only wait for writes that can actually reach this shard of the view
+        let mut token = token.clone();
+        let bases = &self.bases;
+        token.retain(|base, base_shard| {
+            bases
+                .iter()
+                .any(|&(b, aligned)| b == base && (!aligned || base_shard == shard))
+        });
+
+        let node = self.node;
+        let timeout = self.timeout;
+        let rpc = &mut self.shards[shard];
+        future::poll_fn(|cx| rpc.poll_ready(cx)).await?;
+        let reply = rpc
+            .call(Tagged::from(ReadQuery::After {
+                target: (node, shard),
+                keys: vec![Vec::from(key)],
+                token,
+                timeout,
+            }))
+            .await?;
+
+        match reply.v {
+            ReadReply::Normal(Ok(rows)) => {
+                let columns = Arc::from(&self.columns[..]);
+                let rows = rows.into_iter().next().unwrap();
+                Ok(Results::new(rows.into(), columns))
+            }
+            ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+            ReadReply::TimedOut => Err(ViewError::Timeout),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reconnect to the shards of this view, wherever they currently are.
+    ///
+    /// The shards of a view move if the worker that holds them fails. Lookups already call this
+    /// method, and then retry once, if the connection to a shard breaks, so it is rarely necessary
+    /// to call it directly. The exception is [`View::multi_lookup`] and the methods built on it,
+    /// which only retry if the connection broke before the lookup was sent.
+    pub async fn refresh(&mut self) -> Result<(), ViewError> {
+        let refresh = match self.refresh {
+            Some(ref refresh) => Arc::clone(refresh),
+            None => return Ok(()),
+        };
+        let builder = refresh()
+            .await
+            .map_err(ViewError::TransportError)?
+            .ok_or_else(|| ViewError::TransportError(failure::err_msg("the view went away")))?;
+
+        // the connections we have may be broken, so don't let anyone pick them up again
+        {
+            let mut rpcs = self.rpcs.lock().unwrap();
+            for (shardi, &addr) in self.shard_addrs.iter().enumerate() {
+                rpcs.remove(&(addr, shardi));
+            }
+        }
+
+        let timeout = self.timeout;
+        *self = builder
+            .build(Arc::clone(&self.rpcs), Some(refresh))
+            .map_err(|e| ViewError::TransportError(e.into()))?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Retrieve the query results for the given parameter value a page of `page_size` rows at a
+    /// time.
+    ///
+    /// Rather than sending all the results at once, the server sends one page for every item the
+    /// returned [`Pages`] yields. Rows are returned in the order given by the query's `ORDER BY`
+    /// clause, and in a stable order beyond that, so each row is returned exactly once as long as
+    /// the results do not change while paging through them. Like
+    /// [`View::lookup_after`], the lookups always block on missing state.
+    ///
+    /// Panics if `page_size` is zero.
+    pub fn lookup_paged(&mut self, key: &[DataType], page_size: usize) -> Pages {
+        assert_ne!(page_size, 0, "pages must hold at least one row");
+        let shard = if self.shards.len() == 1 {
+            0
+        } else {
+            assert_eq!(key.len(), 1);
+            crate::shard_by(&key[0], self.shards.len())
+        };
+
+        Pages {
+            rpc: self.shards[shard].clone(),
+            target: (self.node, shard),
+            key: Vec::from(key),
+            page_size,
+            timeout: self.timeout,
+            columns: Arc::from(&self.columns[..]),
+            after: None,
+            next: None,
+            ended: false,
+        }
+    }
+
+    /// Subscribe to the changes to the query results for the given parameter value.
+    ///
+    /// The returned [`Subscription`] first yields the current results for `key` as positive
+    /// deltas, and then a batch of deltas for every write that changes them. Applying every batch
+    /// in order thus always gives the current results. If `key` is not yet materialized, it is
+    /// backfilled, and the rows it is filled with are yielded as they arrive.
+    ///
+    /// Every subscription uses its own connection to the server. If the subscriber does not keep
+    /// up with the writes to `key`, the server ends the subscription, and the stream yields
+    /// [`ViewError::SubscriptionEnded`].
+    pub async fn subscribe(&mut self, key: Vec<DataType>) -> Result<Subscription, ViewError> {
+        let shard = if self.shards.len() == 1 {
+            0
+        } else {
+            assert_eq!(key.len(), 1);
+            crate::shard_by(&key[0], self.shards.len())
+        };
+
+        let conn = Endpoint(self.shard_addrs[shard])
+            .call(())
+            .await
+            .map_err(|e| ViewError::TransportError(e.into()))?;
+        let (mut conn, w) = Buffer::pair(conn, 1);
+        tokio::spawn(w);
+
+        future::poll_fn(|cx| conn.poll_ready(cx)).await?;
+        let reply = conn
+            .call(Tagged::from(ReadQuery::Subscribe {
+                target: (self.node, shard),
+                key,
+            }))
+            .await?;
+        match reply.v {
+            ReadReply::Subscribed(Ok(id)) => Ok(Subscription {
+                conn,
+                id,
+                next: None,
+                ended: false,
+            }),
+            ReadReply::Subscribed(Err(())) => Err(ViewError::NotYetAvailable),
+            _ => unreachable!(),
+        }
+    }
+}
+
+type SubscriptionRpc = Buffer<InnerService, Tagged<ReadQuery>>;
+type ReplyFuture = Pin<Box<dyn Future<Output = Result<Tagged<ReadReply>, ViewError>> + Send>>;
+
+/// A stream of the changes to the results for a single key of a [`View`].
+///
+/// Created by [`View::subscribe`]. Dropping the `Subscription` ends it.
+pub struct Subscription {
+    conn: SubscriptionRpc,
+    id: usize,
+    next: Option<ReplyFuture>,
+    ended: bool,
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .field("ended", &self.ended)
+            .finish()
+    }
+}
+
+impl Subscription {
+    /// End the subscription, and wait for the server to acknowledge it.
+    pub async fn unsubscribe(mut self) -> Result<(), ViewError> {
+        self.next = None;
+        future::poll_fn(|cx| self.conn.poll_ready(cx)).await?;
+        self.conn
+            .call(Tagged::from(ReadQuery::Unsubscribe {
+                subscription: self.id,
+            }))
+            .await?;
+        Ok(())
+    }
+}
+
+impl futures_util::stream::Stream for Subscription {
+    type Item = Result<Vec<Delta>, ViewError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.ended {
+            return Poll::Ready(None);
+        }
+
+        if this.next.is_none() {
+            if let Err(e) = ready!(this.conn.poll_ready(cx)) {
+                this.ended = true;
+                return Poll::Ready(Some(Err(ViewError::from(e))));
+            }
+            let request = Tagged::from(ReadQuery::Deltas {
+                subscription: this.id,
+            });
+            this.next = Some(Box::pin(this.conn.call(request).map_err(ViewError::from)));
+        }
+
+        let reply = ready!(this.next.as_mut().unwrap().as_mut().poll(cx));
+        this.next = None;
+        Poll::Ready(Some(match reply {
+            Ok(Tagged {
+                v: ReadReply::Deltas(Ok(deltas)),
+                ..
+            }) => Ok(deltas),
+            Ok(Tagged {
+                v: ReadReply::Deltas(Err(())),
+                ..
+            }) => {
+                this.ended = true;
+                Err(ViewError::SubscriptionEnded)
+            }
+            Ok(_) => unreachable!(),
+            Err(e) => {
+                this.ended = true;
+                Err(e)
+            }
+        }))
+    }
+}
+
+/// A stream of pages of the results for a single key of a [`View`].
+///
+/// Created by [`View::lookup_paged`]. Each page is fetched from the server only once the previous
+/// one has been consumed, and the stream ends after the first page that is not full.
+pub struct Pages {
+    rpc: ViewRpc,
+    target: (NodeIndex, usize),
+    key: Vec<DataType>,
+    page_size: usize,
+    timeout: Option<Duration>,
+    columns: Arc<[String]>,
+    after: Option<(Vec<DataType>, usize)>,
+    next: Option<ReplyFuture>,
+    ended: bool,
+}
+
+impl fmt::Debug for Pages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pages")
+            .field("target", &self.target)
+            .field("key", &self.key)
+            .field("page_size", &self.page_size)
+            .field("timeout", &self.timeout)
+            .field("after", &self.after)
+            .field("ended", &self.ended)
+            .finish()
+    }
+}
+
+impl futures_util::stream::Stream for Pages {
+    type Item = Result<Results, ViewError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.ended {
+            return Poll::Ready(None);
+        }
+
+        if this.next.is_none() {
+            if let Err(e) = ready!(this.rpc.poll_ready(cx)) {
+                this.ended = true;
+                return Poll::Ready(Some(Err(ViewError::from(e))));
+            }
+            let request = Tagged::from(ReadQuery::Page {
+                target: this.target,
+                key: this.key.clone(),
+                after: this.after.clone(),
+                limit: this.page_size,
+                timeout: this.timeout,
+            });
+            this.next = Some(Box::pin(this.rpc.call(request).map_err(ViewError::from)));
+        }
+
+        let reply = ready!(this.next.as_mut().unwrap().as_mut().poll(cx));
+        this.next = None;
+        let rows: Vec<Vec<DataType>> = match reply {
+            Ok(Tagged {
+                v: ReadReply::Normal(Ok(batches)),
+                ..
+            }) => batches.into_iter().next().unwrap().into(),
+            Ok(Tagged {
+                v: ReadReply::Normal(Err(())),
+                ..
+            }) => {
+                this.ended = true;
+                return Poll::Ready(Some(Err(ViewError::NotYetAvailable)));
+            }
+            Ok(Tagged {
+                v: ReadReply::TimedOut,
+                ..
+            }) => {
+                // the cursor is unchanged, so polling again retries the same page
+                return Poll::Ready(Some(Err(ViewError::Timeout)));
+            }
+            Ok(_) => unreachable!(),
+            Err(e) => {
+                this.ended = true;
+                return Poll::Ready(Some(Err(e)));
+            }
+        };
+
+        if rows.len() < this.page_size {
+            this.ended = true;
+        }
+        let last = match rows.last() {
+            Some(last) => last,
+            None => return Poll::Ready(None),
+        };
+
+        // move the cursor past this page, counting the copies of its last row that we have seen
+        let copies = rows.iter().rev().take_while(|&r| r == last).count();
+        this.after = match this.after.take() {
+            Some((prev, n)) if &prev == last => Some((prev, n + copies)),
+            _ => Some((last.clone(), copies)),
+        };
+
+        Poll::Ready(Some(Ok(Results::new(rows, Arc::clone(&this.columns)))))
+    }
 }
 
 #[derive(Debug, Default)]
@@ -22,6 +22,11 @@ impl SizeOf for DataType {
 
         let inner = match *self {
             DataType::Text(ref t) => size_of_val(t) as u64 + t.to_bytes().len() as u64,
+            DataType::ByteArray(ref b) => size_of_val(&**b) as u64 + b.len() as u64,
+            // an approximation: a decimal stores a little more than three bits per digit
+            DataType::Decimal(ref d) => size_of_val(&**d) as u64 + d.digits() / 2,
+            // another approximation: the size of the document's JSON text
+            DataType::Json(ref doc) => size_of_val(&**doc) as u64 + doc.to_string().len() as u64,
             _ => 0u64,
         };
 
@@ -1,33 +1,57 @@
+use crate::ops::filter::FilterCondition;
 use crate::prelude::*;
 use ahash::RandomState;
 use common::SizeOf;
+use nom_sql::OrderType;
+use noria::KeyRange;
 use rand::prelude::*;
 use std::borrow::Cow;
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock};
+
+type Trigger = Arc<dyn Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + Send + Sync>;
+type RangeTrigger = Arc<dyn Fn(&KeyRange) -> bool + Send + Sync>;
+
+/// Channel on which a subscriber is sent the deltas applied to the key it subscribed to.
+pub type Subscriber = tokio::sync::mpsc::Sender<Vec<Record>>;
 
 /// Allocate a new end-user facing result table.
-pub(crate) fn new(cols: usize, key: &[usize]) -> (SingleReadHandle, WriteHandle) {
-    new_inner(cols, key, None)
+///
+/// Range lookups are only supported if `ranges` is set, since they need the keys kept in order.
+pub(crate) fn new(cols: usize, key: &[usize], ranges: bool) -> (SingleReadHandle, WriteHandle) {
+    new_inner(cols, key, ranges, None)
 }
 
 /// Allocate a new partially materialized end-user facing result table.
 ///
 /// Misses in this table will call `trigger` to populate the entry, and retry until successful.
-pub(crate) fn new_partial<F>(
+/// If `ranges` is set, range lookups that are not covered by a previously filled range will call
+/// `range_trigger` to populate the entire range in the same way.
+pub(crate) fn new_partial<F, G>(
     cols: usize,
     key: &[usize],
+    ranges: bool,
     trigger: F,
+    range_trigger: G,
 ) -> (SingleReadHandle, WriteHandle)
 where
     F: Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + 'static + Send + Sync,
+    G: Fn(&KeyRange) -> bool + 'static + Send + Sync,
 {
-    new_inner(cols, key, Some(Arc::new(trigger)))
+    new_inner(
+        cols,
+        key,
+        ranges,
+        Some((Arc::new(trigger), Arc::new(range_trigger))),
+    )
 }
 
 fn new_inner(
     cols: usize,
     key: &[usize],
-    trigger: Option<Arc<dyn Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + Send + Sync>>,
+    ranges: bool,
+    triggers: Option<(Trigger, RangeTrigger)>,
 ) -> (SingleReadHandle, WriteHandle) {
     let contiguous = {
         let mut contiguous = true;
@@ -63,6 +87,18 @@ fn new_inner(
         _ => make!(Many),
     };
 
+    let index = if ranges {
+        Some(Arc::new(RwLock::new(RangeIndex::default())))
+    } else {
+        None
+    };
+    let subscriptions = Arc::new(Mutex::new(Subscriptions::default()));
+    let seen = Arc::new(RwLock::new(WriteToken::default()));
+    let (trigger, range_trigger) = match triggers {
+        Some((trigger, range_trigger)) => (Some(trigger), Some(range_trigger).filter(|_| ranges)),
+        None => (None, None),
+    };
+
     let w = WriteHandle {
         partial: trigger.is_some(),
         handle: w,
@@ -70,16 +106,129 @@ fn new_inner(
         cols,
         contiguous,
         mem_size: 0,
+        index: index.clone(),
+        index_size: 0,
+        touched: HashSet::new(),
+        filled: Vec::new(),
+        filled_changed: false,
+        subscriptions: Arc::clone(&subscriptions),
+        deltas: HashMap::new(),
+        writes: WriteToken::default(),
+        writes_changed: false,
+        seen: Arc::clone(&seen),
     };
     let r = SingleReadHandle {
         handle: r,
         trigger,
+        range_trigger,
+        index,
+        subscriptions,
+        seen,
         key: Vec::from(key),
+        order: Vec::new(),
+        parameter_filter: Vec::new(),
     };
 
     (r, w)
 }
 
+/// An ordered index over the keys that currently have records in a reader.
+///
+/// The evmap that holds the records is hash-based, so range lookups first find the matching keys
+/// here, and then look each one up in the map. For partially materialized readers, `filled` holds
+/// the key ranges that have been replayed in their entirety; only ranges covered by one of those
+/// can be answered without triggering a replay.
+///
+/// Keeping the index costs memory and time on every swap, so a reader only has one if its query
+/// looks up ranges.
+#[derive(Debug, Default)]
+struct RangeIndex {
+    keys: BTreeSet<Vec<DataType>>,
+    filled: Vec<KeyRange>,
+}
+
+/// The subscribers to individual keys of a reader.
+///
+/// A subscriber must see exactly the records that were added to its key after the rows it was
+/// first sent. Those initial rows are read from the published state, so a subscriber can only join
+/// right away if no records have been added since the last swap. Otherwise, it joins on the next
+/// swap, which then sends it its initial rows.
+#[derive(Default)]
+struct Subscriptions {
+    /// True if records have been added since the last swap.
+    dirty: bool,
+    by_key: HashMap<Vec<DataType>, Vec<Subscriber>>,
+    joining: Vec<(Vec<DataType>, Subscriber)>,
+}
+
+/// Send `deltas` to all of `subscribers`, dropping any that have gone away or fallen behind.
+fn publish(subscribers: &mut Vec<Subscriber>, deltas: &[Record]) {
+    let mut i = 0;
+    while i < subscribers.len() {
+        if subscribers[i].try_send(deltas.to_vec()).is_err() {
+            // either the subscriber has gone away, or it has so many deltas buffered that it
+            // clearly isn't keeping up. in both cases, dropping our end lets it know that its
+            // subscription has ended.
+            subscribers.swap_remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn starts_before(a: &Bound<Vec<DataType>>, b: &Bound<Vec<DataType>>) -> bool {
+    match (a, b) {
+        (Bound::Unbounded, _) => true,
+        (_, Bound::Unbounded) => false,
+        (Bound::Excluded(a), Bound::Included(b)) => a < b,
+        (Bound::Included(a), Bound::Included(b))
+        | (Bound::Included(a), Bound::Excluded(b))
+        | (Bound::Excluded(a), Bound::Excluded(b)) => a <= b,
+    }
+}
+
+fn ends_after(a: &Bound<Vec<DataType>>, b: &Bound<Vec<DataType>>) -> bool {
+    match (a, b) {
+        (Bound::Unbounded, _) => true,
+        (_, Bound::Unbounded) => false,
+        (Bound::Excluded(a), Bound::Included(b)) => a > b,
+        (Bound::Included(a), Bound::Included(b))
+        | (Bound::Included(a), Bound::Excluded(b))
+        | (Bound::Excluded(a), Bound::Excluded(b)) => a >= b,
+    }
+}
+
+/// Returns true if every key in `inner` is also in `outer`.
+fn range_covers(outer: &KeyRange, inner: &KeyRange) -> bool {
+    starts_before(&outer.0, &inner.0) && ends_after(&outer.1, &inner.1)
+}
+
+/// Returns true if `key` falls within `range`.
+fn range_contains(range: &KeyRange, key: &[DataType]) -> bool {
+    let after_start = match range.0 {
+        Bound::Included(ref s) => &s[..] <= key,
+        Bound::Excluded(ref s) => &s[..] < key,
+        Bound::Unbounded => true,
+    };
+    let before_end = match range.1 {
+        Bound::Included(ref e) => key <= &e[..],
+        Bound::Excluded(ref e) => key < &e[..],
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
+
+/// Returns true if no key can fall within `range`.
+pub(crate) fn range_is_empty(range: &KeyRange) -> bool {
+    match *range {
+        (Bound::Included(ref s), Bound::Included(ref e)) => s > e,
+        (Bound::Included(ref s), Bound::Excluded(ref e))
+        | (Bound::Excluded(ref s), Bound::Included(ref e))
+        | (Bound::Excluded(ref s), Bound::Excluded(ref e)) => s >= e,
+        _ => false,
+    }
+}
+
 mod multir;
 mod multiw;
 
@@ -111,6 +260,26 @@ pub(crate) struct WriteHandle {
     key: Vec<usize>,
     contiguous: bool,
     mem_size: usize,
+
+    index: Option<Arc<RwLock<RangeIndex>>>,
+    /// Bytes used by the keys in `index`.
+    index_size: u64,
+    /// Keys whose presence in `index` must be re-checked on the next `swap()`.
+    touched: HashSet<Vec<DataType>>,
+    /// Filled ranges, as of the latest write. Published to `index` on `swap()`.
+    filled: Vec<KeyRange>,
+    filled_changed: bool,
+
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    /// Deltas for subscribed keys, as of the latest write. Sent to subscribers on `swap()`.
+    deltas: HashMap<Vec<DataType>, Vec<Record>>,
+
+    /// Base table writes reflected by the records added so far.
+    writes: WriteToken,
+    /// Whether `writes` has changed since the last `swap()`.
+    writes_changed: bool,
+    /// Base table writes reflected by the records visible to readers.
+    seen: Arc<RwLock<WriteToken>>,
 }
 
 type Key<'a> = Cow<'a, [DataType]>;
@@ -130,6 +299,7 @@ impl<'a> MutWriteHandleEntry<'a> {
             .handle
             .meta_get_and(Cow::Borrowed(&*self.key), |rs| rs.is_empty())
         {
+            self.handle.touch(&self.key);
             self.handle.handle.clear(self.key)
         } else {
             unreachable!("attempted to fill already-filled key");
@@ -146,6 +316,9 @@ impl<'a> MutWriteHandleEntry<'a> {
             .map(|r| r.0.unwrap_or(0))
             .unwrap_or(0);
         self.handle.mem_size = self.handle.mem_size.checked_sub(size as usize).unwrap();
+        self.handle.unfill(&self.key);
+        self.handle.unsubscribe(&self.key);
+        self.handle.touch(&self.key);
         self.handle.handle.empty(self.key)
     }
 }
@@ -229,7 +402,132 @@ impl WriteHandle {
     }
 
     pub(crate) fn swap(&mut self) {
+        // hold the subscriptions lock across the refresh so that no subscriber can join between
+        // the refresh and us sending out the deltas that it made visible.
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        // hold the index lock across the refresh so that range lookups never see a key in the
+        // index that the map does not (yet) have, or the other way around.
+        let mut index = self.index.as_ref().map(|index| index.write().unwrap());
         self.handle.refresh();
+        if let Some(ref mut index) = index {
+            for key in self.touched.drain() {
+                let present = self
+                    .handle
+                    .meta_get_and(Cow::Borrowed(&key[..]), |rs| !rs.is_empty())
+                    .and_then(|(present, _)| present)
+                    .unwrap_or(false);
+                if present {
+                    let size = key.deep_size_of();
+                    if index.keys.insert(key) {
+                        self.index_size += size;
+                    }
+                } else if let Some(key) = index.keys.take(&key) {
+                    self.index_size = self.index_size.checked_sub(key.deep_size_of()).unwrap();
+                }
+            }
+            if self.filled_changed {
+                index.filled = self.filled.clone();
+                self.filled_changed = false;
+            }
+        }
+        if self.writes_changed {
+            *self.seen.write().unwrap() = self.writes.clone();
+            self.writes_changed = false;
+        }
+
+        let Subscriptions {
+            ref mut dirty,
+            ref mut by_key,
+            ref mut joining,
+        } = *subscriptions;
+        *dirty = false;
+        for (key, deltas) in self.deltas.drain() {
+            if let Some(subscribers) = by_key.get_mut(&key) {
+                publish(subscribers, &deltas[..]);
+                if subscribers.is_empty() {
+                    by_key.remove(&key);
+                }
+            }
+        }
+        for (key, mut subscriber) in joining.drain(..) {
+            let rows = self
+                .handle
+                .meta_get_and(Cow::Borrowed(&key[..]), |rs| {
+                    rs.iter().cloned().map(Record::Positive).collect()
+                })
+                .and_then(|(rows, _)| rows)
+                .unwrap_or_else(Vec::new);
+            if subscriber.try_send(rows).is_ok() {
+                by_key.entry(key).or_insert_with(Vec::new).push(subscriber);
+            }
+        }
+    }
+
+    /// Note that the records added since the last `swap()` reflect the base table writes in
+    /// `token`.
+    pub(crate) fn add_writes(&mut self, token: &WriteToken) {
+        if !self.writes.covers(token) {
+            self.writes.merge(token);
+            self.writes_changed = true;
+        }
+    }
+
+    /// Note that `key` may have gained or lost its records, if the range index needs to know.
+    fn touch(&mut self, key: &[DataType]) {
+        if self.index.is_some() {
+            self.touched.insert(key.to_vec());
+        }
+    }
+
+    /// Mark every key in `range` as filled.
+    ///
+    /// Any key in the range that has no records after the next `swap()` is then known to be
+    /// empty, rather than a hole.
+    pub(crate) fn mark_range_filled(&mut self, range: KeyRange) {
+        assert!(self.partial);
+        if !self.range_is_filled(&range) {
+            self.filled.push(range);
+            self.filled_changed = true;
+        }
+    }
+
+    /// Returns true if every key in `range` falls within a range that has been filled.
+    pub(crate) fn range_is_filled(&self, range: &KeyRange) -> bool {
+        self.filled.iter().any(|f| range_covers(f, range))
+    }
+
+    /// Returns true if `key` falls within a range that has been filled.
+    pub(crate) fn in_filled_range(&self, key: &[DataType]) -> bool {
+        self.filled.iter().any(|range| range_contains(range, key))
+    }
+
+    /// Returns true if the key of `record` falls within a range that has been filled.
+    pub(crate) fn record_in_filled_range(&self, record: &[DataType]) -> bool {
+        if self.filled.is_empty() {
+            return false;
+        }
+        let key = key_from_record(&self.key[..], self.contiguous, record);
+        self.in_filled_range(&key)
+    }
+
+    /// End all subscriptions to `key`, since `key` is now a hole.
+    ///
+    /// The subscribers would otherwise miss any updates to the key until it is filled again, at
+    /// which point they would be sent all of its rows a second time.
+    fn unsubscribe(&mut self, key: &[DataType]) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.dirty = true;
+        subscriptions.by_key.remove(key);
+        self.deltas.remove(key);
+    }
+
+    /// Forget about any filled range that `key` falls within, since `key` is now a hole.
+    fn unfill(&mut self, key: &[DataType]) {
+        let had = self.filled.len();
+        self.filled.retain(|range| !range_contains(range, key));
+        if self.filled.len() != had {
+            self.filled_changed = true;
+        }
     }
 
     /// Add a new set of records to the backlog.
@@ -239,7 +537,32 @@ impl WriteHandle {
     where
         I: IntoIterator<Item = Record>,
     {
-        let mem_delta = self.handle.add(&self.key[..], self.cols, rs);
+        let WriteHandle {
+            ref mut handle,
+            ref index,
+            ref mut touched,
+            ref mut deltas,
+            ref subscriptions,
+            ref key,
+            contiguous,
+            cols,
+            ..
+        } = *self;
+        let mut subscriptions = subscriptions.lock().unwrap();
+        subscriptions.dirty = true;
+        let rs = rs.into_iter().inspect(|r| {
+            let key = key_from_record(&key[..], contiguous, &r[..]).into_owned();
+            if subscriptions.by_key.contains_key(&key) {
+                deltas
+                    .entry(key.clone())
+                    .or_insert_with(Vec::new)
+                    .push(r.clone());
+            }
+            if index.is_some() {
+                touched.insert(key);
+            }
+        });
+        let mem_delta = handle.add(&key[..], cols, rs);
         if mem_delta > 0 {
             self.mem_size += mem_delta as usize;
         } else if mem_delta < 0 {
@@ -263,11 +586,18 @@ impl WriteHandle {
                 unreachable!("mem size is {}, but map is empty", self.mem_size);
             }
 
-            self.handle.empty_random_for_each(rng, n, |vs| {
+            let mut evicted = Vec::new();
+            self.handle.empty_random_for_each(rng, n, |key, vs| {
                 let size: u64 = vs.iter().map(|r| r.deep_size_of() as u64).sum();
                 bytes_to_be_freed += size;
+                evicted.push(key);
                 n -= 1;
             });
+            for key in evicted {
+                self.unfill(&key);
+                self.unsubscribe(&key);
+                self.touch(&key);
+            }
         }
 
         self.mem_size = self
@@ -286,7 +616,7 @@ impl SizeOf for WriteHandle {
     }
 
     fn deep_size_of(&self) -> u64 {
-        self.mem_size as u64
+        self.mem_size as u64 + self.index_size
     }
 
     fn is_empty(&self) -> bool {
@@ -298,8 +628,14 @@ impl SizeOf for WriteHandle {
 #[derive(Clone)]
 pub struct SingleReadHandle {
     handle: multir::Handle,
-    trigger: Option<Arc<dyn Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + Send + Sync>>,
+    trigger: Option<Trigger>,
+    range_trigger: Option<RangeTrigger>,
+    index: Option<Arc<RwLock<RangeIndex>>>,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+    seen: Arc<RwLock<WriteToken>>,
     key: Vec<usize>,
+    order: Vec<(usize, OrderType)>,
+    parameter_filter: Vec<(usize, FilterCondition)>,
 }
 
 impl std::fmt::Debug for SingleReadHandle {
@@ -308,6 +644,8 @@ impl std::fmt::Debug for SingleReadHandle {
             .field("handle", &self.handle)
             .field("has_trigger", &self.trigger.is_some())
             .field("key", &self.key)
+            .field("order", &self.order)
+            .field("parameter_filter", &self.parameter_filter)
             .finish()
     }
 }
@@ -329,6 +667,16 @@ impl SingleReadHandle {
         (*self.trigger.as_ref().unwrap())(&mut it)
     }
 
+    /// Trigger a replay of a missing key range from a partially materialized view.
+    pub fn trigger_range(&self, range: &KeyRange) -> bool {
+        assert!(
+            self.range_trigger.is_some(),
+            "tried to trigger a range replay for a fully materialized view or one without ranges"
+        );
+
+        (*self.range_trigger.as_ref().unwrap())(range)
+    }
+
     /// Find all entries that matched the given conditions.
     ///
     /// Returned records are passed to `then` before being returned.
@@ -352,6 +700,104 @@ impl SingleReadHandle {
             })
     }
 
+    /// Find all entries whose key falls within `range`, in key order.
+    ///
+    /// Records for each key are passed to `then` before being returned.
+    ///
+    /// Ranges in partially materialized state that have not been filled in their entirety are
+    /// returned as `Ok((None, _))`. Panics if the reader does not serve range lookups.
+    pub fn try_find_range_and<F, T>(
+        &self,
+        range: &KeyRange,
+        mut then: F,
+    ) -> Result<(Option<Vec<T>>, i64), ()>
+    where
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        let index = self
+            .index
+            .as_ref()
+            .expect("range lookup on a reader without ranges")
+            .read()
+            .unwrap();
+        if self.trigger.is_some() && !index.filled.iter().any(|f| range_covers(f, range)) {
+            return self.handle.meta().map(|meta| (None, meta)).ok_or(());
+        }
+
+        if range_is_empty(range) {
+            return self
+                .handle
+                .meta()
+                .map(|meta| (Some(Vec::new()), meta))
+                .ok_or(());
+        }
+
+        self.handle
+            .meta_get_many_and(index.keys.range(range.clone()), &mut then)
+            .ok_or(())
+            .map(|(records, meta)| (Some(records), meta))
+    }
+
+    /// Subscribe to the deltas applied to `key`.
+    ///
+    /// `subscriber` is first sent the rows currently stored for `key` as positive records, and
+    /// then every batch of records applied to `key` once it has been swapped in. If `key` is a
+    /// hole, it is sent no rows initially, and then the rows replayed to fill the hole.
+    ///
+    /// The subscription ends (and `subscriber` is dropped) if it fills up, or if `key` is evicted.
+    pub fn subscribe(&self, key: Vec<DataType>, mut subscriber: Subscriber) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if !subscriptions.dirty {
+            let rows = self
+                .handle
+                .meta_get_and(&key[..], |rs| {
+                    rs.iter().cloned().map(Record::Positive).collect()
+                })
+                .map(|(rows, _)| rows.unwrap_or_else(Vec::new));
+            if let Some(rows) = rows {
+                if subscriber.try_send(rows).is_ok() {
+                    subscriptions
+                        .by_key
+                        .entry(key)
+                        .or_insert_with(Vec::new)
+                        .push(subscriber);
+                }
+                return;
+            }
+            // the map hasn't been swapped in yet, so let the first swap send the initial rows
+        }
+        subscriptions.joining.push((key, subscriber));
+    }
+
+    /// Returns true if the reader keeps its keys in order, so that it can look up ranges of them.
+    pub fn serves_ranges(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// The columns that the rows for each key are returned in the order of, if any.
+    pub fn order(&self) -> &[(usize, OrderType)] {
+        &self.order[..]
+    }
+
+    pub(crate) fn set_order(&mut self, order: &[(usize, OrderType)]) {
+        self.order = Vec::from(order);
+    }
+
+    /// Conditions that compare against the query's parameters, if the reader cannot look them up
+    /// by key. Rows must then be found by the reader's only key and checked against these.
+    pub fn parameter_filter(&self) -> &[(usize, FilterCondition)] {
+        &self.parameter_filter[..]
+    }
+
+    pub(crate) fn set_parameter_filter(&mut self, filter: &[(usize, FilterCondition)]) {
+        self.parameter_filter = Vec::from(filter);
+    }
+
+    /// Returns true if the records visible to readers reflect every base table write in `token`.
+    pub fn has_seen(&self, token: &WriteToken) -> bool {
+        self.seen.read().unwrap().covers(token)
+    }
+
     pub fn len(&self) -> usize {
         self.handle.len()
     }
@@ -369,7 +815,7 @@ mod tests {
     fn store_works() {
         let a = vec![1.into(), "a".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
 
         // initially, store is uninitialized
         assert_eq!(r.try_find_and(&a[0..1], |rs| rs.len()), Err(()));
@@ -402,7 +848,7 @@ mod tests {
         use std::thread;
 
         let n = 1_000;
-        let (r, mut w) = new(1, &[0]);
+        let (r, mut w) = new(1, &[0], false);
         let jh = thread::spawn(move || {
             for i in 0..n {
                 w.add(vec![Record::Positive(vec![i.into()])]);
@@ -432,7 +878,7 @@ mod tests {
         let a = vec![1.into(), "a".into()];
         let b = vec![1.into(), "b".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
         w.add(vec![Record::Positive(a.clone())]);
         w.swap();
         w.add(vec![Record::Positive(b.clone())]);
@@ -453,7 +899,7 @@ mod tests {
         let b = vec![1.into(), "b".into()];
         let c = vec![1.into(), "c".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
         w.add(vec![Record::Positive(a.clone())]);
         w.add(vec![Record::Positive(b.clone())]);
         w.swap();
@@ -481,7 +927,7 @@ mod tests {
         let a = vec![1.into(), "a".into()];
         let b = vec![1.into(), "b".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
         w.add(vec![Record::Positive(a.clone())]);
         w.add(vec![Record::Positive(b.clone())]);
         w.add(vec![Record::Negative(a.clone())]);
@@ -502,7 +948,7 @@ mod tests {
         let a = vec![1.into(), "a".into()];
         let b = vec![1.into(), "b".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
         w.add(vec![Record::Positive(a.clone())]);
         w.add(vec![Record::Positive(b.clone())]);
         w.swap();
@@ -525,7 +971,7 @@ mod tests {
         let b = vec![1.into(), "b".into()];
         let c = vec![1.into(), "c".into()];
 
-        let (r, mut w) = new(2, &[0]);
+        let (r, mut w) = new(2, &[0], false);
         w.add(vec![
             Record::Positive(a.clone()),
             Record::Positive(b.clone()),
@@ -564,4 +1010,207 @@ mod tests {
             .0
             .unwrap());
     }
+
+    #[test]
+    fn range_lookup() {
+        let (r, mut w) = new(2, &[0], true);
+        w.add((0..10).map(|i| Record::Positive(vec![i.into(), "a".into()])));
+        w.add(vec![Record::Positive(vec![5.into(), "b".into()])]);
+        w.swap();
+
+        let range = (
+            Bound::Included(vec![3.into()]),
+            Bound::Excluded(vec![6.into()]),
+        );
+        let (found, _) = r.try_find_range_and(&range, |rs| rs.len()).unwrap();
+        assert_eq!(found, Some(vec![1, 1, 2]));
+
+        let range = (Bound::Excluded(vec![7.into()]), Bound::Unbounded);
+        let (found, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().next().unwrap()[0].clone())
+            .unwrap();
+        assert_eq!(found, Some(vec![8.into(), 9.into()]));
+
+        // removing the last record for a key also removes it from the range index
+        w.add(vec![Record::Negative(vec![8.into(), "a".into()])]);
+        w.swap();
+        let (found, _) = r.try_find_range_and(&range, |rs| rs.len()).unwrap();
+        assert_eq!(found, Some(vec![1]));
+
+        // empty ranges are fine too
+        let range = (
+            Bound::Excluded(vec![3.into()]),
+            Bound::Excluded(vec![3.into()]),
+        );
+        let (found, _) = r.try_find_range_and(&range, |rs| rs.len()).unwrap();
+        assert_eq!(found, Some(vec![]));
+    }
+
+    #[test]
+    fn range_index_only_if_ranges() {
+        let (r0, mut w0) = new(2, &[0], false);
+        let (r, mut w) = new(2, &[0], true);
+        assert!(!r0.serves_ranges());
+        assert!(r.serves_ranges());
+
+        // the ordered keys count towards the size of the reader that keeps them
+        for w in vec![&mut w0, &mut w] {
+            w.add(vec![Record::Positive(vec![1.into(), "a".into()])]);
+            w.swap();
+        }
+        assert!(w.deep_size_of() > w0.deep_size_of());
+
+        for w in vec![&mut w0, &mut w] {
+            w.add(vec![Record::Negative(vec![1.into(), "a".into()])]);
+            w.swap();
+        }
+        assert_eq!(w.deep_size_of(), w0.deep_size_of());
+    }
+
+    #[test]
+    fn partial_range_lookup() {
+        let (r, mut w) = new_partial(2, &[0], true, |_| true, |_| true);
+        w.swap();
+
+        let range = (
+            Bound::Included(vec![3.into()]),
+            Bound::Included(vec![5.into()]),
+        );
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+
+        // filling a single key does not fill the range around it
+        w.mut_with_key(&[DataType::from(4)][..]).mark_filled();
+        w.add(vec![Record::Positive(vec![4.into(), "a".into()])]);
+        w.swap();
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+
+        // but filling a range that covers it does
+        w.add(vec![Record::Positive(vec![2.into(), "a".into()])]);
+        w.mark_range_filled((
+            Bound::Included(vec![0.into()]),
+            Bound::Excluded(vec![10.into()]),
+        ));
+        w.swap();
+        assert_eq!(
+            r.try_find_range_and(&range, |rs| rs.len()).unwrap().0,
+            Some(vec![1])
+        );
+        assert!(w.in_filled_range(&[DataType::from(7)]));
+        assert!(!w.in_filled_range(&[DataType::from(10)]));
+
+        // and turning any key within the range back into a hole unfills it
+        w.mut_with_key(&[DataType::from(2)][..]).mark_hole();
+        w.swap();
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+        assert!(!w.in_filled_range(&[DataType::from(7)]));
+    }
+
+    #[test]
+    fn subscribe() {
+        use tokio::sync::mpsc::{channel, error::TryRecvError};
+
+        let a = vec![1.into(), "a".into()];
+        let b = vec![1.into(), "b".into()];
+
+        let (r, mut w) = new(2, &[0], false);
+        w.add(vec![Record::Positive(a.clone())]);
+        w.swap();
+
+        // a subscriber is first sent the current rows for its key
+        let (tx, mut rx) = channel(2);
+        r.subscribe(vec![1.into()], tx);
+        assert_eq!(rx.try_recv().unwrap(), vec![Record::Positive(a.clone())]);
+
+        // and then the deltas to that key, once they have been swapped in
+        w.add(vec![
+            Record::Positive(b.clone()),
+            Record::Positive(vec![2.into(), "c".into()]),
+        ]);
+        assert!(rx.try_recv().is_err());
+        w.swap();
+        assert_eq!(rx.try_recv().unwrap(), vec![Record::Positive(b.clone())]);
+
+        // a subscriber that joins between a write and a swap gets that write with its rows
+        let (tx2, mut rx2) = channel(2);
+        w.add(vec![Record::Negative(a.clone())]);
+        r.subscribe(vec![1.into()], tx2);
+        assert!(rx2.try_recv().is_err());
+        w.swap();
+        assert_eq!(rx.try_recv().unwrap(), vec![Record::Negative(a.clone())]);
+        assert_eq!(rx2.try_recv().unwrap(), vec![Record::Positive(b.clone())]);
+
+        // a subscriber that falls behind is dropped
+        for _ in 0..4 {
+            w.add(vec![Record::Positive(a.clone())]);
+            w.swap();
+        }
+        let mut received = 0;
+        let err = loop {
+            match rx.try_recv() {
+                Ok(_) => received += 1,
+                Err(e) => break e,
+            }
+        };
+        assert!(received < 4);
+        assert!(matches!(err, TryRecvError::Closed));
+    }
+
+    #[test]
+    fn subscribe_partial() {
+        use tokio::sync::mpsc::{channel, error::TryRecvError};
+
+        let a = vec![1.into(), "a".into()];
+
+        let (r, mut w) = new_partial(2, &[0], false, |_| true, |_| true);
+        w.swap();
+
+        // subscribing to a hole yields no rows until the hole is filled
+        let (tx, mut rx) = channel(8);
+        r.subscribe(vec![1.into()], tx);
+        assert_eq!(rx.try_recv().unwrap(), vec![]);
+
+        w.mut_with_key(&a[0..1]).mark_filled();
+        w.add(vec![Record::Positive(a.clone())]);
+        w.swap();
+        assert_eq!(rx.try_recv().unwrap(), vec![Record::Positive(a.clone())]);
+
+        // evicting the key ends the subscription
+        w.mut_with_key(&a[0..1]).mark_hole();
+        w.swap();
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Closed)));
+    }
+
+    #[test]
+    fn writes_seen_on_swap() {
+        let base = NodeIndex::new(0);
+        let first = WriteToken::new(base, 0, 1, 1);
+        let mut both = WriteToken::new(base, 0, 1, 2);
+        both.merge(&WriteToken::new(base, 1, 1, 1));
+
+        let (r, mut w) = new(2, &[0], false);
+        assert!(r.has_seen(&WriteToken::default()));
+        assert!(!r.has_seen(&first));
+
+        w.add_writes(&first);
+        w.add(vec![Record::Positive(vec![1.into(), "a".into()])]);
+        assert!(!r.has_seen(&first));
+        w.swap();
+        assert!(r.has_seen(&first));
+        assert!(!r.has_seen(&both));
+
+        // an update that carries no records still counts
+        w.add_writes(&WriteToken::new(base, 0, 1, 2));
+        w.add_writes(&WriteToken::new(base, 1, 1, 1));
+        w.swap();
+        assert!(r.has_seen(&first));
+        assert!(r.has_seen(&both));
+
+        // writes after a restart of the base order after all earlier ones
+        let restarted = WriteToken::new(base, 0, 2, 1);
+        assert!(!r.has_seen(&restarted));
+        w.add_writes(&restarted);
+        w.swap();
+        assert!(r.has_seen(&restarted));
+        assert!(r.has_seen(&both));
+    }
 }
@@ -18,6 +18,53 @@ impl Handle {
         }
     }
 
+    pub(super) fn meta(&self) -> Option<i64> {
+        match *self {
+            Handle::Single(ref h) => h.read().map(|map| *map.meta()),
+            Handle::Double(ref h) => h.read().map(|map| *map.meta()),
+            Handle::Many(ref h) => h.read().map(|map| *map.meta()),
+        }
+    }
+
+    /// Look up each of `keys` in turn, skipping any that are not present in the map.
+    pub(super) fn meta_get_many_and<'a, I, F, T>(
+        &self,
+        keys: I,
+        mut then: F,
+    ) -> Option<(Vec<T>, i64)>
+    where
+        I: Iterator<Item = &'a Vec<DataType>>,
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        match *self {
+            Handle::Single(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .filter_map(|key| map.get(&key[0]).map(&mut then))
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+            Handle::Double(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .filter_map(|key| {
+                        // unlike in meta_get_and, we just clone here; range reads are already
+                        // doing a lot more work per key than this.
+                        map.get(&(key[0].clone(), key[1].clone())).map(&mut then)
+                    })
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+            Handle::Many(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .filter_map(|key| map.get(&key[..]).map(&mut then))
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+        }
+    }
+
     pub(super) fn meta_get_and<F, T>(&self, key: &[DataType], then: F) -> Option<(Option<T>, i64)>
     where
         F: FnOnce(&evmap::Values<Vec<DataType>, RandomState>) -> T,
@@ -52,12 +52,16 @@ impl Handle {
         &mut self,
         rng: &mut impl rand::Rng,
         n: usize,
-        mut f: impl FnMut(&evmap::Values<Vec<DataType>, RandomState>),
+        mut f: impl FnMut(Vec<DataType>, &evmap::Values<Vec<DataType>, RandomState>),
     ) {
         match *self {
-            Handle::Single(ref mut h) => h.empty_random(rng, n).for_each(|r| f(r.1)),
-            Handle::Double(ref mut h) => h.empty_random(rng, n).for_each(|r| f(r.1)),
-            Handle::Many(ref mut h) => h.empty_random(rng, n).for_each(|r| f(r.1)),
+            Handle::Single(ref mut h) => h
+                .empty_random(rng, n)
+                .for_each(|r| f(vec![r.0.clone()], r.1)),
+            Handle::Double(ref mut h) => h
+                .empty_random(rng, n)
+                .for_each(|r| f(vec![(r.0).0.clone(), (r.0).1.clone()], r.1)),
+            Handle::Many(ref mut h) => h.empty_random(rng, n).for_each(|r| f(r.0.clone(), r.1)),
         }
     }
 
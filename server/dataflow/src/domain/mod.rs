@@ -16,6 +16,7 @@ use ahash::RandomState;
 use futures_util::{future::FutureExt, stream::StreamExt};
 use noria::channel::{self, TcpSender};
 pub use noria::internal::DomainIndex as Index;
+use noria::KeyRange;
 use slog::Logger;
 use stream_cancel::Valve;
 
@@ -45,6 +46,10 @@ pub struct Config {
 
 const BATCH_SIZE: usize = 256;
 
+/// How long an egress or sharder node may hold back the write tokens of updates that sent nothing
+/// to some of its children before they are sent on by themselves.
+const TOKEN_FLUSH_INTERVAL: time::Duration = time::Duration::from_millis(5);
+
 #[derive(Debug)]
 enum DomainMode {
     Forwarding,
@@ -185,6 +190,8 @@ impl DomainBuilder {
             buffered_replay_requests: Default::default(),
             replay_batch_timeout: self.config.replay_batch_timeout,
             timed_purges: Default::default(),
+            owed_tokens: Default::default(),
+            stalled_readers: Default::default(),
 
             concurrent_replays: 0,
             max_concurrent_replays: self.config.concurrent_replays,
@@ -234,6 +241,10 @@ pub struct Domain {
     replay_paths: HashMap<Tag, ReplayPath>,
     reader_triggered: Map<HashSet<Vec<DataType>, RandomState>>,
     timed_purges: VecDeque<TimedPurge>,
+    /// Nodes that hold back write tokens, and when those must be sent on at the latest.
+    owed_tokens: HashMap<LocalNodeIndex, time::Instant>,
+    /// Readers that wait for the remaining copies of some write, and when they stop waiting.
+    stalled_readers: HashMap<LocalNodeIndex, time::Instant>,
 
     replay_paths_by_dst: Map<HashMap<Vec<usize>, Vec<Tag>>>,
 
@@ -329,6 +340,60 @@ impl Domain {
         }
     }
 
+    /// Ask the source of the replay path that fills `miss_columns` of `miss_in` to replay every
+    /// key within `range`.
+    ///
+    /// Range replays are answered with a single replay piece that covers the whole range, so they
+    /// are only supported along paths that have a single, unsharded, fully materialized source.
+    /// Readers that need them are checked for this when they are planned, and do not serve range
+    /// lookups otherwise.
+    fn find_tags_and_replay_range(
+        &mut self,
+        range: KeyRange,
+        miss_columns: &[usize],
+        miss_in: LocalNodeIndex,
+    ) {
+        let mut tags = Vec::new();
+        if let Some(ref candidates) = self.replay_paths_by_dst.get(miss_in) {
+            if let Some(ts) = candidates.get(miss_columns) {
+                tags = ts.clone();
+            }
+        }
+
+        assert_eq!(
+            tags.len(),
+            1,
+            "range replay along multiple paths to {:?}",
+            miss_in
+        );
+
+        let tag = tags[0];
+        let request = Box::new(Packet::RequestPartialRangeReplay {
+            tag,
+            range,
+            requesting_shard: self.shard.unwrap_or(0),
+        });
+        match self.replay_paths.get_mut(&tag).unwrap().trigger {
+            TriggerEndpoint::Local(..) => {
+                // see find_tags_and_replay for why we don't just seed the replay here
+                self.delayed_for_self.push_back(request);
+            }
+            TriggerEndpoint::End {
+                ref mut options, ..
+            } if options.len() == 1 => {
+                // range replays don't count against max_concurrent_replays, since the
+                // completed replay doesn't tell us how many requests it satisfied.
+                if options[0].send(request).is_err() {
+                    // we're shutting down -- it's fine.
+                }
+            }
+            TriggerEndpoint::End { .. } => unreachable!("range replay from a sharded source"),
+            TriggerEndpoint::Start(..) | TriggerEndpoint::None => {
+                unreachable!("asked to replay along non-existing path")
+            }
+        }
+    }
+
     fn on_replay_miss(
         &mut self,
         miss_in: LocalNodeIndex,
@@ -599,6 +664,15 @@ impl Domain {
             self.process_ptimes.stop();
             self.process_times.stop();
 
+            if n.owes_tokens() {
+                self.owed_tokens
+                    .entry(me)
+                    .or_insert_with(|| time::Instant::now() + TOKEN_FLUSH_INTERVAL);
+            }
+            if let Ok(Some(deadline)) = n.with_reader(|r| r.wait_deadline()) {
+                self.stalled_readers.insert(me, deadline);
+            }
+
             if m.is_none() {
                 // no need to deal with our children if we're not sending them anything
                 return;
@@ -711,8 +785,11 @@ impl Domain {
         }
 
         match &**m.as_ref().unwrap() {
-            m @ &Packet::Message { .. } if m.is_empty() => {
-                // no need to deal with our children if we're not sending them anything
+            m @ &Packet::Message { ref token, .. } if m.is_empty() && token.is_empty() => {
+                // no need to deal with our children if we're not sending them anything. note that
+                // an empty update that carries a write token must still reach the readers below
+                // us, as they would otherwise never learn that the write has been processed.
+                // egress and sharder nodes batch such updates up before they leave the domain.
                 return;
             }
             &Packet::Message { .. } => {}
@@ -892,13 +969,13 @@ impl Domain {
                                 gid,
                                 cols,
                                 key,
+                                ranges,
                                 trigger_domain: (trigger_domain, shards),
                             } => {
                                 use crate::backlog;
                                 let k = key.clone(); // ugh
                                 let txs = (0..shards)
                                     .map(|shard| {
-                                        let key = key.clone();
                                         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
                                         let sender = self
                                             .channel_coordinator
@@ -910,13 +987,6 @@ impl Domain {
                                         tokio::spawn(
                                             self.shutdown_valve
                                                 .wrap(rx)
-                                                .map(move |misses| {
-                                                    Box::new(Packet::RequestReaderReplay {
-                                                        keys: misses,
-                                                        cols: key.clone(),
-                                                        node,
-                                                    })
-                                                })
                                                 .map(Ok)
                                                 .forward(sender)
                                                 .map(|r| {
@@ -932,18 +1002,28 @@ impl Domain {
                                         tx
                                     })
                                     .collect::<Vec<_>>();
-                                let (r_part, w_part) = backlog::new_partial(
+                                let range_tx = txs[self.shard.unwrap_or(0)].clone();
+                                let range_key = key.clone();
+                                let (mut r_part, w_part) = backlog::new_partial(
                                     cols,
                                     &k[..],
+                                    ranges,
                                     move |misses: &mut dyn Iterator<Item = &[DataType]>| {
                                         let n = txs.len();
+                                        let request = |keys| {
+                                            Box::new(Packet::RequestReaderReplay {
+                                                keys,
+                                                cols: key.clone(),
+                                                node,
+                                            })
+                                        };
                                         if n == 1 {
                                             use std::iter::FromIterator;
                                             let misses = Vec::from_iter(misses.map(Vec::from));
                                             if misses.is_empty() {
                                                 return true;
                                             }
-                                            txs[0].send(misses).is_ok()
+                                            txs[0].send(request(misses)).is_ok()
                                         } else {
                                             // TODO: compound reader
                                             let mut per_shard = HashMap::new();
@@ -958,16 +1038,29 @@ impl Domain {
                                             if per_shard.is_empty() {
                                                 return true;
                                             }
-                                            per_shard
-                                                .into_iter()
-                                                .all(|(shard, keys)| txs[shard].send(keys).is_ok())
+                                            per_shard.into_iter().all(|(shard, keys)| {
+                                                txs[shard].send(request(keys)).is_ok()
+                                            })
                                         }
                                     },
+                                    move |range: &KeyRange| {
+                                        // every shard of the reader is asked for the whole range,
+                                        // so each one only needs to fill its own part of it.
+                                        range_tx
+                                            .send(Box::new(Packet::RequestReaderRangeReplay {
+                                                range: range.clone(),
+                                                cols: range_key.clone(),
+                                                node,
+                                            }))
+                                            .is_ok()
+                                    },
                                 );
 
                                 let mut n = self.nodes[node].borrow_mut();
                                 tokio::task::block_in_place(|| {
                                     n.with_reader_mut(|r| {
+                                        r_part.set_order(r.order());
+                                        r_part.set_parameter_filter(r.parameter_filter());
                                         assert!(self
                                             .readers
                                             .lock()
@@ -984,13 +1077,20 @@ impl Domain {
                                 })
                                 .unwrap();
                             }
-                            InitialState::Global { gid, cols, key } => {
+                            InitialState::Global {
+                                gid,
+                                cols,
+                                key,
+                                ranges,
+                            } => {
                                 use crate::backlog;
-                                let (r_part, w_part) = backlog::new(cols, &key[..]);
+                                let (mut r_part, w_part) = backlog::new(cols, &key[..], ranges);
 
                                 let mut n = self.nodes[node].borrow_mut();
                                 tokio::task::block_in_place(|| {
                                     n.with_reader_mut(|r| {
+                                        r_part.set_order(r.order());
+                                        r_part.set_parameter_filter(r.parameter_filter());
                                         assert!(self
                                             .readers
                                             .lock()
@@ -1140,6 +1240,43 @@ impl Domain {
                         }
                         self.total_replay_time.stop();
                     }
+                    Packet::RequestReaderRangeReplay { range, cols, node } => {
+                        self.total_replay_time.start();
+                        // the range could have been filled since the request was sent, in which
+                        // case there's nothing left to do. note that unlike for keys, we don't
+                        // remember which ranges we have requested; if a replay fails to fill the
+                        // range, the reader will simply ask again.
+                        let filled = self.nodes[node]
+                            .borrow_mut()
+                            .with_reader_mut(|r| {
+                                let w = r
+                                    .writer_mut()
+                                    .expect("reader replay requested for non-materialized reader");
+                                w.swap();
+                                w.range_is_filled(&range)
+                            })
+                            .expect("reader replay requested for non-reader node");
+
+                        if !filled {
+                            self.find_tags_and_replay_range(range, &cols[..], node);
+                        }
+                        self.total_replay_time.stop();
+                    }
+                    Packet::RequestPartialRangeReplay {
+                        tag,
+                        range,
+                        requesting_shard,
+                    } => {
+                        trace!(
+                            self.log,
+                           "got range replay request";
+                           "tag" => tag,
+                           "range" => ?range
+                        );
+                        self.total_replay_time.start();
+                        self.seed_range(tag, range, requesting_shard, executor);
+                        self.total_replay_time.stop();
+                    }
                     Packet::RequestPartialReplay {
                         tag,
                         keys,
@@ -1531,10 +1668,51 @@ impl Domain {
                             if let Some(wh) = r.writer_mut() {
                                 wh.swap();
                             }
+                            r.forget_stalled_holes();
                         })
                         .unwrap();
                 }
 
+                if !self.stalled_readers.is_empty() {
+                    let now = time::Instant::now();
+                    let due: Vec<_> = self
+                        .stalled_readers
+                        .iter()
+                        .filter(|&(_, &deadline)| deadline <= now)
+                        .map(|(&n, _)| n)
+                        .collect();
+                    for n in due {
+                        // give up on copies of writes that were lost on their way to the reader
+                        let deadline = self.nodes[n]
+                            .borrow_mut()
+                            .with_reader_mut(|r| {
+                                r.expire_waits(now);
+                                r.wait_deadline()
+                            })
+                            .unwrap();
+                        match deadline {
+                            Some(deadline) => self.stalled_readers.insert(n, deadline),
+                            None => self.stalled_readers.remove(&n),
+                        };
+                    }
+                }
+
+                if !self.owed_tokens.is_empty() {
+                    let now = time::Instant::now();
+                    let due: Vec<_> = self
+                        .owed_tokens
+                        .iter()
+                        .filter(|&(_, &deadline)| deadline <= now)
+                        .map(|(&n, _)| n)
+                        .collect();
+                    for n in due {
+                        self.owed_tokens.remove(&n);
+                        self.nodes[n]
+                            .borrow_mut()
+                            .flush_tokens(self.shard, executor);
+                    }
+                }
+
                 if self.delayed_for_self.is_empty() {
                     break;
                 }
@@ -1610,6 +1788,7 @@ impl Domain {
                             unishard: single_shard, // if we are the only source, only one path
                             ignore: false,
                             requesting_shard,
+                            range: None,
                         },
                         data: rs.into(),
                     }))
@@ -1668,6 +1847,66 @@ impl Domain {
         }
     }
 
+    fn seed_range(
+        &mut self,
+        tag: Tag,
+        range: KeyRange,
+        requesting_shard: usize,
+        ex: &mut dyn Executor,
+    ) {
+        let m = match self.replay_paths[&tag] {
+            ReplayPath {
+                source: Some(source),
+                trigger: TriggerEndpoint::Start(ref cols),
+                ref path,
+                ..
+            }
+            | ReplayPath {
+                source: Some(source),
+                trigger: TriggerEndpoint::Local(ref cols),
+                ref path,
+                ..
+            } => {
+                let state = self
+                    .state
+                    .get_mut(source)
+                    .expect("migration replay path started with non-materialized node");
+
+                // the migration only lets readers look up ranges if their source is fully
+                // materialized, since we couldn't tell which keys in the range are holes here.
+                assert!(!state.is_partial(), "range replay from partial state");
+
+                // the ordered index is built on the first range replay from this state, and kept
+                // up to date from then on.
+                state.add_ordered_key(cols);
+
+                let mut keys = HashSet::new();
+                let mut rs = Vec::new();
+                for r in state.lookup_range(cols, &range) {
+                    keys.insert(cols.iter().map(|&c| r[c].clone()).collect());
+                    rs.push(self.seed_row(source, Cow::Owned(r)));
+                }
+
+                Box::new(Packet::ReplayPiece {
+                    link: Link::new(source, path[0].node),
+                    tag,
+                    context: ReplayPieceContext::Partial {
+                        for_keys: keys,
+                        unishard: true, // range replays only have a single source
+                        ignore: false,
+                        requesting_shard,
+                        range: Some(range),
+                    },
+                    data: rs.into(),
+                })
+            }
+            _ => unreachable!(),
+        };
+
+        trace!(self.log, "satisfied range replay request"; "tag" => tag);
+        self.handle_replay(m, ex);
+    }
+
     fn seed_replay(
         &mut self,
         tag: Tag,
@@ -1738,6 +1977,7 @@ impl Domain {
                             unishard: single_shard, // if we are the only source, only one path
                             ignore: false,
                             requesting_shard,
+                            range: None,
                         },
                         data,
                     }));
@@ -1849,6 +2089,15 @@ impl Domain {
                         debug!(self.log, "replaying batch"; "#" => data.len());
                     }
 
+                    // range replays weren't counted against max_concurrent_replays when they were
+                    // requested, so they also mustn't release any slots when they complete.
+                    let is_range_replay =
+                        if let ReplayPieceContext::Partial { ref range, .. } = context {
+                            range.is_some()
+                        } else {
+                            false
+                        };
+
                     // let's collect some information about the destination of this replay
                     let dst = path.last().unwrap().node;
                     let dst_is_reader = self.nodes[dst]
@@ -1860,12 +2109,30 @@ impl Domain {
                     if dst_is_target {
                         // prune keys and data for keys we're not waiting for
                         if let ReplayPieceContext::Partial {
-                            ref mut for_keys, ..
+                            ref mut for_keys,
+                            ref range,
+                            ..
                         } = context
                         {
                             let had = for_keys.len();
                             let partial_keys = path.last().unwrap().partial_key.as_ref().unwrap();
-                            if let Some(w) = self.waiting.get(dst) {
+                            if range.is_some() {
+                                // a range replay fills every key in the range that isn't already
+                                // filled, whether or not anyone asked for that key.
+                                self.nodes[dst]
+                                    .borrow_mut()
+                                    .with_reader_mut(|r| {
+                                        if let Some(wh) = r.writer_mut() {
+                                            for_keys.retain(|k| {
+                                                wh.with_key(&k[..])
+                                                    .try_find_and(|_| ())
+                                                    .map(|r| r.0.is_none())
+                                                    .unwrap_or(true)
+                                            });
+                                        }
+                                    })
+                                    .expect("range replay to non-reader node");
+                            } else if let Some(w) = self.waiting.get(dst) {
                                 // discard all the keys that we aren't waiting for
                                 for_keys.retain(|k| {
                                     w.redos.contains_key(&(partial_keys.clone(), k.clone()))
@@ -1879,7 +2146,7 @@ impl Domain {
                                 return;
                             }
 
-                            if for_keys.is_empty() {
+                            if for_keys.is_empty() && range.is_none() {
                                 return;
                             } else if for_keys.len() != had {
                                 // discard records in data associated with the keys we weren't
@@ -2042,8 +2309,12 @@ impl Domain {
                                 }
                             } else if is_reader {
                                 // we filled a hole! swap the reader.
+                                let range = replay_context!(m, range).and_then(|r| r.clone());
                                 n.with_reader_mut(|r| {
                                     if let Some(wh) = r.writer_mut() {
+                                        if let Some(range) = range {
+                                            wh.mark_range_filled(range);
+                                        }
                                         wh.swap();
                                     }
                                 })
@@ -2106,6 +2377,7 @@ impl Domain {
                         //     join along the path.
                         if backfill_keys.is_some()
                             && finished_partial == 0
+                            && !is_range_replay
                             && (dst_is_reader || dst_is_target)
                         {
                             finished_partial = backfill_keys.as_ref().unwrap().len();
@@ -2148,6 +2420,13 @@ impl Domain {
                                 unreachable!("backfill_keys.is_some() implies Context::Partial");
                             };
 
+                            // a range replay that missed no longer covers the whole range. the
+                            // missing keys will be filled in by the redo, but the range as a whole
+                            // will have to be requested again.
+                            if let Some(range) = replay_context!(m, range) {
+                                *range = None;
+                            }
+
                             for miss in misses {
                                 need_replay.push((
                                     miss.on,
@@ -2185,11 +2464,14 @@ impl Domain {
                             });
                         }
 
-                        // no more keys to replay, so we might as well terminate early
+                        // no more keys to replay, so we might as well terminate early. range
+                        // replays must still reach their target though, even if the range is
+                        // empty, so that it can be marked as filled.
                         if backfill_keys
                             .as_ref()
                             .map(|b| b.is_empty())
                             .unwrap_or(false)
+                            && replay_context!(m, range).map_or(true, |r| r.is_none())
                         {
                             break 'outer;
                         }
@@ -2401,6 +2683,7 @@ impl Domain {
                             ignore,
                             unishard: _,
                             requesting_shard: _,
+                            range: _,
                         } => {
                             assert!(!ignore);
                             if dst_is_reader {
@@ -2414,7 +2697,9 @@ impl Domain {
                                         tag,
                                     });
                                 }
-                                assert_ne!(finished_partial, 0);
+                                if !is_range_replay {
+                                    assert_ne!(finished_partial, 0);
+                                }
                             } else if dst_is_target {
                                 trace!(self.log, "partial replay completed"; "local" => dst.id());
                                 if finished_partial == 0 {
@@ -2975,13 +3260,29 @@ impl Domain {
                     }
                 });
 
-                let mut timeout = opt1.or(opt2).or(opt3);
+                let opt4 = self
+                    .owed_tokens
+                    .values()
+                    .chain(self.stalled_readers.values())
+                    .min()
+                    .map(|&deadline| {
+                        if deadline > now {
+                            deadline - now
+                        } else {
+                            time::Duration::from_millis(0)
+                        }
+                    });
+
+                let mut timeout = opt1.or(opt2).or(opt3).or(opt4);
                 if let Some(opt2) = opt2 {
                     timeout = Some(std::cmp::min(timeout.unwrap(), opt2));
                 }
                 if let Some(opt3) = opt3 {
                     timeout = Some(std::cmp::min(timeout.unwrap(), opt3));
                 }
+                if let Some(opt4) = opt4 {
+                    timeout = Some(std::cmp::min(timeout.unwrap(), opt4));
+                }
                 ProcessResult::KeepPolling(timeout)
             }
             PollEvent::Process(packet) => {
@@ -3010,7 +3311,11 @@ impl Domain {
                     self.handle(m, executor, true);
                 }
 
-                if !self.buffered_replay_requests.is_empty() || !self.timed_purges.is_empty() {
+                if !self.buffered_replay_requests.is_empty()
+                    || !self.timed_purges.is_empty()
+                    || !self.owed_tokens.is_empty()
+                    || !self.stalled_readers.is_empty()
+                {
                     self.handle(Box::new(Packet::Spin), executor, true);
                 }
 
@@ -93,6 +93,8 @@ impl GroupCommitQueueSet {
         let merged_dst = packets.peek().as_mut().unwrap().dst();
 
         let mut all_senders = vec![];
+        let mut merged_txns = vec![];
+        let mut merged_outcomes = false;
         let merged_data = packets.fold(Vec::new(), |mut acc, p| {
             match *p {
                 Packet::Input {
@@ -100,14 +102,26 @@ impl GroupCommitQueueSet {
                     src,
                     senders,
                 } => {
-                    let Input { dst, data } = unsafe { inner.take() };
+                    let Input {
+                        dst,
+                        data,
+                        txns,
+                        outcomes,
+                    } = unsafe { inner.take() };
 
                     assert_eq!(senders.len(), 0);
                     assert_eq!(merged_dst, dst);
+                    let start = acc.len();
                     acc.extend(data);
+                    merged_txns.extend(txns);
+                    merged_outcomes |= outcomes;
 
                     if let Some(src) = src {
-                        all_senders.push(src);
+                        all_senders.push(InputSender {
+                            src,
+                            ops: start..acc.len(),
+                            outcomes,
+                        });
                     }
                 }
                 _ => unreachable!(),
@@ -119,6 +133,8 @@ impl GroupCommitQueueSet {
             inner: LocalOrNot::new(Input {
                 dst: merged_dst,
                 data: merged_data,
+                txns: merged_txns,
+                outcomes: merged_outcomes,
             }),
             src: None,
             senders: all_senders,
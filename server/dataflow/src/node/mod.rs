@@ -136,6 +136,11 @@ impl Node {
         Ingredient::requires_full_materialization(&**self)
     }
 
+    /// Returns true if this operator requires its parents to be fully materialized
+    pub fn requires_full_parents(&self) -> bool {
+        Ingredient::requires_full_parents(&**self)
+    }
+
     pub fn can_query_through(&self) -> bool {
         Ingredient::can_query_through(&**self)
     }
@@ -35,8 +35,10 @@ impl Node {
                     Some(Packet::Input {
                         inner, mut senders, ..
                     }) => {
-                        let Input { dst, data } = unsafe { inner.take() };
-                        let mut rs = b.process(addr, data, &*state);
+                        let Input {
+                            dst, data, txns, ..
+                        } = unsafe { inner.take() };
+                        let (mut rs, outcomes) = b.process(addr, data, &*state);
 
                         // When a replay originates at a base node, we replay the data *through* that
                         // same base node because its column set may have changed. However, this replay
@@ -50,12 +52,30 @@ impl Node {
                         }
 
                         // Send write-ACKs to all the clients with updates that made
-                        // it into this merged packet:
-                        senders.drain(..).for_each(|src| ex.ack(src));
+                        // it into this merged packet. The token they get back travels along with
+                        // the resulting update, so that readers can tell when they reflect it.
+                        // Clients that asked for them also learn what each of their writes did.
+                        let (epoch, seq) = b.next_seq();
+                        let shard = on_shard.unwrap_or(0);
+                        let token = WriteToken::new(gaddr, shard, epoch, seq);
+                        for sender in senders.drain(..) {
+                            let outcomes = if sender.outcomes {
+                                outcomes[sender.ops].to_vec()
+                            } else {
+                                Vec::new()
+                            };
+                            let ack = WriteAck {
+                                token: token.clone(),
+                                outcomes,
+                            };
+                            ex.ack(sender.src, ack);
+                        }
 
                         *m = Some(Box::new(Packet::Message {
                             link: Link::new(dst, dst),
                             data: rs,
+                            token: Writes::new(gaddr, shard, epoch, seq),
+                            txns,
                         }));
                     }
                     Some(ref p) => {
@@ -66,7 +86,7 @@ impl Node {
                 }
             }
             NodeType::Reader(ref mut r) => {
-                r.process(m, swap);
+                r.process(m, on_shard.unwrap_or(0), swap);
             }
             NodeType::Egress(None) => unreachable!(),
             NodeType::Egress(Some(ref mut e)) => {
@@ -101,6 +121,7 @@ impl Node {
                                     requesting_shard,
                                     unishard,
                                     ignore,
+                                    ..
                                 },
                             ..
                         } => {
@@ -296,6 +317,25 @@ impl Node {
             NodeType::Egress(None) | NodeType::Source => unreachable!(),
         }
     }
+
+    /// Returns true if this node is holding back write tokens that its children must learn about.
+    pub(crate) fn owes_tokens(&self) -> bool {
+        match self.inner {
+            NodeType::Egress(Some(ref e)) => e.owes_tokens(),
+            NodeType::Sharder(ref s) => s.owes_tokens(),
+            _ => false,
+        }
+    }
+
+    /// Send on any write tokens that this node is holding back.
+    pub(crate) fn flush_tokens(&mut self, on_shard: Option<usize>, ex: &mut dyn Executor) {
+        let addr = self.local_addr();
+        match self.inner {
+            NodeType::Egress(Some(ref mut e)) => e.flush_tokens(on_shard.unwrap_or(0), ex),
+            NodeType::Sharder(ref mut s) => s.flush_tokens(addr, ex),
+            _ => {}
+        }
+    }
 }
 
 // When we miss in can_query_through, that miss is *really* in the can_query_through node's
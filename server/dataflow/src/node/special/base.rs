@@ -2,7 +2,8 @@ use crate::prelude::*;
 use noria::{Modification, Operation, TableOperation};
 use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::hash_map::{Entry, HashMap};
+use std::time;
 use vec_map::VecMap;
 
 /// Base is used to represent the root nodes of the Noria data flow graph.
@@ -17,6 +18,16 @@ pub struct Base {
     defaults: Vec<DataType>,
     dropped: Vec<usize>,
     unmodified: bool,
+
+    /// When this shard of the base started assigning sequence numbers to writes.
+    ///
+    /// The write count below starts over whenever the base is restarted, so the tokens handed out
+    /// before that are told apart from newer ones by this epoch.
+    #[serde(skip)]
+    epoch: u64,
+    /// The number of writes this shard of the base has processed in the current epoch.
+    #[serde(skip)]
+    writes: u64,
 }
 
 impl Base {
@@ -95,6 +106,8 @@ impl Clone for Base {
             defaults: self.defaults.clone(),
             dropped: self.dropped.clone(),
             unmodified: self.unmodified,
+            epoch: self.epoch,
+            writes: self.writes,
         }
     }
 }
@@ -107,6 +120,8 @@ impl Default for Base {
             defaults: Vec::new(),
             dropped: Vec::new(),
             unmodified: true,
+            epoch: 0,
+            writes: 0,
         }
     }
 }
@@ -117,6 +132,9 @@ fn key_val(i: usize, col: usize, r: &TableOperation) -> &DataType {
         TableOperation::Delete { ref key } => &key[i],
         TableOperation::Update { ref key, .. } => &key[i],
         TableOperation::InsertOrUpdate { ref row, .. } => &row[col],
+        TableOperation::DeleteWhere { .. } | TableOperation::UpdateWhere { .. } => {
+            unreachable!("predicate-based operation {:?} has no single key", r)
+        }
     }
 }
 
@@ -127,19 +145,129 @@ fn key_of<'a>(key_cols: &'a [usize], r: &'a TableOperation) -> impl Iterator<Ite
         .map(move |(i, col)| key_val(i, *col, r))
 }
 
+fn lookup_key<'a>(
+    db: &'a dyn State,
+    key_cols: &[usize],
+    key: &[DataType],
+) -> Option<Cow<'a, [DataType]>> {
+    match db.lookup(key_cols, &KeyType::from(key)) {
+        LookupResult::Some(rows) => {
+            match rows.len() {
+                0 => None,
+                1 => rows.into_iter().next(),
+                n => {
+                    // primary key, so better be unique!
+                    assert_eq!(n, 1, "key {:?} not unique (n = {})!", key, n);
+                    unreachable!();
+                }
+            }
+        }
+        LookupResult::Missing => unreachable!(),
+    }
+}
+
+/// Apply `op` to `current`, the row with the key that `op` refers to. `was` is what that row was
+/// before the batch that `op` is part of.
+fn apply<'a>(
+    op: TableOperation,
+    was: &Option<Cow<'a, [DataType]>>,
+    current: &mut Option<Cow<'a, [DataType]>>,
+) -> WriteOutcome {
+    let update = match op {
+        TableOperation::Insert(row) => {
+            if let Some(ref was) = *was {
+                eprintln!("base ignoring {:?} since it already has {:?}", row, was);
+                return WriteOutcome::NoOp;
+            } else {
+                //assert!(was.is_none());
+                *current = Some(Cow::Owned(row));
+                return WriteOutcome::Inserted;
+            }
+        }
+        TableOperation::Delete { .. } => {
+            if current.is_some() {
+                *current = None;
+                return WriteOutcome::Deleted;
+            } else {
+                // supposed to delete a non-existing row?
+                // TODO: warn?
+                return WriteOutcome::NotFound;
+            }
+        }
+        TableOperation::Update { set, .. } => set,
+        TableOperation::InsertOrUpdate { row, update } => {
+            if current.is_none() {
+                *current = Some(Cow::Owned(row));
+                return WriteOutcome::Inserted;
+            }
+            update
+        }
+        TableOperation::DeleteWhere { .. } | TableOperation::UpdateWhere { .. } => {
+            unreachable!("predicate-based operations are resolved before they are applied")
+        }
+    };
+
+    if current.is_none() {
+        // supposed to update a non-existing row?
+        // TODO: also warn here?
+        return WriteOutcome::NotFound;
+    }
+
+    let old = current.take().unwrap();
+    let mut future = old.to_vec();
+    for (col, op) in update.into_iter().enumerate() {
+        // XXX: make sure user doesn't update primary key?
+        match op {
+            Modification::Set(v) => future[col] = v,
+            Modification::Apply(op, v) => {
+                let old: i128 = future[col].clone().into();
+                let delta: i128 = v.into();
+                future[col] = match op {
+                    Operation::Add => (old + delta).into(),
+                    Operation::Sub => (old - delta).into(),
+                };
+            }
+            Modification::None => {}
+        }
+    }
+    let outcome = if future[..] == old[..] {
+        WriteOutcome::NoOp
+    } else {
+        WriteOutcome::Updated
+    };
+    *current = Some(Cow::Owned(future));
+    outcome
+}
+
 impl Base {
     pub(in crate::node) fn take(&mut self) -> Self {
         Clone::clone(self)
     }
 
+    /// Assign an epoch and a sequence number to the next write to this shard of the base.
+    pub(in crate::node) fn next_seq(&mut self) -> (u64, u64) {
+        if self.epoch == 0 {
+            // the clock only moves forward across restarts, so later epochs compare greater
+            let now = time::SystemTime::now()
+                .duration_since(time::UNIX_EPOCH)
+                .unwrap();
+            self.epoch = std::cmp::max(now.as_micros() as u64, 1);
+        }
+        self.writes += 1;
+        (self.epoch, self.writes)
+    }
+
+    /// Apply `ops` to this base, and return the resulting updates along with what each operation
+    /// did.
     pub(in crate::node) fn process(
         &mut self,
         us: LocalNodeIndex,
-        mut ops: Vec<TableOperation>,
+        ops: Vec<TableOperation>,
         state: &StateMap,
-    ) -> Records {
+    ) -> (Records, Vec<WriteOutcome>) {
         if self.primary_key.is_none() || ops.is_empty() {
-            return ops
+            let outcomes = vec![WriteOutcome::Inserted; ops.len()];
+            let rs = ops
                 .into_iter()
                 .map(|r| {
                     if let TableOperation::Insert(mut r) = r {
@@ -150,40 +278,37 @@ impl Base {
                     }
                 })
                 .collect();
+            return (rs, outcomes);
         }
 
-        let key_cols = &self.primary_key.as_ref().unwrap()[..];
-        ops.sort_by(|a, b| key_of(key_cols, a).cmp(key_of(key_cols, b)));
-
-        // starting key
-        let mut this_key: Vec<_> = key_of(key_cols, &ops[0]).cloned().collect();
-
         // starting record state
         let db = state
             .get(us)
             .expect("base with primary key must be materialized");
 
-        let get_current = |current_key: &'_ _| {
-            match db.lookup(key_cols, &KeyType::from(current_key)) {
-                LookupResult::Some(rows) => {
-                    match rows.len() {
-                        0 => None,
-                        1 => rows.into_iter().next(),
-                        n => {
-                            // primary key, so better be unique!
-                            assert_eq!(n, 1, "key {:?} not unique (n = {})!", current_key, n);
-                            unreachable!();
-                        }
-                    }
-                }
-                LookupResult::Missing => unreachable!(),
+        // remember which operation each one we apply came from, so that we can report what each
+        // of them did even after predicates are resolved and the operations are reordered.
+        let mut outcomes = vec![WriteOutcome::NotFound; ops.len()];
+        let mut ops: Vec<_> = ops.into_iter().enumerate().collect();
+
+        if ops.iter().any(|(_, op)| op.filter().is_some()) {
+            ops = self.resolve_predicates(ops, &**db);
+            if ops.is_empty() {
+                return (Records::default(), outcomes);
             }
-        };
-        let mut current = get_current(&this_key);
+        }
+
+        let key_cols = &self.primary_key.as_ref().unwrap()[..];
+        ops.sort_by(|(_, a), (_, b)| key_of(key_cols, a).cmp(key_of(key_cols, b)));
+
+        // starting key
+        let mut this_key: Vec<_> = key_of(key_cols, &ops[0].1).cloned().collect();
+
+        let mut current = lookup_key(&**db, key_cols, &this_key);
         let mut was = current.clone();
 
         let mut results = Vec::with_capacity(ops.len());
-        for op in ops {
+        for (opi, op) in ops {
             if this_key.iter().cmp(key_of(key_cols, &op)) != Ordering::Equal {
                 if current != was {
                     if let Some(was) = was {
@@ -195,62 +320,12 @@ impl Base {
                 }
 
                 this_key = key_of(key_cols, &op).cloned().collect();
-                current = get_current(&this_key);
+                current = lookup_key(&**db, key_cols, &this_key);
                 was = current.clone();
             }
 
-            let update = match op {
-                TableOperation::Insert(row) => {
-                    if let Some(ref was) = was {
-                        eprintln!("base ignoring {:?} since it already has {:?}", row, was);
-                    } else {
-                        //assert!(was.is_none());
-                        current = Some(Cow::Owned(row));
-                    }
-                    continue;
-                }
-                TableOperation::Delete { .. } => {
-                    if current.is_some() {
-                        current = None;
-                    } else {
-                        // supposed to delete a non-existing row?
-                        // TODO: warn?
-                    }
-                    continue;
-                }
-                TableOperation::Update { set, .. } => set,
-                TableOperation::InsertOrUpdate { row, update } => {
-                    if current.is_none() {
-                        current = Some(Cow::Owned(row));
-                        continue;
-                    }
-                    update
-                }
-            };
-
-            if current.is_none() {
-                // supposed to update a non-existing row?
-                // TODO: also warn here?
-                continue;
-            }
-
-            let mut future = current.unwrap().into_owned();
-            for (col, op) in update.into_iter().enumerate() {
-                // XXX: make sure user doesn't update primary key?
-                match op {
-                    Modification::Set(v) => future[col] = v,
-                    Modification::Apply(op, v) => {
-                        let old: i128 = future[col].clone().into();
-                        let delta: i128 = v.into();
-                        future[col] = match op {
-                            Operation::Add => (old + delta).into(),
-                            Operation::Sub => (old - delta).into(),
-                        };
-                    }
-                    Modification::None => {}
-                }
-            }
-            current = Some(Cow::Owned(future));
+            let outcome = apply(op, &was, &mut current);
+            outcomes[opi] = outcomes[opi].combine(outcome);
         }
 
         // we may have changed things in the last iteration of the loop above
@@ -267,7 +342,116 @@ impl Base {
             self.fix(r);
         }
 
-        results.into()
+        (results.into(), outcomes)
+    }
+
+    /// Replace every predicate-based operation in `ops` with operations on the primary keys of the
+    /// rows it matches. Each operation is paired with the index of the operation it came from.
+    ///
+    /// A predicate sees the rows in `db` as they are after the operations that precede it in
+    /// `ops`, even though those operations have not been applied to `db` yet.
+    fn resolve_predicates<'a>(
+        &self,
+        ops: Vec<(usize, TableOperation)>,
+        db: &'a dyn State,
+    ) -> Vec<(usize, TableOperation)> {
+        let key_cols = &self.primary_key.as_ref().unwrap()[..];
+        let row_key = |row: &[DataType]| -> Vec<DataType> {
+            key_cols.iter().map(|&col| row[col].clone()).collect()
+        };
+
+        // the rows that earlier operations touched, as they were before the batch, and as they are
+        // after those operations.
+        type Touched<'r> =
+            HashMap<Vec<DataType>, (Option<Cow<'r, [DataType]>>, Option<Cow<'r, [DataType]>>)>;
+        let mut touched = Touched::new();
+        let touch = |touched: &mut Touched<'a>, op: TableOperation, key: Vec<DataType>| {
+            let (ref was, ref mut current) = *match touched.entry(key) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let row = lookup_key(db, key_cols, e.key());
+                    e.insert((row.clone(), row))
+                }
+            };
+            apply(op, was, current);
+        };
+
+        let mut resolved = Vec::with_capacity(ops.len());
+        for (opi, op) in ops {
+            let (filter, set) = match op {
+                TableOperation::DeleteWhere { filter } => (filter, None),
+                TableOperation::UpdateWhere { filter, set } => (filter, Some(set)),
+                op => {
+                    touch(
+                        &mut touched,
+                        op.clone(),
+                        key_of(key_cols, &op).cloned().collect(),
+                    );
+                    resolved.push((opi, op));
+                    continue;
+                }
+            };
+
+            let mut matching: Vec<_> = touched
+                .values()
+                .filter_map(|(_, current)| current.as_ref())
+                .map(|row| row.to_vec())
+                .collect();
+            matching.extend(
+                self.lookup_where(db, &filter)
+                    .into_iter()
+                    .map(Cow::into_owned)
+                    .filter(|row| !touched.contains_key(&row_key(row))),
+            );
+
+            for mut row in matching {
+                self.fix(&mut row);
+                if filter.iter().any(|(col, value)| row[*col] != *value) {
+                    continue;
+                }
+
+                let key = row_key(&row);
+                let op = match set {
+                    None => TableOperation::Delete { key: key.clone() },
+                    Some(ref set) => TableOperation::Update {
+                        key: key.clone(),
+                        set: set.clone(),
+                    },
+                };
+                touch(&mut touched, op.clone(), key);
+                resolved.push((opi, op));
+            }
+        }
+        resolved
+    }
+
+    /// Find the rows in `db` that may match `filter`.
+    ///
+    /// These are the rows that match on the columns of the widest index of `db` that `filter`
+    /// covers. Tables reject filters that cover no index, as those would need a scan of every
+    /// row, so if there is no such index, no rows are returned.
+    fn lookup_where<'a>(
+        &self,
+        db: &'a dyn State,
+        filter: &[(usize, DataType)],
+    ) -> Vec<Cow<'a, [DataType]>> {
+        let value = |col: usize| filter.iter().find(|(c, _)| *c == col).map(|(_, v)| v);
+        let index = db
+            .keys()
+            .into_iter()
+            .filter(|cols| cols.iter().all(|&col| value(col).is_some()))
+            .max_by_key(Vec::len);
+
+        match index {
+            Some(cols) => {
+                let key = KeyType::from(cols.iter().map(|&col| value(col).unwrap()));
+                match db.lookup(&cols, &key) {
+                    LookupResult::Some(rows) => rows.into_iter().collect(),
+                    LookupResult::Missing => unreachable!("base state is never partial"),
+                }
+            }
+            None => Vec::new(),
+        }
     }
 
     pub(in crate::node) fn suggest_indexes(&self, n: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
@@ -307,7 +491,12 @@ mod tests {
         assert_eq!(b.unmodified, true);
     }
 
-    fn test_lots_of_changes_in_same_batch(mut state: Box<dyn State>) {
+    /// Set up a base with columns `x`, `y` and `z` keyed by `x` and `z`, and return a function
+    /// that processes a batch of operations on it.
+    fn base(
+        mut state: Box<dyn State>,
+        extra_indexes: &[&[usize]],
+    ) -> impl FnMut(Vec<TableOperation>) -> (Records, Vec<WriteOutcome>) {
         use crate::node;
         use crate::prelude::*;
 
@@ -338,18 +527,24 @@ mod tests {
         for (_, col) in graph[global].suggest_indexes(global) {
             state.add_key(&col[..], None);
         }
+        for cols in extra_indexes {
+            state.add_key(cols, None);
+        }
 
         let mut states = StateMap::new();
         states.insert(local, state);
         let n = graph[global].take();
         let mut n = n.finalize(&graph);
 
-        let mut one = move |u: Vec<TableOperation>| {
-            let mut m = n.get_base_mut().unwrap().process(local, u, &states);
+        move |u: Vec<TableOperation>| {
+            let (mut m, outcomes) = n.get_base_mut().unwrap().process(local, u, &states);
             node::materialize(&mut m, None, states.get_mut(local));
-            m
-        };
+            (m, outcomes)
+        }
+    }
 
+    fn test_lots_of_changes_in_same_batch(state: Box<dyn State>) {
+        let mut one = base(state, &[]);
         assert_eq!(
             one(vec![
                 TableOperation::Insert(vec![1.into(), "a".into(), 1.into()]),
@@ -397,7 +592,21 @@ mod tests {
                     key: vec![2.into(), 1.into()],
                 },
             ]),
-            Records::default()
+            (
+                Records::default(),
+                vec![
+                    WriteOutcome::Inserted,
+                    WriteOutcome::Inserted,
+                    WriteOutcome::Deleted,
+                    WriteOutcome::Inserted,
+                    WriteOutcome::Updated,
+                    WriteOutcome::Updated,
+                    WriteOutcome::Updated,
+                    WriteOutcome::Updated,
+                    WriteOutcome::Deleted,
+                    WriteOutcome::Deleted,
+                ]
+            )
         );
     }
 
@@ -417,4 +626,146 @@ mod tests {
 
         test_lots_of_changes_in_same_batch(Box::new(state));
     }
+
+    fn test_predicates(state: Box<dyn State>, extra_indexes: &[&[usize]]) {
+        let mut one = base(state, extra_indexes);
+        let set_y = |y: &str| {
+            vec![
+                Modification::None,
+                Modification::Set(y.into()),
+                Modification::None,
+            ]
+        };
+
+        let (_, outcomes) = one(vec![
+            TableOperation::Insert(vec![1.into(), "a".into(), 1.into()]),
+            TableOperation::Insert(vec![2.into(), "a".into(), 1.into()]),
+            TableOperation::Insert(vec![3.into(), "b".into(), 1.into()]),
+            TableOperation::Update {
+                key: vec![1.into(), 1.into()],
+                set: set_y("a"),
+            },
+            TableOperation::Update {
+                key: vec![9.into(), 1.into()],
+                set: set_y("a"),
+            },
+        ]);
+        assert_eq!(
+            outcomes,
+            vec![
+                WriteOutcome::Inserted,
+                WriteOutcome::Inserted,
+                WriteOutcome::Inserted,
+                WriteOutcome::NoOp,
+                WriteOutcome::NotFound,
+            ]
+        );
+
+        // predicates see the operations before them in the same batch, but not those after
+        let (mut rs, outcomes) = one(vec![
+            TableOperation::Insert(vec![4.into(), "a".into(), 1.into()]),
+            TableOperation::Update {
+                key: vec![3.into(), 1.into()],
+                set: set_y("a"),
+            },
+            TableOperation::Delete {
+                key: vec![2.into(), 1.into()],
+            },
+            TableOperation::UpdateWhere {
+                filter: vec![(1, "a".into())],
+                set: set_y("c"),
+            },
+            TableOperation::Insert(vec![5.into(), "a".into(), 1.into()]),
+        ]);
+        rs.sort();
+        let mut expected: Records = vec![
+            (vec![1.into(), "a".into(), 1.into()], false),
+            (vec![1.into(), "c".into(), 1.into()], true),
+            (vec![2.into(), "a".into(), 1.into()], false),
+            (vec![3.into(), "b".into(), 1.into()], false),
+            (vec![3.into(), "c".into(), 1.into()], true),
+            (vec![4.into(), "c".into(), 1.into()], true),
+            (vec![5.into(), "a".into(), 1.into()], true),
+        ]
+        .into_iter()
+        .map(Record::from)
+        .collect();
+        expected.sort();
+        assert_eq!(rs, expected);
+        assert_eq!(
+            outcomes,
+            vec![
+                WriteOutcome::Inserted,
+                WriteOutcome::Updated,
+                WriteOutcome::Deleted,
+                WriteOutcome::Updated,
+                WriteOutcome::Inserted,
+            ]
+        );
+
+        let (mut rs, outcomes) = one(vec![TableOperation::DeleteWhere {
+            filter: vec![(1, "c".into()), (2, 1.into())],
+        }]);
+        rs.sort();
+        let mut expected: Records = vec![
+            (vec![1.into(), "c".into(), 1.into()], false),
+            (vec![3.into(), "c".into(), 1.into()], false),
+            (vec![4.into(), "c".into(), 1.into()], false),
+        ]
+        .into_iter()
+        .map(Record::from)
+        .collect();
+        expected.sort();
+        assert_eq!(rs, expected);
+        assert_eq!(outcomes, vec![WriteOutcome::Deleted]);
+
+        assert_eq!(
+            one(vec![TableOperation::DeleteWhere {
+                filter: vec![(1, "c".into())],
+            }]),
+            (Records::default(), vec![WriteOutcome::NotFound])
+        );
+    }
+
+    #[test]
+    fn predicates_need_an_index() {
+        let mut one = base(Box::new(MemoryState::default()), &[]);
+        let row = vec![1.into(), "a".into(), 1.into()];
+        one(vec![TableOperation::Insert(row.clone())]);
+
+        // no index covers column 1, and the base won't scan all its rows instead
+        assert_eq!(
+            one(vec![TableOperation::DeleteWhere {
+                filter: vec![(1, "a".into())],
+            }]),
+            (Records::default(), vec![WriteOutcome::NotFound])
+        );
+
+        // but the key is always indexed
+        assert_eq!(
+            one(vec![TableOperation::DeleteWhere {
+                filter: vec![(0, 1.into()), (1, "a".into()), (2, 1.into())],
+            }]),
+            (
+                vec![Record::from((row, false))].into(),
+                vec![WriteOutcome::Deleted]
+            )
+        );
+    }
+
+    #[test]
+    fn predicates_indexed() {
+        test_predicates(Box::new(MemoryState::default()), &[&[1]]);
+    }
+
+    #[test]
+    fn predicates_persistent() {
+        let state = PersistentState::new(
+            String::from("predicates_persistent"),
+            None,
+            &PersistenceParameters::default(),
+        );
+
+        test_predicates(Box::new(state), &[&[1]]);
+    }
 }
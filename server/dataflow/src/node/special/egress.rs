@@ -12,6 +12,8 @@ struct EgressTx {
 pub struct Egress {
     txs: Vec<EgressTx>,
     tags: HashMap<Tag, NodeIndex>,
+    /// Base table writes that our children must learn about, but that produced no records.
+    owed: Writes,
 }
 
 impl Clone for Egress {
@@ -21,6 +23,7 @@ impl Clone for Egress {
         Self {
             txs: Vec::new(),
             tags: self.tags.clone(),
+            owed: Writes::default(),
         }
     }
 }
@@ -30,6 +33,7 @@ impl Default for Egress {
         Self {
             tags: Default::default(),
             txs: Default::default(),
+            owed: Default::default(),
         }
     }
 }
@@ -56,8 +60,31 @@ impl Egress {
         let &mut Self {
             ref mut txs,
             ref tags,
+            ref mut owed,
         } = self;
 
+        if let Packet::Message {
+            ref data,
+            ref mut token,
+            ref txns,
+            ..
+        } = **m.as_mut().unwrap()
+        {
+            // NOTE: readers count how many times they see each part of a transaction, so updates
+            // that are part of one are always sent on by themselves.
+            if txns.is_empty() && data.is_empty() {
+                // the update only tells our children about writes that produced no records.
+                // rather than sending it to every child domain, we tell them along with the next
+                // update, or when the domain next flushes the tokens we owe.
+                owed.merge(token);
+                *m = None;
+                return;
+            } else if txns.is_empty() {
+                token.merge(owed);
+                *owed = Writes::default();
+            }
+        }
+
         // send any queued updates to all external children
         assert!(!txs.is_empty());
         let txn = txs.len() - 1;
@@ -102,4 +129,29 @@ impl Egress {
             }
         }
     }
+
+    /// Returns true if our children have yet to learn about writes that produced no records.
+    pub fn owes_tokens(&self) -> bool {
+        !self.owed.is_empty()
+    }
+
+    /// Tell our children about the writes they are owed in an update that carries no records.
+    pub fn flush_tokens(&mut self, shard: usize, output: &mut dyn Executor) {
+        if self.owed.is_empty() {
+            return;
+        }
+
+        let token = std::mem::take(&mut self.owed);
+        for tx in &self.txs {
+            output.send(
+                tx.dest,
+                Box::new(Packet::Message {
+                    link: Link::new(unsafe { LocalNodeIndex::make(shard as u32) }, tx.local),
+                    data: Records::default(),
+                    token: token.clone(),
+                    txns: Vec::new(),
+                }),
+            );
+        }
+    }
 }
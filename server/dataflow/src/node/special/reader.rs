@@ -1,5 +1,50 @@
 use crate::backlog;
+use crate::ops::filter::FilterCondition;
 use crate::prelude::*;
+use nom_sql::OrderType;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How long a reader waits for the remaining copies of a write that has arrived along only some
+/// of the paths that lead to it.
+///
+/// After this, it assumes that the remaining copies were lost, which happens if the write reached
+/// a node along the way while that node was still being added to the graph.
+const COPY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The writes to a base table shard that have yet to arrive along every path to a reader.
+struct Arrivals {
+    epoch: u64,
+    /// The first write that has yet to arrive along every path.
+    next: u64,
+    /// How many copies of each later write have arrived so far.
+    copies: BTreeMap<u64, usize>,
+    /// When we started waiting for the write `next`.
+    since: Instant,
+}
+
+/// Records kept from readers until every transaction they depend on is complete.
+#[derive(Default)]
+struct Stall {
+    /// The transactions that must all be complete before the records are released.
+    txns: HashSet<u64>,
+    /// The keys of the records. Later records for the same keys must wait behind them.
+    keys: HashSet<Vec<DataType>>,
+    records: Vec<Record>,
+    /// The base table writes reflected by the records, and by any later updates from the same
+    /// base table shards, which readers must not be told about before these records are visible.
+    token: WriteToken,
+}
+
+impl Stall {
+    fn absorb(&mut self, other: Stall) {
+        self.txns.extend(other.txns);
+        self.keys.extend(other.keys);
+        self.records.extend(other.records);
+        self.token.merge(&other.token);
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Reader {
@@ -8,6 +53,34 @@ pub struct Reader {
 
     for_node: NodeIndex,
     state: Option<Vec<usize>>,
+    /// The `ORDER BY` columns of the query, which lookups return the rows for a key in.
+    order: Vec<(usize, OrderType)>,
+    /// Conditions that compare against the query's parameters, which lookups check the rows of
+    /// the reader's single key against instead of looking the parameters up.
+    parameter_filter: Vec<(usize, FilterCondition)>,
+    /// Whether lookups may ask for ranges of keys, which needs the reader to keep its keys in
+    /// order.
+    ranges: bool,
+
+    /// The base tables this reader is computed from, and along how many paths the writes to a
+    /// shard of each reach the reader. The first count is of paths that only lead to the
+    /// matching shard of the reader, the second of paths that lead to every shard.
+    bases: Vec<(NodeIndex, usize, usize)>,
+    /// Transactions we have seen only some parts of, and how many more times we expect to see
+    /// the writes to each base table shard they are still missing.
+    ///
+    /// We wait for the missing parts for as long as it takes. If they never arrive because the
+    /// client failed part-way through committing the transaction, the keys it changed are held
+    /// back for good rather than showing readers only part of the transaction.
+    #[serde(skip)]
+    txns: HashMap<u64, HashMap<(NodeIndex, usize), usize>>,
+    /// The writes to each base table shard that we have yet to see along every path. Readers must
+    /// not learn about a write before then, as it may still be on its way along a slower path.
+    #[serde(skip)]
+    arrivals: HashMap<(NodeIndex, usize), Arrivals>,
+    /// Records of incomplete transactions, and of later writes to the same keys.
+    #[serde(skip)]
+    stalls: Vec<Stall>,
 }
 
 impl Clone for Reader {
@@ -17,6 +90,13 @@ impl Clone for Reader {
             writer: None,
             state: self.state.clone(),
             for_node: self.for_node,
+            order: self.order.clone(),
+            parameter_filter: self.parameter_filter.clone(),
+            ranges: self.ranges,
+            bases: self.bases.clone(),
+            txns: HashMap::new(),
+            arrivals: HashMap::new(),
+            stalls: Vec::new(),
         }
     }
 }
@@ -27,6 +107,13 @@ impl Reader {
             writer: None,
             state: None,
             for_node,
+            order: Vec::new(),
+            parameter_filter: Vec::new(),
+            ranges: false,
+            bases: Vec::new(),
+            txns: HashMap::new(),
+            arrivals: HashMap::new(),
+            stalls: Vec::new(),
         }
     }
 
@@ -50,9 +137,20 @@ impl Reader {
             writer: self.writer.take(),
             state: self.state.clone(),
             for_node: self.for_node,
+            order: self.order.clone(),
+            parameter_filter: self.parameter_filter.clone(),
+            ranges: self.ranges,
+            bases: self.bases.clone(),
+            txns: std::mem::take(&mut self.txns),
+            arrivals: std::mem::take(&mut self.arrivals),
+            stalls: std::mem::take(&mut self.stalls),
         }
     }
 
+    pub fn set_bases(&mut self, bases: Vec<(NodeIndex, usize, usize)>) {
+        self.bases = bases;
+    }
+
     pub fn is_materialized(&self) -> bool {
         self.state.is_some()
     }
@@ -81,6 +179,30 @@ impl Reader {
         }
     }
 
+    pub fn order(&self) -> &[(usize, OrderType)] {
+        &self.order[..]
+    }
+
+    pub fn set_order(&mut self, order: &[(usize, OrderType)]) {
+        self.order = Vec::from(order);
+    }
+
+    pub fn parameter_filter(&self) -> &[(usize, FilterCondition)] {
+        &self.parameter_filter[..]
+    }
+
+    pub fn set_parameter_filter(&mut self, filter: &[(usize, FilterCondition)]) {
+        self.parameter_filter = Vec::from(filter);
+    }
+
+    pub fn ranges(&self) -> bool {
+        self.ranges
+    }
+
+    pub fn set_ranges(&mut self, ranges: bool) {
+        self.ranges = ranges;
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.writer.as_ref().map(|w| w.is_empty()).unwrap_or(true)
     }
@@ -99,6 +221,7 @@ impl Reader {
             bytes_freed = handle.evict_random_keys(&mut rng, n);
             handle.swap();
         }
+        self.forget_stalled_holes();
         bytes_freed
     }
 
@@ -110,66 +233,376 @@ impl Reader {
             }
             w.swap();
         }
+        self.forget_stalled_holes();
+    }
+
+    /// Drop the stalled records for keys that have been evicted.
+    ///
+    /// A later replay of such a key already reflects those records, so they must not be added
+    /// to it once released.
+    pub(crate) fn forget_stalled_holes(&mut self) {
+        if let Some(ref state) = self.writer {
+            if state.is_partial() {
+                for stall in &mut self.stalls {
+                    stall.records.retain(|r| keep_regular(state, &r[..]));
+                }
+            }
+        }
+    }
+
+    /// When we next give up waiting for the remaining copies of a write.
+    pub(crate) fn wait_deadline(&self) -> Option<Instant> {
+        self.arrivals
+            .values()
+            .filter(|a| !a.copies.is_empty())
+            .map(|a| a.since + COPY_TIMEOUT)
+            .min()
+    }
+
+    /// Give up on the copies of writes we have waited too long for, and let readers learn about
+    /// those writes.
+    pub(crate) fn expire_waits(&mut self, now: Instant) {
+        let mut given_up = WriteToken::default();
+        for (&(base, s), a) in &mut self.arrivals {
+            if let Some(&last) = a.copies.keys().next_back() {
+                if now.duration_since(a.since) >= COPY_TIMEOUT {
+                    a.next = last + 1;
+                    a.copies.clear();
+                    a.since = now;
+                    given_up.merge(&WriteToken::new(base, s, a.epoch, last));
+                }
+            }
+        }
+
+        if !given_up.is_empty() && self.writer.is_some() {
+            let visible = self.stall(Records::default(), &given_up, Vec::new());
+            debug_assert!(visible.is_empty());
+            self.release_stalls();
+            self.writer.as_mut().unwrap().swap();
+        }
+    }
+
+    /// Count the copies of the writes in `writes`, which arrived at our shard `shard`.
+    ///
+    /// Returns the writes that have now arrived along every path that leads here, and whether
+    /// every write in `writes` had already done so before.
+    fn arrive(&mut self, writes: &Writes, shard: usize) -> (WriteToken, bool) {
+        let now = Instant::now();
+        let mut arrived = WriteToken::default();
+        let mut repeated = !writes.is_empty();
+        for (&(base, s), runs) in writes.runs() {
+            // a reader that has not been told about its bases only has the one path
+            let paths = cmp::max(paths(&self.bases, base, s, shard), 1);
+            let a = self.arrivals.entry((base, s)).or_insert_with(|| Arrivals {
+                epoch: 0,
+                next: 0,
+                copies: BTreeMap::new(),
+                since: now,
+            });
+            let before = (a.epoch, a.next);
+            for &(epoch, first, last) in runs {
+                if epoch < a.epoch || (epoch == a.epoch && last < a.next) {
+                    // every copy of these writes has arrived, or we gave up waiting for them
+                    continue;
+                }
+                repeated = false;
+
+                if epoch > a.epoch {
+                    // this is the first write we see since the base table shard was (re)started,
+                    // and we won't ever see the copies of earlier writes that we are missing
+                    a.epoch = epoch;
+                    a.next = first;
+                    a.copies.clear();
+                }
+
+                let first = cmp::max(first, a.next);
+                if paths == 1 && first == a.next && a.copies.is_empty() {
+                    // the writes along a single path arrive in order
+                    a.next = last + 1;
+                    continue;
+                }
+                for seq in first..=last {
+                    *a.copies.entry(seq).or_insert(0) += 1;
+                }
+                while a.copies.get(&a.next).map(|&n| n >= paths).unwrap_or(false) {
+                    a.copies.remove(&a.next);
+                    a.next += 1;
+                }
+            }
+
+            if (a.epoch, a.next) != before {
+                a.since = now;
+                arrived.merge(&WriteToken::new(base, s, a.epoch, a.next - 1));
+            }
+        }
+        (arrived, repeated)
     }
 
-    pub(in crate::node) fn process(&mut self, m: &mut Option<Box<Packet>>, swap: bool) {
-        if let Some(ref mut state) = self.writer {
-            let m = m.as_mut().unwrap();
-            // make sure we don't fill a partial materialization
-            // hole with incomplete (i.e., non-replay) state.
-            if m.is_regular() && state.is_partial() {
-                m.map_data(|data| {
-                    data.retain(|row| {
-                        match state.entry_from_record(&row[..]).try_find_and(|_| ()) {
-                            Ok((None, _)) => {
-                                // row would miss in partial state.
-                                // leave it blank so later lookup triggers replay.
-                                false
-                            }
-                            Err(_) => unreachable!(),
-                            _ => {
-                                // state is already present,
-                                // so we can safely keep it up to date.
-                                true
-                            }
+    /// Keep track of the transactions that the writes in `token` are part of.
+    ///
+    /// Returns the transactions among `txns` that we have still only seen some parts of.
+    fn track_txns(
+        &mut self,
+        token: &Writes,
+        txns: &[Txn],
+        repeated: bool,
+        shard: usize,
+    ) -> Vec<u64> {
+        let mut incomplete = Vec::new();
+        for txn in txns {
+            if !self.txns.contains_key(&txn.id) {
+                if repeated {
+                    // NOTE: this is a write we have already seen, so the transaction it is part
+                    // of has either been completed, or we gave up waiting for this copy of it.
+                    continue;
+                }
+
+                let bases = &self.bases;
+                let expected = txn
+                    .parts
+                    .iter()
+                    .filter_map(|&(base, s)| {
+                        // we'll only ever see the parts whose writes can reach our shard, once
+                        // along every path that leads here
+                        let paths = paths(bases, base, s, shard);
+                        if paths == 0 {
+                            None
+                        } else {
+                            Some(((base, s), paths))
                         }
-                    });
-                });
+                    })
+                    .collect();
+                self.txns.insert(txn.id, expected);
+            }
+
+            let missing = self.txns.get_mut(&txn.id).unwrap();
+            for &(base, s) in &txn.parts {
+                if !token.contains(base, s) {
+                    continue;
+                }
+                if let Some(paths) = missing.get_mut(&(base, s)) {
+                    *paths -= 1;
+                    if *paths == 0 {
+                        missing.remove(&(base, s));
+                    }
+                }
+            }
+            if missing.is_empty() {
+                self.txns.remove(&txn.id);
+            } else {
+                incomplete.push(txn.id);
             }
+        }
+        incomplete
+    }
+
+    /// Add `records` and the writes in `token` to the stalls, keeping back from readers any
+    /// record that is part of one of the transactions in `txns`, or that has the same key as a
+    /// stalled record. Returns the records that may be made visible right away.
+    fn stall(&mut self, records: Records, token: &WriteToken, txns: Vec<u64>) -> Records {
+        let key = self.state.as_ref().unwrap();
+        let mut stall = Stall::default();
+        stall.txns.extend(txns);
+
+        let mut visible = Records::default();
+        for r in records {
+            let k: Vec<_> = key.iter().map(|&c| r[c].clone()).collect();
+            if !stall.txns.is_empty() || self.stalls.iter().any(|s| s.keys.contains(&k)) {
+                stall.keys.insert(k);
+                stall.records.push(r);
+            } else {
+                visible.push(r);
+            }
+        }
+
+        // readers must not learn about a write before all of its records are visible, nor about
+        // a later write to a base table shard that an earlier stalled write came from, since
+        // that would cover the earlier one too.
+        let overlaps = |s: &Stall| {
+            s.txns.iter().any(|t| stall.txns.contains(t))
+                || s.keys.iter().any(|k| stall.keys.contains(k))
+                || s.token.overlaps(token)
+        };
+        if stall.txns.is_empty() && stall.records.is_empty() && !self.stalls.iter().any(overlaps) {
+            self.writer.as_mut().unwrap().add_writes(token);
+            return visible;
+        }
+
+        // stalls that share transactions, keys, or base table shards are released together
+        let (merged, rest) = std::mem::take(&mut self.stalls)
+            .into_iter()
+            .partition::<Vec<_>, _>(overlaps);
+        self.stalls = rest;
+        for s in merged {
+            stall.absorb(s);
+        }
+        stall.token.merge(token);
+        self.stalls.push(stall);
+        visible
+    }
+
+    /// Make visible the records of every stall whose transactions are all complete.
+    fn release_stalls(&mut self) {
+        let txns = &self.txns;
+        let (done, waiting) = std::mem::take(&mut self.stalls)
+            .into_iter()
+            .partition::<Vec<_>, _>(|s| s.txns.iter().all(|t| !txns.contains_key(t)));
+        self.stalls = waiting;
+
+        let state = self.writer.as_mut().unwrap();
+        for mut stall in done {
+            if state.is_partial() {
+                stall.records.retain(|r| keep_regular(state, &r[..]));
+            }
+            state.add_writes(&stall.token);
+            state.add(stall.records);
+        }
+    }
+
+    pub(in crate::node) fn process(
+        &mut self,
+        m: &mut Option<Box<Packet>>,
+        shard: usize,
+        swap: bool,
+    ) {
+        let state = match self.writer {
+            Some(ref mut state) => state,
+            None => return,
+        };
 
-            // it *can* happen that multiple readers miss (and thus request replay for) the
-            // same hole at the same time. we need to make sure that we ignore any such
-            // duplicated replay.
-            if !m.is_regular() && state.is_partial() {
-                m.map_data(|data| {
-                    data.retain(|row| {
-                        match state.entry_from_record(&row[..]).try_find_and(|_| ()) {
-                            Ok((None, _)) => {
-                                // filling a hole with replay -- ok
-                                true
-                            }
-                            Ok((Some(_), _)) => {
-                                // a given key should only be replayed to once!
-                                false
-                            }
-                            Err(_) => {
-                                // state has not yet been swapped, which means it's new,
-                                // which means there are no readers, which means no
-                                // requests for replays have been issued by readers, which
-                                // means no duplicates can be received.
-                                true
-                            }
+        let m = m.as_mut().unwrap();
+        // make sure we don't fill a partial materialization
+        // hole with incomplete (i.e., non-replay) state.
+        if m.is_regular() && state.is_partial() {
+            m.map_data(|data| {
+                data.retain(|row| keep_regular(state, &row[..]));
+            });
+        }
+
+        // it *can* happen that multiple readers miss (and thus request replay for) the
+        // same hole at the same time. we need to make sure that we ignore any such
+        // duplicated replay.
+        if !m.is_regular() && state.is_partial() {
+            m.map_data(|data| {
+                data.retain(|row| {
+                    match state.entry_from_record(&row[..]).try_find_and(|_| ()) {
+                        Ok((None, _)) => {
+                            // filling a hole with replay -- ok
+                            true
+                        }
+                        Ok((Some(_), _)) => {
+                            // a given key should only be replayed to once!
+                            false
                         }
-                    });
+                        Err(_) => {
+                            // state has not yet been swapped, which means it's new,
+                            // which means there are no readers, which means no
+                            // requests for replays have been issued by readers, which
+                            // means no duplicates can be received.
+                            true
+                        }
+                    }
                 });
+            });
+        }
+
+        let data = m.take_data();
+        match **m {
+            Packet::Message {
+                ref token,
+                ref txns,
+                ..
+            } => {
+                // readers may only learn about a write once it has arrived along every path
+                let (arrived, repeated) = self.arrive(token, shard);
+                if !txns.is_empty() || !self.stalls.is_empty() {
+                    // make sure that readers never see only part of a transaction, by keeping
+                    // back the records of incomplete transactions along with later records for
+                    // the same keys. all other keys are unaffected.
+                    let incomplete = self.track_txns(token, txns, repeated, shard);
+                    let visible = self.stall(data, &arrived, incomplete);
+                    self.release_stalls();
+                    self.writer.as_mut().unwrap().add(visible);
+                } else {
+                    let state = self.writer.as_mut().unwrap();
+                    state.add_writes(&arrived);
+                    state.add(data);
+                }
             }
+            _ => {
+                state.add(data);
+            }
+        }
+
+        if swap {
+            // TODO: avoid doing the pointer swap if we didn't modify anything (inc. ts)
+            self.writer.as_mut().unwrap().swap();
+        }
+    }
+}
 
-            state.add(m.take_data());
+/// How many copies of each write to shard `s` of the base table `base` reach our shard `shard`,
+/// given the paths from each base table in `bases`.
+fn paths(bases: &[(NodeIndex, usize, usize)], base: NodeIndex, s: usize, shard: usize) -> usize {
+    bases
+        .iter()
+        .find(|&&(b, ..)| b == base)
+        .map(|&(_, aligned, other)| other + if s == shard { aligned } else { 0 })
+        .unwrap_or(0)
+}
 
-            if swap {
-                // TODO: avoid doing the pointer swap if we didn't modify anything (inc. ts)
-                state.swap();
-            }
+/// Returns false if `row` is for a key that would miss in the partial state, which it then must
+/// not be added to.
+fn keep_regular(state: &backlog::WriteHandle, row: &[DataType]) -> bool {
+    match state.entry_from_record(row).try_find_and(|_| ()) {
+        Ok((None, _)) => {
+            // row would miss in partial state.
+            // leave it blank so later lookup triggers replay, unless the
+            // key lies in a range that has been filled in its entirety, in
+            // which case this is simply the key's first record.
+            state.record_in_filled_range(row)
         }
+        Err(_) => unreachable!(),
+        _ => {
+            // state is already present,
+            // so we can safely keep it up to date.
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_arrive_along_every_path() {
+        let base = NodeIndex::new(0);
+        let mut r = Reader::new(NodeIndex::new(1));
+        r.set_bases(vec![(base, 0, 2)]);
+        let write = |seq| Writes::new(base, 0, 1, seq);
+        let token = |seq| WriteToken::new(base, 0, 1, seq);
+
+        // the first write has only arrived along one of the two paths
+        let (arrived, repeated) = r.arrive(&write(1), 0);
+        assert!(!arrived.covers(&token(1)));
+        assert!(!repeated);
+        let (arrived, _) = r.arrive(&write(1), 0);
+        assert!(arrived.covers(&token(1)));
+
+        // later writes along the fast path don't cover those still on the slow path
+        let (arrived, _) = r.arrive(&write(2), 0);
+        assert!(arrived.is_empty());
+        let (arrived, _) = r.arrive(&write(3), 0);
+        assert!(arrived.is_empty());
+        let mut slow = write(2);
+        slow.merge(&write(3));
+        let (arrived, _) = r.arrive(&slow, 0);
+        assert!(arrived.covers(&token(3)));
+
+        // and copies of writes that have arrived along every path are nothing new
+        let (arrived, repeated) = r.arrive(&write(3), 0);
+        assert!(arrived.is_empty());
+        assert!(repeated);
     }
 }
@@ -7,6 +7,8 @@ pub struct Sharder {
     txs: Vec<(LocalNodeIndex, ReplicaAddr)>,
     sharded: VecMap<Box<Packet>>,
     shard_by: usize,
+    /// Base table writes that every shard must learn about, but that produced no records for it.
+    owed: VecMap<Writes>,
 }
 
 impl Clone for Sharder {
@@ -17,6 +19,7 @@ impl Clone for Sharder {
             txs: Vec::new(),
             sharded: Default::default(),
             shard_by: self.shard_by,
+            owed: Default::default(),
         }
     }
 }
@@ -27,6 +30,7 @@ impl Sharder {
             txs: Default::default(),
             shard_by: by,
             sharded: VecMap::default(),
+            owed: VecMap::default(),
         }
     }
 
@@ -37,6 +41,7 @@ impl Sharder {
             txs,
             sharded: VecMap::default(),
             shard_by: self.shard_by,
+            owed: VecMap::default(),
         }
     }
 
@@ -118,6 +123,40 @@ impl Sharder {
             }
         } else {
             assert!(is_last_sharder_for_tag.is_none());
+            if let Packet::Message {
+                ref token,
+                ref txns,
+                ..
+            } = *m
+            {
+                // every shard needs to learn about the writes in this update, even if none of its
+                // records ended up there, so that its readers can tell that they reflect them.
+                // sending an empty update to every such shard for every write would be wasteful
+                // though, so instead we tell them along with the next update they do get, or when
+                // the domain next flushes the tokens we owe.
+                //
+                // the exception is updates that are part of transactions: readers count how many
+                // times they see each part of a transaction, so every shard must get this update
+                // exactly once, and without any other writes mixed in.
+                for shard in 0..self.txs.len() {
+                    if !txns.is_empty() {
+                        dest = Destination::All;
+                        break;
+                    }
+                    if let Some(p) = self.sharded.get_mut(shard) {
+                        if let Some(owed) = self.owed.remove(shard) {
+                            if let Packet::Message { ref mut token, .. } = **p {
+                                token.merge(&owed);
+                            }
+                        }
+                    } else if !token.is_empty() {
+                        self.owed
+                            .entry(shard)
+                            .or_insert_with(Writes::default)
+                            .merge(token);
+                    }
+                }
+            }
         }
 
         match dest {
@@ -160,6 +199,28 @@ impl Sharder {
         }
     }
 
+    /// Returns true if some shard has yet to learn about writes that produced no records for it.
+    pub fn owes_tokens(&self) -> bool {
+        !self.owed.is_empty()
+    }
+
+    /// Tell every shard about the writes it is owed in an update that carries no records.
+    pub fn flush_tokens(&mut self, index: LocalNodeIndex, output: &mut dyn Executor) {
+        for (i, &mut (dst, addr)) in self.txs.iter_mut().enumerate() {
+            if let Some(token) = self.owed.remove(i) {
+                output.send(
+                    addr,
+                    Box::new(Packet::Message {
+                        link: Link::new(index, dst),
+                        data: Records::default(),
+                        token,
+                        txns: Vec::new(),
+                    }),
+                );
+            }
+        }
+    }
+
     pub fn process_eviction(
         &mut self,
         key_columns: &[usize],
@@ -1,10 +1,9 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt::{self, Display};
 use std::sync;
 
 use crate::prelude::*;
-pub use nom_sql::Operator;
+pub use noria::filter::{FilterCondition, Operator, Value};
 
 /// Filters incoming records according to some filter.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,33 +12,6 @@ pub struct Filter {
     filter: sync::Arc<Vec<(usize, FilterCondition)>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum Value {
-    Constant(DataType),
-    Column(usize),
-}
-
-impl From<DataType> for Value {
-    fn from(dt: DataType) -> Self {
-        Value::Constant(dt)
-    }
-}
-
-impl Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Value::Constant(ref c) => write!(f, "{}", c),
-            Value::Column(ref ci) => write!(f, "col: {}", ci),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum FilterCondition {
-    Comparison(Operator, Value),
-    In(Vec<DataType>),
-}
-
 impl Filter {
     /// Construct a new filter operator. The `filter` vector must have as many elements as the
     /// `src` node has columns. Each column that is set to `None` matches any value, while columns
@@ -81,31 +53,7 @@ impl Ingredient for Filter {
         _: &DomainNodes,
         _: &StateMap,
     ) -> ProcessingResult {
-        rs.retain(|r| {
-            self.filter.iter().all(|(i, cond)| {
-                // check if this filter matches
-                let d = &r[*i];
-                match cond {
-                    FilterCondition::Comparison(ref op, ref f) => {
-                        let v = match *f {
-                            Value::Constant(ref dt) => dt,
-                            Value::Column(c) => &r[c],
-                        };
-                        match *op {
-                            Operator::Equal => d == v,
-                            Operator::NotEqual => d != v,
-                            Operator::Greater => d > v,
-                            Operator::GreaterOrEqual => d >= v,
-                            Operator::Less => d < v,
-                            Operator::LessOrEqual => d <= v,
-                            Operator::In => unreachable!(),
-                            _ => unimplemented!(),
-                        }
-                    }
-                    FilterCondition::In(ref fs) => fs.contains(d),
-                }
-            })
-        });
+        rs.retain(|r| self.filter.iter().all(|(i, cond)| cond.matches(r, *i)));
 
         ProcessingResult {
             results: rs,
@@ -150,6 +98,15 @@ impl Ingredient for Filter {
                             .collect::<Vec<_>>()
                             .join(", ")
                     )),
+                    FilterCondition::IsNull | FilterCondition::IsNotNull => {
+                        Some(format!("f{} {}", i, cond))
+                    }
+                    FilterCondition::Json(..) => {
+                        Some(format!("f{}{}", i, escape(&cond.to_string())))
+                    }
+                    FilterCondition::And(..) | FilterCondition::Or(..) => {
+                        Some(escape(&cond.to_string()))
+                    }
                 })
                 .collect::<Vec<_>>()
                 .as_slice()
@@ -172,30 +129,7 @@ impl Ingredient for Filter {
         self.lookup(*self.src, columns, key, nodes, states)
             .and_then(|result| {
                 let f = self.filter.clone();
-                let filter = move |r: &[DataType]| {
-                    f.iter().all(|(i, ref cond)| {
-                        // check if this filter matches
-                        let d = &r[*i];
-                        match *cond {
-                            FilterCondition::Comparison(ref op, ref f) => {
-                                let v = match *f {
-                                    Value::Constant(ref dt) => dt,
-                                    Value::Column(c) => &r[c],
-                                };
-                                match *op {
-                                    Operator::Equal => d == v,
-                                    Operator::NotEqual => d != v,
-                                    Operator::Greater => d > v,
-                                    Operator::GreaterOrEqual => d >= v,
-                                    Operator::Less => d < v,
-                                    Operator::LessOrEqual => d <= v,
-                                    _ => unimplemented!(),
-                                }
-                            }
-                            FilterCondition::In(ref fs) => fs.contains(d),
-                        }
-                    })
-                };
+                let filter = move |r: &[DataType]| f.iter().all(|(i, cond)| cond.matches(r, *i));
 
                 match result {
                     Some(rs) => {
@@ -420,4 +354,124 @@ mod tests {
         left = vec![42.into(), "b".into()];
         assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
     }
+
+    #[test]
+    fn it_works_with_nulls() {
+        let mut g = setup(
+            false,
+            Some(&[
+                (
+                    0,
+                    FilterCondition::Comparison(Operator::NotEqual, Value::Column(1)),
+                ),
+                (1, FilterCondition::IsNotNull),
+            ]),
+        );
+
+        let mut left: Vec<DataType>;
+
+        left = vec![1.into(), 2.into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+
+        // NULL is neither equal nor unequal to anything
+        left = vec![DataType::None, 2.into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+        left = vec![1.into(), DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        let mut g = setup(
+            false,
+            Some(&[
+                (0, FilterCondition::IsNull),
+                (1, FilterCondition::In(vec![DataType::None, "a".into()])),
+            ]),
+        );
+
+        left = vec![DataType::None, "a".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![1.into(), "a".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+        left = vec![DataType::None, DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
+
+    #[test]
+    fn it_works_with_disjunctions() {
+        // x = 1 OR (x < 3 AND y != 'a')
+        let eq = |v: DataType| FilterCondition::Comparison(Operator::Equal, Value::Constant(v));
+        let mut g = setup(
+            false,
+            Some(&[(
+                0,
+                FilterCondition::Or(vec![
+                    (0, eq(1.into())),
+                    (
+                        0,
+                        FilterCondition::And(vec![
+                            (
+                                0,
+                                FilterCondition::Comparison(
+                                    Operator::Less,
+                                    Value::Constant(3.into()),
+                                ),
+                            ),
+                            (
+                                1,
+                                FilterCondition::Comparison(
+                                    Operator::NotEqual,
+                                    Value::Constant("a".into()),
+                                ),
+                            ),
+                        ]),
+                    ),
+                ]),
+            )]),
+        );
+
+        let mut left: Vec<DataType>;
+
+        left = vec![1.into(), "a".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![2.into(), "b".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![2.into(), "a".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+        left = vec![3.into(), "b".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // a row that matches both sides is only emitted once
+        left = vec![1.into(), "b".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![2.into(), DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
+
+    #[test]
+    fn it_works_with_json_paths() {
+        let priority = |cond| FilterCondition::Json("$.priority".parse().unwrap(), Box::new(cond));
+        let mut g = setup(
+            false,
+            Some(&[(
+                1,
+                priority(FilterCondition::Comparison(
+                    Operator::GreaterOrEqual,
+                    Value::Column(0),
+                )),
+            )]),
+        );
+
+        let doc = |text| DataType::json(text).unwrap();
+        let mut left: Vec<DataType>;
+
+        left = vec![2.into(), doc(r#"{"priority": 3}"#)];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![4.into(), doc(r#"{"priority": 3}"#)];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // documents stored as text work too, but those without the path never match
+        left = vec![2.into(), r#"{"priority": 3, "owner": "bob"}"#.into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![2.into(), doc(r#"{"owner": "bob"}"#)];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
 }
@@ -2,6 +2,8 @@ use crate::ops::grouped::GroupedOperation;
 use crate::ops::grouped::GroupedOperator;
 
 use crate::prelude::*;
+use noria::BigDecimal;
+use std::ops::{Add, Neg};
 
 /// Supported aggregation operators.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -59,8 +61,90 @@ pub struct Aggregator {
     group: Vec<usize>,
 }
 
+/// A change to a count or sum.
+///
+/// Sums of integers (and booleans) are kept exact, become exact decimals once a `Decimal` is
+/// added to them, and only become floating point once a `Double` is added to them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SumDiff {
+    /// An integral change.
+    Integer(i128),
+    /// An exact decimal change.
+    Decimal(BigDecimal),
+    /// A floating point change.
+    Double(f64),
+}
+
+impl SumDiff {
+    /// The change from adding `value` to a sum, or `None` if `value` can't be summed.
+    ///
+    /// `NULL` values do not change the sum.
+    pub fn of(value: &DataType) -> Option<Self> {
+        match *value {
+            DataType::None => Some(SumDiff::Integer(0)),
+            DataType::Double(d) => Some(SumDiff::Double(d)),
+            DataType::Decimal(ref d) => Some(SumDiff::Decimal((**d).clone())),
+            DataType::Bool(..)
+            | DataType::Int(..)
+            | DataType::UnsignedInt(..)
+            | DataType::BigInt(..)
+            | DataType::UnsignedBigInt(..) => Some(SumDiff::Integer(value.into())),
+            _ => None,
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match *self {
+            SumDiff::Integer(n) => n as f64,
+            SumDiff::Decimal(ref d) => f64::from(&DataType::from(d.clone())),
+            SumDiff::Double(d) => d,
+        }
+    }
+
+    /// Apply `diffs` to the sum `current` (if any), and return the new sum.
+    pub fn apply(current: Option<&DataType>, diffs: &mut dyn Iterator<Item = Self>) -> DataType {
+        let n = match current {
+            Some(n) => SumDiff::of(n).unwrap_or_else(|| unreachable!()),
+            None => SumDiff::Integer(0),
+        };
+        match diffs.fold(n, Add::add) {
+            SumDiff::Integer(n) => n.into(),
+            SumDiff::Decimal(d) => d.into(),
+            SumDiff::Double(d) => DataType::Double(d),
+        }
+    }
+}
+
+impl Neg for SumDiff {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            SumDiff::Integer(n) => SumDiff::Integer(-n),
+            SumDiff::Decimal(d) => SumDiff::Decimal(-d),
+            SumDiff::Double(d) => SumDiff::Double(-d),
+        }
+    }
+}
+
+impl Add for SumDiff {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (SumDiff::Integer(a), SumDiff::Integer(b)) => SumDiff::Integer(a + b),
+            (SumDiff::Decimal(a), SumDiff::Decimal(b)) => SumDiff::Decimal(a + b),
+            (SumDiff::Integer(n), SumDiff::Decimal(d))
+            | (SumDiff::Decimal(d), SumDiff::Integer(n)) => {
+                SumDiff::Decimal(d + BigDecimal::from(DataType::from(n)))
+            }
+            (a, b) => SumDiff::Double(a.to_f64() + b.to_f64()),
+        }
+    }
+}
+
 impl GroupedOperation for Aggregator {
-    type Diff = i128;
+    type Diff = SumDiff;
 
     fn setup(&mut self, parent: &Node) {
         assert!(
@@ -75,21 +159,16 @@ impl GroupedOperation for Aggregator {
 
     fn to_diff(&self, r: &[DataType], pos: bool) -> Self::Diff {
         match self.op {
-            Aggregation::COUNT if pos => 1,
-            Aggregation::COUNT => -1,
+            Aggregation::COUNT if pos => SumDiff::Integer(1),
+            Aggregation::COUNT => SumDiff::Integer(-1),
             Aggregation::SUM => {
-                let v = match r[self.over] {
-                    DataType::Int(n) => i128::from(n),
-                    DataType::UnsignedInt(n) => i128::from(n),
-                    DataType::BigInt(n) => i128::from(n),
-                    DataType::UnsignedBigInt(n) => i128::from(n),
-                    DataType::None => 0,
-                    ref x => unreachable!("tried to aggregate over {:?} on {:?}", x, r),
-                };
+                let v = SumDiff::of(&r[self.over]).unwrap_or_else(|| {
+                    unreachable!("tried to aggregate over {:?} on {:?}", r[self.over], r)
+                });
                 if pos {
                     v
                 } else {
-                    0i128 - v
+                    -v
                 }
             }
         }
@@ -100,15 +179,7 @@ impl GroupedOperation for Aggregator {
         current: Option<&DataType>,
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
-        let n = match current {
-            Some(&DataType::Int(n)) => i128::from(n),
-            Some(&DataType::UnsignedInt(n)) => i128::from(n),
-            Some(&DataType::BigInt(n)) => i128::from(n),
-            Some(&DataType::UnsignedBigInt(n)) => i128::from(n),
-            None => 0,
-            _ => unreachable!(),
-        };
-        diffs.fold(n, |n, d| n + d).into()
+        SumDiff::apply(current, diffs)
     }
 
     fn description(&self, detailed: bool) -> String {
@@ -387,7 +458,64 @@ mod tests {
         }
     }
 
-    // TODO: also test SUM
+    #[test]
+    fn it_sums_doubles() {
+        let mut c = ops::test::MockGraph::new();
+        let s = c.add_base("source", &["x", "y"]);
+        c.set_op(
+            "identity",
+            &["x", "ys"],
+            Aggregation::SUM.over(s.as_global(), 1, &[0]),
+            true,
+        );
+
+        let rs = c.narrow_one_row(vec![1.into(), 2.into()], true);
+        let expected: Records = vec![(vec![1.into(), 2.into()], true)].into();
+        assert_eq!(rs, expected);
+
+        // the sum becomes a double once a double is added to it
+        let rs = c.narrow_one_row(vec![1.into(), DataType::Double(0.5)], true);
+        let expected: Records = vec![
+            (vec![1.into(), 2.into()], false),
+            (vec![1.into(), DataType::Double(2.5)], true),
+        ]
+        .into();
+        assert_eq!(rs, expected);
+
+        let rs = c.narrow_one_row((vec![1.into(), 2.into()], false), true);
+        let expected: Records = vec![
+            (vec![1.into(), DataType::Double(2.5)], false),
+            (vec![1.into(), DataType::Double(0.5)], true),
+        ]
+        .into();
+        assert_eq!(rs, expected);
+    }
+
+    #[test]
+    fn it_sums_decimals_exactly() {
+        use std::str::FromStr;
+        let d = |s: &str| DataType::from(BigDecimal::from_str(s).unwrap());
+
+        let mut c = ops::test::MockGraph::new();
+        let s = c.add_base("source", &["x", "y"]);
+        c.set_op(
+            "identity",
+            &["x", "ys"],
+            Aggregation::SUM.over(s.as_global(), 1, &[0]),
+            true,
+        );
+
+        c.narrow_one_row(vec![1.into(), d("0.10")], true);
+        c.narrow_one_row(vec![1.into(), 2.into()], true);
+        let rs = c.narrow_one_row(vec![1.into(), d("0.20")], true);
+        let expected: Records = vec![
+            (vec![1.into(), d("2.10")], false),
+            (vec![1.into(), d("2.30")], true),
+        ]
+        .into();
+        assert_eq!(rs, expected);
+        assert_eq!(rs[1][1].to_string(), "2.30");
+    }
 
     #[test]
     fn it_suggests_indices() {
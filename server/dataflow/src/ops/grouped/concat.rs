@@ -97,7 +97,15 @@ impl GroupConcat {
                     DataType::UnsignedInt(ref n) => s.push_str(&n.to_string()),
                     DataType::BigInt(ref n) => s.push_str(&n.to_string()),
                     DataType::UnsignedBigInt(ref n) => s.push_str(&n.to_string()),
-                    DataType::Real(..) => s.push_str(&rec[*i].to_string()),
+                    DataType::Bool(ref b) => s.push_str(if *b { "1" } else { "0" }),
+                    DataType::Real(..)
+                    | DataType::Double(..)
+                    | DataType::Decimal(..)
+                    | DataType::Date(..)
+                    | DataType::Time(..)
+                    | DataType::Interval(..)
+                    | DataType::Json(..)
+                    | DataType::ByteArray(..) => s.push_str(&rec[*i].to_string()),
                     DataType::Timestamp(ref ts) => s.push_str(&ts.format("%+").to_string()),
                     DataType::None => unreachable!(),
                 },
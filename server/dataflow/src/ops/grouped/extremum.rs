@@ -59,8 +59,8 @@ pub struct ExtremumOperator {
 }
 
 pub enum DiffType {
-    Insert(i128),
-    Remove(i128),
+    Insert(DataType),
+    Remove(DataType),
 }
 
 impl GroupedOperation for ExtremumOperator {
@@ -79,10 +79,7 @@ impl GroupedOperation for ExtremumOperator {
 
     fn to_diff(&self, r: &[DataType], pos: bool) -> Self::Diff {
         let v = match r[self.over] {
-            DataType::Int(n) => i128::from(n),
-            DataType::UnsignedInt(n) => i128::from(n),
-            DataType::BigInt(n) => i128::from(n),
-            DataType::UnsignedBigInt(n) => i128::from(n),
+            ref n if n.is_numeric() => n.clone(),
             _ => {
                 // the column we're aggregating over is non-numerical (or rather, this value is).
                 // if you've removed a column, chances are the  default value has the wrong type.
@@ -111,26 +108,14 @@ impl GroupedOperation for ExtremumOperator {
     ) -> DataType {
         // Extreme values are those that are at least as extreme as the current min/max (if any).
         // let mut is_extreme_value : Box<dyn Fn(i64) -> bool> = Box::new(|_|true);
-        let mut extreme_values: Vec<i128> = vec![];
+        let mut extreme_values: Vec<DataType> = vec![];
         if let Some(data) = current {
-            match *data {
-                DataType::Int(n) => extreme_values.push(i128::from(n)),
-                DataType::UnsignedInt(n) => extreme_values.push(i128::from(n)),
-                DataType::BigInt(n) => extreme_values.push(i128::from(n)),
-                DataType::UnsignedBigInt(n) => extreme_values.push(i128::from(n)),
-                _ => unreachable!(),
-            }
+            assert!(data.is_numeric());
+            extreme_values.push(data.clone());
         };
 
-        let is_extreme_value = |x: i128| {
-            if let Some(data) = current {
-                let n = match *data {
-                    DataType::Int(n) => i128::from(n),
-                    DataType::UnsignedInt(n) => i128::from(n),
-                    DataType::BigInt(n) => i128::from(n),
-                    DataType::UnsignedBigInt(n) => i128::from(n),
-                    _ => unreachable!(),
-                };
+        let is_extreme_value = |x: &DataType| {
+            if let Some(n) = current {
                 match self.op {
                     Extremum::MAX => x >= n,
                     Extremum::MIN => x <= n,
@@ -142,9 +127,9 @@ impl GroupedOperation for ExtremumOperator {
 
         for d in diffs {
             match d {
-                DiffType::Insert(v) if is_extreme_value(v) => extreme_values.push(v),
-                DiffType::Remove(v) if is_extreme_value(v) => {
-                    if let Some(i) = extreme_values.iter().position(|x: &i128| *x == v) {
+                DiffType::Insert(v) if is_extreme_value(&v) => extreme_values.push(v),
+                DiffType::Remove(v) if is_extreme_value(&v) => {
+                    if let Some(i) = extreme_values.iter().position(|x| *x == v) {
                         extreme_values.swap_remove(i);
                     }
                 }
@@ -158,7 +143,7 @@ impl GroupedOperation for ExtremumOperator {
         };
 
         if let Some(extreme) = extreme {
-            return extreme.into();
+            return extreme;
         }
 
         // TODO: handle this case by querying into the parent.
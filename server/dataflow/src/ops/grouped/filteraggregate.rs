@@ -1,6 +1,7 @@
 use std::sync;
 
-use crate::ops::filter::{FilterCondition, Value};
+use crate::ops::filter::FilterCondition;
+use crate::ops::grouped::aggregate::SumDiff;
 use crate::ops::grouped::GroupedOperation;
 use crate::ops::grouped::GroupedOperator;
 pub use nom_sql::{Literal, Operator};
@@ -72,7 +73,7 @@ pub struct FilterAggregator {
 }
 
 impl GroupedOperation for FilterAggregator {
-    type Diff = i128;
+    type Diff = SumDiff;
 
     fn setup(&mut self, parent: &Node) {
         assert!(
@@ -86,60 +87,33 @@ impl GroupedOperation for FilterAggregator {
     }
 
     fn to_diff(&self, r: &[DataType], pos: bool) -> Self::Diff {
-        let passes_filter = self.filter.iter().all(|(i, cond)| {
-            // check if this filter matches
-            let d = &r[*i];
-            match *cond {
-                FilterCondition::Comparison(ref op, ref f) => {
-                    let v = match *f {
-                        Value::Constant(ref dt) => dt,
-                        Value::Column(c) => &r[c],
-                    };
-                    match *op {
-                        Operator::Equal => d == v,
-                        Operator::NotEqual => d != v,
-                        Operator::Greater => d > v,
-                        Operator::GreaterOrEqual => d >= v,
-                        Operator::Less => d < v,
-                        Operator::LessOrEqual => d <= v,
-                        Operator::In => unreachable!(),
-                        _ => unimplemented!(),
-                    }
-                }
-                FilterCondition::In(ref fs) => fs.contains(d),
-            }
-        });
+        let passes_filter = self.filter.iter().all(|(i, cond)| cond.matches(r, *i));
         let v = if passes_filter {
             match self.op {
-                FilterAggregation::COUNT => 1,
-                FilterAggregation::SUM => match r[self.over] {
-                    DataType::Int(n) => i128::from(n),
-                    DataType::UnsignedInt(n) => i128::from(n),
-                    DataType::BigInt(n) => i128::from(n),
-                    DataType::UnsignedBigInt(n) => i128::from(n),
-                    DataType::None => 0,
-                    ref x => unreachable!("tried to aggregate over {:?} on {:?}", x, r),
-                },
+                FilterAggregation::COUNT => SumDiff::Integer(1),
+                FilterAggregation::SUM => SumDiff::of(&r[self.over]).unwrap_or_else(|| {
+                    unreachable!("tried to aggregate over {:?} on {:?}", r[self.over], r)
+                }),
             }
         } else {
             // the filter returned false, so check whether we have an else case
             match self.over_else.clone() {
                 Some(over_else) => match self.op {
-                    FilterAggregation::COUNT => 1,
+                    FilterAggregation::COUNT => SumDiff::Integer(1),
                     FilterAggregation::SUM => match over_else {
-                        Literal::Integer(n) => i128::from(n),
-                        Literal::UnsignedInteger(n) => i128::from(n),
+                        Literal::Integer(n) => SumDiff::Integer(i128::from(n)),
+                        Literal::UnsignedInteger(n) => SumDiff::Integer(i128::from(n)),
                         ref x => unreachable!("tried to aggregate over {:?} on {:?}", x, r),
                     },
                 },
-                None => 0,
+                None => SumDiff::Integer(0),
             }
         };
 
         if pos {
             v
         } else {
-            0i128 - v
+            -v
         }
     }
 
@@ -148,15 +122,7 @@ impl GroupedOperation for FilterAggregator {
         current: Option<&DataType>,
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
-        let n = match current {
-            Some(&DataType::Int(n)) => i128::from(n),
-            Some(&DataType::UnsignedInt(n)) => i128::from(n),
-            Some(&DataType::BigInt(n)) => i128::from(n),
-            Some(&DataType::UnsignedBigInt(n)) => i128::from(n),
-            None => 0,
-            _ => unreachable!(),
-        };
-        diffs.fold(n, |n, d| n + d).into()
+        SumDiff::apply(current, diffs)
     }
 
     fn description(&self, detailed: bool) -> String {
@@ -192,6 +158,7 @@ mod tests {
     use super::*;
 
     use crate::ops;
+    use crate::ops::filter::Value;
 
     fn setup(mat: bool) -> ops::test::MockGraph {
         let mut g = ops::test::MockGraph::new();
@@ -292,6 +292,25 @@ impl Ingredient for Join {
             let mut new_right_count = None;
             let prev_join_key = rs[at][from_key].clone();
 
+            if prev_join_key.is_none() {
+                // NULL is not equal to anything, not even another NULL, so these rows never join.
+                // in a left join, the lefts still show up with NULLs in place of the right row.
+                let start = at;
+                at = rs[at..]
+                    .iter()
+                    .position(|r| r[from_key] != prev_join_key)
+                    .map(|p| at + p)
+                    .unwrap_or_else(|| rs.len());
+                if self.kind == JoinType::Left && from == *self.left {
+                    ret.extend(
+                        rs[start..at]
+                            .iter()
+                            .map(|r| (self.generate_null(r), r.is_positive()).into()),
+                    );
+                }
+                continue;
+            }
+
             if from == *self.right && self.kind == JoinType::Left {
                 let rc = self
                     .lookup(
@@ -737,6 +756,32 @@ mod tests {
         assert_eq!(rs.len(), 0);
     }
 
+    #[test]
+    fn it_does_not_join_nulls() {
+        let (mut j, l, r) = setup();
+        let l_null = vec![DataType::None, "a".into()];
+        let r_null = vec![DataType::None, "x".into()];
+
+        // a right with a NULL key has no lefts to join with
+        j.seed(r, r_null.clone());
+        let rs = j.one_row(r, r_null.clone(), false);
+        assert!(rs.is_empty());
+
+        // and a left with a NULL key doesn't join with it, but is still part of the left join
+        j.seed(l, l_null.clone());
+        let rs = j.one_row(l, l_null.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![DataType::None, "a".into(), DataType::None], true)].into()
+        );
+
+        let rs = j.one_row(l, (l_null, false), false);
+        assert_eq!(
+            rs,
+            vec![(vec![DataType::None, "a".into(), DataType::None], false)].into()
+        );
+    }
+
     #[test]
     fn it_suggests_indices() {
         use std::collections::HashMap;
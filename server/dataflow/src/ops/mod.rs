@@ -12,6 +12,8 @@ pub mod join;
 pub mod latest;
 pub mod project;
 pub mod rewrite;
+pub mod semi_join;
+pub mod theta_join;
 pub mod topk;
 pub mod trigger;
 pub mod union;
@@ -24,12 +26,15 @@ pub enum NodeOperator {
     Concat(grouped::GroupedOperator<grouped::concat::GroupConcat>),
     FilterSum(grouped::GroupedOperator<grouped::filteraggregate::FilterAggregator>),
     Join(join::Join),
+    ThetaJoin(theta_join::ThetaJoin),
+    SemiJoin(semi_join::SemiJoin),
     Latest(latest::Latest),
     Project(project::Project),
     Union(union::Union),
     Identity(identity::Identity),
     Filter(filter::Filter),
     TopK(topk::TopK),
+    TopKWindow(topk::TopKWindow),
     Trigger(trigger::Trigger),
     Rewrite(rewrite::Rewrite),
     Distinct(distinct::Distinct),
@@ -62,12 +67,15 @@ nodeop_from_impl!(
     grouped::GroupedOperator<grouped::filteraggregate::FilterAggregator>
 );
 nodeop_from_impl!(NodeOperator::Join, join::Join);
+nodeop_from_impl!(NodeOperator::ThetaJoin, theta_join::ThetaJoin);
+nodeop_from_impl!(NodeOperator::SemiJoin, semi_join::SemiJoin);
 nodeop_from_impl!(NodeOperator::Latest, latest::Latest);
 nodeop_from_impl!(NodeOperator::Project, project::Project);
 nodeop_from_impl!(NodeOperator::Union, union::Union);
 nodeop_from_impl!(NodeOperator::Identity, identity::Identity);
 nodeop_from_impl!(NodeOperator::Filter, filter::Filter);
 nodeop_from_impl!(NodeOperator::TopK, topk::TopK);
+nodeop_from_impl!(NodeOperator::TopKWindow, topk::TopKWindow);
 nodeop_from_impl!(NodeOperator::Trigger, trigger::Trigger);
 nodeop_from_impl!(NodeOperator::Rewrite, rewrite::Rewrite);
 nodeop_from_impl!(NodeOperator::Distinct, distinct::Distinct);
@@ -80,12 +88,15 @@ macro_rules! impl_ingredient_fn_mut {
             NodeOperator::Concat(ref mut i) => i.$fn($($arg),*),
             NodeOperator::FilterSum(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Join(ref mut i) => i.$fn($($arg),*),
+            NodeOperator::ThetaJoin(ref mut i) => i.$fn($($arg),*),
+            NodeOperator::SemiJoin(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Latest(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Project(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Union(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Identity(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Filter(ref mut i) => i.$fn($($arg),*),
             NodeOperator::TopK(ref mut i) => i.$fn($($arg),*),
+            NodeOperator::TopKWindow(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Trigger(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Rewrite(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Distinct(ref mut i) => i.$fn($($arg),*),
@@ -101,12 +112,15 @@ macro_rules! impl_ingredient_fn_ref {
             NodeOperator::Concat(ref i) => i.$fn($($arg),*),
             NodeOperator::FilterSum(ref i) => i.$fn($($arg),*),
             NodeOperator::Join(ref i) => i.$fn($($arg),*),
+            NodeOperator::ThetaJoin(ref i) => i.$fn($($arg),*),
+            NodeOperator::SemiJoin(ref i) => i.$fn($($arg),*),
             NodeOperator::Latest(ref i) => i.$fn($($arg),*),
             NodeOperator::Project(ref i) => i.$fn($($arg),*),
             NodeOperator::Union(ref i) => i.$fn($($arg),*),
             NodeOperator::Identity(ref i) => i.$fn($($arg),*),
             NodeOperator::Filter(ref i) => i.$fn($($arg),*),
             NodeOperator::TopK(ref i) => i.$fn($($arg),*),
+            NodeOperator::TopKWindow(ref i) => i.$fn($($arg),*),
             NodeOperator::Trigger(ref i) => i.$fn($($arg),*),
             NodeOperator::Rewrite(ref i) => i.$fn($($arg),*),
             NodeOperator::Distinct(ref i) => i.$fn($($arg),*),
@@ -223,6 +237,9 @@ impl Ingredient for NodeOperator {
     fn requires_full_materialization(&self) -> bool {
         impl_ingredient_fn_ref!(self, requires_full_materialization,)
     }
+    fn requires_full_parents(&self) -> bool {
+        impl_ingredient_fn_ref!(self, requires_full_parents,)
+    }
 }
 
 #[cfg(test)]
@@ -419,7 +436,7 @@ pub mod test {
             struct Ex;
 
             impl Executor for Ex {
-                fn ack(&mut self, _: SourceChannelIdentifier) {}
+                fn ack(&mut self, _: SourceChannelIdentifier, _: WriteAck) {}
                 fn create_universe(&mut self, _: HashMap<String, DataType>) {}
                 fn send(&mut self, _: ReplicaAddr, _: Box<Packet>) {}
             }
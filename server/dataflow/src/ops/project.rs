@@ -1,4 +1,5 @@
 use nom_sql::ArithmeticOperator;
+use noria::{JsonPath, TimeUnit};
 
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -12,11 +13,33 @@ pub enum ProjectExpressionBase {
     Literal(DataType),
 }
 
+/// A function of a single value that a `Project` can compute.
+///
+/// Our SQL parser does not support any of these functions yet, so queries installed from SQL never
+/// use them. They are only reachable by building the dataflow graph directly.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProjectExpression {
-    op: ArithmeticOperator,
-    left: ProjectExpressionBase,
-    right: ProjectExpressionBase,
+pub enum ProjectFunction {
+    /// `DATE(x)`, the date part of a timestamp.
+    Date,
+    /// `EXTRACT(unit FROM x)`.
+    Extract(TimeUnit),
+    /// The start of the day (or week, month, ...) that a date or timestamp falls in, which
+    /// daily (or weekly, monthly, ...) rollups group by.
+    Bucket(TimeUnit),
+    /// `JSON_EXTRACT(x, path)`.
+    JsonExtract(JsonPath),
+    /// `x->>path`, which unlike `JSON_EXTRACT` turns scalars into plain values.
+    JsonExtractUnquoted(JsonPath),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProjectExpression {
+    Arithmetic {
+        op: ArithmeticOperator,
+        left: ProjectExpressionBase,
+        right: ProjectExpressionBase,
+    },
+    Function(ProjectFunction, ProjectExpressionBase),
 }
 
 impl ProjectExpression {
@@ -25,7 +48,11 @@ impl ProjectExpression {
         left: ProjectExpressionBase,
         right: ProjectExpressionBase,
     ) -> ProjectExpression {
-        ProjectExpression { op, left, right }
+        ProjectExpression::Arithmetic { op, left, right }
+    }
+
+    pub fn function(function: ProjectFunction, arg: ProjectExpressionBase) -> ProjectExpression {
+        ProjectExpression::Function(function, arg)
     }
 }
 
@@ -40,14 +67,40 @@ impl fmt::Display for ProjectExpressionBase {
 
 impl fmt::Display for ProjectExpression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let op = match self.op {
-            ArithmeticOperator::Add => "+",
-            ArithmeticOperator::Subtract => "-",
-            ArithmeticOperator::Divide => "/",
-            ArithmeticOperator::Multiply => "*",
-        };
-
-        write!(f, "{} {} {}", self.left, op, self.right)
+        match *self {
+            ProjectExpression::Arithmetic {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                let op = match *op {
+                    ArithmeticOperator::Add => "+",
+                    ArithmeticOperator::Subtract => "-",
+                    ArithmeticOperator::Divide => "/",
+                    ArithmeticOperator::Multiply => "*",
+                };
+
+                write!(f, "{} {} {}", left, op, right)
+            }
+            ProjectExpression::Function(ProjectFunction::Date, ref arg) => {
+                write!(f, "DATE({})", arg)
+            }
+            ProjectExpression::Function(ProjectFunction::Extract(unit), ref arg) => {
+                write!(f, "EXTRACT({} FROM {})", unit, arg)
+            }
+            ProjectExpression::Function(ProjectFunction::Bucket(unit), ref arg) => {
+                write!(f, "BUCKET({}, {})", unit, arg)
+            }
+            ProjectExpression::Function(ProjectFunction::JsonExtract(ref path), ref arg) => {
+                write!(f, "JSON_EXTRACT({}, '{}')", arg, path)
+            }
+            ProjectExpression::Function(
+                ProjectFunction::JsonExtractUnquoted(ref path),
+                ref arg,
+            ) => {
+                write!(f, "{}->>'{}'", arg, path)
+            }
+        }
     }
 }
 
@@ -100,22 +153,39 @@ impl Project {
     }
 }
 
-fn eval_expression(expression: &ProjectExpression, record: &[DataType]) -> DataType {
-    let left = match expression.left {
+fn eval_base<'a>(base: &'a ProjectExpressionBase, record: &'a [DataType]) -> &'a DataType {
+    match *base {
         ProjectExpressionBase::Column(i) => &record[i],
         ProjectExpressionBase::Literal(ref data) => data,
-    };
-
-    let right = match expression.right {
-        ProjectExpressionBase::Column(i) => &record[i],
-        ProjectExpressionBase::Literal(ref data) => data,
-    };
+    }
+}
 
-    match expression.op {
-        ArithmeticOperator::Add => left + right,
-        ArithmeticOperator::Subtract => left - right,
-        ArithmeticOperator::Multiply => left * right,
-        ArithmeticOperator::Divide => left / right,
+fn eval_expression(expression: &ProjectExpression, record: &[DataType]) -> DataType {
+    match *expression {
+        ProjectExpression::Arithmetic {
+            ref op,
+            ref left,
+            ref right,
+        } => {
+            let left = eval_base(left, record);
+            let right = eval_base(right, record);
+            match *op {
+                ArithmeticOperator::Add => left + right,
+                ArithmeticOperator::Subtract => left - right,
+                ArithmeticOperator::Multiply => left * right,
+                ArithmeticOperator::Divide => left / right,
+            }
+        }
+        ProjectExpression::Function(ref function, ref arg) => {
+            let arg = eval_base(arg, record);
+            match *function {
+                ProjectFunction::Date => arg.date(),
+                ProjectFunction::Extract(unit) => arg.extract(unit),
+                ProjectFunction::Bucket(unit) => arg.bucket(unit),
+                ProjectFunction::JsonExtract(ref path) => arg.json_extract(path),
+                ProjectFunction::JsonExtractUnquoted(ref path) => arg.json_extract_unquoted(path),
+            }
+        }
     }
 }
 
@@ -351,11 +421,11 @@ mod tests {
     }
 
     fn setup_column_arithmetic(op: ArithmeticOperator) -> ops::test::MockGraph {
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Column(1),
+        let expression = ProjectExpression::new(
             op,
-        };
+            ProjectExpressionBase::Column(0),
+            ProjectExpressionBase::Column(1),
+        );
 
         setup_arithmetic(expression)
     }
@@ -473,11 +543,11 @@ mod tests {
     #[test]
     fn it_forwards_arithmetic_w_literals() {
         let number: DataType = 40.into();
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Literal(number),
-            op: ArithmeticOperator::Multiply,
-        };
+        let expression = ProjectExpression::new(
+            ArithmeticOperator::Multiply,
+            ProjectExpressionBase::Column(0),
+            ProjectExpressionBase::Literal(number),
+        );
 
         let mut p = setup_arithmetic(expression);
         let rec = vec![10.into(), 0.into()];
@@ -491,11 +561,11 @@ mod tests {
     fn it_forwards_arithmetic_w_only_literals() {
         let a: DataType = 80.into();
         let b: DataType = 40.into();
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Literal(a),
-            right: ProjectExpressionBase::Literal(b),
-            op: ArithmeticOperator::Divide,
-        };
+        let expression = ProjectExpression::new(
+            ArithmeticOperator::Divide,
+            ProjectExpressionBase::Literal(a),
+            ProjectExpressionBase::Literal(b),
+        );
 
         let mut p = setup_arithmetic(expression);
         let rec = vec![0.into(), 0.into()];
@@ -505,6 +575,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_forwards_interval_arithmetic() {
+        use chrono::NaiveDate;
+
+        let expression = ProjectExpression::new(
+            ArithmeticOperator::Add,
+            ProjectExpressionBase::Column(0),
+            ProjectExpressionBase::Literal(DataType::interval(1, TimeUnit::Month)),
+        );
+
+        let mut p = setup_arithmetic(expression);
+        let ts = NaiveDate::from_ymd(2020, 1, 31).and_hms(12, 0, 0);
+        let rec = vec![ts.into(), 0.into()];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![
+                ts.into(),
+                0.into(),
+                NaiveDate::from_ymd(2020, 2, 29).and_hms(12, 0, 0).into(),
+            ]]
+            .into()
+        );
+    }
+
+    #[test]
+    fn it_forwards_date_functions() {
+        use chrono::NaiveDate;
+
+        let ts = NaiveDate::from_ymd(2020, 3, 5).and_hms(17, 30, 0);
+        let cases = vec![
+            (
+                ProjectFunction::Date,
+                DataType::from(NaiveDate::from_ymd(2020, 3, 5)),
+            ),
+            (ProjectFunction::Extract(TimeUnit::Hour), 17.into()),
+            (ProjectFunction::Extract(TimeUnit::Quarter), 1.into()),
+            (
+                ProjectFunction::Bucket(TimeUnit::Week),
+                NaiveDate::from_ymd(2020, 3, 1).and_hms(0, 0, 0).into(),
+            ),
+        ];
+        for (function, expected) in cases {
+            let expression =
+                ProjectExpression::function(function, ProjectExpressionBase::Column(0));
+            let mut p = setup_arithmetic(expression);
+            let rec = vec![ts.into(), 0.into()];
+            assert_eq!(
+                p.narrow_one_row(rec, false),
+                vec![vec![ts.into(), 0.into(), expected]].into()
+            );
+        }
+    }
+
+    #[test]
+    fn it_describes_date_functions() {
+        let expression = ProjectExpression::function(
+            ProjectFunction::Extract(TimeUnit::Day),
+            ProjectExpressionBase::Column(1),
+        );
+        let p = setup_arithmetic(expression);
+        assert_eq!(p.node().description(true), "π[0, 1, EXTRACT(DAY FROM 1)]");
+    }
+
+    #[test]
+    fn it_forwards_json_extraction() {
+        let doc = DataType::json(r#"{"owner": {"name": "alice"}, "tags": ["a", "b"]}"#).unwrap();
+        let cases = vec![
+            (
+                ProjectFunction::JsonExtract("$.owner.name".parse().unwrap()),
+                DataType::json(r#""alice""#).unwrap(),
+            ),
+            (
+                ProjectFunction::JsonExtractUnquoted("$.owner.name".parse().unwrap()),
+                "alice".into(),
+            ),
+            (
+                ProjectFunction::JsonExtractUnquoted("$.tags".parse().unwrap()),
+                DataType::json(r#"["a","b"]"#).unwrap(),
+            ),
+            (
+                ProjectFunction::JsonExtractUnquoted("$.missing".parse().unwrap()),
+                DataType::None,
+            ),
+        ];
+        for (function, expected) in cases {
+            let expression =
+                ProjectExpression::function(function, ProjectExpressionBase::Column(0));
+            let mut p = setup_arithmetic(expression);
+            let rec = vec![doc.clone(), 0.into()];
+            assert_eq!(
+                p.narrow_one_row(rec, false),
+                vec![vec![doc.clone(), 0.into(), expected]].into()
+            );
+        }
+    }
+
     fn setup_query_through(
         mut state: Box<dyn State>,
         permutation: &[usize],
@@ -0,0 +1,401 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use crate::prelude::*;
+
+/// Kind of semi-join
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SemiJoinType {
+    /// Emit the left rows that have a match in the right parent
+    Semi,
+    /// Emit the left rows that have no match in the right parent
+    Anti,
+}
+
+/// SemiJoin passes on the rows of its left parent that do (or, for an anti-join, do not) have a
+/// row with an equal join column in its right parent.
+///
+/// Unlike a join, it emits each left row at most once no matter how many right rows it matches,
+/// and emits none of the right parent's columns. This is what `IN` and `NOT IN` with a subquery
+/// need.
+///
+/// `NULL` never matches anything, so an anti-join passes on a left row whose join column is
+/// `NULL`, and ignores right rows whose join column is `NULL`. SQL's `NOT IN` would instead emit
+/// nothing if the subquery returns a `NULL`, and drop left rows with a `NULL` join column unless
+/// the subquery is empty, so the SQL layer only lowers `NOT IN` to an anti-join when both columns
+/// are declared `NOT NULL`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemiJoin {
+    left: IndexPair,
+    right: IndexPair,
+
+    // Key column in the left and right parents respectively
+    on: (usize, usize),
+
+    kind: SemiJoinType,
+}
+
+impl SemiJoin {
+    /// Create a new instance of SemiJoin
+    ///
+    /// `left` and `right` are the left and right parents respectively. `on` is a tuple specifying
+    /// the join columns: (left_parent_column, right_parent_column). The output has the columns of
+    /// the left parent.
+    pub fn new(left: NodeIndex, right: NodeIndex, kind: SemiJoinType, on: (usize, usize)) -> Self {
+        Self {
+            left: left.into(),
+            right: right.into(),
+            on,
+            kind,
+        }
+    }
+
+    /// Whether a left row that has `matches` rows in the right parent is part of the output.
+    fn emits(&self, matches: usize) -> bool {
+        (matches != 0) == (self.kind == SemiJoinType::Semi)
+    }
+}
+
+impl Ingredient for SemiJoin {
+    fn take(&mut self) -> NodeOperator {
+        Clone::clone(self).into()
+    }
+
+    fn ancestors(&self) -> Vec<NodeIndex> {
+        vec![self.left.as_global(), self.right.as_global()]
+    }
+
+    fn is_join(&self) -> bool {
+        true
+    }
+
+    fn must_replay_among(&self) -> Option<HashSet<NodeIndex>> {
+        // all of our columns come from the left
+        Some(Some(self.left.as_global()).into_iter().collect())
+    }
+
+    fn on_connected(&mut self, _g: &Graph) {}
+
+    fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
+        self.left.remap(remap);
+        self.right.remap(remap);
+    }
+
+    fn on_input(
+        &mut self,
+        _: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        replay_key_cols: Option<&[usize]>,
+        nodes: &DomainNodes,
+        state: &StateMap,
+    ) -> ProcessingResult {
+        let mut misses = Vec::new();
+        let mut lookups = Vec::new();
+
+        if rs.is_empty() {
+            return ProcessingResult {
+                results: rs,
+                ..Default::default()
+            };
+        }
+
+        let from_left = from == *self.left;
+        let from_key = if from_left { self.on.0 } else { self.on.1 };
+
+        // sort the batch by join key, so that each key is only looked up once
+        let mut rs: Vec<_> = rs.into();
+        rs.sort_by(|a, b| a[from_key].cmp(&b[from_key]));
+
+        let mut ret: Vec<Record> = Vec::with_capacity(rs.len());
+        let mut at = 0;
+        while at != rs.len() {
+            let start = at;
+            let key = rs[at][from_key].clone();
+            at = rs[at..]
+                .iter()
+                .position(|r| r[from_key] != key)
+                .map(|p| at + p)
+                .unwrap_or_else(|| rs.len());
+
+            if key.is_none() {
+                // NULL is not equal to anything, not even another NULL, so these rows never match
+                if from_left && self.emits(0) {
+                    ret.extend(rs[start..at].iter().cloned());
+                }
+                continue;
+            }
+
+            // the right parent has already seen this batch if it came from there, so this is the
+            // number of matches after it
+            let matches = match self
+                .lookup(
+                    *self.right,
+                    &[self.on.1],
+                    &KeyType::Single(&key),
+                    nodes,
+                    state,
+                )
+                .unwrap()
+            {
+                Some(rows) => rows.count(),
+                None if from_left => {
+                    misses.extend((start..at).map(|i| Miss {
+                        on: *self.right,
+                        lookup_idx: vec![self.on.1],
+                        lookup_cols: vec![from_key],
+                        replay_cols: replay_key_cols.map(Vec::from),
+                        // NOTE: we're stealing data here!
+                        record: mem::replace(&mut *rs[i], Vec::new()),
+                    }));
+                    continue;
+                }
+                None => {
+                    // we got something from right, but that row's key is not in right. as for
+                    // left joins, this can only happen if the row is being replayed to fill some
+                    // other index of right, and then nothing below us holds lefts for this key.
+                    continue;
+                }
+            };
+
+            if replay_key_cols.is_some() {
+                lookups.push(Lookup {
+                    on: *self.right,
+                    cols: vec![self.on.1],
+                    key: vec![key.clone()],
+                });
+            }
+
+            if from_left {
+                if self.emits(matches) {
+                    ret.extend(
+                        rs[start..at]
+                            .iter_mut()
+                            .map(|r| mem::replace(r, Record::Positive(Vec::new()))),
+                    );
+                }
+                continue;
+            }
+
+            // undo the batch to find out whether the lefts for this key were emitted before it
+            let before = rs[start..at].iter().fold(matches as isize, |n, r| {
+                if r.is_positive() {
+                    n - 1
+                } else {
+                    n + 1
+                }
+            });
+            let emitted = self.emits(before as usize);
+            if emitted == self.emits(matches) {
+                continue;
+            }
+
+            match self
+                .lookup(
+                    *self.left,
+                    &[self.on.0],
+                    &KeyType::Single(&key),
+                    nodes,
+                    state,
+                )
+                .unwrap()
+            {
+                Some(lefts) => {
+                    if replay_key_cols.is_some() {
+                        lookups.push(Lookup {
+                            on: *self.left,
+                            cols: vec![self.on.0],
+                            key: vec![key.clone()],
+                        });
+                    }
+                    ret.extend(lefts.map(|l| (l.into_owned(), !emitted).into()));
+                }
+                None => {
+                    misses.extend((start..at).map(|i| Miss {
+                        on: *self.left,
+                        lookup_idx: vec![self.on.0],
+                        lookup_cols: vec![from_key],
+                        replay_cols: replay_key_cols.map(Vec::from),
+                        // NOTE: we're stealing data here!
+                        record: mem::replace(&mut *rs[i], Vec::new()),
+                    }));
+                }
+            }
+        }
+
+        ProcessingResult {
+            results: ret.into(),
+            lookups,
+            misses,
+        }
+    }
+
+    fn suggest_indexes(&self, _this: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
+        vec![
+            (self.left.as_global(), vec![self.on.0]),
+            (self.right.as_global(), vec![self.on.1]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn resolve(&self, col: usize) -> Option<Vec<(NodeIndex, usize)>> {
+        Some(vec![(self.left.as_global(), col)])
+    }
+
+    fn description(&self, detailed: bool) -> String {
+        let op = match self.kind {
+            SemiJoinType::Semi => "∈",
+            SemiJoinType::Anti => "∉",
+        };
+        if !detailed {
+            return String::from(op);
+        }
+
+        format!(
+            "{}:{} {} {}:{}",
+            self.left.as_global().index(),
+            self.on.0,
+            op,
+            self.right.as_global().index(),
+            self.on.1
+        )
+    }
+
+    fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
+        if col == self.on.0 {
+            // join column comes from both parents
+            vec![
+                (self.left.as_global(), Some(self.on.0)),
+                (self.right.as_global(), Some(self.on.1)),
+            ]
+        } else {
+            vec![(self.left.as_global(), Some(col))]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops;
+
+    fn setup(kind: SemiJoinType) -> (ops::test::MockGraph, IndexPair, IndexPair) {
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("left", &["l0", "l1"]);
+        let r = g.add_base("right", &["r0", "r1"]);
+
+        let j = SemiJoin::new(l.as_global(), r.as_global(), kind, (0, 1));
+        g.set_op("semi", &["l0", "l1"], j, false);
+        (g, l, r)
+    }
+
+    #[test]
+    fn it_describes() {
+        let (j, l, r) = setup(SemiJoinType::Semi);
+        assert_eq!(j.node().description(true), format!("{}:0 ∈ {}:1", l, r));
+        let (j, l, r) = setup(SemiJoinType::Anti);
+        assert_eq!(j.node().description(true), format!("{}:0 ∉ {}:1", l, r));
+    }
+
+    #[test]
+    fn it_works() {
+        let (mut j, l, r) = setup(SemiJoinType::Semi);
+        let l_a1 = vec![1.into(), "a".into()];
+        let r_x1 = vec!["x".into(), 1.into()];
+        let r_y1 = vec!["y".into(), 1.into()];
+        let r_z3 = vec!["z".into(), 3.into()];
+
+        // a left without a match on the right is not emitted
+        j.seed(l, l_a1.clone());
+        let rs = j.one_row(l, l_a1.clone(), false);
+        assert!(rs.is_empty());
+
+        // until a match shows up
+        j.seed(r, r_x1.clone());
+        let rs = j.one_row(r, r_x1.clone(), false);
+        assert_eq!(rs, vec![(l_a1.clone(), true)].into());
+
+        // further matches do not emit it again
+        j.seed(r, r_y1.clone());
+        let rs = j.one_row(r, r_y1.clone(), false);
+        assert!(rs.is_empty());
+
+        // and neither does a left that matches several rights
+        j.seed(l, l_a1.clone());
+        let rs = j.one_row(l, l_a1.clone(), false);
+        assert_eq!(rs, vec![(l_a1.clone(), true)].into());
+
+        // a right that matches nothing has no effect
+        j.seed(r, r_z3.clone());
+        let rs = j.one_row(r, r_z3.clone(), false);
+        assert!(rs.is_empty());
+
+        // the lefts go away only once the last match does
+        j.unseed(r);
+        j.seed(r, r_y1.clone());
+        let rs = j.one_row(r, (r_x1.clone(), false), false);
+        assert!(rs.is_empty());
+        j.unseed(r);
+        let rs = j.one_row(r, (r_y1.clone(), false), false);
+        assert_eq!(
+            rs,
+            vec![(l_a1.clone(), false), (l_a1.clone(), false)].into()
+        );
+    }
+
+    #[test]
+    fn it_works_as_anti_join() {
+        let (mut j, l, r) = setup(SemiJoinType::Anti);
+        let l_a1 = vec![1.into(), "a".into()];
+        let l_null = vec![DataType::None, "b".into()];
+        let r_x1 = vec!["x".into(), 1.into()];
+
+        // a left without a match on the right is emitted
+        j.seed(l, l_a1.clone());
+        let rs = j.one_row(l, l_a1.clone(), false);
+        assert_eq!(rs, vec![(l_a1.clone(), true)].into());
+
+        // and revoked once a match shows up
+        j.seed(r, r_x1.clone());
+        let rs = j.one_row(r, r_x1.clone(), false);
+        assert_eq!(rs, vec![(l_a1.clone(), false)].into());
+
+        // a batch that removes and adds back the match changes nothing
+        let rs = j.one(
+            r,
+            vec![(r_x1.clone(), false).into(), (r_x1.clone(), true).into()],
+            false,
+        );
+        assert!(rs.is_empty());
+
+        // NULL never matches
+        j.seed(r, vec!["n".into(), DataType::None]);
+        j.seed(l, l_null.clone());
+        let rs = j.one_row(l, l_null.clone(), false);
+        assert_eq!(rs, vec![(l_null, true)].into());
+
+        // removing the match brings the left back
+        j.unseed(r);
+        let rs = j.one_row(r, (r_x1, false), false);
+        assert_eq!(rs, vec![(l_a1, true)].into());
+    }
+
+    #[test]
+    fn it_suggests_indices() {
+        let me = 2.into();
+        let (g, l, r) = setup(SemiJoinType::Semi);
+        let hm: HashMap<_, _> = vec![(l.as_global(), vec![0]), (r.as_global(), vec![1])]
+            .into_iter()
+            .collect();
+        assert_eq!(g.node().suggest_indexes(me), hm);
+    }
+
+    #[test]
+    fn it_resolves() {
+        let (g, l, _) = setup(SemiJoinType::Semi);
+        assert_eq!(g.node().resolve(0), Some(vec![(l.as_global(), 0)]));
+        assert_eq!(g.node().resolve(1), Some(vec![(l.as_global(), 1)]));
+    }
+}
@@ -0,0 +1,537 @@
+use slog::Logger;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+use crate::ops::join::JoinSource;
+use crate::prelude::*;
+pub use noria::filter::Operator;
+
+/// The distinct values of one parent's comparison column, along with how many rows hold each.
+type OrderedIndex = BTreeMap<DataType, usize>;
+
+/// ThetaJoin provides an inner join between two views on arbitrary comparisons of their columns.
+///
+/// Rows from one side are matched against the other side by walking an ordered index of the
+/// other side's column in the first condition, so that inequalities and band conditions do not
+/// have to scan the whole of the other side. Any further conditions are checked on each pair of
+/// rows that the first condition matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThetaJoin {
+    left: IndexPair,
+    right: IndexPair,
+
+    // Conditions that a pair of rows must satisfy, each as (left column, operator, right column)
+    on: Vec<(usize, Operator, usize)>,
+
+    // Which columns to emit. True means the column is from the left parent, false means from the
+    // right
+    emit: Vec<(bool, usize)>,
+
+    // Ordered indexes of the first condition's left and right columns. These are built from the
+    // parents' state the first time this node sees any records, and are kept up to date with
+    // the updates that flow through it after that.
+    #[serde(skip)]
+    ordered: Option<(OrderedIndex, OrderedIndex)>,
+}
+
+/// Returns true if `l op r` holds. Comparisons involving `NULL` never hold.
+fn compare(l: &DataType, op: &Operator, r: &DataType) -> bool {
+    if l.is_none() || r.is_none() {
+        return false;
+    }
+    match *op {
+        Operator::Equal => l == r,
+        Operator::NotEqual => l != r,
+        Operator::Greater => l > r,
+        Operator::GreaterOrEqual => l >= r,
+        Operator::Less => l < r,
+        Operator::LessOrEqual => l <= r,
+        ref op => unreachable!("unsupported theta join operator {}", op),
+    }
+}
+
+/// The values in `index` for which `v op value` holds.
+fn matching(index: &OrderedIndex, v: &DataType, op: &Operator) -> Vec<DataType> {
+    let v = v.clone();
+    let range = match *op {
+        Operator::Equal => (Bound::Included(v.clone()), Bound::Included(v)),
+        Operator::Less => (Bound::Excluded(v), Bound::Unbounded),
+        Operator::LessOrEqual => (Bound::Included(v), Bound::Unbounded),
+        Operator::Greater => (Bound::Unbounded, Bound::Excluded(v)),
+        Operator::GreaterOrEqual => (Bound::Unbounded, Bound::Included(v)),
+        Operator::NotEqual => {
+            return index.keys().filter(|k| **k != v).cloned().collect();
+        }
+        ref op => unreachable!("unsupported theta join operator {}", op),
+    };
+    index.range(range).map(|(k, _)| k.clone()).collect()
+}
+
+/// The operator that holds for `r op' l` whenever `l op r` holds.
+fn flip(op: &Operator) -> Operator {
+    match *op {
+        Operator::Less => Operator::Greater,
+        Operator::LessOrEqual => Operator::GreaterOrEqual,
+        Operator::Greater => Operator::Less,
+        Operator::GreaterOrEqual => Operator::LessOrEqual,
+        ref op => op.clone(),
+    }
+}
+
+fn index_records(index: &mut OrderedIndex, rs: &[Record], col: usize) {
+    for r in rs {
+        let v = &r[col];
+        if v.is_none() {
+            continue;
+        }
+        if r.is_positive() {
+            *index.entry(v.clone()).or_insert(0) += 1;
+        } else if let Some(n) = index.get_mut(v) {
+            *n -= 1;
+            if *n == 0 {
+                index.remove(v);
+            }
+        }
+    }
+}
+
+impl ThetaJoin {
+    /// Create a new instance of ThetaJoin
+    ///
+    /// `left` and `right` are the left and right parents respectively. Each entry in `on` is a
+    /// condition `(left_parent_column, operator, right_parent_column)` that joined rows must
+    /// satisfy; the first one is used to find candidate rows, so it should be the most selective.
+    /// `emit` dictates the source of each output column, and may only contain `JoinSource::L`
+    /// and `JoinSource::R`.
+    pub fn new(
+        left: NodeIndex,
+        right: NodeIndex,
+        on: Vec<(usize, Operator, usize)>,
+        emit: Vec<JoinSource>,
+    ) -> Self {
+        assert!(!on.is_empty(), "theta join needs at least one condition");
+        for &(_, ref op, _) in &on {
+            match *op {
+                Operator::Equal
+                | Operator::NotEqual
+                | Operator::Greater
+                | Operator::GreaterOrEqual
+                | Operator::Less
+                | Operator::LessOrEqual => {}
+                ref op => panic!("unsupported theta join operator {}", op),
+            }
+        }
+
+        let emit = emit
+            .into_iter()
+            .map(|join_source| match join_source {
+                JoinSource::L(c) => (true, c),
+                JoinSource::R(c) => (false, c),
+                JoinSource::B(..) => panic!("theta joins do not share columns between parents"),
+            })
+            .collect();
+
+        Self {
+            left: left.into(),
+            right: right.into(),
+            on,
+            emit,
+            ordered: None,
+        }
+    }
+
+    fn generate_row(&self, left: &[DataType], right: &[DataType]) -> Vec<DataType> {
+        self.emit
+            .iter()
+            .map(|&(from_left, col)| {
+                if from_left {
+                    left[col].clone()
+                } else {
+                    right[col].clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Make sure that the ordered indexes exist, and return true if they were just built.
+    ///
+    /// Parents update their state before forwarding records to us, so indexes built now already
+    /// include whatever records we are currently processing.
+    fn ensure_ordered(&mut self, states: &StateMap) -> bool {
+        if self.ordered.is_some() {
+            return false;
+        }
+
+        let build = |parent: LocalNodeIndex, col: usize| {
+            let state = states
+                .get(parent)
+                .expect("theta join parents must be materialized");
+            assert!(
+                !state.is_partial(),
+                "theta join parents must be fully materialized"
+            );
+            let mut index = OrderedIndex::new();
+            for row in state.cloned_records() {
+                if !row[col].is_none() {
+                    *index.entry(row[col].clone()).or_insert(0) += 1;
+                }
+            }
+            index
+        };
+        self.ordered = Some((
+            build(*self.left, self.on[0].0),
+            build(*self.right, self.on[0].2),
+        ));
+        true
+    }
+
+    /// Join the records in `rs` from parent `from` with the current contents of the other parent.
+    fn join(
+        &self,
+        from: LocalNodeIndex,
+        rs: &[Record],
+        nodes: &DomainNodes,
+        states: &StateMap,
+    ) -> Vec<Record> {
+        let (ref left_index, ref right_index) = *self.ordered.as_ref().unwrap();
+        let from_left = from == *self.left;
+        let (other, other_index, from_key, other_key, op) = if from_left {
+            let (l, ref op, r) = self.on[0];
+            (*self.right, right_index, l, r, op.clone())
+        } else {
+            let (l, ref op, r) = self.on[0];
+            (*self.left, left_index, r, l, flip(op))
+        };
+
+        let mut ret = Vec::new();
+        for rec in rs {
+            let v = &rec[from_key];
+            if v.is_none() {
+                continue;
+            }
+
+            for k in matching(other_index, v, &op) {
+                let other_rows = self
+                    .lookup(other, &[other_key], &KeyType::Single(&k), nodes, states)
+                    .unwrap()
+                    .expect("theta join parents must be fully materialized");
+                for other_row in other_rows {
+                    let (l, r) = if from_left {
+                        (&rec[..], &other_row[..])
+                    } else {
+                        (&other_row[..], &rec[..])
+                    };
+                    if self.on[1..]
+                        .iter()
+                        .all(|&(lc, ref op, rc)| compare(&l[lc], op, &r[rc]))
+                    {
+                        ret.push((self.generate_row(l, r), rec.is_positive()).into());
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl Ingredient for ThetaJoin {
+    fn take(&mut self) -> NodeOperator {
+        Clone::clone(self).into()
+    }
+
+    fn ancestors(&self) -> Vec<NodeIndex> {
+        vec![self.left.as_global(), self.right.as_global()]
+    }
+
+    fn is_join(&self) -> bool {
+        true
+    }
+
+    fn must_replay_among(&self) -> Option<HashSet<NodeIndex>> {
+        Some(
+            vec![self.left.as_global(), self.right.as_global()]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    fn on_connected(&mut self, _g: &Graph) {}
+
+    fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
+        self.left.remap(remap);
+        self.right.remap(remap);
+    }
+
+    fn on_input(
+        &mut self,
+        _: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        _: Option<&[usize]>,
+        nodes: &DomainNodes,
+        states: &StateMap,
+    ) -> ProcessingResult {
+        let just_built = self.ensure_ordered(states);
+        let results = self.join(from, &rs, nodes, states);
+
+        // a newly built index has already seen these records in the parent's state
+        if !just_built {
+            let (ref mut left_index, ref mut right_index) = *self.ordered.as_mut().unwrap();
+            if from == *self.left {
+                index_records(left_index, &rs, self.on[0].0);
+            } else {
+                index_records(right_index, &rs, self.on[0].2);
+            }
+        }
+
+        ProcessingResult {
+            results: results.into(),
+            ..Default::default()
+        }
+    }
+
+    fn on_input_raw(
+        &mut self,
+        ex: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        replay: ReplayContext,
+        nodes: &DomainNodes,
+        states: &StateMap,
+        _: &Logger,
+    ) -> RawProcessingResult {
+        if let ReplayContext::None = replay {
+            return RawProcessingResult::Regular(self.on_input(ex, from, rs, None, nodes, states));
+        }
+
+        // replayed records are already in the parent's state, and so in the ordered indexes
+        self.ensure_ordered(states);
+        let results = self.join(from, &rs, nodes, states);
+        RawProcessingResult::Regular(ProcessingResult {
+            results: results.into(),
+            ..Default::default()
+        })
+    }
+
+    fn suggest_indexes(&self, _this: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
+        vec![
+            (self.left.as_global(), vec![self.on[0].0]),
+            (self.right.as_global(), vec![self.on[0].2]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn resolve(&self, col: usize) -> Option<Vec<(NodeIndex, usize)>> {
+        let e = self.emit[col];
+        if e.0 {
+            Some(vec![(self.left.as_global(), e.1)])
+        } else {
+            Some(vec![(self.right.as_global(), e.1)])
+        }
+    }
+
+    fn description(&self, detailed: bool) -> String {
+        if !detailed {
+            return String::from("⋈θ");
+        }
+
+        let emit = self
+            .emit
+            .iter()
+            .map(|&(from_left, col)| {
+                let src = if from_left { self.left } else { self.right };
+                format!("{}:{}", src.as_global().index(), col)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let on = self
+            .on
+            .iter()
+            .map(|&(l, ref op, r)| {
+                format!(
+                    "{}:{} {} {}:{}",
+                    self.left.as_global().index(),
+                    l,
+                    op,
+                    self.right.as_global().index(),
+                    r
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ∧ ");
+
+        format!("[{}] ⋈θ {}", emit, on)
+    }
+
+    fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
+        let pcol = self.emit[col];
+        vec![(
+            if pcol.0 { &self.left } else { &self.right }.as_global(),
+            Some(pcol.1),
+        )]
+    }
+
+    fn requires_full_materialization(&self) -> bool {
+        true
+    }
+
+    fn requires_full_parents(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops;
+
+    fn setup(on: Vec<(usize, Operator, usize)>) -> (ops::test::MockGraph, IndexPair, IndexPair) {
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("left", &["l0", "l1"]);
+        let r = g.add_base("right", &["r0", "r1"]);
+
+        use crate::ops::join::JoinSource::*;
+        let j = ThetaJoin::new(
+            l.as_global(),
+            r.as_global(),
+            on,
+            vec![L(0), L(1), R(0), R(1)],
+        );
+
+        g.set_op("join", &["j0", "j1", "j2", "j3"], j, false);
+        (g, l, r)
+    }
+
+    #[test]
+    fn it_describes() {
+        let (j, l, r) = setup(vec![(0, Operator::Less, 0), (1, Operator::NotEqual, 1)]);
+        assert_eq!(
+            j.node().description(true),
+            format!(
+                "[{}:0, {}:1, {}:0, {}:1] ⋈θ {}:0 < {}:0 ∧ {}:1 != {}:1",
+                l, l, r, r, l, r, l, r
+            )
+        );
+    }
+
+    #[test]
+    fn it_works_with_inequalities() {
+        let (mut j, l, r) = setup(vec![(0, Operator::Less, 0)]);
+        let row = |a: i32, b: &str| -> Vec<DataType> { vec![a.into(), b.into()] };
+        let joined = |(la, lb): (i32, &str), (ra, rb): (i32, &str)| -> Vec<DataType> {
+            vec![la.into(), lb.into(), ra.into(), rb.into()]
+        };
+
+        j.seed(r, row(2, "x"));
+        j.one_row(r, row(2, "x"), false);
+        j.seed(r, row(4, "y"));
+        j.one_row(r, row(4, "y"), false);
+
+        // 3 < 4, but not 3 < 2
+        j.seed(l, row(3, "a"));
+        let rs = j.one_row(l, row(3, "a"), false);
+        assert_eq!(rs, vec![(joined((3, "a"), (4, "y")), true)].into());
+
+        // 1 < 2 and 1 < 4
+        j.seed(l, row(1, "b"));
+        let mut rs: Vec<_> = j.one_row(l, row(1, "b"), false).into();
+        rs.sort();
+        assert_eq!(
+            rs,
+            vec![
+                (joined((1, "b"), (2, "x")), true).into(),
+                (joined((1, "b"), (4, "y")), true).into(),
+            ]
+        );
+
+        // a new right row joins with every left row that is smaller
+        j.seed(r, row(5, "z"));
+        let mut rs: Vec<_> = j.one_row(r, row(5, "z"), false).into();
+        rs.sort();
+        assert_eq!(
+            rs,
+            vec![
+                (joined((1, "b"), (5, "z")), true).into(),
+                (joined((3, "a"), (5, "z")), true).into(),
+            ]
+        );
+
+        // and revoking a left row revokes its joined rows
+        j.unseed(l);
+        j.seed(l, row(1, "b"));
+        let mut rs: Vec<_> = j.one_row(l, (row(3, "a"), false), false).into();
+        rs.sort();
+        assert_eq!(
+            rs,
+            vec![
+                (joined((3, "a"), (4, "y")), false).into(),
+                (joined((3, "a"), (5, "z")), false).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_works_with_bands() {
+        // l0 >= r0 AND l0 <= r1, i.e., l0 BETWEEN r0 AND r1
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("left", &["l0"]);
+        let r = g.add_base("right", &["r0", "r1"]);
+        use crate::ops::join::JoinSource::*;
+        let j = ThetaJoin::new(
+            l.as_global(),
+            r.as_global(),
+            vec![
+                (0, Operator::GreaterOrEqual, 0),
+                (0, Operator::LessOrEqual, 1),
+            ],
+            vec![L(0), R(0), R(1)],
+        );
+        g.set_op("join", &["j0", "j1", "j2"], j, false);
+
+        g.seed(r, vec![0.into(), 10.into()]);
+        g.seed(r, vec![5.into(), 8.into()]);
+        g.seed(r, vec![DataType::None, 20.into()]);
+        g.seed(l, vec![7.into()]);
+        let mut rs: Vec<_> = g.one_row(l, vec![7.into()], false).into();
+        rs.sort();
+        assert_eq!(
+            rs,
+            vec![
+                (vec![7.into(), 0.into(), 10.into()], true).into(),
+                (vec![7.into(), 5.into(), 8.into()], true).into(),
+            ]
+        );
+
+        g.seed(l, vec![9.into()]);
+        let rs = g.one_row(l, vec![9.into()], false);
+        assert_eq!(rs, vec![(vec![9.into(), 0.into(), 10.into()], true)].into());
+
+        // NULL matches nothing
+        g.seed(l, vec![DataType::None]);
+        let rs = g.one_row(l, vec![DataType::None], false);
+        assert!(rs.is_empty());
+    }
+
+    #[test]
+    fn it_suggests_indices() {
+        let me = 2.into();
+        let (g, l, r) = setup(vec![(1, Operator::Greater, 0), (0, Operator::Equal, 1)]);
+        let hm: HashMap<_, _> = vec![
+            (l.as_global(), vec![1]), /* join column for left */
+            (r.as_global(), vec![0]), /* join column for right */
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(g.node().suggest_indexes(me), hm);
+    }
+
+    #[test]
+    fn it_resolves() {
+        let (g, l, r) = setup(vec![(0, Operator::Less, 0)]);
+        assert_eq!(g.node().resolve(0), Some(vec![(l.as_global(), 0)]));
+        assert_eq!(g.node().resolve(3), Some(vec![(r.as_global(), 1)]));
+    }
+}
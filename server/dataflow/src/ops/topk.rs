@@ -1,8 +1,10 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::ops::Range;
 
 use crate::prelude::*;
+use slog::Logger;
 
 use nom_sql::OrderType;
 
@@ -33,7 +35,7 @@ impl From<Vec<(usize, OrderType)>> for Order {
 ///
 /// Positives are generally fast to process, while negative records can trigger expensive backwards
 /// queries. It is also worth noting that due the nature of Soup, the results of this operator are
-/// unordered.
+/// unordered; it is the reader of the query that returns them in order.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TopK {
     src: IndexPair,
@@ -317,6 +319,266 @@ impl Ingredient for TopK {
     }
 }
 
+/// TopKWindow skips the first `offset` rows of each group of a [`TopK`], like `OFFSET` does.
+///
+/// It sits directly below a TopK that keeps `offset` more rows per group than the query asks
+/// for. Those extra rows have to stay in the TopK's state so that they can move into the window
+/// when rows ranked ahead of them go away, but they must not reach the query's readers. Since the
+/// TopK's state is exactly its output, this operator works out which rows move in and out of the
+/// window by looking at the group in that state, and keeps no state of its own.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TopKWindow {
+    src: IndexPair,
+    group_by: Vec<usize>,
+    order: Order,
+    offset: usize,
+}
+
+impl TopKWindow {
+    /// Construct a new TopKWindow operator.
+    ///
+    /// `src` is the TopK to skip rows of, and `order` and `group_by` must be the ones that it was
+    /// constructed with. `offset` is the number of rows to skip in each group.
+    pub fn new(
+        src: NodeIndex,
+        order: Vec<(usize, OrderType)>,
+        group_by: Vec<usize>,
+        offset: usize,
+    ) -> Self {
+        let mut group_by = group_by;
+        group_by.sort();
+
+        TopKWindow {
+            src: src.into(),
+            group_by,
+            order: order.into(),
+            offset,
+        }
+    }
+
+    /// The rows of `group` that are not skipped, in no particular order.
+    fn window<'a>(&self, mut group: Vec<Cow<'a, [DataType]>>) -> Vec<Cow<'a, [DataType]>> {
+        if group.len() <= self.offset {
+            return Vec::new();
+        }
+
+        // the TopK keeps the rows that sort last, so those are the ones that come first. ties are
+        // broken by the rows themselves so that the window does not depend on the order in which
+        // the rows happen to be stored.
+        group.sort_by(|a, b| self.order.cmp(b, a).then_with(|| b.cmp(a)));
+        group.split_off(self.offset)
+    }
+
+    /// The group that each record belongs to, along with the range of `rs` it spans.
+    ///
+    /// `rs` is sorted by group first, so that each group is only looked up once.
+    fn groups(&self, rs: &mut Vec<Record>) -> Vec<(Vec<DataType>, Range<usize>)> {
+        let group_by = &self.group_by;
+        rs.sort_by(|a, b| {
+            group_by
+                .iter()
+                .map(|&col| &a[col])
+                .cmp(group_by.iter().map(|&col| &b[col]))
+        });
+
+        let mut groups: Vec<(Vec<DataType>, Range<usize>)> = Vec::new();
+        for (i, r) in rs.iter().enumerate() {
+            match groups.last_mut() {
+                Some((ref grp, ref mut range))
+                    if grp.iter().eq(group_by.iter().map(|&col| &r[col])) =>
+                {
+                    range.end = i + 1;
+                }
+                _ => groups.push((
+                    group_by.iter().map(|&col| r[col].clone()).collect(),
+                    i..i + 1,
+                )),
+            }
+        }
+        groups
+    }
+}
+
+impl Ingredient for TopKWindow {
+    fn take(&mut self) -> NodeOperator {
+        Clone::clone(self).into()
+    }
+
+    fn ancestors(&self) -> Vec<NodeIndex> {
+        vec![self.src.as_global()]
+    }
+
+    fn on_connected(&mut self, _: &Graph) {}
+
+    fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
+        self.src.remap(remap);
+    }
+
+    fn on_input(
+        &mut self,
+        _: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        replay_key_cols: Option<&[usize]>,
+        nodes: &DomainNodes,
+        states: &StateMap,
+    ) -> ProcessingResult {
+        debug_assert_eq!(from, *self.src);
+
+        let mut rs: Vec<_> = rs.into();
+        let mut out = Vec::new();
+        let mut misses = Vec::new();
+        let mut lookups = Vec::new();
+
+        for (grp, range) in self.groups(&mut rs) {
+            // the TopK has already applied these records to its state, so that is what the group
+            // looks like now
+            let now = self
+                .lookup(
+                    *self.src,
+                    &self.group_by,
+                    &KeyType::from(&grp[..]),
+                    nodes,
+                    states,
+                )
+                .expect("topk windows must have their parent materialized");
+            let now: Vec<_> = match now {
+                Some(now) => now.collect(),
+                None => {
+                    misses.extend(rs[range].iter().map(|r| Miss {
+                        on: *self.src,
+                        lookup_idx: self.group_by.clone(),
+                        lookup_cols: self.group_by.clone(),
+                        replay_cols: replay_key_cols.map(Vec::from),
+                        record: (**r).clone(),
+                    }));
+                    continue;
+                }
+            };
+
+            if replay_key_cols.is_some() {
+                lookups.push(Lookup {
+                    on: *self.src,
+                    cols: self.group_by.clone(),
+                    key: grp,
+                });
+            }
+
+            // undo the records to find what the group looked like before them
+            let mut before = now.clone();
+            for r in &rs[range] {
+                if r.is_positive() {
+                    if let Some(i) = before.iter().position(|b| **b == r[..]) {
+                        before.swap_remove(i);
+                    }
+                } else {
+                    before.push(Cow::Borrowed(&r[..]));
+                }
+            }
+
+            let mut now = self.window(now);
+            for r in self.window(before) {
+                match now.iter().position(|n| *n == r) {
+                    Some(i) => {
+                        now.swap_remove(i);
+                    }
+                    None => out.push(Record::Negative(r.into_owned())),
+                }
+            }
+            out.extend(now.into_iter().map(|r| Record::Positive(r.into_owned())));
+        }
+
+        ProcessingResult {
+            results: out.into(),
+            lookups,
+            misses,
+        }
+    }
+
+    fn on_input_raw(
+        &mut self,
+        ex: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        replay: ReplayContext,
+        nodes: &DomainNodes,
+        states: &StateMap,
+        _: &Logger,
+    ) -> RawProcessingResult {
+        match replay {
+            ReplayContext::Full { .. } => {}
+            _ => {
+                let key = replay.key();
+                return RawProcessingResult::Regular(
+                    self.on_input(ex, from, rs, key, nodes, states),
+                );
+            }
+        }
+
+        // a full replay sends the TopK's state in chunks that may split a group, so rather than
+        // comparing against what the group looked like before, forward the rows of each chunk
+        // that fall within the window of their group
+        let mut rs: Vec<_> = rs.into();
+        let mut out = Vec::new();
+        for (grp, range) in self.groups(&mut rs) {
+            let group = self
+                .lookup(
+                    *self.src,
+                    &self.group_by,
+                    &KeyType::from(&grp[..]),
+                    nodes,
+                    states,
+                )
+                .expect("topk windows must have their parent materialized")
+                .expect("full replays should not miss");
+            let mut window = self.window(group.collect());
+            for r in &rs[range] {
+                if let Some(i) = window.iter().position(|w| **w == r[..]) {
+                    window.swap_remove(i);
+                    out.push(r.clone());
+                }
+            }
+        }
+
+        RawProcessingResult::Regular(ProcessingResult {
+            results: out.into(),
+            ..Default::default()
+        })
+    }
+
+    fn suggest_indexes(&self, _: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
+        vec![(self.src.as_global(), self.group_by.clone())]
+            .into_iter()
+            .collect()
+    }
+
+    fn resolve(&self, col: usize) -> Option<Vec<(NodeIndex, usize)>> {
+        Some(vec![(self.src.as_global(), col)])
+    }
+
+    fn description(&self, detailed: bool) -> String {
+        if !detailed {
+            return String::from("Offset");
+        }
+
+        let group_cols = self
+            .group_by
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("Offset {} γ[{}]", self.offset, group_cols)
+    }
+
+    fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
+        vec![(self.src.as_global(), Some(col))]
+    }
+
+    fn is_selective(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -561,4 +823,79 @@ mod tests {
         assert!(emit.iter().any(|r| !r.is_positive() && r[2] == 10.into()));
         assert!(emit.iter().any(|r| r.is_positive() && r[2] == 11.into()));
     }
+
+    fn setup_window() -> (ops::test::MockGraph, IndexPair) {
+        let mut g = ops::test::MockGraph::new();
+        let s = g.add_base("source", &["x", "y", "z"]);
+        g.set_op(
+            "window",
+            &["x", "y", "z"],
+            TopKWindow::new(
+                s.as_global(),
+                vec![(2, OrderType::OrderAscending)],
+                vec![1],
+                1,
+            ),
+            false,
+        );
+        (g, s)
+    }
+
+    #[test]
+    fn it_skips_offset() {
+        let (mut g, s) = setup_window();
+
+        let r12: Vec<DataType> = vec![1.into(), "z".into(), 12.into()];
+        let r10: Vec<DataType> = vec![2.into(), "z".into(), 10.into()];
+        let r15: Vec<DataType> = vec![3.into(), "z".into(), 15.into()];
+
+        // the parent stands in for the topk, whose state has already seen each record
+        g.seed(s, r12.clone());
+        let a = g.narrow_one_row(r12.clone(), false);
+        assert_eq!(a.len(), 0);
+
+        g.seed(s, r10.clone());
+        let a = g.narrow_one_row(r10.clone(), false);
+        assert_eq!(a, vec![r10.clone()].into());
+
+        // a new top row pushes the old one into the window
+        g.seed(s, r15.clone());
+        let a = g.narrow_one_row(r15.clone(), false);
+        assert_eq!(a, vec![r12.clone()].into());
+
+        // and removing it again pushes it back out
+        g.unseed(s);
+        g.seed(s, r12.clone());
+        g.seed(s, r10.clone());
+        let a = g.narrow_one_row((r15.clone(), false), false);
+        assert_eq!(a, vec![(r12.clone(), false)].into());
+
+        // when the skipped row goes away, the next one takes its place
+        g.unseed(s);
+        g.seed(s, r10.clone());
+        let a = g.narrow_one_row((r12.clone(), false), false);
+        assert_eq!(a, vec![(r10.clone(), false)].into());
+    }
+
+    #[test]
+    fn it_keeps_groups_apart() {
+        let (mut g, s) = setup_window();
+
+        let z: Vec<DataType> = vec![1.into(), "z".into(), 12.into()];
+        let y: Vec<DataType> = vec![2.into(), "y".into(), 10.into()];
+
+        g.seed(s, z.clone());
+        g.seed(s, y.clone());
+        let a = g.narrow_one(vec![z, y], false);
+        assert_eq!(a.len(), 0);
+    }
+
+    #[test]
+    fn it_suggests_parent_indices() {
+        let (g, s) = setup_window();
+        let me = 2.into();
+        let idx = g.node().suggest_indexes(me);
+        assert_eq!(idx.len(), 1);
+        assert_eq!(idx[&s.as_global()], vec![1]);
+    }
 }
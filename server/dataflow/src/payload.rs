@@ -5,8 +5,9 @@ use crate::domain;
 use crate::prelude::*;
 use noria;
 use noria::internal::LocalOrNot;
+use noria::KeyRange;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::net::SocketAddr;
 
@@ -48,12 +49,16 @@ pub enum InitialState {
         gid: petgraph::graph::NodeIndex,
         cols: usize,
         key: Vec<usize>,
+        /// Whether the reader serves lookups for ranges of keys.
+        ranges: bool,
         trigger_domain: (domain::Index, usize),
     },
     Global {
         gid: petgraph::graph::NodeIndex,
         cols: usize,
         key: Vec<usize>,
+        /// Whether the reader serves lookups for ranges of keys.
+        ranges: bool,
     },
 }
 
@@ -64,6 +69,9 @@ pub enum ReplayPieceContext {
         requesting_shard: usize,
         unishard: bool,
         ignore: bool,
+        /// Set if this piece answers a range replay request, in which case `for_keys` holds every
+        /// key the source had within the range.
+        range: Option<KeyRange>,
     },
     Regular {
         last: bool,
@@ -77,6 +85,69 @@ pub struct SourceChannelIdentifier {
     pub tag: u32,
 }
 
+/// A client whose writes make up part of a merged `Input` packet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputSender {
+    pub src: SourceChannelIdentifier,
+    /// The operations in the merged packet that came from this client.
+    pub ops: std::ops::Range<usize>,
+    /// Whether the client asked for the outcome of each of those operations.
+    pub outcomes: bool,
+}
+
+/// The base table writes that produced an update.
+///
+/// Unlike a [`WriteToken`], this keeps every copy of every write. The writes to a base table may
+/// reach a node along several paths, and an update that carries the writes of several earlier
+/// updates can then hold a write more than once. Readers count the copies so that they know when
+/// a write has arrived along every path that leads to them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Writes {
+    /// Runs of consecutive writes to each base table shard, as the epoch along with the first
+    /// and the last sequence number of the run.
+    runs: BTreeMap<(NodeIndex, usize), Vec<(u64, u64, u64)>>,
+}
+
+impl Writes {
+    /// The `seq`th write in epoch `epoch` to the given shard of the base table `base`.
+    pub(crate) fn new(base: NodeIndex, shard: usize, epoch: u64, seq: u64) -> Self {
+        let mut runs = BTreeMap::new();
+        runs.insert((base, shard), vec![(epoch, seq, seq)]);
+        Writes { runs }
+    }
+
+    /// Add all the copies of the writes in `other`.
+    pub(crate) fn merge(&mut self, other: &Writes) {
+        for (&k, others) in &other.runs {
+            let runs = self.runs.entry(k).or_default();
+            for &(epoch, first, last) in others {
+                // a run of writes along one path is continued by the next write along that path
+                match runs
+                    .iter_mut()
+                    .find(|&&mut (e, _, l)| e == epoch && l + 1 == first)
+                {
+                    Some(run) => run.2 = last,
+                    None => runs.push((epoch, first, last)),
+                }
+            }
+        }
+    }
+
+    /// Returns true if this holds any writes to the given shard of the base table `base`.
+    pub(crate) fn contains(&self, base: NodeIndex, shard: usize) -> bool {
+        self.runs.contains_key(&(base, shard))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// The runs of writes to each base table shard, as `(epoch, first seq, last seq)`.
+    pub(crate) fn runs(&self) -> impl Iterator<Item = (&(NodeIndex, usize), &[(u64, u64, u64)])> {
+        self.runs.iter().map(|(k, runs)| (k, &runs[..]))
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[allow(clippy::large_enum_variant)]
 pub enum Packet {
@@ -85,13 +156,17 @@ pub enum Packet {
     Input {
         inner: LocalOrNot<Input>,
         src: Option<SourceChannelIdentifier>,
-        senders: Vec<SourceChannelIdentifier>,
+        senders: Vec<InputSender>,
     },
 
     /// Regular data-flow update.
     Message {
         link: Link,
         data: Records,
+        /// The base table writes that produced this update.
+        token: Writes,
+        /// The transactions that those writes are part of.
+        txns: Vec<Txn>,
     },
 
     /// Update that is part of a tagged data-flow replay path.
@@ -200,6 +275,20 @@ pub enum Packet {
         keys: Vec<Vec<DataType>>,
     },
 
+    /// Ask domain (nicely) to replay every key within a range.
+    RequestPartialRangeReplay {
+        tag: Tag,
+        range: KeyRange,
+        requesting_shard: usize,
+    },
+
+    /// Ask domain (nicely) to replay every key within a range into a Reader.
+    RequestReaderRangeReplay {
+        node: LocalNodeIndex,
+        cols: Vec<usize>,
+        range: KeyRange,
+    },
+
     /// Instruct domain to replay the state of a particular node along an existing replay path.
     StartReplay {
         tag: Tag,
@@ -308,9 +397,16 @@ impl Packet {
 
     pub(crate) fn clone_data(&self) -> Self {
         match *self {
-            Packet::Message { link, ref data } => Packet::Message {
+            Packet::Message {
+                link,
+                ref data,
+                ref token,
+                ref txns,
+            } => Packet::Message {
                 link,
                 data: data.clone(),
+                token: token.clone(),
+                txns: txns.clone(),
             },
             Packet::ReplayPiece {
                 link,
@@ -339,6 +435,16 @@ impl fmt::Debug for Packet {
             Packet::RequestPartialReplay { ref tag, .. } => {
                 write!(f, "Packet::RequestPartialReplay({:?})", tag)
             }
+            Packet::RequestReaderRangeReplay { ref range, .. } => {
+                write!(f, "Packet::RequestReaderRangeReplay({:?})", range)
+            }
+            Packet::RequestPartialRangeReplay {
+                ref tag, ref range, ..
+            } => write!(
+                f,
+                "Packet::RequestPartialRangeReplay({:?}, {:?})",
+                tag, range
+            ),
             Packet::ReplayPiece {
                 ref link,
                 ref tag,
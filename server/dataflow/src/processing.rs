@@ -148,6 +148,9 @@ where
     ///    𝛴    |  Sum
     ///    ⋈    |  Join
     ///    ⋉    |  Left join
+    ///   ⋈θ    |  Theta join
+    ///    ∈    |  Semi-join
+    ///    ∉    |  Anti-join
     ///    ⋃    |  Union
     ///    σ    |  Filter
     ///    π    |  Projection
@@ -279,4 +282,10 @@ where
     fn requires_full_materialization(&self) -> bool {
         false
     }
+
+    /// Returns true if this operator reads ranges of its parents' state rather than individual
+    /// keys, so neither its parents nor any view keyed through it can be partially materialized
+    fn requires_full_parents(&self) -> bool {
+        false
+    }
 }
@@ -1,15 +1,19 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::rc::Rc;
 
 use rand::{self, Rng};
 
 use crate::prelude::*;
+use crate::state::ordered_index::OrderedIndex;
 use crate::state::single_state::SingleState;
 use common::SizeOf;
+use noria::KeyRange;
 
 #[derive(Default)]
 pub struct MemoryState {
     state: Vec<SingleState>,
+    ordered: Vec<OrderedIndex>,
     by_tag: HashMap<Tag, usize>,
     mem_size: u64,
 }
@@ -22,7 +26,7 @@ impl SizeOf for MemoryState {
     }
 
     fn deep_size_of(&self) -> u64 {
-        self.mem_size
+        self.mem_size + self.ordered.iter().map(SizeOf::deep_size_of).sum::<u64>()
     }
 
     fn is_empty(&self) -> bool {
@@ -68,6 +72,24 @@ impl State for MemoryState {
         }
     }
 
+    fn add_ordered_key(&mut self, columns: &[usize]) {
+        assert!(!self.is_partial(), "partial state can't be ordered");
+        if self.ordered.iter().any(|o| o.columns() == columns) {
+            return;
+        }
+
+        // rows are looked up through the regular index once the ordered one has found their keys
+        self.add_key(columns, None);
+
+        let mut ordered = OrderedIndex::new(columns);
+        for rs in self.state[0].values() {
+            for r in rs {
+                ordered.insert(&r[..]);
+            }
+        }
+        self.ordered.push(ordered);
+    }
+
     fn is_useful(&self) -> bool {
         !self.state.is_empty()
     }
@@ -102,10 +124,16 @@ impl State for MemoryState {
             for r in records.iter() {
                 match *r {
                     Record::Positive(ref r) => {
+                        for ordered in &mut self.ordered {
+                            ordered.insert(r);
+                        }
                         let hit = self.insert(r.clone(), None);
                         debug_assert!(hit);
                     }
                     Record::Negative(ref r) => {
+                        for ordered in &mut self.ordered {
+                            ordered.remove(r);
+                        }
                         let hit = self.remove(r);
                         debug_assert!(hit);
                     }
@@ -139,6 +167,22 @@ impl State for MemoryState {
         self.state[index].lookup(key)
     }
 
+    fn lookup_range(&self, columns: &[usize], range: &KeyRange) -> Vec<Vec<DataType>> {
+        let ordered = self
+            .ordered
+            .iter()
+            .find(|o| o.columns() == columns)
+            .expect("range lookup on non-ordered column set");
+        let mut rs = Vec::new();
+        for key in ordered.range(range) {
+            match self.lookup(columns, &KeyType::from(&key[..])) {
+                LookupResult::Some(records) => rs.extend(records.into_iter().map(Cow::into_owned)),
+                LookupResult::Missing => unreachable!("ordered state is never partial"),
+            }
+        }
+        rs
+    }
+
     fn keys(&self) -> Vec<Vec<usize>> {
         self.state.iter().map(|s| s.key().to_vec()).collect()
     }
@@ -176,6 +220,9 @@ impl State for MemoryState {
         for state in &mut self.state {
             state.clear();
         }
+        for ordered in &mut self.ordered {
+            ordered.clear();
+        }
         self.mem_size = 0;
     }
 }
@@ -284,4 +331,47 @@ mod tests {
             _ => unreachable!(),
         };
     }
+
+    #[test]
+    fn memory_state_lookup_range() {
+        use std::ops::Bound;
+
+        let mut state = MemoryState::default();
+        state.add_key(&[0], None);
+        insert(&mut state, vec![1.into(), "A".into()]);
+        insert(&mut state, vec![3.into(), "C".into()]);
+        state.add_ordered_key(&[1]);
+        insert(&mut state, vec![2.into(), "B".into()]);
+        insert(&mut state, vec![4.into(), "C".into()]);
+
+        let range = (
+            Bound::Excluded(vec!["A".into()]),
+            Bound::Included(vec!["C".into()]),
+        );
+        let mut rows = state.lookup_range(&[1], &range);
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![
+                vec![2.into(), "B".into()],
+                vec![3.into(), "C".into()],
+                vec![4.into(), "C".into()],
+            ]
+        );
+
+        let mut records: Records = vec![(vec![4.into(), "C".into()], false)].into();
+        state.process_records(&mut records, None);
+        let range = (Bound::Included(vec!["C".into()]), Bound::Unbounded);
+        assert_eq!(
+            state.lookup_range(&[1], &range),
+            vec![vec![DataType::from(3), "C".into()]]
+        );
+
+        // an inverted range is empty rather than a panic
+        let range = (
+            Bound::Included(vec!["C".into()]),
+            Bound::Included(vec!["A".into()]),
+        );
+        assert!(state.lookup_range(&[1], &range).is_empty());
+    }
 }
@@ -1,6 +1,7 @@
 mod keyed_state;
 mod memory_state;
 mod mk_key;
+mod ordered_index;
 mod persistent_state;
 mod single_state;
 
@@ -13,6 +14,7 @@ use crate::prelude::*;
 use ahash::RandomState;
 use common::SizeOf;
 use hashbag::HashBag;
+use noria::KeyRange;
 
 pub(crate) use self::memory_state::MemoryState;
 pub(crate) use self::persistent_state::PersistentState;
@@ -21,6 +23,10 @@ pub(crate) trait State: SizeOf + Send {
     /// Add an index keyed by the given columns and replayed to by the given partial tags.
     fn add_key(&mut self, columns: &[usize], partial: Option<Vec<Tag>>);
 
+    /// Add an ordered index over the given columns, so that `lookup_range` can find the records
+    /// whose values in those columns fall within a range. Panics if the state is partial.
+    fn add_ordered_key(&mut self, columns: &[usize]);
+
     /// Returns whether this state is currently keyed on anything. If not, then it cannot store any
     /// infromation and is thus "not useful".
     fn is_useful(&self) -> bool;
@@ -37,6 +43,10 @@ pub(crate) trait State: SizeOf + Send {
 
     fn lookup<'a>(&'a self, columns: &[usize], key: &KeyType) -> LookupResult<'a>;
 
+    /// Return a copy of every record whose values in `columns` fall within `range`, in key order.
+    /// Panics if `add_ordered_key` was never called for `columns`.
+    fn lookup_range(&self, columns: &[usize], range: &KeyRange) -> Vec<Vec<DataType>>;
+
     fn rows(&self) -> usize;
 
     fn keys(&self) -> Vec<Vec<usize>>;
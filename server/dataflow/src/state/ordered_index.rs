@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use common::SizeOf;
+use noria::KeyRange;
+
+/// An ordered index over the values a fully materialized state holds in some of its columns.
+///
+/// The indices of a state are hashed, so they can't tell which keys fall within a range. An
+/// ordered index keeps the keys in order, along with how many rows hold each of them, and the rows
+/// themselves are then looked up through the state's regular index on the same columns.
+pub(super) struct OrderedIndex {
+    columns: Vec<usize>,
+    keys: BTreeMap<Vec<DataType>, usize>,
+    mem_size: u64,
+}
+
+impl OrderedIndex {
+    pub(super) fn new(columns: &[usize]) -> Self {
+        OrderedIndex {
+            columns: Vec::from(columns),
+            keys: BTreeMap::new(),
+            mem_size: 0,
+        }
+    }
+
+    pub(super) fn columns(&self) -> &[usize] {
+        &self.columns[..]
+    }
+
+    fn key(&self, row: &[DataType]) -> Vec<DataType> {
+        self.columns.iter().map(|&c| row[c].clone()).collect()
+    }
+
+    /// Count `row` towards the key it holds.
+    pub(super) fn insert(&mut self, row: &[DataType]) {
+        let key = self.key(row);
+        let size = key.deep_size_of() + std::mem::size_of::<usize>() as u64;
+        let count = self.keys.entry(key).or_insert(0);
+        if *count == 0 {
+            self.mem_size += size;
+        }
+        *count += 1;
+    }
+
+    /// Stop counting `row` towards the key it holds, dropping the key once no row holds it.
+    pub(super) fn remove(&mut self, row: &[DataType]) {
+        let key = self.key(row);
+        match self.keys.get_mut(&key) {
+            Some(count) if *count > 1 => *count -= 1,
+            Some(_) => {
+                let size = key.deep_size_of() + std::mem::size_of::<usize>() as u64;
+                self.keys.remove(&key);
+                self.mem_size = self.mem_size.checked_sub(size).unwrap();
+            }
+            None => unreachable!("removed a row that was never inserted"),
+        }
+    }
+
+    /// Returns the keys within `range` that some row holds, in order.
+    pub(super) fn range<'a>(&'a self, range: &KeyRange) -> Vec<&'a Vec<DataType>> {
+        if crate::backlog::range_is_empty(range) {
+            // BTreeMap::range panics on inverted bounds
+            return Vec::new();
+        }
+        self.keys.range(range.clone()).map(|(k, _)| k).collect()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.keys.clear();
+        self.mem_size = 0;
+    }
+}
+
+impl SizeOf for OrderedIndex {
+    fn size_of(&self) -> u64 {
+        use std::mem::size_of;
+
+        size_of::<Self>() as u64
+    }
+
+    fn deep_size_of(&self) -> u64 {
+        self.mem_size
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
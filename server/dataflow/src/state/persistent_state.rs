@@ -5,8 +5,10 @@ use serde;
 use tempfile::{tempdir, TempDir};
 
 use crate::prelude::*;
+use crate::state::ordered_index::OrderedIndex;
 use crate::state::{RecordResult, State};
 use common::SizeOf;
+use noria::KeyRange;
 
 // Incremented on each PersistentState initialization so that IndexSeq
 // can be used to create unique identifiers for rows.
@@ -50,6 +52,8 @@ pub struct PersistentState {
     // read during lookups. When `self.has_unique_index` is true the first index is a primary key,
     // and all its keys are considered unique.
     indices: Vec<PersistentIndex>,
+    // Ordered indices live in memory only, and are rebuilt from the rows whenever they're added.
+    ordered: Vec<OrderedIndex>,
     seq: IndexSeq,
     epoch: IndexEpoch,
     has_unique_index: bool,
@@ -69,9 +73,15 @@ impl State for PersistentState {
         for r in records.iter() {
             match *r {
                 Record::Positive(ref r) => {
+                    for ordered in &mut self.ordered {
+                        ordered.insert(r);
+                    }
                     self.insert(&mut batch, r);
                 }
                 Record::Negative(ref r) => {
+                    for ordered in &mut self.ordered {
+                        ordered.remove(r);
+                    }
                     self.remove(&mut batch, r);
                 }
             }
@@ -161,6 +171,40 @@ impl State for PersistentState {
         });
     }
 
+    fn add_ordered_key(&mut self, columns: &[usize]) {
+        if self.ordered.iter().any(|o| o.columns() == columns) {
+            return;
+        }
+
+        // rows are looked up through the regular index once the ordered one has found their keys
+        self.add_key(columns, None);
+
+        let mut ordered = OrderedIndex::new(columns);
+        tokio::task::block_in_place(|| {
+            for (_, ref value) in self.all_rows() {
+                let row: Vec<DataType> = bincode::deserialize(&value).unwrap();
+                ordered.insert(&row);
+            }
+        });
+        self.ordered.push(ordered);
+    }
+
+    fn lookup_range(&self, columns: &[usize], range: &KeyRange) -> Vec<Vec<DataType>> {
+        let ordered = self
+            .ordered
+            .iter()
+            .find(|o| o.columns() == columns)
+            .expect("range lookup on non-ordered column set");
+        let mut rs = Vec::new();
+        for key in ordered.range(range) {
+            match self.lookup(columns, &KeyType::from(&key[..])) {
+                LookupResult::Some(RecordResult::Owned(records)) => rs.extend(records),
+                _ => unreachable!(),
+            }
+        }
+        rs
+    }
+
     fn keys(&self) -> Vec<Vec<usize>> {
         self.indices
             .iter()
@@ -283,6 +327,7 @@ impl PersistentState {
             let mut state = Self {
                 seq: 0,
                 indices,
+                ordered: Vec::new(),
                 has_unique_index: primary_key.is_some(),
                 epoch: meta.epoch,
                 db_opts: opts,
@@ -599,6 +644,7 @@ impl SizeOf for PersistentState {
         db.property_int_value("rocksdb.estimate-live-data-size")
             .unwrap()
             .unwrap()
+            + self.ordered.iter().map(SizeOf::deep_size_of).sum::<u64>()
     }
 
     fn is_empty(&self) -> bool {
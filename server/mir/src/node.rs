@@ -1,4 +1,4 @@
-use nom_sql::{ArithmeticExpression, ColumnSpecification, Literal, OrderType};
+use nom_sql::{ArithmeticExpression, ColumnSpecification, Literal, Operator, OrderType};
 use petgraph::graph::NodeIndex;
 use std::cell::RefCell;
 use std::fmt::{Debug, Display, Error, Formatter};
@@ -367,6 +367,33 @@ impl MirNode {
                     }
                 }
             }
+            MirNodeType::SemiJoin {
+                ref on_left,
+                ref on_right,
+                ..
+            }
+            | MirNodeType::AntiJoin {
+                ref on_left,
+                ref on_right,
+                ..
+            } => {
+                // need the join columns of both parents, though only the left ones are emitted
+                for c in on_left.iter().chain(on_right) {
+                    if !columns.contains(c) {
+                        columns.push(c.clone());
+                    }
+                }
+            }
+            MirNodeType::ThetaJoin { ref on, .. } => {
+                // need the compared columns of both parents
+                for (l, _, r) in on {
+                    for c in &[l, r] {
+                        if !columns.contains(c) {
+                            columns.push((*c).clone());
+                        }
+                    }
+                }
+            }
             _ => (),
         }
         columns
@@ -446,6 +473,25 @@ pub enum MirNodeType {
         on_right: Vec<Column>,
         project: Vec<Column>,
     },
+    /// on left column, on right column, emit columns (all from the left); left rows that have
+    /// a match in the right parent
+    SemiJoin {
+        on_left: Vec<Column>,
+        on_right: Vec<Column>,
+        project: Vec<Column>,
+    },
+    /// on left column, on right column, emit columns (all from the left); left rows that have
+    /// no match in the right parent
+    AntiJoin {
+        on_left: Vec<Column>,
+        on_right: Vec<Column>,
+        project: Vec<Column>,
+    },
+    /// (left column, operator, right column) conditions, emit columns
+    ThetaJoin {
+        on: Vec<(Column, Operator, Column)>,
+        project: Vec<Column>,
+    },
     /// group columns
     // currently unused
     #[allow(dead_code)]
@@ -477,10 +523,15 @@ pub enum MirNodeType {
     Reuse {
         node: MirNodeRef,
     },
-    /// leaf (reader) node, keys
+    /// leaf (reader) node, keys, the order in which rows for a key are returned, the conditions
+    /// on `node`'s columns that compare against query parameters, if the query's parameters
+    /// cannot all be looked up by key, and whether lookups may ask for ranges of keys
     Leaf {
         node: MirNodeRef,
         keys: Vec<Column>,
+        order: Option<Vec<(Column, OrderType)>>,
+        parameter_filter: Vec<(usize, FilterCondition)>,
+        ranges: bool,
     },
     /// Rewrite node
     Rewrite {
@@ -519,6 +570,15 @@ impl MirNodeType {
             }
             | MirNodeType::LeftJoin {
                 ref mut project, ..
+            }
+            | MirNodeType::ThetaJoin {
+                ref mut project, ..
+            }
+            | MirNodeType::SemiJoin {
+                ref mut project, ..
+            }
+            | MirNodeType::AntiJoin {
+                ref mut project, ..
             } => {
                 project.push(c);
             }
@@ -692,6 +752,40 @@ impl MirNodeType {
                     _ => false,
                 }
             }
+            MirNodeType::SemiJoin {
+                on_left: ref our_on_left,
+                on_right: ref our_on_right,
+                project: ref our_project,
+            } => match *other {
+                MirNodeType::SemiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref project,
+                } => our_on_left == on_left && our_on_right == on_right && our_project == project,
+                _ => false,
+            },
+            MirNodeType::AntiJoin {
+                on_left: ref our_on_left,
+                on_right: ref our_on_right,
+                project: ref our_project,
+            } => match *other {
+                MirNodeType::AntiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref project,
+                } => our_on_left == on_left && our_on_right == on_right && our_project == project,
+                _ => false,
+            },
+            MirNodeType::ThetaJoin {
+                on: ref our_on,
+                project: ref our_project,
+            } => match *other {
+                MirNodeType::ThetaJoin {
+                    ref on,
+                    ref project,
+                } => our_on == on && our_project == project,
+                _ => false,
+            },
             MirNodeType::Project {
                 emit: ref our_emit,
                 literals: ref our_literals,
@@ -742,9 +836,24 @@ impl MirNodeType {
                 _ => false,
             },
             MirNodeType::Leaf {
-                keys: ref our_keys, ..
+                keys: ref our_keys,
+                order: ref our_order,
+                parameter_filter: ref our_parameter_filter,
+                ranges: our_ranges,
+                ..
             } => match *other {
-                MirNodeType::Leaf { ref keys, .. } => keys == our_keys,
+                MirNodeType::Leaf {
+                    ref keys,
+                    ref order,
+                    ref parameter_filter,
+                    ranges,
+                    ..
+                } => {
+                    keys == our_keys
+                        && order == our_order
+                        && parameter_filter == our_parameter_filter
+                        && ranges == our_ranges
+                }
                 _ => false,
             },
             MirNodeType::Union { emit: ref our_emit } => match *other {
@@ -874,6 +983,15 @@ impl Debug for MirNodeType {
                                     .collect::<Vec<_>>()
                                     .join(", ")
                             )),
+                            FilterCondition::IsNull | FilterCondition::IsNotNull => {
+                                Some(format!("f{} {}", i, cond))
+                            }
+                            FilterCondition::Json(..) => {
+                                Some(format!("f{}{}", i, escape(&cond.to_string())))
+                            }
+                            FilterCondition::And(..) | FilterCondition::Or(..) => {
+                                Some(escape(&cond.to_string()))
+                            }
                         })
                         .collect::<Vec<_>>()
                         .as_slice()
@@ -955,6 +1073,58 @@ impl Debug for MirNodeType {
                     jc
                 )
             }
+            MirNodeType::SemiJoin {
+                ref on_left,
+                ref on_right,
+                ref project,
+            }
+            | MirNodeType::AntiJoin {
+                ref on_left,
+                ref on_right,
+                ref project,
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", l.name, r.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let op = match *self {
+                    MirNodeType::SemiJoin { .. } => "∈",
+                    _ => "∉",
+                };
+                write!(
+                    f,
+                    "{} [{} on {}]",
+                    op,
+                    project
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    jc
+                )
+            }
+            MirNodeType::ThetaJoin {
+                ref on,
+                ref project,
+            } => {
+                let jc = on
+                    .iter()
+                    .map(|(l, op, r)| format!("{} {} {}", l.name, op, r.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "⋈θ [{} on {}]",
+                    project
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    jc
+                )
+            }
             MirNodeType::Latest { ref group_by } => {
                 let key_cols = group_by
                     .iter()
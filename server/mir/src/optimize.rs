@@ -85,6 +85,15 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
                     if child.ancestors.len() != 1 {
                         continue;
                     }
+                    // merging moves the conditions onto the aggregation's parent, and only the
+                    // outermost column of each condition is reindexed to match
+                    let combined = conditions.iter().any(|(_, cond)| match *cond {
+                        FilterCondition::And(..) | FilterCondition::Or(..) => true,
+                        _ => false,
+                    });
+                    if combined {
+                        continue;
+                    }
                     candidate = true;
 
                     // But wait -- need to check if the filter is on the aggregation result
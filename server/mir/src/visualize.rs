@@ -193,6 +193,15 @@ impl GraphViz for MirNodeType {
                                     .collect::<Vec<_>>()
                                     .join(", ")
                             )),
+                            FilterCondition::IsNull | FilterCondition::IsNotNull => {
+                                Some(format!("f{} {}", i, cond))
+                            }
+                            FilterCondition::Json(..) => {
+                                Some(format!("f{}{}", i, escape(&cond.to_string())))
+                            }
+                            FilterCondition::And(..) | FilterCondition::Or(..) => {
+                                Some(escape(&cond.to_string()))
+                            }
                         })
                         .collect::<Vec<_>>()
                         .as_slice()
@@ -242,6 +251,36 @@ impl GraphViz for MirNodeType {
                     .join(", ");
                 write!(out, "⋉  | on: {}", jc)?;
             }
+            MirNodeType::SemiJoin {
+                ref on_left,
+                ref on_right,
+                ..
+            }
+            | MirNodeType::AntiJoin {
+                ref on_left,
+                ref on_right,
+                ..
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", print_col(l), print_col(r)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let op = match *self {
+                    MirNodeType::SemiJoin { .. } => "∈",
+                    _ => "∉",
+                };
+                write!(out, "{}  | on: {}", op, jc)?;
+            }
+            MirNodeType::ThetaJoin { ref on, .. } => {
+                let jc = on
+                    .iter()
+                    .map(|(l, op, r)| format!("{} {} {}", print_col(l), op, print_col(r)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(out, "⋈θ | on: {}", jc)?;
+            }
             MirNodeType::Latest { ref group_by } => {
                 let key_cols = group_by
                     .iter()
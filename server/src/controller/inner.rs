@@ -9,7 +9,7 @@ use dataflow::prelude::*;
 use dataflow::{node, payload::ControlReplyPacket, prelude::Packet, DomainBuilder, DomainConfig};
 use futures_util::stream::StreamExt;
 use hyper::{self, Method, StatusCode};
-use nom_sql::ColumnSpecification;
+use nom_sql::{ColumnSpecification, SqlQuery};
 use noria::builders::*;
 use noria::channel::tcp::{SendError, TcpSender};
 use noria::consensus::{Authority, Epoch, STATE_KEY};
@@ -271,6 +271,12 @@ impl ControllerInner {
                     self.extend_recipe(authority, args)
                         .map(|r| json::to_string(&r).unwrap())
                 }),
+            (Method::POST, "/prepare") => json::from_slice(&body)
+                .map_err(|_| StatusCode::BAD_REQUEST)
+                .map(|args| {
+                    self.prepare(authority, args)
+                        .map(|r| json::to_string(&r).unwrap())
+                }),
             (Method::POST, "/install_recipe") => json::from_slice(&body)
                 .map_err(|_| StatusCode::BAD_REQUEST)
                 .map(|args| {
@@ -767,23 +773,121 @@ impl ControllerInner {
             None => name,
             Some(alias) => alias,
         };
-        self.find_view_for(node, name).map(|r| {
+        let reader = self.find_view_for(node, name).or_else(|| {
+            // a query that exactly matches an existing one reuses the existing query's reader,
+            // which is named after that query instead.
+            self.recipe
+                .sql_inc()
+                .get_queries_for_node(node)
+                .iter()
+                .find_map(|other| self.find_view_for(node, other))
+        });
+        reader.map(|r| {
             let domain = self.ingredients[r].domain();
             let columns = self.ingredients[r].fields().to_vec();
             let schema = self.view_schema(r);
             let shards = (0..self.domains[&domain].shards())
                 .map(|i| self.read_addrs[&self.domains[&domain].assignment(i)])
                 .collect();
+            let bases = self.upstream_bases(r);
+            let order = self.ingredients[r]
+                .with_reader(|r| r.order().to_vec())
+                .unwrap();
+            let ranges = self.materializations.serves_ranges(r);
 
             ViewBuilder {
                 node: r,
                 columns,
                 schema,
                 shards,
+                bases,
+                order,
+                ranges,
             }
         })
     }
 
+    /// Find the base tables that the reader `r` is computed from.
+    ///
+    /// Each base is paired with whether its writes only ever reach the matching shard of the
+    /// reader. That is the case if the reader is sharded, and no path from the base to the reader
+    /// goes through a sharder.
+    pub(in crate::controller) fn upstream_bases(&self, r: NodeIndex) -> Vec<(NodeIndex, bool)> {
+        self.upstream_base_paths(r)
+            .into_iter()
+            .map(|(base, _, other)| (base, other == 0))
+            .collect()
+    }
+
+    /// Find the base tables that the reader `r` is computed from, and how many copies of each
+    /// write to them a shard of the reader receives.
+    ///
+    /// The first count is of the copies that only reach the matching shard of the reader, which
+    /// is only ever non-zero if the reader is sharded. The second is of the copies that reach
+    /// every shard.
+    pub(in crate::controller) fn upstream_base_paths(
+        &self,
+        r: NodeIndex,
+    ) -> Vec<(NodeIndex, usize, usize)> {
+        let sharded = !self.ingredients[r].sharded_by().is_none();
+        let mut memo = HashMap::new();
+        self.copies_from_bases(r, &mut memo)
+            .into_iter()
+            .map(|(base, (aligned, resharded))| {
+                if sharded {
+                    (base, aligned, resharded)
+                } else {
+                    (base, 0, aligned + resharded)
+                }
+            })
+            .collect()
+    }
+
+    /// Count the copies of each base table write that reach a single shard of `ni`, split by
+    /// whether they have been through a sharder on the way.
+    fn copies_from_bases(
+        &self,
+        ni: NodeIndex,
+        memo: &mut HashMap<NodeIndex, BTreeMap<NodeIndex, (usize, usize)>>,
+    ) -> BTreeMap<NodeIndex, (usize, usize)> {
+        if let Some(copies) = memo.get(&ni) {
+            return copies.clone();
+        }
+
+        let n = &self.ingredients[ni];
+        let mut copies = BTreeMap::new();
+        if n.is_base() {
+            copies.insert(ni, (1, 0));
+        } else {
+            for p in self
+                .ingredients
+                .neighbors_directed(ni, petgraph::EdgeDirection::Incoming)
+            {
+                // where an unsharded node follows a sharded one, it receives a copy from every
+                // shard that a write reached. writes that have not been resharded only reach the
+                // shard of the base they were made to.
+                let merged = if n.sharded_by().is_none() {
+                    self.ingredients[p].sharded_by().shards().unwrap_or(1)
+                } else {
+                    1
+                };
+                for (base, (aligned, resharded)) in self.copies_from_bases(p, memo) {
+                    let e = copies.entry(base).or_insert((0, 0));
+                    e.0 += aligned;
+                    e.1 += resharded * merged;
+                }
+            }
+            if n.is_sharder() {
+                for e in copies.values_mut() {
+                    *e = (0, e.0 + e.1);
+                }
+            }
+        }
+
+        memo.insert(ni, copies.clone());
+        copies
+    }
+
     fn view_schema(&self, view_ni: NodeIndex) -> Option<Vec<ColumnSpecification>> {
         let n = &self.ingredients[view_ni];
         let schema: Vec<_> = (0..n.fields().len())
@@ -855,6 +959,7 @@ impl ControllerInner {
             key,
             key_is_primary: is_primary,
             dropped: base_operator.get_dropped(),
+            indices: self.materializations.indices(ni),
             table_name: node.name().to_owned(),
             columns,
             schema,
@@ -1103,6 +1208,31 @@ impl ControllerInner {
         }
     }
 
+    /// Make sure that the SELECT query `sql` has a view, and return the name of that view.
+    ///
+    /// If the recipe already contains an equivalent query, its view is reused. Otherwise, the
+    /// recipe is extended with the query.
+    fn prepare<A: Authority + 'static>(
+        &mut self,
+        authority: &Arc<A>,
+        sql: String,
+    ) -> Result<String, String> {
+        let q = nom_sql::parser::parse_query(&sql)
+            .map_err(|e| format!("failed to parse query: {}", e))?;
+        match q {
+            SqlQuery::Select(_) | SqlQuery::CompoundSelect(_) => {}
+            _ => return Err("only SELECT queries can be prepared".to_owned()),
+        }
+
+        let name = self.recipe.prepared_name(&q);
+        if self.recipe.node_addr_for(&name).is_err() {
+            info!(self.log, "installing prepared query {}", name; "query" => sql.as_str());
+            let sql = sql.trim().trim_end_matches(';');
+            self.extend_recipe(authority, format!("QUERY {}: {};", name, sql))?;
+        }
+        Ok(name)
+    }
+
     fn install_recipe<A: Authority + 'static>(
         &mut self,
         authority: &Arc<A>,
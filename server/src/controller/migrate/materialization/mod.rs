@@ -54,6 +54,8 @@ pub(in crate::controller) struct Materializations {
 
     partial: HashSet<NodeIndex>,
     partial_enabled: bool,
+    /// Readers whose lookups may ask for ranges of keys.
+    ranges: HashSet<NodeIndex>,
     frontier_strategy: FrontierStrategy,
 
     tag_generator: AtomicUsize,
@@ -70,6 +72,7 @@ impl Materializations {
 
             partial: HashSet::default(),
             partial_enabled: true,
+            ranges: HashSet::default(),
             frontier_strategy: FrontierStrategy::None,
 
             tag_generator: AtomicUsize::default(),
@@ -335,6 +338,15 @@ impl Materializations {
                 able = false;
             }
 
+            // our state is looked up by ranges rather than by key
+            if graph
+                .neighbors_directed(ni, petgraph::EdgeDirection::Outgoing)
+                .any(|child| graph[child].is_internal() && graph[child].requires_full_parents())
+            {
+                warn!(self.log, "full because child looks up ranges"; "node" => ni.index());
+                able = false;
+            }
+
             // we are already fully materialized, so can't be made partial
             if !new.contains(&ni)
                 && self.added.get(&ni).map(|i| i.len()).unwrap_or(0)
@@ -388,6 +400,12 @@ impl Materializations {
 
                 for path in paths {
                     for (pni, cols) in path.into_iter().skip(1) {
+                        if graph[pni].is_internal() && graph[pni].requires_full_parents() {
+                            warn!(self.log, "full because key passes through a range lookup";
+                                  "node" => ni.index(), "broken at" => pni.index());
+                            able = false;
+                            break 'attempt;
+                        }
                         if let Some(p) = cols.iter().position(Option::is_none) {
                             warn!(self.log, "full because column {} does not resolve", index[p];
                                   "node" => ni.index(), "broken at" => pni.index());
@@ -473,6 +491,19 @@ impl Materializations {
         }
     }
 
+    /// The columns of each index of the materialization of `index`.
+    pub(in crate::controller) fn indices(&self, index: NodeIndex) -> Vec<Vec<usize>> {
+        self.have
+            .get(&index)
+            .map(|indices| indices.iter().cloned().collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Returns true if lookups into the reader `index` may ask for ranges of keys.
+    pub(in crate::controller) fn serves_ranges(&self, index: NodeIndex) -> bool {
+        self.ranges.contains(&index)
+    }
+
     /// Commit to all materialization decisions since the last time `commit` was called.
     ///
     /// This includes setting up replay paths, adding new indices to existing materializations, and
@@ -13,6 +13,8 @@ pub(super) struct Plan<'a> {
     domains: &'a mut HashMap<DomainIndex, DomainHandle>,
     workers: &'a HashMap<WorkerIdentifier, Worker>,
     partial: bool,
+    /// Whether the node is a reader that serves lookups for ranges of keys.
+    ranges: bool,
 
     tags: HashMap<Vec<usize>, Vec<(Tag, DomainIndex)>>,
     paths: HashMap<Tag, Vec<NodeIndex>>,
@@ -36,6 +38,7 @@ impl<'a> Plan<'a> {
         workers: &'a HashMap<WorkerIdentifier, Worker>,
     ) -> Plan<'a> {
         let partial = m.partial.contains(&node);
+        let ranges = graph[node].with_reader(|r| r.ranges()).unwrap_or(false);
         Plan {
             m,
             graph,
@@ -44,6 +47,7 @@ impl<'a> Plan<'a> {
             workers,
 
             partial,
+            ranges,
 
             pending: Vec::new(),
             tags: Default::default(),
@@ -117,6 +121,27 @@ impl<'a> Plan<'a> {
 
         let paths = self.paths(&index_on[..]);
 
+        if self.partial && self.ranges {
+            // a range replay is answered with a single replay piece that covers the whole range,
+            // so it must come from a single source that holds every key in the range. and since
+            // nothing could fill a range miss otherwise, we refuse range lookups up front.
+            let supported = match paths[..] {
+                [ref path] => {
+                    let source = path[0].0;
+                    self.graph[source].sharded_by().is_none() && !self.m.partial.contains(&source)
+                }
+                _ => false,
+            };
+            if !supported {
+                warn!(self.m.log,
+                      "reader cannot replay ranges of keys; range lookups will be refused";
+                      "node" => self.node.index(),
+                      "paths" => paths.len(),
+                );
+                self.ranges = false;
+            }
+        }
+
         // all right, story time!
         //
         // image you have this graph:
@@ -523,12 +548,14 @@ impl<'a> Plan<'a> {
                         gid: self.node,
                         cols: self.graph[self.node].fields().len(),
                         key: Vec::from(r.key().unwrap()),
+                        ranges: self.ranges,
                         trigger_domain: (last_domain, num_shards),
                     }
                 } else {
                     InitialState::Global {
                         cols: self.graph[self.node].fields().len(),
                         key: Vec::from(r.key().unwrap()),
+                        ranges: self.ranges,
                         gid: self.node,
                     }
                 }
@@ -549,6 +576,10 @@ impl<'a> Plan<'a> {
                 }
             });
 
+        if self.ranges {
+            self.m.ranges.insert(self.node);
+        }
+
         self.domains
             .get_mut(&self.graph[self.node].domain())
             .unwrap()
@@ -21,8 +21,10 @@
 //! Beware, Here be dragons™
 
 use crate::controller::ControllerInner;
+use dataflow::ops::filter::FilterCondition;
 use dataflow::prelude::*;
 use dataflow::{node, prelude::Packet};
+use nom_sql::OrderType;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
@@ -266,14 +268,33 @@ impl<'a> Migration<'a> {
 
     /// Set up the given node such that its output can be efficiently queried.
     ///
+    /// Lookups return the rows for each key sorted by the columns in `order`. If
+    /// `parameter_filter` is not empty, `key` must be a single column that holds the same value in
+    /// every row, and lookups instead return the rows that match `parameter_filter` once the key
+    /// they give is bound to its parameters. If `ranges` is set, lookups may also ask for all the
+    /// keys within a range, provided the node's state can be replayed by range.
+    ///
     /// To query into the maintained state, use `ControllerInner::get_getter`.
-    pub fn maintain(&mut self, name: String, n: NodeIndex, key: &[usize]) {
+    pub fn maintain(
+        &mut self,
+        name: String,
+        n: NodeIndex,
+        key: &[usize],
+        order: &[(usize, OrderType)],
+        parameter_filter: &[(usize, FilterCondition)],
+        ranges: bool,
+    ) {
         self.ensure_reader_for(n, Some(name));
 
         let ri = self.readers[&n];
 
         self.mainline.ingredients[ri]
-            .with_reader_mut(|r| r.set_key(key))
+            .with_reader_mut(|r| {
+                r.set_key(key);
+                r.set_order(order);
+                r.set_parameter_filter(parameter_filter);
+                r.set_ranges(ranges);
+            })
             .unwrap();
     }
 
@@ -450,6 +471,17 @@ impl<'a> Migration<'a> {
             sharding::validate(&log, &mainline.ingredients, &topo, shards)
         };
 
+        // Readers need to know which bases feed them, and along how many paths, so they can tell
+        // when they have seen all of a transaction.
+        for &ni in &new {
+            if mainline.ingredients[ni].is_reader() {
+                let bases = mainline.upstream_base_paths(ni);
+                mainline.ingredients[ni]
+                    .with_reader_mut(|r| r.set_bases(bases))
+                    .unwrap();
+            }
+        }
+
         // at this point, we've hooked up the graph such that, for any given domain, the graph
         // looks like this:
         //
@@ -113,6 +113,15 @@ pub fn shard(
             continue;
         }
 
+        if graph[node].is_internal() && graph[node].requires_full_parents() {
+            // the operator looks up ranges of values, which may be on any shard
+            info!(log, "de-sharding node that looks up ranges"; "node" => ?node);
+            for &ni in input_shardings.keys() {
+                reshard(log, new, &mut swaps, graph, ni, node, Sharding::ForcedNone);
+            }
+            continue;
+        }
+
         let mut complex = false;
         for lookup_col in need_sharding.values() {
             if lookup_col.len() != 1 {
@@ -1,5 +1,6 @@
 use nom_sql::{
-    ArithmeticBase, ArithmeticExpression, ColumnConstraint, ColumnSpecification, Literal, OrderType,
+    ArithmeticBase, ArithmeticExpression, ColumnConstraint, ColumnSpecification, Literal, Operator,
+    OrderType,
 };
 use std::collections::HashMap;
 
@@ -9,6 +10,8 @@ use dataflow::ops::filter::FilterCondition;
 use dataflow::ops::join::{Join, JoinType};
 use dataflow::ops::latest::Latest;
 use dataflow::ops::project::{Project, ProjectExpression, ProjectExpressionBase};
+use dataflow::ops::semi_join::{SemiJoin, SemiJoinType};
+use dataflow::ops::theta_join::ThetaJoin;
 use dataflow::{node, ops};
 use mir::node::{GroupedNodeType, MirNode, MirNodeType};
 use mir::query::{MirQuery, QueryFlowParts};
@@ -212,10 +215,24 @@ fn mir_node_to_flow_parts(
                     let parent = mir_node.ancestors[0].clone();
                     make_latest_node(&name, parent, mir_node.columns.as_slice(), group_by, mig)
                 }
-                MirNodeType::Leaf { ref keys, .. } => {
+                MirNodeType::Leaf {
+                    ref keys,
+                    ref order,
+                    ref parameter_filter,
+                    ranges,
+                    ..
+                } => {
                     assert_eq!(mir_node.ancestors.len(), 1);
                     let parent = mir_node.ancestors[0].clone();
-                    materialize_leaf_node(&parent, name, keys, mig);
+                    materialize_leaf_node(
+                        &parent,
+                        name,
+                        keys,
+                        order,
+                        parameter_filter,
+                        ranges,
+                        mig,
+                    );
                     // TODO(malte): below is yucky, but required to satisfy the type system:
                     // each match arm must return a `FlowNode`, so we use the parent's one
                     // here.
@@ -245,6 +262,63 @@ fn mir_node_to_flow_parts(
                         mig,
                     )
                 }
+                MirNodeType::SemiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref project,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_semi_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on_left,
+                        on_right,
+                        project,
+                        SemiJoinType::Semi,
+                        mig,
+                    )
+                }
+                MirNodeType::AntiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref project,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_semi_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on_left,
+                        on_right,
+                        project,
+                        SemiJoinType::Anti,
+                        mig,
+                    )
+                }
+                MirNodeType::ThetaJoin {
+                    ref on,
+                    ref project,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_theta_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on,
+                        project,
+                        mig,
+                    )
+                }
                 MirNodeType::Project {
                     ref emit,
                     ref literals,
@@ -362,7 +436,9 @@ fn adapt_base_node(
             .constraints
             .iter()
             .filter_map(|c| match *c {
-                ColumnConstraint::DefaultValue(ref dv) => Some(dv.into()),
+                ColumnConstraint::DefaultValue(ref dv) => {
+                    Some(DataType::from_literal(dv, &a.sql_type))
+                }
                 _ => None,
             })
             .next()
@@ -424,7 +500,7 @@ fn make_base_node(
         .map(|&(ref cs, _)| {
             for c in &cs.constraints {
                 if let ColumnConstraint::DefaultValue(ref dv) = *c {
-                    return dv.into();
+                    return DataType::from_literal(dv, &cs.sql_type);
                 }
             }
             DataType::None
@@ -740,6 +816,114 @@ fn make_join_node(
     FlowNode::New(n)
 }
 
+fn make_theta_join_node(
+    name: &str,
+    left: MirNodeRef,
+    right: MirNodeRef,
+    columns: &[Column],
+    on: &[(Column, Operator, Column)],
+    proj_cols: &[Column],
+    mig: &mut Migration,
+) -> FlowNode {
+    use dataflow::ops::join::JoinSource;
+
+    let column_names = column_names(columns);
+
+    let position = |n: &MirNodeRef, c: &Column| {
+        n.borrow()
+            .columns
+            .iter()
+            .position(|nc| nc == c)
+            .unwrap_or_else(|| {
+                panic!(
+                    "missing theta join column {:#?} in {:#?}",
+                    c,
+                    n.borrow().columns
+                )
+            })
+    };
+    let on = on
+        .iter()
+        .map(|(l, op, r)| (position(&left, l), op.clone(), position(&right, r)))
+        .collect();
+
+    // unlike an equi-join, both sides keep their compared columns, so no column is shared
+    let emit = proj_cols
+        .iter()
+        .map(|c| {
+            if left.borrow().columns.contains(c) {
+                JoinSource::L(position(&left, c))
+            } else if right.borrow().columns.contains(c) {
+                JoinSource::R(position(&right, c))
+            } else {
+                panic!(
+                    "could not resolve output column projected from join: {:?}",
+                    c
+                )
+            }
+        })
+        .collect();
+
+    let left_na = left.borrow().flow_node_addr().unwrap();
+    let right_na = right.borrow().flow_node_addr().unwrap();
+
+    let j = ThetaJoin::new(left_na, right_na, on, emit);
+    let n = mig.add_ingredient(String::from(name), column_names.as_slice(), j);
+
+    FlowNode::New(n)
+}
+
+fn make_semi_join_node(
+    name: &str,
+    left: MirNodeRef,
+    right: MirNodeRef,
+    columns: &[Column],
+    on_left: &[Column],
+    on_right: &[Column],
+    proj_cols: &[Column],
+    kind: SemiJoinType,
+    mig: &mut Migration,
+) -> FlowNode {
+    assert_eq!(on_left.len(), 1, "no support for multiple column joins");
+    assert_eq!(on_right.len(), 1, "no support for multiple column joins");
+    assert_eq!(
+        proj_cols,
+        left.borrow().columns.as_slice(),
+        "semi-joins emit exactly the columns of their left parent"
+    );
+
+    let column_names = column_names(columns);
+
+    let position = |n: &MirNodeRef, c: &Column| {
+        n.borrow()
+            .columns
+            .iter()
+            .position(|nc| nc == c)
+            .unwrap_or_else(|| {
+                panic!(
+                    "missing semi-join column {:#?} in {:#?}",
+                    c,
+                    n.borrow().columns
+                )
+            })
+    };
+    let left_join_col_id = position(&left, &on_left[0]);
+    let right_join_col_id = position(&right, &on_right[0]);
+
+    let left_na = left.borrow().flow_node_addr().unwrap();
+    let right_na = right.borrow().flow_node_addr().unwrap();
+
+    let j = SemiJoin::new(
+        left_na,
+        right_na,
+        kind,
+        (left_join_col_id, right_join_col_id),
+    );
+    let n = mig.add_ingredient(String::from(name), column_names.as_slice(), j);
+
+    FlowNode::New(n)
+}
+
 fn make_latest_node(
     name: &str,
     parent: MirNodeRef,
@@ -882,8 +1066,6 @@ fn make_topk_node(
 
     let cmp_rows = match *order {
         Some(ref o) => {
-            assert_eq!(offset, 0); // Non-zero offset not supported
-
             let columns: Vec<_> = o
                 .iter()
                 .map(|&(ref c, ref order_type)| {
@@ -909,7 +1091,23 @@ fn make_topk_node(
     let na = mig.add_ingredient(
         String::from(name),
         column_names.as_slice(),
-        ops::topk::TopK::new(parent_na, cmp_rows, group_by_indx, k),
+        ops::topk::TopK::new(
+            parent_na,
+            cmp_rows.clone(),
+            group_by_indx.clone(),
+            k + offset,
+        ),
+    );
+    if offset == 0 {
+        return FlowNode::New(na);
+    }
+
+    // the TopK also keeps the rows that the offset skips, so that they are there to move up when
+    // rows ahead of them are removed; a window below it leaves them out of the results
+    let na = mig.add_ingredient(
+        format!("{}_window", name),
+        column_names.as_slice(),
+        ops::topk::TopKWindow::new(na, cmp_rows, group_by_indx, offset),
     );
     FlowNode::New(na)
 }
@@ -918,6 +1116,9 @@ fn materialize_leaf_node(
     parent: &MirNodeRef,
     name: String,
     key_cols: &[Column],
+    order: &Option<Vec<(Column, OrderType)>>,
+    parameter_filter: &[(usize, FilterCondition)],
+    ranges: bool,
     mig: &mut Migration,
 ) {
     let na = parent.borrow().flow_node_addr().unwrap();
@@ -929,14 +1130,32 @@ fn materialize_leaf_node(
 
     // TODO(malte): consider the case when the projected columns need reordering
 
+    // the query may be ordered by columns that it does not return. the reader can only order by
+    // the ones it has, and it must stop at the first one it does not, since the columns after it
+    // only break ties within that column.
+    let mut order_cols = Vec::new();
+    for &(ref c, ref order_type) in order.iter().flatten() {
+        match parent.borrow().columns().iter().position(|pc| pc == c) {
+            Some(i) => order_cols.push((i, order_type.clone())),
+            None => break,
+        }
+    }
+
     if !key_cols.is_empty() {
         let key_cols: Vec<_> = key_cols
             .iter()
             .map(|c| parent.borrow().column_id_for_column(c, None))
             .collect();
-        mig.maintain(name, na, &key_cols[..]);
+        mig.maintain(
+            name,
+            na,
+            &key_cols[..],
+            &order_cols[..],
+            parameter_filter,
+            ranges,
+        );
     } else {
         // if no key specified, default to the first column
-        mig.maintain(name, na, &[0]);
+        mig.maintain(name, na, &[0], &order_cols[..], parameter_filter, false);
     }
 }
@@ -192,6 +192,18 @@ impl Recipe {
         })
     }
 
+    /// The name under which the view for `q` can be found if the query is prepared on demand.
+    ///
+    /// If the recipe already has a query with the same text, that query's name is used. Otherwise,
+    /// the name is derived from the query, so that preparing it again finds the same view.
+    pub(in crate::controller) fn prepared_name(&self, q: &SqlQuery) -> String {
+        let qid = hash_query(q);
+        match self.expressions.get(&qid) {
+            Some((Some(name), _, true)) => name.clone(),
+            _ => format!("prepared_{:x}", qid),
+        }
+    }
+
     /// Obtains the `NodeIndex` for the node corresponding to a named query or a write type.
     pub(in crate::controller) fn node_addr_for(&self, name: &str) -> Result<NodeIndex, String> {
         match self.inc {
@@ -13,17 +13,29 @@ type Path = std::vec::Vec<(
 
 fn to_sql_type(d: &DataType) -> Option<SqlType> {
     match d {
+        DataType::Bool(_) => Some(SqlType::Bool),
         DataType::Int(_) => Some(SqlType::Int(32)),
         DataType::UnsignedInt(_) => Some(SqlType::UnsignedInt(32)),
         DataType::BigInt(_) => Some(SqlType::Bigint(64)),
         DataType::UnsignedBigInt(_) => Some(SqlType::UnsignedBigint(64)),
         DataType::Real(_, _) => Some(SqlType::Real),
+        DataType::Double(_) => Some(SqlType::Double),
+        DataType::Decimal(ref d) => {
+            let (_, scale) = d.as_bigint_and_exponent();
+            Some(SqlType::Decimal(d.digits() as u8, scale.max(0) as u8))
+        }
         DataType::Text(_) => Some(SqlType::Text),
         DataType::TinyText(_) => Some(SqlType::Varchar(8)),
         // TODO(malte): There is no SqlType for `NULL` (as it's not a
         // type), so caller must handle appropriately.
         DataType::None => None,
         DataType::Timestamp(_) => Some(SqlType::Timestamp),
+        DataType::Date(_) => Some(SqlType::Date),
+        // nom-sql has no type for times of day, and intervals can't be stored in columns
+        DataType::Time(_) | DataType::Interval(..) => None,
+        // JSON documents are stored as text in MySQL's place
+        DataType::Json(_) => Some(SqlType::Text),
+        DataType::ByteArray(_) => Some(SqlType::Blob),
     }
 }
 
@@ -80,7 +92,9 @@ fn type_for_internal_column(
                 unreachable!();
             }
         }
-        ops::NodeOperator::Join(_) => {
+        ops::NodeOperator::Join(_)
+        | ops::NodeOperator::ThetaJoin(_)
+        | ops::NodeOperator::SemiJoin(_) => {
             // join doesn't "generate" columns, but they may come from one of the other
             // ancestors; so keep iterating to try the other paths
             None
@@ -1,8 +1,9 @@
 use crate::controller::sql::mir::SqlToMirConverter;
 use crate::controller::sql::query_graph::{JoinRef, QueryGraph, QueryGraphEdge};
 use dataflow::ops::join::JoinType;
-use mir::MirNodeRef;
-use nom_sql::ConditionTree;
+use dataflow::ops::semi_join::SemiJoinType;
+use mir::{Column, MirNodeRef};
+use nom_sql::{ConditionBase, ConditionExpression, ConditionTree, Operator};
 use std::collections::{HashMap, HashSet};
 
 struct JoinChain {
@@ -40,19 +41,93 @@ pub(super) fn make_joins(
     let mut join_nodes: Vec<MirNodeRef> = Vec::new();
     let mut join_chains = Vec::new();
     let mut node_count = node_count;
+    let mut theta_joined = HashSet::new();
 
     for jref in qg.join_order.iter() {
+        if let Some((kind, jp)) = semi_join_ref(jref, qg) {
+            let (left_chain, right_chain) =
+                pick_join_chains(&jref.src, &jref.dst, &mut join_chains, node_for_rel);
+
+            let carried = &qg.relations[&jref.dst].parameters;
+            let jn = if carried.is_empty() {
+                mir_converter.make_semi_join_node(
+                    &format!("{}_n{}", name, node_count),
+                    jp,
+                    left_chain.last_node.clone(),
+                    right_chain.last_node.clone(),
+                    kind,
+                )
+            } else {
+                // the subquery's parameters must make it to the reader, so we have to join with
+                // its view after all. Making its rows distinct first still keeps every left row
+                // from showing up more than once for the same parameters.
+                assert_eq!(
+                    kind,
+                    SemiJoinType::Semi,
+                    "NOT IN subqueries cannot have query parameters"
+                );
+                let on = match *jp.right {
+                    ConditionExpression::Base(ConditionBase::Field(ref f)) => Column::from(f),
+                    _ => unimplemented!(),
+                };
+                let group_by: Vec<Column> = std::iter::once(on)
+                    .chain(carried.iter().map(Column::from))
+                    .collect();
+                let distinct = mir_converter.make_distinct_node(
+                    &format!("{}_n{}", name, node_count),
+                    right_chain.last_node.clone(),
+                    group_by.iter().collect(),
+                );
+                node_count += 1;
+                join_nodes.push(distinct.clone());
+
+                mir_converter.make_join_node(
+                    &format!("{}_n{}", name, node_count),
+                    jp,
+                    left_chain.last_node.clone(),
+                    distinct,
+                    JoinType::Inner,
+                )
+            };
+
+            join_chains.push(left_chain.merge_chain(right_chain, jn.clone()));
+            node_count += 1;
+            join_nodes.push(jn);
+            continue;
+        }
+
         let (join_type, jp) = from_join_ref(jref, &qg);
+        let theta_jps = theta_join_predicates(jref, qg);
+        if theta_jps.is_some() && !theta_joined.insert((&jref.src, &jref.dst)) {
+            // all predicates of this edge were handled by the theta join for its first one
+            continue;
+        }
+
         let (left_chain, right_chain) =
             pick_join_chains(&jref.src, &jref.dst, &mut join_chains, node_for_rel);
 
-        let jn = mir_converter.make_join_node(
-            &format!("{}_n{}", name, node_count),
-            jp,
-            left_chain.last_node.clone(),
-            right_chain.last_node.clone(),
-            join_type,
-        );
+        let jn = match theta_jps {
+            Some(jps) => {
+                assert_eq!(
+                    join_type,
+                    JoinType::Inner,
+                    "only inner joins can compare more than a single pair of equal columns"
+                );
+                mir_converter.make_theta_join_node(
+                    &format!("{}_n{}", name, node_count),
+                    &jps,
+                    left_chain.last_node.clone(),
+                    right_chain.last_node.clone(),
+                )
+            }
+            None => mir_converter.make_join_node(
+                &format!("{}_n{}", name, node_count),
+                jp,
+                left_chain.last_node.clone(),
+                right_chain.last_node.clone(),
+                join_type,
+            ),
+        };
 
         // merge node chains
         let new_chain = left_chain.merge_chain(right_chain, jn.clone());
@@ -70,10 +145,46 @@ fn from_join_ref<'a>(jref: &JoinRef, qg: &'a QueryGraph) -> (JoinType, &'a Condi
     match qg.edges[&(jref.src.clone(), jref.dst.clone())] {
         QueryGraphEdge::Join(ref jps) => (JoinType::Inner, &jps[jref.index]),
         QueryGraphEdge::LeftJoin(ref jps) => (JoinType::Left, &jps[jref.index]),
-        QueryGraphEdge::GroupBy(_) => unreachable!(),
+        QueryGraphEdge::SemiJoin(_) | QueryGraphEdge::AntiJoin(_) | QueryGraphEdge::GroupBy(_) => {
+            unreachable!()
+        }
+    }
+}
+
+// Returns the kind and predicate of the given join if it is a comparison with a subquery.
+fn semi_join_ref<'a>(
+    jref: &JoinRef,
+    qg: &'a QueryGraph,
+) -> Option<(SemiJoinType, &'a ConditionTree)> {
+    match qg.edges[&(jref.src.clone(), jref.dst.clone())] {
+        QueryGraphEdge::SemiJoin(ref jps) => Some((SemiJoinType::Semi, &jps[jref.index])),
+        QueryGraphEdge::AntiJoin(ref jps) => Some((SemiJoinType::Anti, &jps[jref.index])),
+        _ => None,
     }
 }
 
+// Returns all predicates of the edge of the given join if they cannot be implemented by a single
+// equi-join, i.e., if the edge has more than one predicate, or one that is not an equality. Such
+// edges are joined by a single theta join, which looks up rows using the first predicate
+// returned, so equalities come first.
+fn theta_join_predicates<'a>(jref: &JoinRef, qg: &'a QueryGraph) -> Option<Vec<&'a ConditionTree>> {
+    let jps = match qg.edges[&(jref.src.clone(), jref.dst.clone())] {
+        QueryGraphEdge::Join(ref jps) | QueryGraphEdge::LeftJoin(ref jps) => jps,
+        QueryGraphEdge::SemiJoin(_) | QueryGraphEdge::AntiJoin(_) | QueryGraphEdge::GroupBy(_) => {
+            unreachable!()
+        }
+    };
+    let is_equality =
+        |jp: &ConditionTree| jp.operator == Operator::Equal || jp.operator == Operator::In;
+    if jps.len() == 1 && is_equality(&jps[0]) {
+        return None;
+    }
+
+    let (mut theta_jps, rest): (Vec<_>, Vec<_>) = jps.iter().partition(|jp| is_equality(jp));
+    theta_jps.extend(rest);
+    Some(theta_jps)
+}
+
 fn pick_join_chains(
     src: &str,
     dst: &str,
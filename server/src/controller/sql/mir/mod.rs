@@ -4,17 +4,20 @@ use mir::{Column, MirNodeRef};
 use noria::DataType;
 use petgraph::graph::NodeIndex;
 // TODO(malte): remove if possible
-use dataflow::ops::filter::FilterCondition;
+use dataflow::ops::filter::{self, FilterCondition};
 use dataflow::ops::join::JoinType;
+use dataflow::ops::semi_join::SemiJoinType;
 
-use crate::controller::sql::query_graph::{OutputColumn, QueryGraph};
+use crate::controller::sql::query_graph::{
+    has_placeholder, has_range_placeholder, OutputColumn, QueryGraph,
+};
 use crate::controller::sql::query_signature::Signature;
 use nom_sql::{
     ArithmeticExpression, CaseWhenExpression, ColumnOrLiteral, ColumnSpecification,
     CompoundSelectOperator, ConditionBase, ConditionExpression, ConditionTree, Literal, Operator,
     SqlQuery, TableKey,
 };
-use nom_sql::{LimitClause, OrderClause, SelectStatement};
+use nom_sql::{LimitClause, OrderClause, OrderType, SelectStatement};
 
 use slog;
 use std::collections::{HashMap, HashSet};
@@ -36,6 +39,16 @@ fn sanitize_leaf_column(c: &mut Column, view_name: &str) {
     c.aliases = vec![];
 }
 
+/// Returns the columns of an `ORDER BY` clause, along with the direction to sort each in
+pub(super) fn order_columns(order: &Option<OrderClause>) -> Option<Vec<(Column, OrderType)>> {
+    order.as_ref().map(|o| {
+        o.columns
+            .iter()
+            .map(|(c, o)| (Column::from(c), o.clone()))
+            .collect()
+    })
+}
+
 /// Returns all collumns used in a predicate
 fn predicate_columns(ce: &ConditionExpression) -> HashSet<Column> {
     use nom_sql::ConditionExpression::*;
@@ -59,6 +72,40 @@ fn predicate_columns(ce: &ConditionExpression) -> HashSet<Column> {
     cols
 }
 
+/// Converts a comparison into the condition it places on the column on its left-hand side. A
+/// column or query parameter on its right-hand side is turned into a value by `value`.
+fn comparison_to_condition<F>(ct: &ConditionTree, value: F) -> FilterCondition
+where
+    F: FnOnce(&ConditionBase) -> filter::Value,
+{
+    match *ct.right.as_ref() {
+        // the parser turns `IS NULL` and `IS NOT NULL` into `= NULL` and `!= NULL`. under SQL's
+        // rules those comparisons never hold, so this is the only useful reading of them
+        ConditionExpression::Base(ConditionBase::Literal(Literal::Null))
+            if ct.operator == Operator::Equal =>
+        {
+            FilterCondition::IsNull
+        }
+        ConditionExpression::Base(ConditionBase::Literal(Literal::Null))
+            if ct.operator == Operator::NotEqual =>
+        {
+            FilterCondition::IsNotNull
+        }
+        ConditionExpression::Base(ref b @ ConditionBase::Literal(Literal::Placeholder))
+        | ConditionExpression::Base(ref b @ ConditionBase::Field(_)) => {
+            FilterCondition::Comparison(ct.operator.clone(), value(b))
+        }
+        ConditionExpression::Base(ConditionBase::Literal(ref l)) => FilterCondition::Comparison(
+            ct.operator.clone(),
+            filter::Value::Constant(DataType::from(l)),
+        ),
+        ConditionExpression::Base(ConditionBase::LiteralList(ref ll)) => {
+            FilterCondition::In(ll.iter().map(|l| DataType::from(l.clone())).collect())
+        }
+        _ => unimplemented!(),
+    }
+}
+
 fn value_columns_needed_for_predicates(
     value_columns: &[OutputColumn],
     predicates: &[ConditionExpression],
@@ -169,29 +216,48 @@ impl SqlToMirConverter {
         columns: &mut Vec<Column>,
         n: &MirNodeRef,
     ) -> Vec<(usize, FilterCondition)> {
+        let mut left_filter = self.expression_to_conditions(ct.left.as_ref(), columns, n);
+        let mut right_filter = self.expression_to_conditions(ct.right.as_ref(), columns, n);
         match ct.operator {
             Operator::And => {
-                let mut left_filter = match ct.left.as_ref() {
-                    ConditionExpression::LogicalOp(ref ct2) => {
-                        self.logical_op_to_conditions(ct2, columns, n)
-                    }
-                    ConditionExpression::ComparisonOp(ref ct2) => {
-                        self.to_conditions(ct2, columns, n)
-                    }
-                    _ => unimplemented!(),
-                };
-                let mut right_filter = match ct.right.as_ref() {
-                    ConditionExpression::LogicalOp(ref ct2) => {
-                        self.logical_op_to_conditions(ct2, columns, n)
-                    }
-                    ConditionExpression::ComparisonOp(ref ct2) => {
-                        self.to_conditions(ct2, columns, n)
-                    }
-                    _ => unimplemented!(),
-                };
                 left_filter.append(&mut right_filter);
                 left_filter
             }
+            Operator::Or => {
+                // a disjunction is a single condition, which holds if either side's conditions
+                // all hold. it is paired with the first column it checks, but does not use it.
+                let mut disjuncts = Vec::new();
+                for mut side in vec![left_filter, right_filter] {
+                    match side.pop() {
+                        Some((_, FilterCondition::Or(nested))) if side.is_empty() => {
+                            disjuncts.extend(nested)
+                        }
+                        Some(last) if side.is_empty() => disjuncts.push(last),
+                        Some(last) => {
+                            side.push(last);
+                            disjuncts.push((side[0].0, FilterCondition::And(side)));
+                        }
+                        None => unreachable!("empty side of OR"),
+                    }
+                }
+                vec![(disjuncts[0].0, FilterCondition::Or(disjuncts))]
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn expression_to_conditions(
+        &self,
+        ce: &ConditionExpression,
+        columns: &mut Vec<Column>,
+        n: &MirNodeRef,
+    ) -> Vec<(usize, FilterCondition)> {
+        match *ce {
+            ConditionExpression::LogicalOp(ref ct) => self.logical_op_to_conditions(ct, columns, n),
+            ConditionExpression::ComparisonOp(ref ct) => self.to_conditions(ct, columns, n),
+            ConditionExpression::Bracketed(ref inner) => {
+                self.expression_to_conditions(inner, columns, n)
+            }
             _ => unimplemented!(),
         }
     }
@@ -211,38 +277,16 @@ impl SqlToMirConverter {
             ConditionExpression::Base(ConditionBase::Field(ref f)) => f.clone(),
             _ => unimplemented!(),
         };
-        use dataflow::ops::filter;
-        let f = match *ct.right.as_ref() {
-            ConditionExpression::Base(ConditionBase::Literal(Literal::Integer(ref i))) => {
-                FilterCondition::Comparison(
-                    ct.operator.clone(),
-                    filter::Value::Constant(DataType::from(*i)),
-                )
-            }
-            ConditionExpression::Base(ConditionBase::Literal(Literal::String(ref s))) => {
-                FilterCondition::Comparison(
-                    ct.operator.clone(),
-                    filter::Value::Constant(DataType::from(s.clone())),
-                )
-            }
-            ConditionExpression::Base(ConditionBase::Literal(Literal::Null)) => {
-                FilterCondition::Comparison(
-                    ct.operator.clone(),
-                    filter::Value::Constant(DataType::None),
-                )
-            }
-            ConditionExpression::Base(ConditionBase::LiteralList(ref ll)) => {
-                FilterCondition::In(ll.iter().map(|l| DataType::from(l.clone())).collect())
-            }
-            ConditionExpression::Base(ConditionBase::Field(ref f)) => {
+        let f = comparison_to_condition(ct, |b| match *b {
+            ConditionBase::Field(ref f) => {
                 // NOTE(jon): the uwnrap here is almost certainly wrong given the business
                 // that goes on further down where it appens a column in magical circumstances.
                 // also, what if two columns share a name, but differ in .table?
                 let fi = columns.iter().rposition(|c| *c.name == f.name).unwrap();
-                FilterCondition::Comparison(ct.operator.clone(), filter::Value::Column(fi))
+                filter::Value::Column(fi)
             }
-            _ => unimplemented!(),
-        };
+            _ => unreachable!("query parameters are looked up by key"),
+        });
 
         let absolute_column_ids: Vec<usize> = columns
             .iter()
@@ -277,11 +321,85 @@ impl SqlToMirConverter {
         filters
     }
 
+    /// Compiles the conjuncts of `ce` that compare against query parameters into conditions on
+    /// the columns of `n`, numbering the parameters in the order in which they appear.
+    fn parameter_conditions(
+        &self,
+        ce: &ConditionExpression,
+        n: &MirNodeRef,
+        next_param: &mut usize,
+    ) -> Vec<(usize, FilterCondition)> {
+        match *ce {
+            ConditionExpression::LogicalOp(ConditionTree {
+                operator: Operator::And,
+                ref left,
+                ref right,
+            }) => {
+                let mut conds = self.parameter_conditions(left, n, next_param);
+                conds.extend(self.parameter_conditions(right, n, next_param));
+                conds
+            }
+            ConditionExpression::Bracketed(ref inner) => {
+                self.parameter_conditions(inner, n, next_param)
+            }
+            _ if !has_placeholder(ce) => vec![],
+            _ => vec![self.parameter_condition(ce, n, next_param)],
+        }
+    }
+
+    fn parameter_condition(
+        &self,
+        ce: &ConditionExpression,
+        n: &MirNodeRef,
+        next_param: &mut usize,
+    ) -> (usize, FilterCondition) {
+        let column_id =
+            |f: &nom_sql::Column| n.borrow().column_id_for_column(&Column::from(f), None);
+        match *ce {
+            ConditionExpression::LogicalOp(ref ct) => {
+                let mut conds = Vec::new();
+                for side in vec![&ct.left, &ct.right] {
+                    match (&ct.operator, self.parameter_condition(side, n, next_param)) {
+                        (Operator::And, (_, FilterCondition::And(nested)))
+                        | (Operator::Or, (_, FilterCondition::Or(nested))) => conds.extend(nested),
+                        (_, cond) => conds.push(cond),
+                    }
+                }
+                let col = conds[0].0;
+                match ct.operator {
+                    Operator::And => (col, FilterCondition::And(conds)),
+                    Operator::Or => (col, FilterCondition::Or(conds)),
+                    _ => unreachable!("LogicalOp operator is {:?}", ct.operator),
+                }
+            }
+            ConditionExpression::ComparisonOp(ref ct) => {
+                let col = match *ct.left.as_ref() {
+                    ConditionExpression::Base(ConditionBase::Field(ref f)) => column_id(f),
+                    _ => unimplemented!(),
+                };
+                let cond = comparison_to_condition(ct, |b| match *b {
+                    ConditionBase::Field(ref f) => filter::Value::Column(column_id(f)),
+                    _ => {
+                        *next_param += 1;
+                        filter::Value::Parameter(*next_param - 1)
+                    }
+                });
+                (col, cond)
+            }
+            ConditionExpression::Bracketed(ref inner) => {
+                self.parameter_condition(inner, n, next_param)
+            }
+            _ => unimplemented!(),
+        }
+    }
+
     pub(super) fn add_leaf_below(
         &mut self,
         prior_leaf: MirNodeRef,
         name: &str,
         params: &[Column],
+        order: &Option<OrderClause>,
+        ranges: bool,
         project_columns: Option<Vec<Column>>,
     ) -> MirQuery {
         // hang off the previous logical leaf node
@@ -335,6 +453,9 @@ impl SqlToMirConverter {
             MirNodeType::Leaf {
                 node: parent.clone(),
                 keys: Vec::from(params),
+                order: order_columns(order),
+                parameter_filter: vec![],
+                ranges,
             },
             vec![n],
             vec![],
@@ -419,6 +540,9 @@ impl SqlToMirConverter {
                 MirNodeType::Leaf {
                     node: final_node.clone(),
                     keys: vec![],
+                    order: order_columns(order),
+                    parameter_filter: vec![],
+                    ranges: false,
                 },
                 vec![final_node.clone()],
                 vec![],
@@ -941,30 +1065,15 @@ impl SqlToMirConverter {
         )
     }
 
-    fn make_union_from_same_base(
-        &self,
-        name: &str,
-        ancestors: Vec<MirNodeRef>,
-        columns: Vec<Column>,
-    ) -> MirNodeRef {
-        assert!(ancestors.len() > 1, "union must have more than 1 ancestors");
-        trace!(self.log, "Added union node wiht columns {:?}", columns);
-        let emit = ancestors.iter().map(|_| columns.clone()).collect();
-
-        MirNode::new(
-            name,
-            self.schema_version,
-            columns,
-            MirNodeType::Union { emit },
-            ancestors.clone(),
-            vec![],
-        )
-    }
-
     fn make_filter_node(&self, name: &str, parent: MirNodeRef, cond: &ConditionTree) -> MirNodeRef {
         let mut fields = parent.borrow().columns().to_vec();
 
-        let filter = self.to_conditions(cond, &mut fields, &parent);
+        let filter = match cond.operator {
+            Operator::And | Operator::Or => {
+                self.logical_op_to_conditions(cond, &mut fields, &parent)
+            }
+            _ => self.to_conditions(cond, &mut fields, &parent),
+        };
         trace!(
             self.log,
             "Added filter node {} with condition {:?}",
@@ -1195,10 +1304,14 @@ impl SqlToMirConverter {
                         self.logical_op_to_conditions(ct, &mut fields, &parent_node)
                     }
                     ComparisonOp(ref ct) => self.to_conditions(ct, &mut fields, &parent_node),
-                    Bracketed(_) => unimplemented!(),
+                    Bracketed(ref inner) => {
+                        self.expression_to_conditions(inner, &mut fields, &parent_node)
+                    }
                     NegationOp(_) => unreachable!("negation should have been removed earlier"),
                     Base(_) => unreachable!("dangling base predicate"),
-                    Arithmetic(_) => unimplemented!(),
+                    Arithmetic(_) => {
+                        unreachable!("arithmetic should have been computed earlier")
+                    }
                 };
                 MirNode::new(
                     name,
@@ -1316,6 +1429,94 @@ impl SqlToMirConverter {
         )
     }
 
+    fn make_theta_join_node(
+        &self,
+        name: &str,
+        jps: &[&ConditionTree],
+        left_node: MirNodeRef,
+        right_node: MirNodeRef,
+    ) -> MirNodeRef {
+        // both sides keep their compared columns, since they generally hold different values
+        let fields = left_node
+            .borrow()
+            .columns()
+            .iter()
+            .chain(right_node.borrow().columns())
+            .cloned()
+            .collect::<Vec<Column>>();
+
+        let column = |ce: &ConditionExpression| match *ce {
+            ConditionExpression::Base(ConditionBase::Field(ref f)) => Column::from(f),
+            _ => unimplemented!(),
+        };
+        let on = jps
+            .iter()
+            .map(|jp| {
+                let op = match jp.operator {
+                    Operator::In => Operator::Equal,
+                    ref op => op.clone(),
+                };
+                (column(&jp.left), op, column(&jp.right))
+            })
+            .collect();
+
+        let inner = MirNodeType::ThetaJoin {
+            on,
+            project: fields.clone(),
+        };
+        trace!(self.log, "Added theta join node {:?}", inner);
+        MirNode::new(
+            name,
+            self.schema_version,
+            fields,
+            inner,
+            vec![left_node.clone(), right_node.clone()],
+            vec![],
+        )
+    }
+
+    fn make_semi_join_node(
+        &self,
+        name: &str,
+        jp: &ConditionTree,
+        left_node: MirNodeRef,
+        right_node: MirNodeRef,
+        kind: SemiJoinType,
+    ) -> MirNodeRef {
+        // only the left side's rows make it through, so the right side contributes no columns
+        let fields = left_node.borrow().columns().to_vec();
+
+        assert_eq!(jp.operator, Operator::In);
+        let column = |ce: &ConditionExpression| match *ce {
+            ConditionExpression::Base(ConditionBase::Field(ref f)) => Column::from(f),
+            _ => unimplemented!(),
+        };
+        let on_left = vec![column(&jp.left)];
+        let on_right = vec![column(&jp.right)];
+
+        let inner = match kind {
+            SemiJoinType::Semi => MirNodeType::SemiJoin {
+                on_left,
+                on_right,
+                project: fields.clone(),
+            },
+            SemiJoinType::Anti => MirNodeType::AntiJoin {
+                on_left,
+                on_right,
+                project: fields.clone(),
+            },
+        };
+        trace!(self.log, "Added semi-join node {:?}", inner);
+        MirNode::new(
+            name,
+            self.schema_version,
+            fields,
+            inner,
+            vec![left_node.clone(), right_node.clone()],
+            vec![],
+        )
+    }
+
     fn make_projection_helper(
         &self,
         name: &str,
@@ -1415,18 +1616,7 @@ impl SqlToMirConverter {
         limit: &LimitClause,
     ) -> MirNodeRef {
         let combined_columns = parent.borrow().columns().to_vec();
-
-        let order = match *order {
-            Some(ref o) => Some(
-                o.columns
-                    .iter()
-                    .map(|(c, o)| (Column::from(c), o.clone()))
-                    .collect(),
-            ),
-            None => None,
-        };
-
-        assert_eq!(limit.offset, 0); // Non-zero offset not supported
+        let order = order_columns(order);
 
         // make the new operator and record its metadata
         MirNode::new(
@@ -1437,7 +1627,7 @@ impl SqlToMirConverter {
                 order,
                 group_by: group_by.into_iter().cloned().collect(),
                 k: limit.limit as usize,
-                offset: 0,
+                offset: limit.offset as usize,
             },
             vec![parent.clone()],
             vec![],
@@ -1454,15 +1644,13 @@ impl SqlToMirConverter {
         use nom_sql::ConditionExpression::*;
 
         let mut pred_nodes: Vec<MirNodeRef> = Vec::new();
-        let output_cols = parent.borrow().columns().to_vec();
         match *ce {
             LogicalOp(ref ct) => {
-                let (left, right);
                 match ct.operator {
                     Operator::And => {
-                        left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc);
+                        let left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc);
 
-                        right = self.make_predicate_nodes(
+                        let right = self.make_predicate_nodes(
                             name,
                             left.last().unwrap().clone(),
                             &*ct.right,
@@ -1473,28 +1661,10 @@ impl SqlToMirConverter {
                         pred_nodes.extend(right.clone());
                     }
                     Operator::Or => {
-                        left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc);
-
-                        right = self.make_predicate_nodes(
-                            name,
-                            parent.clone(),
-                            &*ct.right,
-                            nc + left.len(),
-                        );
-
-                        debug!(self.log, "Creating union node for `or` predicate");
-
-                        let last_left = left.last().unwrap().clone();
-                        let last_right = right.last().unwrap().clone();
-                        let union = self.make_union_from_same_base(
-                            &format!("{}_un", name),
-                            vec![last_left, last_right],
-                            output_cols,
-                        );
-
-                        pred_nodes.extend(left.clone());
-                        pred_nodes.extend(right.clone());
-                        pred_nodes.push(union);
+                        // a single filter that checks both sides, rather than a union of two
+                        // filters, so that rows matching both sides are only emitted once
+                        let f = self.make_filter_node(&format!("{}_f{}", name, nc), parent, ct);
+                        pred_nodes.push(f);
                     }
                     _ => unreachable!("LogicalOp operator is {:?}", ct.operator),
                 }
@@ -1511,7 +1681,7 @@ impl SqlToMirConverter {
             }
             NegationOp(_) => unreachable!("negation should have been removed earlier"),
             Base(_) => unreachable!("dangling base predicate"),
-            Arithmetic(_) => unimplemented!(),
+            Arithmetic(_) => unreachable!("arithmetic should have been computed earlier"),
         }
 
         pred_nodes
@@ -1602,6 +1772,48 @@ impl SqlToMirConverter {
         }
     }
 
+    /// Project the arithmetic expressions in `qg.arithmetic` that belong to `table` next to the
+    /// columns of `parent`, or return `None` if there are none.
+    fn make_arithmetic_project_node(
+        &self,
+        name: &str,
+        qg: &QueryGraph,
+        parent: MirNodeRef,
+        table: Option<&str>,
+    ) -> Option<MirNodeRef> {
+        let arithmetic: Vec<(String, ArithmeticExpression)> = qg
+            .arithmetic
+            .iter()
+            .filter(|ac| ac.table.as_ref().map(String::as_str) == table)
+            .map(|ac| (ac.name.clone(), ac.expression.clone()))
+            .collect();
+        if arithmetic.is_empty() {
+            return None;
+        }
+
+        let emit = parent.borrow().columns().to_vec();
+        // unlike in make_project_node, the computed columns keep the table they belong to, as
+        // the predicates on them refer to it
+        let fields = emit
+            .iter()
+            .cloned()
+            .chain(arithmetic.iter().map(|&(ref n, _)| Column::new(table, n)))
+            .collect();
+
+        Some(MirNode::new(
+            name,
+            self.schema_version,
+            fields,
+            MirNodeType::Project {
+                emit,
+                literals: vec![],
+                arithmetic,
+            },
+            vec![parent],
+            vec![],
+        ))
+    }
+
     /// Returns list of nodes added
     #[allow(clippy::cognitive_complexity)]
     fn make_nodes_for_selection(
@@ -1620,6 +1832,12 @@ impl SqlToMirConverter {
         ),
         String,
     > {
+        if st.limit.is_some() && !qg.parameter_predicates.is_empty() {
+            return Err(String::from(
+                "LIMIT is not supported with query parameters that cannot be looked up by key",
+            ));
+        }
+
         // TODO: make this take &self!
         use crate::controller::sql::mir::grouped::make_grouped;
         use crate::controller::sql::mir::grouped::make_predicates_above_grouped;
@@ -1660,6 +1878,26 @@ impl SqlToMirConverter {
                 node_for_rel.insert(*rel, base_for_rel);
             }
 
+            // 1. Compute the arithmetic expressions that predicates on a single relation compare
+            // before joining it with others, since join predicates may compare them too
+            let mut arithmetic_nodes = Vec::new();
+            for rel in &sorted_rels {
+                if *rel == "computed_columns" {
+                    continue;
+                }
+
+                if let Some(projected) = self.make_arithmetic_project_node(
+                    &format!("q_{:x}_n{}{}", qg.signature().hash, new_node_count, uformat),
+                    qg,
+                    node_for_rel[rel].clone(),
+                    Some(rel),
+                ) {
+                    new_node_count += 1;
+                    arithmetic_nodes.push(projected.clone());
+                    node_for_rel.insert(*rel, projected);
+                }
+            }
+
             let join_nodes = make_joins(
                 self,
                 &format!("q_{:x}{}", qg.signature().hash, uformat),
@@ -1674,10 +1912,24 @@ impl SqlToMirConverter {
                 Some(n) => Some(n.clone()),
                 None => {
                     assert_eq!(base_nodes.len(), 1);
-                    Some(base_nodes.last().unwrap().clone())
+                    Some(node_for_rel.values().next().unwrap().clone())
                 }
             };
 
+            // 1b. The remaining arithmetic expressions span relations, and can only be computed
+            // once they have been joined
+            let mut joined_arithmetic_nodes = Vec::new();
+            if let Some(projected) = self.make_arithmetic_project_node(
+                &format!("q_{:x}_n{}{}", qg.signature().hash, new_node_count, uformat),
+                qg,
+                prev_node.clone().unwrap(),
+                None,
+            ) {
+                new_node_count += 1;
+                joined_arithmetic_nodes.push(projected.clone());
+                prev_node = Some(projected);
+            }
+
             // 2. Get columns used by each predicate. This will be used to check
             // if we need to reorder predicates before group_by nodes.
             let mut column_to_predicates: HashMap<Column, Vec<&ConditionExpression>> =
@@ -1764,7 +2016,9 @@ impl SqlToMirConverter {
 
             nodes_added = base_nodes
                 .into_iter()
+                .chain(arithmetic_nodes.into_iter())
                 .chain(join_nodes.into_iter())
+                .chain(joined_arithmetic_nodes.into_iter())
                 .chain(predicates_above_group_by_nodes.into_iter())
                 .chain(policy_nodes.into_iter())
                 .chain(ancestors.clone().into_iter())
@@ -1988,6 +2242,26 @@ impl SqlToMirConverter {
                 }
             }
 
+            // the reader checks the conditions that compare against parameters without a key to
+            // look up, so it needs the columns they check
+            let mut predicate_cols: Vec<Column> = qg
+                .parameter_predicates
+                .iter()
+                .flat_map(predicate_columns)
+                .collect();
+            predicate_cols.sort();
+            for pc in predicate_cols {
+                if !final_node_cols.contains(&pc) {
+                    return Err(format!(
+                        "query parameter is compared to column {} that the query does not have",
+                        pc.name
+                    ));
+                }
+                if !projected_columns.contains(&pc) {
+                    projected_columns.push(pc);
+                }
+            }
+
             // We may already have added some of the arithmetic and literal columns
             let (_, already_computed): (Vec<_>, Vec<_>) =
                 value_columns_needed_for_predicates(&qg.columns, &qg.global_predicates)
@@ -2026,8 +2300,11 @@ impl SqlToMirConverter {
                 })
                 .collect();
 
-            // if this query does not have any parameters, we must add a bogokey
-            let has_bogokey = if has_leaf && qg.parameters().is_empty() {
+            // if this query does not have any parameters that can be looked up by key, we must add
+            // a bogokey
+            let has_bogokey = if has_leaf
+                && (qg.parameters().is_empty() || !qg.parameter_predicates.is_empty())
+            {
                 // only add the bogokey if we haven't already added it prior to a TopK above
                 if !projected_columns.contains(&Column::new(None, "bogokey")) {
                     projected_literals.push(("bogokey".into(), DataType::from(0 as i32)));
@@ -2074,6 +2351,15 @@ impl SqlToMirConverter {
                     qg.parameters().into_iter().map(Column::from).collect()
                 };
 
+                // all of the query's parameters are then checked by the reader, in the order in
+                // which they appear in the query
+                let parameter_filter = match st.where_clause {
+                    Some(ref ce) if !qg.parameter_predicates.is_empty() => {
+                        self.parameter_conditions(ce, &leaf_project_node, &mut 0)
+                    }
+                    _ => vec![],
+                };
+
                 let leaf_node = MirNode::new(
                     name,
                     self.schema_version,
@@ -2081,6 +2367,13 @@ impl SqlToMirConverter {
                     MirNodeType::Leaf {
                         node: leaf_project_node.clone(),
                         keys: query_params,
+                        order: order_columns(&st.order),
+                        parameter_filter,
+                        ranges: st
+                            .where_clause
+                            .as_ref()
+                            .map(has_range_placeholder)
+                            .unwrap_or(false),
                     },
                     vec![leaf_project_node.clone()],
                     vec![],
@@ -7,7 +7,7 @@ mod reuse;
 pub(super) mod security;
 
 use self::mir::SqlToMirConverter;
-use self::query_graph::{to_query_graph, QueryGraph};
+use self::query_graph::{has_range_placeholder, to_query_graph, QueryGraph};
 use self::query_signature::Signature;
 use self::reuse::ReuseConfig;
 use super::mir_to_flow::mir_query_to_flow_parts;
@@ -19,7 +19,7 @@ use ::mir::Column;
 use ::mir::MirNodeRef;
 use dataflow::prelude::DataType;
 use nom_sql::parser as sql_parser;
-use nom_sql::{ArithmeticBase, CreateTableStatement, SqlQuery};
+use nom_sql::{ArithmeticBase, CreateTableStatement, OrderClause, SqlQuery};
 use nom_sql::{CompoundSelectOperator, CompoundSelectStatement, SelectStatement};
 use petgraph::graph::NodeIndex;
 
@@ -190,20 +190,17 @@ impl SqlIncorporator {
         query_name: &str,
         universe: UniverseId,
         st: &SelectStatement,
-    ) -> (QueryGraph, QueryGraphReuse) {
+    ) -> Result<(QueryGraph, QueryGraphReuse), String> {
         debug!(self.log, "Making QG for \"{}\"", query_name);
         trace!(self.log, "Query \"{}\": {:#?}", query_name, st);
 
-        let mut qg = match to_query_graph(st) {
-            Ok(qg) => qg,
-            Err(e) => panic!(e),
-        };
+        let mut qg = to_query_graph(st)?;
 
         trace!(self.log, "QG for \"{}\": {:#?}", query_name, qg);
 
         // if reuse is disabled, we're done
         if self.reuse_type == ReuseConfigType::NoReuse {
-            return (qg, QueryGraphReuse::None);
+            return Ok((qg, QueryGraphReuse::None));
         }
 
         // Do we already have this exact query or a subset of it in the same universe?
@@ -216,6 +213,25 @@ impl SqlIncorporator {
                     .query_graphs
                     .get(&qg_hash)
                     .expect("query graph should be present");
+
+                // the query graph does not capture the ORDER BY clause, but readers return rows in
+                // that order. a query that is ordered differently from an existing one with the
+                // same query graph thus needs a reader of its own, and since a node can only have
+                // one reader, we cannot share the existing query's nodes either.
+                if let ::mir::node::MirNodeType::Leaf { ref order, .. } =
+                    mir_query.leaf.borrow().inner
+                {
+                    if *order != self::mir::order_columns(&st.order) {
+                        info!(
+                            self.log,
+                            "Query \"{}\" is ordered differently from \"{}\", adding fresh query",
+                            query_name,
+                            mir_query.name,
+                        );
+                        return Ok((qg, QueryGraphReuse::None));
+                    }
+                }
+
                 // note that this also checks the *order* in which parameters are specified; a
                 // different order means that we cannot simply reuse the existing reader.
                 if existing_qg.signature() == qg.signature()
@@ -238,9 +254,11 @@ impl SqlIncorporator {
                         existing_qg,
                     );
 
-                    return (qg, QueryGraphReuse::ExactMatch(mir_query.leaf.clone()));
+                    return Ok((qg, QueryGraphReuse::ExactMatch(mir_query.leaf.clone())));
                 } else if existing_qg.signature() == qg.signature()
                     && existing_qg.parameters() != qg.parameters()
+                    && existing_qg.parameter_predicates.is_empty()
+                    && qg.parameter_predicates.is_empty()
                 {
                     use self::query_graph::OutputColumn;
 
@@ -331,10 +349,10 @@ impl SqlIncorporator {
                                     Some(project_columns)
                                 }
                             };
-                            return (
+                            return Ok((
                                 qg,
                                 QueryGraphReuse::ReaderOntoExisting(mn, project_columns, params),
-                            );
+                            ));
                         }
                     }
                 }
@@ -372,18 +390,20 @@ impl SqlIncorporator {
                 mir_queries.extend(mqs);
             }
 
-            return (qg, QueryGraphReuse::ExtendExisting(mir_queries));
+            return Ok((qg, QueryGraphReuse::ExtendExisting(mir_queries)));
         } else {
             info!(self.log, "No reuse opportunity, adding fresh query");
         }
 
-        (qg, QueryGraphReuse::None)
+        Ok((qg, QueryGraphReuse::None))
     }
 
     fn add_leaf_to_existing_query(
         &mut self,
         query_name: &str,
         params: &[Column],
+        order: &Option<OrderClause>,
+        ranges: bool,
         final_query_node: MirNodeRef,
         project_columns: Option<Vec<Column>>,
         mut mig: &mut Migration,
@@ -394,6 +414,8 @@ impl SqlIncorporator {
             final_query_node,
             query_name,
             params,
+            order,
+            ranges,
             project_columns,
         );
 
@@ -483,7 +505,7 @@ impl SqlIncorporator {
         is_leaf: bool,
         mig: &mut Migration,
     ) -> Result<(QueryFlowParts, Option<MirQuery>), String> {
-        let (qg, reuse) = self.consider_query_graph(&query_name, mig.universe(), sq);
+        let (qg, reuse) = self.consider_query_graph(&query_name, mig.universe(), sq)?;
         Ok(match reuse {
             QueryGraphReuse::ExactMatch(mn) => {
                 let flow_node = mn.borrow().flow_node.as_ref().unwrap().address();
@@ -500,8 +522,18 @@ impl SqlIncorporator {
                 (qfp, None)
             }
             QueryGraphReuse::ReaderOntoExisting(mn, project_columns, params) => {
-                let qfp =
-                    self.add_leaf_to_existing_query(&query_name, &params, mn, project_columns, mig);
+                let qfp = self.add_leaf_to_existing_query(
+                    &query_name,
+                    &params,
+                    &sq.order,
+                    sq.where_clause
+                        .as_ref()
+                        .map(has_range_placeholder)
+                        .unwrap_or(false),
+                    mn,
+                    project_columns,
+                    mig,
+                );
                 (qfp, None)
             }
             QueryGraphReuse::None => {
@@ -770,6 +802,37 @@ impl SqlIncorporator {
         self.nodes_for_named_query(q, name, is_leaf, mig)
     }
 
+    /// Whether `column`, which belongs to one of `tables`, is declared `NOT NULL` (or part of a
+    /// primary key) by the schema of its base table. Columns of views are assumed to be nullable.
+    fn is_not_null(&self, column: &nom_sql::Column, tables: &[nom_sql::Table]) -> bool {
+        use nom_sql::{ColumnConstraint, TableKey};
+
+        let candidates: Vec<&str> = match column.table {
+            Some(ref t) => vec![tables
+                .iter()
+                .find(|table| table.alias.as_ref() == Some(t))
+                .map(|table| table.name.as_str())
+                .unwrap_or(t)],
+            None => tables.iter().map(|table| table.name.as_str()).collect(),
+        };
+        candidates
+            .into_iter()
+            .filter_map(|t| self.base_schemas.get(t))
+            .find_map(|ctq| {
+                let spec = ctq.fields.iter().find(|f| f.column.name == column.name)?;
+                let in_primary_key = ctq.keys.iter().flatten().any(|k| match *k {
+                    TableKey::PrimaryKey(ref cols) => cols.iter().any(|c| c.name == column.name),
+                    _ => false,
+                });
+                Some(
+                    in_primary_key
+                        || spec.constraints.contains(&ColumnConstraint::NotNull)
+                        || spec.constraints.contains(&ColumnConstraint::PrimaryKey),
+                )
+            })
+            .unwrap_or(false)
+    }
+
     /// Runs some standard rewrite passes on the query.
     fn rewrite_query(&mut self, q: SqlQuery, mig: &mut Migration) -> Result<SqlQuery, String> {
         // TODO: make this not take &mut self
@@ -788,22 +851,65 @@ impl SqlIncorporator {
         // so we will end up incrementing this for every subquery.
         self.num_queries += 1;
 
+        // the tables that the columns compared with NOT IN subqueries may come from
+        let outer_tables: Vec<nom_sql::Table> = match q {
+            SqlQuery::Select(ref st) => st
+                .tables
+                .iter()
+                .cloned()
+                .chain(st.join.iter().filter_map(|j| match j.right {
+                    nom_sql::JoinRightSide::Table(ref t) => Some(t.clone()),
+                    _ => None,
+                }))
+                .collect(),
+            _ => Vec::new(),
+        };
+
         // flattens out the query by replacing subqueries for references
         // to existing views in the graph
         let mut fq = q.clone();
         for sq in fq.extract_subqueries() {
             use self::passes::subqueries::{
-                field_with_table_name, query_from_condition_base, Subquery,
+                compare_with_view, query_from_comparison, query_parameters, Subquery,
             };
             use nom_sql::{JoinRightSide, Table};
             match sq {
-                Subquery::InComparison(cond_base) => {
-                    let (sq, column) = query_from_condition_base(&cond_base);
+                Subquery::InComparison(ce, negated) => {
+                    let (sq, column) = query_from_comparison(&ce);
+                    let params = query_parameters(&sq)?;
+                    if negated && !params.is_empty() {
+                        // the anti-join would have to know which rows to leave out for each
+                        // value of the parameters
+                        return Err(String::from(
+                            "NOT IN subqueries cannot compare against query parameters",
+                        ));
+                    }
+                    if negated {
+                        // the anti-join that implements NOT IN treats NULLs as NOT EXISTS would,
+                        // so we only use it where there can't be any
+                        let compared = match *ce {
+                            nom_sql::ConditionExpression::ComparisonOp(ref ct) => match *ct.left {
+                                nom_sql::ConditionExpression::Base(
+                                    nom_sql::ConditionBase::Field(ref c),
+                                ) => Some(c.clone()),
+                                _ => None,
+                            },
+                            _ => None,
+                        };
+                        let not_null = compared
+                            .map(|c| self.is_not_null(&c, &outer_tables))
+                            .unwrap_or(false);
+                        if !not_null || !self.is_not_null(&column, &sq.tables) {
+                            return Err(format!(
+                                "NOT IN subqueries are only supported if both of the compared \
+                                 columns are declared NOT NULL: {}",
+                                ce
+                            ));
+                        }
+                    }
 
-                    let qfp = self
-                        .add_parsed_query(sq, None, false, mig)
-                        .expect("failed to add subquery");
-                    *cond_base = field_with_table_name(qfp.name.clone(), column);
+                    let qfp = self.add_parsed_query(SqlQuery::Select(sq), None, false, mig)?;
+                    compare_with_view(ce, qfp.name.clone(), column, params);
                 }
                 Subquery::InJoin(join_right_side) => {
                     *join_right_side = match *join_right_side {
@@ -1057,6 +1163,42 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_unsupported_predicates() {
+        let mut g = integration::start_simple("it_rejects_unsupported_predicates").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!("CREATE TABLE users (id int, name varchar(40));"
+                .to_flow_parts(&mut inc, None, mig)
+                .is_ok());
+            assert!(
+                "CREATE TABLE articles (id int, author int, title varchar(255));"
+                    .to_flow_parts(&mut inc, None, mig)
+                    .is_ok()
+            );
+            let ncount = mig.graph().node_count();
+
+            // OR over a join predicate
+            assert!("SELECT users.name, articles.title FROM users, articles \
+                     WHERE users.id = articles.author OR articles.id = 1;"
+                .to_flow_parts(&mut inc, None, mig)
+                .is_err());
+            // NOT IN over columns that may hold NULLs
+            assert!("SELECT users.name FROM users \
+                     WHERE users.id NOT IN (SELECT articles.author FROM articles);"
+                .to_flow_parts(&mut inc, None, mig)
+                .is_err());
+            // a query parameter compared with an expression over several tables
+            assert!("SELECT users.name FROM users, articles \
+                     WHERE users.id = articles.author AND users.id + articles.id = ?;"
+                .to_flow_parts(&mut inc, None, mig)
+                .is_err());
+            // none of the rejected queries should have added any nodes
+            assert_eq!(mig.graph().node_count(), ncount);
+        })
+        .await;
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn it_incorporates_simple_join() {
         // set up graph
@@ -2185,6 +2327,55 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn it_incorporates_arithmetic_predicate() {
+        // set up graph
+        let mut g = integration::start_simple("it_incorporates_arithmetic_predicate").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!(inc
+                .add_query(
+                    "CREATE TABLE posts (id int, upvotes int, downvotes int);",
+                    None,
+                    mig
+                )
+                .is_ok());
+
+            let res = inc.add_query(
+                "SELECT id FROM posts WHERE 10 < upvotes - downvotes;",
+                None,
+                mig,
+            );
+            assert!(res.is_ok());
+
+            let parent = |na| {
+                let parents: Vec<_> = mig
+                    .graph()
+                    .neighbors_directed(na, petgraph::EdgeDirection::Incoming)
+                    .collect();
+                assert_eq!(parents.len(), 1);
+                parents[0]
+            };
+
+            // the difference is computed into a column, which is then filtered on
+            let leaf = inc.get_flow_node_address(&res.unwrap().name, 0).unwrap();
+            let filter = parent(leaf);
+            let project = parent(filter);
+            assert_eq!(mig.graph()[filter].description(true), "σ[f3 \\> 10]");
+            assert_eq!(
+                mig.graph()[project].fields(),
+                &[
+                    "id",
+                    "upvotes",
+                    "downvotes",
+                    "posts.upvotes - posts.downvotes"
+                ]
+            );
+            assert_eq!(mig.graph()[project].description(true), "π[0, 1, 2, 1 - 2]");
+        })
+        .await;
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn it_incorporates_join_with_nested_query() {
         let mut g = integration::start_simple("it_incorporates_join_with_nested_query").await;
@@ -50,6 +50,21 @@ where
             left: Box::new(rewrite_conditional(expand_columns, *left, avail_tables)),
             right: Box::new(rewrite_conditional(expand_columns, *right, avail_tables)),
         }),
+        Arithmetic(mut ae) => {
+            if let ArithmeticBase::Column(ref mut c) = ae.left {
+                *c = expand_columns(c.clone(), avail_tables);
+            }
+            if let ArithmeticBase::Column(ref mut c) = ae.right {
+                *c = expand_columns(c.clone(), avail_tables);
+            }
+            Arithmetic(ae)
+        }
+        // negation removal keeps NOT in front of IN comparisons
+        NegationOp(inner) => NegationOp(Box::new(rewrite_conditional(
+            expand_columns,
+            *inner,
+            avail_tables,
+        ))),
         x => x,
     }
 }
@@ -26,6 +26,18 @@ fn normalize_condition_expr(ce: &mut ConditionExpression, negate: bool) {
             normalize_condition_expr(left, negate);
             normalize_condition_expr(right, negate);
         }
+        ConditionExpression::ComparisonOp(ConditionTree {
+            operator: Operator::In,
+            ..
+        }) if negate => {
+            // there is no operator for NOT IN, so the negation stays; for subqueries, it turns
+            // the semi-join into an anti-join
+            let inner = mem::replace(
+                ce,
+                ConditionExpression::Base(ConditionBase::Literal(Literal::Placeholder)),
+            );
+            *ce = ConditionExpression::NegationOp(Box::new(inner));
+        }
         ConditionExpression::ComparisonOp(ConditionTree {
             ref mut operator,
             ref mut left,
@@ -61,8 +73,7 @@ fn normalize_condition_expr(ce: &mut ConditionExpression, negate: bool) {
         ConditionExpression::Bracketed(ref mut inner) => {
             normalize_condition_expr(inner, negate);
         }
-        ConditionExpression::Base(_) => {}
-        ConditionExpression::Arithmetic(_) => unimplemented!(),
+        ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => {}
     }
 }
 
@@ -122,4 +133,23 @@ mod tests {
         normalize_condition_expr(&mut expr, false);
         assert_eq!(expr, target);
     }
+
+    #[test]
+    fn it_keeps_negated_in() {
+        let is_in = ConditionExpression::ComparisonOp(ConditionTree {
+            operator: Operator::In,
+            left: Box::new(ConditionExpression::Base(ConditionBase::Field("a".into()))),
+            right: Box::new(ConditionExpression::Base(ConditionBase::Field("b".into()))),
+        });
+        let not_in = ConditionExpression::NegationOp(Box::new(is_in.clone()));
+
+        let mut expr = not_in.clone();
+        normalize_condition_expr(&mut expr, false);
+        assert_eq!(expr, not_in);
+
+        // a double negation still goes away
+        let mut expr = ConditionExpression::NegationOp(Box::new(not_in));
+        normalize_condition_expr(&mut expr, false);
+        assert_eq!(expr, is_in);
+    }
 }
@@ -1,32 +1,53 @@
 use nom_sql::ConditionExpression::*;
-use nom_sql::{Column, ConditionBase, ConditionExpression, JoinRightSide, SqlQuery};
+use nom_sql::{
+    Column, ConditionBase, ConditionExpression, ConditionTree, JoinRightSide, Operator,
+    SelectStatement, SqlQuery,
+};
+
+use crate::controller::sql::query_graph::has_placeholder;
+
+use std::mem;
 
 #[derive(Debug, PartialEq)]
 pub enum Subquery<'a> {
     InJoin(&'a mut JoinRightSide),
-    InComparison(&'a mut ConditionBase),
+    /// An `IN` comparison with a subquery, and whether it is negated
+    InComparison(&'a mut ConditionExpression, bool),
 }
 
 pub trait SubQueries {
     fn extract_subqueries(&mut self) -> Vec<Subquery>;
 }
 
-fn extract_subqueries_from_condition(ce: &mut ConditionExpression) -> Vec<Subquery> {
-    use nom_sql::ConditionBase::NestedSelect;
+fn is_in_subquery(ce: &ConditionExpression) -> bool {
+    match *ce {
+        ComparisonOp(ConditionTree {
+            operator: Operator::In,
+            ref right,
+            ..
+        }) => match **right {
+            Base(ConditionBase::NestedSelect(_)) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn extract_subqueries_from_condition(ce: &mut ConditionExpression, negated: bool) -> Vec<Subquery> {
+    if is_in_subquery(ce) {
+        return vec![Subquery::InComparison(ce, negated)];
+    }
+
     match *ce {
         ComparisonOp(ref mut ct) | LogicalOp(ref mut ct) => {
-            let lb = extract_subqueries_from_condition(&mut *ct.left);
-            let rb = extract_subqueries_from_condition(&mut *ct.right);
+            let lb = extract_subqueries_from_condition(&mut *ct.left, negated);
+            let rb = extract_subqueries_from_condition(&mut *ct.right, negated);
 
             lb.into_iter().chain(rb.into_iter()).collect()
         }
-        NegationOp(ref mut bce) => extract_subqueries_from_condition(&mut *bce),
-        Bracketed(ref mut bce) => extract_subqueries_from_condition(&mut *bce),
-        Base(ref mut cb) => match *cb {
-            NestedSelect(_) => vec![Subquery::InComparison(cb)],
-            _ => vec![],
-        },
-        Arithmetic(_) => unimplemented!(),
+        NegationOp(ref mut bce) => extract_subqueries_from_condition(&mut *bce, !negated),
+        Bracketed(ref mut bce) => extract_subqueries_from_condition(&mut *bce, negated),
+        Base(_) | Arithmetic(_) => vec![],
     }
 }
 
@@ -39,29 +60,103 @@ pub fn field_with_table_name(name: String, column: Column) -> ConditionBase {
     })
 }
 
-pub fn query_from_condition_base(cond: &ConditionBase) -> (SqlQuery, Column) {
+/// Returns the subquery of an `IN` comparison, and the column it selects.
+pub fn query_from_comparison(ce: &ConditionExpression) -> (SelectStatement, Column) {
     use nom_sql::ConditionBase::NestedSelect;
     use nom_sql::FieldDefinitionExpression;
     let (sq, column);
-    match *cond {
-        NestedSelect(ref bst) => {
-            sq = SqlQuery::Select(*bst.clone());
-            column = bst
-                .fields
-                .iter()
-                .map(|fe| match *fe {
-                    FieldDefinitionExpression::Col(ref c) => c.clone(),
-                    _ => unreachable!(),
-                })
-                .nth(0)
-                .unwrap();
-        }
+    match *ce {
+        ComparisonOp(ConditionTree { ref right, .. }) => match **right {
+            Base(NestedSelect(ref bst)) => {
+                sq = *bst.clone();
+                column = bst
+                    .fields
+                    .iter()
+                    .map(|fe| match *fe {
+                        FieldDefinitionExpression::Col(ref c) => c.clone(),
+                        _ => unreachable!(),
+                    })
+                    .nth(0)
+                    .unwrap();
+            }
+            _ => unreachable!(),
+        },
         _ => unreachable!(),
     };
 
     (sq, column)
 }
 
+/// Returns the columns that the subquery compares to query parameters.
+///
+/// The view for the subquery has a column for each of them, which the query has to compare to its
+/// own parameters instead. This only works for comparisons with `=` that all rows must satisfy.
+pub fn query_parameters(st: &SelectStatement) -> Result<Vec<Column>, String> {
+    fn collect(ce: &ConditionExpression, params: &mut Vec<Column>) -> Result<(), String> {
+        match *ce {
+            LogicalOp(ref ct) if ct.operator == Operator::And => {
+                collect(&ct.left, params)?;
+                collect(&ct.right, params)
+            }
+            Bracketed(ref inner) => collect(inner, params),
+            ComparisonOp(ref ct) if ct.operator == Operator::Equal => {
+                match (ct.left.as_ref(), ct.right.as_ref()) {
+                    (
+                        Base(ConditionBase::Field(ref c)),
+                        Base(ConditionBase::Literal(nom_sql::Literal::Placeholder)),
+                    ) => {
+                        params.push(c.clone());
+                        Ok(())
+                    }
+                    _ if has_placeholder(ce) => {
+                        Err(format!("unsupported query parameter in subquery: {}", ce))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ if has_placeholder(ce) => {
+                Err(format!("unsupported query parameter in subquery: {}", ce))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    let mut params = Vec::new();
+    if let Some(ref ce) = st.where_clause {
+        collect(ce, &mut params)?;
+    }
+    Ok(params)
+}
+
+/// Turn an `IN` comparison with a subquery into one with the given column of the view for the
+/// subquery, and compare the view's columns for the subquery's `parameters` to query parameters.
+pub fn compare_with_view(
+    ce: &mut ConditionExpression,
+    view: String,
+    column: Column,
+    parameters: Vec<Column>,
+) {
+    if let ComparisonOp(ref mut ct) = *ce {
+        *ct.right = Base(field_with_table_name(view.clone(), column));
+    }
+
+    for p in parameters {
+        let in_ce = mem::replace(
+            ce,
+            Base(ConditionBase::Literal(nom_sql::Literal::Placeholder)),
+        );
+        *ce = LogicalOp(ConditionTree {
+            operator: Operator::And,
+            left: Box::new(in_ce),
+            right: Box::new(ComparisonOp(ConditionTree {
+                operator: Operator::Equal,
+                left: Box::new(Base(field_with_table_name(view.clone(), p))),
+                right: Box::new(Base(ConditionBase::Literal(nom_sql::Literal::Placeholder))),
+            })),
+        });
+    }
+}
+
 impl SubQueries for SqlQuery {
     fn extract_subqueries(&mut self) -> Vec<Subquery> {
         let mut subqueries = Vec::new();
@@ -72,7 +167,7 @@ impl SubQueries for SqlQuery {
                 }
             }
             if let Some(ref mut ce) = st.where_clause {
-                subqueries.extend(extract_subqueries_from_condition(ce));
+                subqueries.extend(extract_subqueries_from_condition(ce, false));
             }
         }
 
@@ -106,24 +201,70 @@ mod tests {
             ..Default::default()
         };
 
-        let mut expected = NestedSelect(Box::new(sq.clone()));
+        let mut expected = ComparisonOp(ConditionTree {
+            operator: Operator::In,
+            left: wrap(Field(Column::from("author"))),
+            right: wrap(NestedSelect(Box::new(sq.clone()))),
+        });
 
         // select pid from post where author in (select userid from role where type=1)
         let st = SelectStatement {
             tables: vec![Table::from("post")],
             fields: vec![FieldDefinitionExpression::Col(Column::from("pid"))],
-            where_clause: Some(ComparisonOp(ConditionTree {
-                operator: Operator::In,
-                left: wrap(Field(Column::from("author"))),
-                right: wrap(expected.clone()),
-            })),
+            where_clause: Some(expected.clone()),
             ..Default::default()
         };
 
-        let mut q = SqlQuery::Select(st);
+        let mut q = SqlQuery::Select(st.clone());
         let res = q.extract_subqueries();
 
-        assert_eq!(res, vec![Subquery::InComparison(&mut expected)]);
+        assert_eq!(res, vec![Subquery::InComparison(&mut expected, false)]);
+
+        // select pid from post where not author in (select userid from role where type=1)
+        let mut q = SqlQuery::Select(SelectStatement {
+            where_clause: Some(NegationOp(Box::new(expected.clone()))),
+            ..st
+        });
+        let res = q.extract_subqueries();
+
+        assert_eq!(res, vec![Subquery::InComparison(&mut expected, true)]);
+    }
+
+    #[test]
+    fn it_finds_query_parameters() {
+        // select userid from role where type=1 and cid=?
+        let st = SelectStatement {
+            tables: vec![Table::from("role")],
+            fields: vec![FieldDefinitionExpression::Col(Column::from("userid"))],
+            where_clause: Some(LogicalOp(ConditionTree {
+                operator: Operator::And,
+                left: Box::new(ComparisonOp(ConditionTree {
+                    operator: Operator::Equal,
+                    left: wrap(Field(Column::from("type"))),
+                    right: wrap(Literal(1.into())),
+                })),
+                right: Box::new(ComparisonOp(ConditionTree {
+                    operator: Operator::Equal,
+                    left: wrap(Field(Column::from("cid"))),
+                    right: wrap(Literal(nom_sql::Literal::Placeholder)),
+                })),
+            })),
+            ..Default::default()
+        };
+        assert_eq!(query_parameters(&st), Ok(vec![Column::from("cid")]));
+
+        // select userid from role where type=1 or cid=?
+        let st = SelectStatement {
+            where_clause: st.where_clause.map(|ce| match ce {
+                LogicalOp(ct) => LogicalOp(ConditionTree {
+                    operator: Operator::Or,
+                    ..ct
+                }),
+                _ => unreachable!(),
+            }),
+            ..st
+        };
+        assert!(query_parameters(&st).is_err());
     }
 
     #[test]
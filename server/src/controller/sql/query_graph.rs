@@ -1,8 +1,9 @@
 use nom_sql::SelectStatement;
 use nom_sql::{
-    ArithmeticBase, ArithmeticExpression, Column, ConditionBase, ConditionExpression,
-    ConditionTree, FieldDefinitionExpression, FieldValueExpression, JoinConstraint, JoinOperator,
-    JoinRightSide, Literal, Operator, Table,
+    ArithmeticBase, ArithmeticExpression, CaseWhenExpression, Column, ConditionBase,
+    ConditionExpression, ConditionTree, FieldDefinitionExpression, FieldValueExpression,
+    FunctionArguments, FunctionExpression, JoinConstraint, JoinOperator, JoinRightSide, Literal,
+    Operator, Table,
 };
 
 use std::cmp::Ordering;
@@ -147,6 +148,10 @@ pub struct QueryGraphNode {
 pub enum QueryGraphEdge {
     Join(Vec<ConditionTree>),
     LeftJoin(Vec<ConditionTree>),
+    /// `IN` subquery, whose view is the destination relation
+    SemiJoin(Vec<ConditionTree>),
+    /// `NOT IN` subquery, whose view is the destination relation
+    AntiJoin(Vec<ConditionTree>),
     GroupBy(Vec<Column>),
 }
 
@@ -164,6 +169,13 @@ pub struct QueryGraph {
     pub join_order: Vec<JoinRef>,
     /// Global predicates (not associated with a particular relation)
     pub global_predicates: Vec<ConditionExpression>,
+    /// Predicates that compare against query parameters in a way that cannot be looked up by key
+    /// (e.g., inside an OR). The reader of the query checks these on every lookup.
+    pub parameter_predicates: Vec<ConditionExpression>,
+    /// Arithmetic expressions that predicates compare, each of which is computed into a column
+    /// named after it. Those on the columns of a single relation belong to it and are computed
+    /// before it is joined with others; the rest are computed right after all joins.
+    pub arithmetic: Vec<ArithmeticColumn>,
 }
 
 impl QueryGraph {
@@ -174,6 +186,8 @@ impl QueryGraph {
             columns: Vec::new(),
             join_order: Vec::new(),
             global_predicates: Vec::new(),
+            parameter_predicates: Vec::new(),
+            arithmetic: Vec::new(),
         }
     }
 
@@ -215,6 +229,92 @@ impl Hash for QueryGraph {
         self.columns.hash(state);
         self.join_order.hash(state);
         self.global_predicates.hash(state);
+        self.parameter_predicates.hash(state);
+        self.arithmetic.hash(state);
+    }
+}
+
+/// Replaces the arithmetic expressions that `ce` compares with the columns that hold their values,
+/// and adds those to `arithmetic`.
+fn extract_arithmetic(ce: &mut ConditionExpression, arithmetic: &mut Vec<ArithmeticColumn>) {
+    match *ce {
+        ConditionExpression::LogicalOp(ref mut ct) => {
+            extract_arithmetic(&mut ct.left, arithmetic);
+            extract_arithmetic(&mut ct.right, arithmetic);
+        }
+        ConditionExpression::ComparisonOp(ref mut ct) => {
+            for side in vec![&mut ct.left, &mut ct.right] {
+                let ac = match **side {
+                    ConditionExpression::Arithmetic(ref ae) => arithmetic_column(ae),
+                    _ => continue,
+                };
+                **side = ConditionExpression::Base(ConditionBase::Field(Column {
+                    name: ac.name.clone(),
+                    alias: None,
+                    table: ac.table.clone(),
+                    function: None,
+                }));
+                if !arithmetic.contains(&ac) {
+                    arithmetic.push(ac);
+                }
+            }
+
+            // a comparison constrains the column on its left, so `1 < a + b` must become
+            // `a + b > 1`
+            if let ConditionExpression::Base(ConditionBase::Literal(_)) = *ct.left {
+                if let ConditionExpression::Base(ConditionBase::Field(_)) = *ct.right {
+                    *ct = reverse_comparison(ct);
+                }
+            }
+        }
+        ConditionExpression::NegationOp(ref mut inner)
+        | ConditionExpression::Bracketed(ref mut inner) => extract_arithmetic(inner, arithmetic),
+        ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => {}
+    }
+}
+
+/// Does what `extract_arithmetic` does for the condition of a filtered aggregation in `c`.
+fn extract_function_arithmetic(c: &Column, arithmetic: &mut Vec<ArithmeticColumn>) -> Column {
+    let mut c = c.clone();
+    if let Some(ref mut f) = c.function {
+        match **f {
+            FunctionExpression::Count(
+                FunctionArguments::Conditional(CaseWhenExpression {
+                    ref mut condition, ..
+                }),
+                _,
+            )
+            | FunctionExpression::Sum(
+                FunctionArguments::Conditional(CaseWhenExpression {
+                    ref mut condition, ..
+                }),
+                _,
+            ) => extract_arithmetic(condition, arithmetic),
+            _ => {}
+        }
+    }
+    c
+}
+
+/// The column that holds the value of `ae`. It belongs to the table whose columns `ae` uses if
+/// there is just one, and to no table otherwise.
+fn arithmetic_column(ae: &ArithmeticExpression) -> ArithmeticColumn {
+    let mut tables: Vec<&String> = [&ae.left, &ae.right]
+        .iter()
+        .filter_map(|b| match **b {
+            ArithmeticBase::Column(ref c) => c.table.as_ref(),
+            ArithmeticBase::Scalar(_) => None,
+        })
+        .collect();
+    tables.dedup();
+
+    ArithmeticColumn {
+        name: ae.to_string(),
+        table: match tables[..] {
+            [t] => Some(t.clone()),
+            _ => None,
+        },
+        expression: ae.clone(),
     }
 }
 
@@ -243,28 +343,105 @@ fn split_conjunctions(ces: Vec<ConditionExpression>) -> Vec<ConditionExpression>
     new_ces
 }
 
+/// Returns true if the predicate compares against a query parameter anywhere
+pub fn has_placeholder(ce: &ConditionExpression) -> bool {
+    match *ce {
+        ConditionExpression::LogicalOp(ref ct) | ConditionExpression::ComparisonOp(ref ct) => {
+            has_placeholder(&ct.left) || has_placeholder(&ct.right)
+        }
+        ConditionExpression::Bracketed(ref inner) => has_placeholder(inner),
+        ConditionExpression::Base(ConditionBase::Literal(Literal::Placeholder)) => true,
+        _ => false,
+    }
+}
+
+/// Returns true if the condition compares a parameter by order rather than by equality, in which
+/// case lookups into the query's view ask for ranges of keys
+pub fn has_range_placeholder(ce: &ConditionExpression) -> bool {
+    match *ce {
+        ConditionExpression::LogicalOp(ref ct) => {
+            has_range_placeholder(&ct.left) || has_range_placeholder(&ct.right)
+        }
+        ConditionExpression::ComparisonOp(ref ct) => match ct.operator {
+            Operator::Greater
+            | Operator::GreaterOrEqual
+            | Operator::Less
+            | Operator::LessOrEqual => has_placeholder(ce),
+            _ => false,
+        },
+        ConditionExpression::Bracketed(ref inner) => has_range_placeholder(inner),
+        _ => false,
+    }
+}
+
+/// Returns true if two tables can be joined on columns compared with the given operator
+fn is_join_operator(op: &Operator) -> bool {
+    match *op {
+        Operator::Equal
+        | Operator::In
+        | Operator::NotEqual
+        | Operator::Greater
+        | Operator::GreaterOrEqual
+        | Operator::Less
+        | Operator::LessOrEqual => true,
+        _ => false,
+    }
+}
+
+/// Swaps the sides of a comparison, adjusting the operator so that the comparison still holds for
+/// the same values (e.g., `a < b` becomes `b > a`)
+fn reverse_comparison(ct: &ConditionTree) -> ConditionTree {
+    let operator = match ct.operator {
+        Operator::Greater => Operator::Less,
+        Operator::GreaterOrEqual => Operator::LessOrEqual,
+        Operator::Less => Operator::Greater,
+        Operator::LessOrEqual => Operator::GreaterOrEqual,
+        ref op => op.clone(),
+    };
+    ConditionTree {
+        operator,
+        left: ct.right.clone(),
+        right: ct.left.clone(),
+    }
+}
+
+// Whether `ct` is an `IN` comparison with a subquery, which has been rewritten to compare with a
+// column of the view for the subquery by now.
+fn is_subquery_comparison(ct: &ConditionTree) -> bool {
+    match *ct.right {
+        ConditionExpression::Base(ConditionBase::Field(_)) => ct.operator == Operator::In,
+        _ => false,
+    }
+}
+
 // 1. Extract any predicates with placeholder parameters. We push these down to the edge
 //    nodes, since we cannot instantiate the parameters inside the data flow graph (except for
 //    non-materialized nodes).
 // 2. Extract local predicates
 // 3. Extract join predicates
-// 4. Collect remaining predicates as global predicates
+// 4. Extract (possibly negated) comparisons with subqueries
+// 5. Collect remaining predicates as global predicates
 fn classify_conditionals(
     ce: &ConditionExpression,
     tables: &[Table],
     local: &mut HashMap<String, Vec<ConditionExpression>>,
     join: &mut Vec<ConditionTree>,
+    subqueries: &mut Vec<(ConditionTree, bool)>,
     global: &mut Vec<ConditionExpression>,
     params: &mut Vec<Column>,
-) {
+) -> Result<(), String> {
     // Handling OR and AND expressions requires some care as there are some corner cases.
-    //    a) we don't support OR expressions with predicates with placeholder parameters,
-    //       because these expressions are meaningless in the Soup context.
+    //    a) OR expressions with predicates with placeholder parameters can't be turned into
+    //       lookup keys, since rows that match the other side must be returned for every key.
+    //       They are kept whole as global predicates, which the reader checks on each lookup.
+    //       Such readers are keyed on a bogokey, so every lookup scans the whole view, and the
+    //       view can't usefully be partial, since its single key covers all of its rows.
     //    b) we don't support OR expressions with join predicates because they are weird and
-    //       too hard.
+    //       too hard, and return an error for them.
     //    c) we don't support OR expressions between different tables (e.g table1.x = 1 OR
     //       table2.y= 42). this is a global predicate according to finkelstein algorithm
     //       and we don't support these yet.
+    //    d) we don't support OR expressions with subqueries, since these become joins too.
 
     match *ce {
         ConditionExpression::LogicalOp(ref ct) => {
@@ -277,6 +454,7 @@ fn classify_conditionals(
             //     remain a local predicate) or over several (so it must be a global predicate)
             let mut new_params = Vec::new();
             let mut new_join = Vec::new();
+            let mut new_subqueries = Vec::new();
             let mut new_local = HashMap::new();
             let mut new_global = Vec::new();
 
@@ -285,17 +463,19 @@ fn classify_conditionals(
                 tables,
                 &mut new_local,
                 &mut new_join,
+                &mut new_subqueries,
                 &mut new_global,
                 &mut new_params,
-            );
+            )?;
             classify_conditionals(
                 ct.right.as_ref(),
                 tables,
                 &mut new_local,
                 &mut new_join,
+                &mut new_subqueries,
                 &mut new_global,
                 &mut new_params,
-            );
+            )?;
 
             match ct.operator {
                 Operator::And => {
@@ -324,17 +504,24 @@ fn classify_conditionals(
                     // one side of the AND might be a global predicate, so we need to keep
                     // new_global around
                     global.extend(new_global);
+                    subqueries.extend(new_subqueries);
                 }
                 Operator::Or => {
-                    assert!(
-                        new_join.is_empty(),
-                        "can't handle OR expressions between join predicates"
-                    );
-                    assert!(
-                        new_params.is_empty(),
-                        "can't handle OR expressions between query parameter predicates"
-                    );
-                    if new_local.keys().len() == 1 && new_global.is_empty() {
+                    if !new_join.is_empty() {
+                        return Err(String::from(
+                            "OR expressions over join predicates are not supported",
+                        ));
+                    }
+                    if !new_subqueries.is_empty() {
+                        return Err(String::from(
+                            "OR expressions over IN subqueries are not supported",
+                        ));
+                    }
+                    if !new_params.is_empty() {
+                        // OR over query parameters => checked by the reader, not looked up by key
+                        new_params.clear();
+                        global.push(ce.clone())
+                    } else if new_local.keys().len() == 1 && new_global.is_empty() {
                         // OR over a single table => local predicate
                         let (t, ces) = new_local.into_iter().next().unwrap();
                         assert_eq!(ces.len(), 2, "should combine only 2 ConditionExpressions");
@@ -357,6 +544,9 @@ fn classify_conditionals(
             join.extend(new_join);
             params.extend(new_params);
         }
+        ConditionExpression::ComparisonOp(ref ct) if is_subquery_comparison(ct) => {
+            subqueries.push((ct.clone(), false));
+        }
         ConditionExpression::ComparisonOp(ref ct) => {
             // atomic selection predicate
             if let ConditionExpression::Base(ref l) = *ct.left.as_ref() {
@@ -375,20 +565,18 @@ fn classify_conditionals(
                                         .contains(&Table::from(rf.table.as_ref().unwrap().as_str()))
                                 {
                                     // both columns' tables appear in table list --> comma join
-                                    if ct.operator == Operator::Equal || ct.operator == Operator::In
+                                    if !is_join_operator(&ct.operator) {
+                                        unimplemented!(
+                                            "joins on {} are not supported",
+                                            ct.operator
+                                        );
+                                    }
+                                    if let Ordering::Less =
+                                        rf.table.as_ref().cmp(&lf.table.as_ref())
                                     {
-                                        // equi-join between two tables
-                                        let mut join_ct = ct.clone();
-                                        if let Ordering::Less =
-                                            rf.table.as_ref().cmp(&lf.table.as_ref())
-                                        {
-                                            use std::mem;
-                                            mem::swap(&mut join_ct.left, &mut join_ct.right);
-                                        }
-                                        join.push(join_ct);
+                                        join.push(reverse_comparison(ct));
                                     } else {
-                                        // non-equi-join?
-                                        unimplemented!();
+                                        join.push(ct.clone());
                                     }
                                 } else {
                                     // not a comma join, just an ordinary comparison with a
@@ -423,36 +611,39 @@ fn classify_conditionals(
                             }
                         }
                         ConditionBase::LiteralList(_) => (),
-                        ConditionBase::NestedSelect(_) => unimplemented!(),
+                        ConditionBase::NestedSelect(_) => {
+                            return Err(String::from("only IN subqueries are supported"));
+                        }
                     }
                 };
             };
         }
         ConditionExpression::Bracketed(ref inner) => {
-            let mut new_params = Vec::new();
-            let mut new_join = Vec::new();
-            let mut new_local = HashMap::new();
             classify_conditionals(
                 inner.as_ref(),
                 tables,
-                &mut new_local,
-                &mut new_join,
+                local,
+                join,
+                subqueries,
                 global,
-                &mut new_params,
-            );
-            join.extend(new_join);
-            params.extend(new_params);
+                params,
+            )?;
         }
         ConditionExpression::Base(_) => {
             // don't expect to see a base here: we ought to exit when classifying its
             // parent selection predicate
             panic!("encountered unexpected standalone base of condition expression");
         }
-        ConditionExpression::NegationOp(_) => {
-            panic!("negation should have been removed earlier");
-        }
+        ConditionExpression::NegationOp(ref inner) => match **inner {
+            // there is no operator for NOT IN, so negation removal leaves these be
+            ConditionExpression::ComparisonOp(ref ct) if is_subquery_comparison(ct) => {
+                subqueries.push((ct.clone(), true));
+            }
+            _ => panic!("negation should have been removed earlier"),
+        },
         ConditionExpression::Arithmetic(_) => unimplemented!(),
     }
+    Ok(())
 }
 
 #[allow(clippy::cognitive_complexity)]
@@ -542,62 +733,86 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                 let left_table;
                 let right_table;
 
-                let join_pred = match jc.constraint {
+                let join_preds = match jc.constraint {
                     JoinConstraint::On(ref cond) => {
                         use crate::controller::sql::query_utils::ReferredTables;
 
+                        // the values of arithmetic expressions must be known before the join
+                        let mut cond = cond.clone();
+                        let mut arithmetic = Vec::new();
+                        extract_arithmetic(&mut cond, &mut arithmetic);
+                        for ac in arithmetic {
+                            if ac.table.is_none() {
+                                return Err(format!(
+                                    "arithmetic expression {} in join condition must use the \
+                                     columns of a single table",
+                                    ac.name
+                                ));
+                            }
+                            if !qg.arithmetic.contains(&ac) {
+                                qg.arithmetic.push(ac);
+                            }
+                        }
+
                         // find all distinct tables mentioned in the condition
                         // conditions for now.
                         let mut tables_mentioned: Vec<String> =
                             cond.referred_tables().into_iter().map(|t| t.name).collect();
 
-                        match *cond {
-                            ConditionExpression::ComparisonOp(ref ct) => {
-                                if tables_mentioned.len() == 2 {
-                                    // tables can appear in any order in the join predicate, but
-                                    // we cannot just rely on that order, since it may lead us to
-                                    // flip LEFT JOINs by accident (yes, this happened)
-                                    if tables_mentioned[1] != table.name {
-                                        // tables are in the wrong order in join predicate, swap
-                                        tables_mentioned.swap(0, 1);
-                                        assert_eq!(tables_mentioned[1], table.name);
+                        if tables_mentioned.len() == 2 {
+                            // tables can appear in any order in the join predicate, but
+                            // we cannot just rely on that order, since it may lead us to
+                            // flip LEFT JOINs by accident (yes, this happened)
+                            if tables_mentioned[1] != table.name {
+                                // tables are in the wrong order in join predicate, swap
+                                tables_mentioned.swap(0, 1);
+                                assert_eq!(tables_mentioned[1], table.name);
+                            }
+                            left_table = tables_mentioned.remove(0);
+                            right_table = tables_mentioned.remove(0);
+                        } else if tables_mentioned.len() == 1 {
+                            // just one table mentioned --> this is a self-join
+                            left_table = tables_mentioned.remove(0);
+                            right_table = left_table.clone();
+                        } else {
+                            unreachable!("more than 2 tables mentioned in join condition!");
+                        };
+
+                        // each conjunct of the condition is a separate join predicate
+                        split_conjunctions(vec![cond.clone()])
+                            .into_iter()
+                            .map(|ce| match ce {
+                                ConditionExpression::ComparisonOp(ref ct) => {
+                                    if !is_join_operator(&ct.operator) {
+                                        unimplemented!(
+                                            "joins on {} are not supported",
+                                            ct.operator
+                                        );
                                     }
-                                    left_table = tables_mentioned.remove(0);
-                                    right_table = tables_mentioned.remove(0);
-                                } else if tables_mentioned.len() == 1 {
-                                    // just one table mentioned --> this is a self-join
-                                    left_table = tables_mentioned.remove(0);
-                                    right_table = left_table.clone();
-                                } else {
-                                    unreachable!("more than 2 tables mentioned in join condition!");
-                                };
-
-                                // the condition tree might specify tables in opposite order to
-                                // their join order in the query; if so, flip them
-                                // TODO(malte): this only deals with simple, flat join
-                                // conditions for now.
-                                let l = match *ct.left.as_ref() {
-                                    ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
-                                    _ => unimplemented!(),
-                                };
-                                let r = match *ct.right.as_ref() {
-                                    ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
-                                    _ => unimplemented!(),
-                                };
-                                if *l.table.as_ref().unwrap() == right_table
-                                    && *r.table.as_ref().unwrap() == left_table
-                                {
-                                    ConditionTree {
-                                        operator: ct.operator.clone(),
-                                        left: ct.right.clone(),
-                                        right: ct.left.clone(),
+
+                                    // the condition tree might specify tables in opposite order to
+                                    // their join order in the query; if so, flip them
+                                    // TODO(malte): this only deals with simple, flat join
+                                    // conditions for now.
+                                    let l = match *ct.left.as_ref() {
+                                        ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
+                                        _ => unimplemented!(),
+                                    };
+                                    let r = match *ct.right.as_ref() {
+                                        ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
+                                        _ => unimplemented!(),
+                                    };
+                                    if *l.table.as_ref().unwrap() == right_table
+                                        && *r.table.as_ref().unwrap() == left_table
+                                    {
+                                        reverse_comparison(ct)
+                                    } else {
+                                        ct.clone()
                                     }
-                                } else {
-                                    ct.clone()
                                 }
-                            }
-                            _ => panic!("join condition is not a comparison!"),
-                        }
+                                _ => panic!("join condition is not a comparison!"),
+                            })
+                            .collect()
                     }
                     JoinConstraint::Using(ref cols) => {
                         assert_eq!(cols.len(), 1);
@@ -606,11 +821,11 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                         left_table = prev_table.as_ref().unwrap().clone();
                         right_table = table.name.clone();
 
-                        ConditionTree {
+                        vec![ConditionTree {
                             operator: Operator::Equal,
                             left: wrapcol(&left_table, &col.name),
                             right: wrapcol(&right_table, &col.name),
-                        }
+                        }]
                     }
                 };
 
@@ -619,9 +834,9 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                     .edges
                     .entry((left_table.clone(), right_table.clone()))
                     .or_insert_with(|| match jc.operator {
-                        JoinOperator::LeftJoin => QueryGraphEdge::LeftJoin(vec![join_pred]),
+                        JoinOperator::LeftJoin => QueryGraphEdge::LeftJoin(join_preds),
                         JoinOperator::Join | JoinOperator::InnerJoin => {
-                            QueryGraphEdge::Join(vec![join_pred])
+                            QueryGraphEdge::Join(join_preds)
                         }
                         _ => unimplemented!(),
                     });
@@ -631,18 +846,23 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
     }
 
     if let Some(ref cond) = st.where_clause {
+        let mut cond = cond.clone();
+        extract_arithmetic(&mut cond, &mut qg.arithmetic);
+
         let mut local_predicates = HashMap::new();
+        let mut subquery_predicates = Vec::new();
         let mut global_predicates = Vec::new();
         let mut query_parameters = Vec::new();
         // Let's classify the predicates we have in the query
         classify_conditionals(
-            cond,
+            &cond,
             &st.tables,
             &mut local_predicates,
             &mut join_predicates,
+            &mut subquery_predicates,
             &mut global_predicates,
             &mut query_parameters,
-        );
+        )?;
 
         for (_, ces) in local_predicates.iter_mut() {
             *ces = split_conjunctions(ces.clone());
@@ -688,13 +908,51 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
             }
         }
 
+        // 2b. Add semi-joins (or, if negated, anti-joins) for comparisons with subqueries. The
+        //     views for the subqueries are not in the FROM part of the statement, so they are
+        //     added as relations here.
+        for (sp, negated) in subquery_predicates {
+            let (l, r) = match (sp.left.as_ref(), sp.right.as_ref()) {
+                (
+                    ConditionExpression::Base(ConditionBase::Field(l)),
+                    ConditionExpression::Base(ConditionBase::Field(r)),
+                ) => (l.table.clone().unwrap(), r.table.clone().unwrap()),
+                _ => return Err(String::from("left hand side of IN must be a field")),
+            };
+
+            qg.relations
+                .entry(r.clone())
+                .or_insert_with(|| new_node(r.clone(), Vec::new(), st));
+
+            let e = qg.edges.entry((l, r)).or_insert_with(|| {
+                if negated {
+                    QueryGraphEdge::AntiJoin(vec![])
+                } else {
+                    QueryGraphEdge::SemiJoin(vec![])
+                }
+            });
+            match (e, negated) {
+                (QueryGraphEdge::SemiJoin(preds), false)
+                | (QueryGraphEdge::AntiJoin(preds), true) => preds.push(sp),
+                _ => return Err(format!("subquery {:?} is also used in another way", sp)),
+            };
+        }
+
         // 3. Add any columns that are query parameters, and which therefore must appear in the leaf
         //    node for this query. Such columns will be carried all the way through the operators
         //    implementing the query (unlike in a traditional query plan, where the predicates on
         //    parameters might be evaluated sooner).
         for column in query_parameters.into_iter() {
             match column.table {
-                None => panic!("each parameter's column must have an associated table!"),
+                // the value of an expression over several tables is only known after the join,
+                // and only columns of a single table can be looked up by key
+                None => {
+                    return Err(format!(
+                        "query parameters can't be compared with expressions over several \
+                         tables, like {}",
+                        column.name
+                    ))
+                }
                 Some(ref table) => {
                     let rel = qg.relations.get_mut(table).unwrap();
                     if !rel.columns.contains(&column) {
@@ -708,8 +966,12 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
             }
         }
 
-        // 4. Add global predicates
+        // 4. Add global predicates, keeping those on query parameters apart, since they are
+        //    checked by the reader rather than by the data flow
+        let (parameter_predicates, global_predicates) =
+            global_predicates.into_iter().partition(has_placeholder);
         qg.global_predicates = global_predicates;
+        qg.parameter_predicates = parameter_predicates;
     }
 
     // Adds a computed column to the query graph if the given column has a function:
@@ -762,8 +1024,9 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                 }));
             }
             FieldDefinitionExpression::Col(ref c) => {
-                add_computed_column(&mut qg, c);
-                qg.columns.push(OutputColumn::Data(c.clone()));
+                let c = extract_function_arithmetic(c, &mut qg.arithmetic);
+                add_computed_column(&mut qg, &c);
+                qg.columns.push(OutputColumn::Data(c));
             }
         }
     }
@@ -813,7 +1076,9 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                         })
                         .collect::<Vec<_>>(),
                 ),
-                QueryGraphEdge::LeftJoin(ref jps) => qg.join_order.extend(
+                QueryGraphEdge::LeftJoin(ref jps)
+                | QueryGraphEdge::SemiJoin(ref jps)
+                | QueryGraphEdge::AntiJoin(ref jps) => qg.join_order.extend(
                     jps.iter()
                         .enumerate()
                         .map(|(idx, _)| JoinRef {
@@ -117,7 +117,9 @@ impl Signature for QueryGraph {
         for e in self.edges.values() {
             match *e {
                 QueryGraphEdge::Join(ref join_predicates)
-                | QueryGraphEdge::LeftJoin(ref join_predicates) => {
+                | QueryGraphEdge::LeftJoin(ref join_predicates)
+                | QueryGraphEdge::SemiJoin(ref join_predicates)
+                | QueryGraphEdge::AntiJoin(ref join_predicates) => {
                     for p in join_predicates {
                         for c in &p.contained_columns() {
                             attrs_vec.push(c);
@@ -134,8 +136,12 @@ impl Signature for QueryGraph {
             }
         }
 
-        // Global predicates are part of the attributes too
-        for p in &self.global_predicates {
+        // Global predicates are part of the attributes too, as are those on query parameters
+        for p in self
+            .global_predicates
+            .iter()
+            .chain(self.parameter_predicates.iter())
+        {
             match *p {
                 ComparisonOp(ref ct) | LogicalOp(ref ct) => {
                     for c in &ct.contained_columns() {
@@ -137,10 +137,7 @@ impl Multiverse for SqlIncorporator {
             // represented as a query graph. This will change for more complex policies eg. column
             // replacement and aggregation permission.
 
-            let qg = match to_query_graph(st) {
-                Ok(qg) => qg,
-                Err(e) => panic!(e),
-            };
+            let qg = to_query_graph(st)?;
 
             let e = row_policies_qg
                 .entry(policy.table().clone())
@@ -291,7 +291,7 @@ async fn broad_recursing_upquery() {
             Join::new(x, y, JoinType::Left, vec![L(0), B(1, 0), L(2)]),
         );
         // reader, sharded by the lookup column, which is the third column on x
-        mig.maintain("reader".to_string(), join, &[2]);
+        mig.maintain("reader".to_string(), join, &[2], &[], &[], false);
     })
     .await;
 
@@ -673,6 +673,1042 @@ async fn it_works_deletion() {
     );
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_predicate_deletes_and_updates() {
+    use noria::Modification;
+
+    let mut g = start_simple("it_works_with_predicate_deletes_and_updates").await;
+    let sql = "
+        CREATE TABLE Session (id int, uid int, active int, PRIMARY KEY(id));
+        QUERY SessionsByUser: SELECT id, active FROM Session WHERE uid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Session").await.unwrap();
+    let mut getter = g.view("SessionsByUser").await.unwrap();
+
+    for (id, uid) in &[(1, 1), (2, 1), (3, 1), (4, 2)] {
+        mutator
+            .insert(vec![(*id).into(), (*uid).into(), 1.into()])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    // deactivate all of user 1's sessions, and nobody else's
+    mutator
+        .update_where(vec![(1, 1.into())], vec![(2, Modification::Set(0.into()))])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let mut res: Vec<_> = getter.lookup(&[1.into()], true).await.unwrap().into();
+    res.sort();
+    assert_eq!(
+        res,
+        vec![
+            vec![1.into(), 0.into(), 1.into()],
+            vec![2.into(), 0.into(), 1.into()],
+            vec![3.into(), 0.into(), 1.into()],
+        ]
+    );
+    assert_eq!(
+        getter.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![4.into(), 1.into(), 2.into()]]
+    );
+
+    // filters on the key only touch that one row
+    mutator
+        .delete_where(vec![(0, 2.into()), (1, 1.into())])
+        .await
+        .unwrap();
+    sleep().await;
+    assert_eq!(getter.lookup(&[1.into()], true).await.unwrap().len(), 2);
+
+    mutator.delete_where(vec![(1, 1.into())]).await.unwrap();
+    sleep().await;
+    assert!(getter.lookup(&[1.into()], true).await.unwrap().is_empty());
+    assert_eq!(
+        getter.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![4.into(), 1.into(), 2.into()]]
+    );
+
+    // filters on columns the table does not have are rejected
+    assert!(mutator.delete_where(vec![(3, 1.into())]).await.is_err());
+
+    // as are empty filters, and filters that no index covers
+    assert!(matches!(
+        mutator.delete_where(vec![]).await,
+        Err(noria::error::TableError::EmptyFilter)
+    ));
+    assert!(matches!(
+        mutator.delete_where(vec![(2, 1.into())]).await,
+        Err(noria::error::TableError::NoIndexForFilter(_))
+    ));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_reports_write_outcomes() {
+    use noria::{Modification, TableOperation, WriteOutcome};
+
+    let mut g = start_simple("it_reports_write_outcomes").await;
+    let sql = "
+        CREATE TABLE Session (id int, uid int, active int, PRIMARY KEY(id));
+        QUERY SessionsByUser: SELECT id, active FROM Session WHERE uid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Session").await.unwrap();
+    let mut getter = g.view("SessionsByUser").await.unwrap();
+    let deactivate = || vec![(2, Modification::Set(0.into()))];
+
+    let (outcome, _) = mutator
+        .insert_or_update_acked(vec![1.into(), 1.into(), 1.into()], deactivate())
+        .await
+        .unwrap();
+    assert_eq!(outcome, WriteOutcome::Inserted);
+    let (outcome, _) = mutator
+        .update_acked(vec![2.into()], deactivate())
+        .await
+        .unwrap();
+    assert_eq!(outcome, WriteOutcome::NotFound);
+    let (outcome, _) = mutator
+        .update_acked(vec![1.into()], deactivate())
+        .await
+        .unwrap();
+    assert_eq!(outcome, WriteOutcome::Updated);
+    let (outcome, token) = mutator
+        .update_acked(vec![1.into()], deactivate())
+        .await
+        .unwrap();
+    assert_eq!(outcome, WriteOutcome::NoOp);
+    assert_eq!(
+        getter.lookup_after(&[1.into()], &token).await.unwrap(),
+        vec![vec![1.into(), 0.into(), 1.into()]]
+    );
+
+    // outcomes line up with the operations, even when they go to different shards
+    let (outcomes, _) = mutator
+        .perform_all_acked(vec![
+            TableOperation::from(vec![2.into(), 1.into(), 1.into()]),
+            TableOperation::from(vec![1.into(), 1.into(), 1.into()]),
+            TableOperation::DeleteWhere {
+                filter: vec![(1, 2.into())],
+            },
+            TableOperation::DeleteWhere {
+                filter: vec![(1, 1.into())],
+            },
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        vec![
+            WriteOutcome::Inserted,
+            WriteOutcome::NoOp,
+            WriteOutcome::NotFound,
+            WriteOutcome::Deleted,
+        ]
+    );
+
+    let (outcome, _) = mutator.delete_acked(vec![2.into()]).await.unwrap();
+    assert_eq!(outcome, WriteOutcome::NotFound);
+
+    // writes that did not ask for outcomes are unaffected
+    mutator
+        .insert(vec![3.into(), 1.into(), 1.into()])
+        .await
+        .unwrap();
+    let (outcome, _) = mutator.delete_acked(vec![3.into()]).await.unwrap();
+    assert_eq!(outcome, WriteOutcome::Deleted);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_filtered_lookups() {
+    use noria::filter::{FilterCondition, Operator, Value};
+
+    let mut g = start_simple("it_works_with_filtered_lookups").await;
+    let sql = "
+        CREATE TABLE Ticket (id int, project int, priority int, PRIMARY KEY(id));
+        QUERY TicketsByProject: SELECT id, priority FROM Ticket WHERE project = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Ticket").await.unwrap();
+    let mut getter = g.view("TicketsByProject").await.unwrap();
+    for (id, project, priority) in &[(1, 1, 1), (2, 1, 4), (3, 1, 3), (4, 2, 5)] {
+        mutator
+            .insert(vec![(*id).into(), (*project).into(), (*priority).into()])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let urgent = FilterCondition::Comparison(Operator::GreaterOrEqual, Value::Constant(3.into()));
+    let mut rows: Vec<_> = getter
+        .lookup_filtered(&[1.into()], &[(1, urgent.clone())], true)
+        .await
+        .unwrap()
+        .into();
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            vec![2.into(), 4.into(), 1.into()],
+            vec![3.into(), 3.into(), 1.into()],
+        ]
+    );
+
+    // conditions are combined, and may compare against other columns
+    let same = FilterCondition::Comparison(Operator::Equal, Value::Column(1));
+    let rows = getter
+        .lookup_filtered(&[1.into()], &[(1, urgent), (0, same)], true)
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![vec![3.into(), 3.into(), 1.into()]]);
+
+    let rows = getter
+        .lookup_filtered(
+            &[2.into()],
+            &[(0, FilterCondition::In(vec![1.into(), 2.into()]))],
+            true,
+        )
+        .await
+        .unwrap();
+    assert!(rows.is_empty());
+
+    // filters that cannot be evaluated are rejected before they are sent
+    let bad = FilterCondition::Comparison(Operator::Like, Value::Constant("%".into()));
+    match getter.lookup_filtered(&[1.into()], &[(1, bad)], true).await {
+        Err(noria::error::ViewError::InvalidFilter(_)) => {}
+        r => panic!("expected an invalid filter error, got {:?}", r),
+    }
+    let eq = FilterCondition::Comparison(Operator::Equal, Value::Constant(1.into()));
+    match getter.lookup_filtered(&[1.into()], &[(3, eq)], true).await {
+        Err(noria::error::ViewError::InvalidFilter(_)) => {}
+        r => panic!("expected an invalid filter error, got {:?}", r),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_json_filtered_lookups() {
+    use noria::filter::{FilterCondition, Operator, Value};
+
+    let mut g = start_simple("it_works_with_json_filtered_lookups").await;
+    let sql = "
+        CREATE TABLE Ticket (id int, project int, metadata text, PRIMARY KEY(id));
+        QUERY TicketsByProject: SELECT id, metadata FROM Ticket WHERE project = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Ticket").await.unwrap();
+    let mut getter = g.view("TicketsByProject").await.unwrap();
+    let docs = [
+        r#"{"owner": {"name": "alice"}, "estimate": 3}"#,
+        r#"{"owner": {"name": "bob"}, "estimate": 8}"#,
+        r#"{"owner": null}"#,
+    ];
+    for (id, doc) in docs.iter().enumerate() {
+        let doc = DataType::json(doc).unwrap();
+        mutator
+            .insert(vec![id.into(), 1.into(), doc])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let owner = FilterCondition::Json(
+        "$.owner.name".parse().unwrap(),
+        Box::new(FilterCondition::Comparison(
+            Operator::Equal,
+            Value::Constant("bob".into()),
+        )),
+    );
+    let rows = getter
+        .lookup_filtered(&[1.into()], &[(1, owner)], true)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0], 1.into());
+    assert_eq!(
+        rows[0][1].json_extract_unquoted(&"$.estimate".parse().unwrap()),
+        8.into()
+    );
+
+    let small = FilterCondition::Json(
+        "$.estimate".parse().unwrap(),
+        Box::new(FilterCondition::In(vec![1.into(), 2.into(), 3.into()])),
+    );
+    let rows = getter
+        .lookup_filtered(&[1.into()], &[(1, small)], true)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0], 0.into());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_bool_double_and_blob_columns() {
+    let mut g = start_simple("it_works_with_bool_double_and_blob_columns").await;
+    let sql = "
+        CREATE TABLE Reading (id int, sensor int, ok bool, reading double, raw blob, PRIMARY KEY(id));
+        QUERY GoodReadings: SELECT id, raw FROM Reading WHERE ok = ? AND reading > 1.5;
+        QUERY SensorTotal: SELECT sensor, SUM(reading) AS total FROM Reading \
+                           WHERE sensor = ? GROUP BY sensor;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Reading").await.unwrap();
+    let mut good = g.view("GoodReadings").await.unwrap();
+    let mut totals = g.view("SensorTotal").await.unwrap();
+    let rows = vec![
+        (1, 1, true, 1.25, vec![0u8, 1]),
+        (2, 1, true, 2.5, vec![2, 3]),
+        (3, 1, false, 4.0, vec![]),
+        (4, 2, true, 0.5, vec![255]),
+    ];
+    for (id, sensor, ok, reading, raw) in rows {
+        mutator
+            .insert(vec![
+                id.into(),
+                sensor.into(),
+                ok.into(),
+                DataType::Double(reading),
+                raw.into(),
+            ])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let expected: Vec<Vec<DataType>> = vec![vec![2.into(), vec![2u8, 3].into(), true.into()]];
+    assert_eq!(good.lookup(&[true.into()], true).await.unwrap(), expected);
+    // booleans are interchangeable with 0 and 1
+    assert_eq!(good.lookup(&[1.into()], true).await.unwrap(), expected);
+    assert!(good.lookup(&[false.into()], true).await.unwrap().is_empty());
+
+    let total = totals
+        .lookup_first(&[1.into()], true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total[1], DataType::Double(7.75));
+
+    mutator.delete(vec![3.into()]).await.unwrap();
+    sleep().await;
+
+    let total = totals
+        .lookup_first(&[1.into()], true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total[1], DataType::Double(3.75));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_decimal_columns() {
+    use noria::BigDecimal;
+    use std::str::FromStr;
+    let d = |s: &str| DataType::from(BigDecimal::from_str(s).unwrap());
+
+    let mut g = start_simple("it_works_with_decimal_columns").await;
+    let sql = "
+        CREATE TABLE Item (id int, cart int, price decimal(12,2), PRIMARY KEY(id));
+        QUERY CartTotal: SELECT cart, SUM(price) AS total FROM Item WHERE cart = ? GROUP BY cart;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut items = g.table("Item").await.unwrap();
+    let mut totals = g.view("CartTotal").await.unwrap();
+    items
+        .insert(vec![1.into(), 1.into(), d("0.10")])
+        .await
+        .unwrap();
+    items
+        .insert(vec![2.into(), 1.into(), d("0.20")])
+        .await
+        .unwrap();
+    // values are rounded to the scale of the column
+    items
+        .insert(vec![3.into(), 1.into(), d("19.995")])
+        .await
+        .unwrap();
+    items
+        .insert(vec![4.into(), 1.into(), 5.into()])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let total = totals
+        .lookup_first(&[1.into()], true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total[1], d("25.30"));
+    assert_eq!(total[1].to_string(), "25.30");
+
+    items.delete(vec![3.into()]).await.unwrap();
+    sleep().await;
+
+    let total = totals
+        .lookup_first(&[1.into()], true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total[1].to_string(), "5.30");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_filters_decimal_columns_with_literals() {
+    use noria::BigDecimal;
+    use std::str::FromStr;
+    let d = |s: &str| DataType::from(BigDecimal::from_str(s).unwrap());
+
+    let mut g = start_simple("it_filters_decimal_columns_with_literals").await;
+    let sql = "
+        CREATE TABLE Item (id int, price decimal(12,2), PRIMARY KEY(id));
+        QUERY Exactly: SELECT id FROM Item WHERE price = 9.99;
+        QUERY AtMost: SELECT id FROM Item WHERE price <= 10;
+        QUERY Above: SELECT id FROM Item WHERE price > 9.99;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut items = g.table("Item").await.unwrap();
+    items
+        .perform_all(vec![
+            vec![1.into(), d("9.98")],
+            vec![2.into(), d("9.99")],
+            vec![3.into(), d("10.00")],
+            vec![4.into(), d("10.01")],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    // literals are compared with the column's values, whatever type they parse as
+    for (query, expected) in vec![
+        ("Exactly", vec![2]),
+        ("AtMost", vec![1, 2, 3]),
+        ("Above", vec![3, 4]),
+    ] {
+        let mut view = g.view(query).await.unwrap();
+        let rows: Vec<Vec<DataType>> = view.lookup(&[0.into()], true).await.unwrap().into();
+        let mut ids: Vec<_> = rows.into_iter().map(|r| r[0].clone()).collect();
+        ids.sort();
+        let expected: Vec<DataType> = expected.into_iter().map(DataType::from).collect();
+        assert_eq!(ids, expected, "{}", query);
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_coerces_written_values_to_column_types() {
+    use noria::error::TableError;
+    use noria::Modification;
+
+    let mut g = start_simple("it_coerces_written_values_to_column_types").await;
+    let sql = "
+        CREATE TABLE Article (id int, author varchar(255) NOT NULL, score double, \
+                              PRIMARY KEY(id));
+        QUERY ArticleScore: SELECT id, author, score FROM Article WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut articles = g.table("Article").await.unwrap();
+    let mut scores = g.view("ArticleScore").await.unwrap();
+    // numeric strings are parsed, and numbers are written out for text columns
+    articles
+        .insert(vec!["1".into(), 7.into(), "2.5".into()])
+        .await
+        .unwrap();
+
+    match articles
+        .insert(vec!["two".into(), "alice".into(), 1.into()])
+        .await
+    {
+        Err(TableError::TypeMismatch { column, got, .. }) => {
+            assert_eq!(column, "id");
+            assert_eq!(got, "two".into());
+        }
+        r => panic!("expected a type mismatch, got {:?}", r),
+    }
+    match articles
+        .insert(vec![2.into(), DataType::None, 1.into()])
+        .await
+    {
+        Err(TableError::NullValue(column)) => assert_eq!(column, "author"),
+        r => panic!("expected a NOT NULL violation, got {:?}", r),
+    }
+    match articles
+        .update(vec![1.into()], vec![(2, Modification::Set("lots".into()))])
+        .await
+    {
+        Err(TableError::TypeMismatch { column, .. }) => assert_eq!(column, "score"),
+        r => panic!("expected a type mismatch, got {:?}", r),
+    }
+    match articles
+        .insert(vec![(1i64 << 40).into(), "alice".into(), 1.into()])
+        .await
+    {
+        Err(TableError::TypeMismatch { column, .. }) => assert_eq!(column, "id"),
+        r => panic!("expected a type mismatch, got {:?}", r),
+    }
+
+    // keys and filter values are converted to their columns' types too
+    articles
+        .insert(vec![3.into(), "bob".into(), 1.into()])
+        .await
+        .unwrap();
+    articles
+        .insert(vec![4.into(), "carol".into(), 1.into()])
+        .await
+        .unwrap();
+    articles
+        .update(vec!["1".into()], vec![(2, Modification::Set(4.into()))])
+        .await
+        .unwrap();
+    articles.delete(vec![DataType::Double(3.0)]).await.unwrap();
+    articles.delete_where(vec![(0, "4".into())]).await.unwrap();
+    sleep().await;
+
+    assert_eq!(
+        scores.lookup(&[1.into()], true).await.unwrap(),
+        vec![vec![1.into(), "7".into(), 4.into()]]
+    );
+    assert!(scores.lookup(&[2.into()], true).await.unwrap().is_empty());
+    assert!(scores.lookup(&[3.into()], true).await.unwrap().is_empty());
+    assert!(scores.lookup(&[4.into()], true).await.unwrap().is_empty());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_follows_sql_null_semantics() {
+    let mut g = start_simple("it_follows_sql_null_semantics").await;
+    let sql = "
+        CREATE TABLE Task (id int, owner int, reviewer int, PRIMARY KEY(id));
+        CREATE TABLE Reviewer (rid int, name varchar(40));
+        QUERY Unowned: SELECT id FROM Task WHERE owner IS NULL AND reviewer = ?;
+        QUERY Reviewed: SELECT Task.id, Reviewer.name FROM Task \
+                        JOIN Reviewer ON (Task.reviewer = Reviewer.rid) WHERE Task.owner = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut tasks = g.table("Task").await.unwrap();
+    let mut reviewers = g.table("Reviewer").await.unwrap();
+    let mut unowned = g.view("Unowned").await.unwrap();
+    let mut reviewed = g.view("Reviewed").await.unwrap();
+    tasks
+        .perform_all(vec![
+            vec![1.into(), 1.into(), 2.into()],
+            vec![2.into(), 1.into(), DataType::None],
+            vec![3.into(), DataType::None, DataType::None],
+            vec![4.into(), DataType::None, 2.into()],
+        ])
+        .await
+        .unwrap();
+    reviewers
+        .perform_all(vec![
+            vec![2.into(), "dave".into()],
+            vec![DataType::None, "nobody".into()],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    assert_eq!(
+        unowned.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![DataType::from(4), 2.into()]]
+    );
+    // a NULL reviewer does not join with the reviewer whose id is NULL
+    assert_eq!(
+        reviewed.lookup(&[1.into()], true).await.unwrap(),
+        vec![vec![1.into(), "dave".into(), 1.into()]]
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_disjunctions() {
+    let mut g = start_simple("it_works_with_disjunctions").await;
+    let sql = "
+        CREATE TABLE Issue (id int, status varchar(10), owner int, priority int, PRIMARY KEY(id));
+        QUERY Visible: SELECT id FROM Issue WHERE status = 'open' OR owner = ?;
+        QUERY Urgent: SELECT id FROM Issue \
+                      WHERE priority > 2 OR (status = 'open' AND owner IS NULL);
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Issue").await.unwrap();
+    let mut visible = g.view("Visible").await.unwrap();
+    let mut urgent = g.view("Urgent").await.unwrap();
+    mutator
+        .perform_all(vec![
+            vec![1.into(), "open".into(), 1.into(), 1.into()],
+            vec![2.into(), "closed".into(), 1.into(), 3.into()],
+            vec![3.into(), "closed".into(), 2.into(), 1.into()],
+            vec![4.into(), "open".into(), DataType::None, 1.into()],
+            vec![5.into(), "closed".into(), DataType::None, 1.into()],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    // the parameter cannot be looked up by key, so the columns it is checked against are returned
+    // after the selected ones, followed by the bogokey the reader is keyed on instead
+    let mut rows: Vec<_> = visible.lookup(&[1.into()], true).await.unwrap().into();
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            vec![1.into(), 1.into(), "open".into(), 0.into()],
+            vec![2.into(), 1.into(), "closed".into(), 0.into()],
+            vec![4.into(), DataType::None, "open".into(), 0.into()],
+        ]
+    );
+    let mut rows: Vec<_> = visible.lookup(&[3.into()], true).await.unwrap().into();
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            vec![1.into(), 1.into(), "open".into(), 0.into()],
+            vec![4.into(), DataType::None, "open".into(), 0.into()],
+        ]
+    );
+
+    // issues that match both sides are only returned once
+    mutator
+        .perform_all(vec![
+            vec![6.into(), "open".into(), 3.into(), 3.into()],
+            vec![7.into(), "open".into(), DataType::None, 3.into()],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let mut rows: Vec<_> = visible.lookup(&[3.into()], true).await.unwrap().into();
+    rows.sort();
+    let ids: Vec<_> = rows.into_iter().map(|r| r[0].clone()).collect();
+    assert_eq!(ids, vec![1.into(), 4.into(), 6.into(), 7.into()]);
+    let mut rows: Vec<_> = urgent.lookup(&[0.into()], true).await.unwrap().into();
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            vec![2.into(), 0.into()],
+            vec![4.into(), 0.into()],
+            vec![6.into(), 0.into()],
+            vec![7.into(), 0.into()],
+        ]
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_non_equi_joins() {
+    let mut g = start_simple("it_works_with_non_equi_joins").await;
+    let sql = "
+        CREATE TABLE Reading (id int, value int, PRIMARY KEY(id));
+        CREATE TABLE Band (name varchar(10), lo int, hi int, PRIMARY KEY(name));
+        QUERY Above: SELECT Reading.id, Band.name FROM Reading, Band \
+                     WHERE Reading.value > Band.hi AND Band.name = ?;
+        QUERY InBand: SELECT Reading.id, Band.name FROM Reading \
+                      JOIN Band ON Reading.value >= Band.lo AND Reading.value < Band.hi \
+                      WHERE Band.name = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut readings = g.table("Reading").await.unwrap();
+    let mut bands = g.table("Band").await.unwrap();
+    let mut above = g.view("Above").await.unwrap();
+    let mut in_band = g.view("InBand").await.unwrap();
+    bands
+        .perform_all(vec![
+            vec!["low".into(), 0.into(), 10.into()],
+            vec!["high".into(), 10.into(), 100.into()],
+        ])
+        .await
+        .unwrap();
+    readings
+        .perform_all(vec![
+            vec![1.into(), 5.into()],
+            vec![2.into(), 10.into()],
+            vec![3.into(), 50.into()],
+            vec![4.into(), DataType::None],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let lookup = |rows: Vec<Vec<DataType>>| {
+        let mut ids: Vec<_> = rows.into_iter().map(|r| r[0].clone()).collect();
+        ids.sort();
+        ids
+    };
+    let low = || vec!["low".into()];
+    let high = || vec!["high".into()];
+
+    assert_eq!(
+        lookup(above.lookup(&low(), true).await.unwrap().into()),
+        vec![3.into()]
+    );
+    assert!(above.lookup(&high(), true).await.unwrap().is_empty());
+    assert_eq!(
+        lookup(in_band.lookup(&low(), true).await.unwrap().into()),
+        vec![1.into()]
+    );
+    assert_eq!(
+        lookup(in_band.lookup(&high(), true).await.unwrap().into()),
+        vec![2.into(), 3.into()]
+    );
+
+    // new rows on either side are joined with the matching range of the other side
+    readings.insert(vec![5.into(), 150.into()]).await.unwrap();
+    bands
+        .insert(vec!["all".into(), 0.into(), 1000.into()])
+        .await
+        .unwrap();
+    readings.delete(vec![3.into()]).await.unwrap();
+    sleep().await;
+
+    assert_eq!(
+        lookup(above.lookup(&low(), true).await.unwrap().into()),
+        vec![5.into()]
+    );
+    assert_eq!(
+        lookup(above.lookup(&high(), true).await.unwrap().into()),
+        vec![5.into()]
+    );
+    assert_eq!(
+        lookup(in_band.lookup(&high(), true).await.unwrap().into()),
+        vec![2.into()]
+    );
+    assert_eq!(
+        lookup(in_band.lookup(&["all".into()], true).await.unwrap().into()),
+        vec![1.into(), 2.into(), 5.into()]
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_returns_rows_in_query_order() {
+    use futures_util::stream::TryStreamExt;
+
+    let mut g = start_simple("it_returns_rows_in_query_order").await;
+    let sql = "
+        CREATE TABLE Post (id int, author int, score int, PRIMARY KEY(id));
+        QUERY TopPosts: SELECT id, score FROM Post WHERE author = ? ORDER BY score DESC;
+        QUERY WorstPosts: SELECT id, score FROM Post WHERE author = ? ORDER BY score ASC;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Post").await.unwrap();
+    let mut top = g.view("TopPosts").await.unwrap();
+    let mut worst = g.view("WorstPosts").await.unwrap();
+    for (id, score) in &[(1, 20), (2, 50), (3, 10), (4, 40), (5, 30)] {
+        mutator
+            .insert(vec![(*id).into(), 1.into(), (*score).into()])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let expected: Vec<Vec<DataType>> = [(2, 50), (4, 40), (5, 30), (1, 20), (3, 10)]
+        .iter()
+        .map(|&(id, score)| vec![id.into(), score.into(), 1.into()])
+        .collect();
+    assert_eq!(top.lookup(&[1.into()], true).await.unwrap(), expected);
+    let reversed: Vec<_> = expected.iter().rev().cloned().collect();
+    assert_eq!(worst.lookup(&[1.into()], true).await.unwrap(), reversed);
+    assert_eq!(
+        top.lookup_first(&[1.into()], true).await.unwrap().unwrap(),
+        expected[0]
+    );
+
+    // pages also follow the order of the query
+    let pages: Vec<_> = top
+        .lookup_paged(&[1.into()], 2)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(pages.len(), 3);
+    let rows: Vec<Vec<DataType>> = pages
+        .into_iter()
+        .flat_map(Into::<Vec<Vec<DataType>>>::into)
+        .collect();
+    assert_eq!(rows, expected);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_limit_offset() {
+    let mut g = start_simple("it_works_with_limit_offset").await;
+    let sql = "
+        CREATE TABLE Post (id int, author int, score int, PRIMARY KEY(id));
+        QUERY RunnersUp: SELECT id, score FROM Post WHERE author = ? \
+                         ORDER BY score DESC LIMIT 2 OFFSET 1;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Post").await.unwrap();
+    let mut q = g.view("RunnersUp").await.unwrap();
+    for (id, score) in &[(1, 20), (2, 50), (3, 10), (4, 40)] {
+        mutator
+            .insert(vec![(*id).into(), 1.into(), (*score).into()])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let rows = |ids: &[(i32, i32)]| -> Vec<Vec<DataType>> {
+        ids.iter()
+            .map(|&(id, score)| vec![id.into(), score.into(), 1.into()])
+            .collect()
+    };
+    assert_eq!(
+        q.lookup(&[1.into()], true).await.unwrap(),
+        rows(&[(4, 40), (1, 20)])
+    );
+
+    // a new top post pushes the old one into the window
+    mutator
+        .insert(vec![5.into(), 1.into(), 60.into()])
+        .await
+        .unwrap();
+    sleep().await;
+    assert_eq!(
+        q.lookup(&[1.into()], true).await.unwrap(),
+        rows(&[(2, 50), (4, 40)])
+    );
+
+    // while posts below the window do not affect it
+    mutator.delete(vec![3.into()]).await.unwrap();
+    sleep().await;
+    assert_eq!(
+        q.lookup(&[1.into()], true).await.unwrap(),
+        rows(&[(2, 50), (4, 40)])
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_in_subqueries() {
+    let mut g = start_simple("it_works_with_in_subqueries").await;
+    let sql = "
+        CREATE TABLE Story (id int, title varchar(255), PRIMARY KEY(id));
+        CREATE TABLE Tagging (id int, story_id int NOT NULL, tag varchar(255), PRIMARY KEY(id));
+        QUERY Tagged: SELECT id, title FROM Story \
+                      WHERE id IN (SELECT story_id FROM Tagging WHERE tag = ?);
+        QUERY Untagged: SELECT id, title FROM Story \
+                        WHERE id NOT IN (SELECT story_id FROM Tagging);
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut story = g.table("Story").await.unwrap();
+    let mut tagging = g.table("Tagging").await.unwrap();
+    let mut tagged = g.view("Tagged").await.unwrap();
+    let mut untagged = g.view("Untagged").await.unwrap();
+    story
+        .perform_all(vec![
+            vec![1.into(), "a".into()],
+            vec![2.into(), "b".into()],
+            vec![3.into(), "c".into()],
+        ])
+        .await
+        .unwrap();
+    tagging
+        .perform_all(vec![
+            vec![1.into(), 1.into(), "rust".into()],
+            vec![2.into(), 1.into(), "rust".into()],
+            vec![3.into(), 2.into(), "rust".into()],
+            vec![4.into(), 2.into(), "go".into()],
+        ])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let ids = |rows: Vec<Vec<DataType>>| -> Vec<DataType> {
+        let mut ids: Vec<_> = rows.into_iter().map(|r| r[0].clone()).collect();
+        ids.sort();
+        ids
+    };
+
+    // a story that is tagged twice still shows up once
+    let rows = tagged.lookup(&["rust".into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into(), 2.into()]);
+    let rows = tagged.lookup(&["go".into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![2.into()]);
+    let rows = untagged.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![3.into()]);
+
+    // and keeps showing up until its last tag goes away
+    tagging.delete(vec![1.into()]).await.unwrap();
+    sleep().await;
+    let rows = tagged.lookup(&["rust".into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into(), 2.into()]);
+
+    tagging.delete(vec![2.into()]).await.unwrap();
+    sleep().await;
+    let rows = tagged.lookup(&["rust".into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![2.into()]);
+    let rows = untagged.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into(), 3.into()]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_arithmetic_predicates() {
+    let mut g = start_simple("it_works_with_arithmetic_predicates").await;
+    let sql = "
+        CREATE TABLE Post (id int, author int, upvotes int, downvotes int, PRIMARY KEY(id));
+        CREATE TABLE Author (id int, karma int, PRIMARY KEY(id));
+        QUERY Popular: SELECT id FROM Post WHERE upvotes - downvotes > 10 AND author = ?;
+        QUERY Boosted: SELECT Post.id FROM Post JOIN Author ON (Post.author = Author.id) \
+                       WHERE Post.upvotes + Author.karma > 20;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut post = g.table("Post").await.unwrap();
+    let mut author = g.table("Author").await.unwrap();
+    let mut popular = g.view("Popular").await.unwrap();
+    let mut boosted = g.view("Boosted").await.unwrap();
+    post.perform_all(vec![
+        vec![1.into(), 1.into(), 20.into(), 5.into()],
+        vec![2.into(), 1.into(), 5.into(), 0.into()],
+        vec![3.into(), 2.into(), 30.into(), 1.into()],
+    ])
+    .await
+    .unwrap();
+    author
+        .perform_all(vec![vec![1.into(), 10.into()], vec![2.into(), 0.into()]])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let ids = |rows: Vec<Vec<DataType>>| -> Vec<DataType> {
+        let mut ids: Vec<_> = rows.into_iter().map(|r| r[0].clone()).collect();
+        ids.sort();
+        ids
+    };
+
+    let rows = popular.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into()]);
+    let rows = popular.lookup(&[2.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![3.into()]);
+    let rows = boosted.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into(), 3.into()]);
+
+    // the computed values follow updates to the columns they are computed from
+    post.delete(vec![1.into()]).await.unwrap();
+    post.insert(vec![1.into(), 1.into(), 20.into(), 15.into()])
+        .await
+        .unwrap();
+    author.delete(vec![1.into()]).await.unwrap();
+    author.insert(vec![1.into(), 20.into()]).await.unwrap();
+    sleep().await;
+
+    let rows = popular.lookup(&[1.into()], true).await.unwrap();
+    assert!(rows.is_empty());
+    let rows = boosted.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(ids(rows.into()), vec![1.into(), 2.into(), 3.into()]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_prepared_queries() {
+    let mut g = start_simple("it_works_with_prepared_queries").await;
+    let sql = "
+        CREATE TABLE Article (aid int, title varchar(255), PRIMARY KEY(aid));
+        QUERY ArticleById: SELECT aid, title FROM Article WHERE aid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    mutator.insert(vec![1.into(), "a".into()]).await.unwrap();
+    mutator.insert(vec![2.into(), "b".into()]).await.unwrap();
+    sleep().await;
+
+    // a query that is already in the recipe is reused as-is
+    let outputs = g.outputs().await.unwrap().len();
+    let mut by_id = g
+        .prepare("SELECT aid, title FROM Article WHERE aid = ?")
+        .await
+        .unwrap();
+    assert_eq!(g.outputs().await.unwrap().len(), outputs);
+    assert_eq!(
+        by_id.lookup(&[1.into()], true).await.unwrap(),
+        vec![vec![1.into(), "a".into()]]
+    );
+
+    // so is one that only differs in how it is written
+    let mut qualified = g
+        .prepare("SELECT Article.aid, Article.title FROM Article WHERE Article.aid = ?")
+        .await
+        .unwrap();
+    assert_eq!(
+        qualified.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![2.into(), "b".into()]]
+    );
+
+    // new queries are installed the first time they are prepared
+    let mut by_title = g
+        .prepare("SELECT aid FROM Article WHERE title = ?")
+        .await
+        .unwrap();
+    assert_eq!(
+        by_title.lookup(&["b".into()], true).await.unwrap(),
+        vec![vec![2.into(), "b".into()]]
+    );
+    let outputs = g.outputs().await.unwrap().len();
+    let mut again = g
+        .prepare("SELECT aid FROM Article WHERE title = ?;")
+        .await
+        .unwrap();
+    assert_eq!(g.outputs().await.unwrap().len(), outputs);
+    assert_eq!(
+        again.lookup(&["a".into()], true).await.unwrap(),
+        vec![vec![1.into(), "a".into()]]
+    );
+
+    assert!(g
+        .prepare("INSERT INTO Article (aid, title) VALUES (3, 'c')")
+        .await
+        .is_err());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_sync_handles() {
+    let authority = Arc::new(LocalAuthority::new());
+    let (mut g, done) = Builder::default().start(authority.clone()).await.unwrap();
+    let sql = "
+        CREATE TABLE Car (id int, price int, PRIMARY KEY(id));
+        QUERY CarPrice: SELECT id, price FROM Car WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    tokio::task::spawn_blocking(move || {
+        let mut c = noria::sync::SyncControllerHandle::make(authority).unwrap();
+        assert!(c.inputs().unwrap().contains_key("Car"));
+
+        let mut car = c.table("Car").unwrap();
+        let mut price = c.view("CarPrice").unwrap();
+        car.insert(vec![1.into(), 10.into()]).unwrap();
+        let token = car
+            .perform_all(vec![vec![2.into(), 20.into()], vec![3.into(), 30.into()]])
+            .unwrap();
+        assert_eq!(
+            price.lookup_after(&[2.into()], &token).unwrap(),
+            vec![vec![2.into(), 20.into()]]
+        );
+
+        let token = car.delete(vec![1.into()]).unwrap();
+        assert!(price.lookup_after(&[1.into()], &token).unwrap().is_empty());
+
+        c.extend_recipe("QUERY Expensive: SELECT id FROM Car WHERE price = ?;")
+            .unwrap();
+        let mut expensive = c.view("Expensive").unwrap();
+        assert_eq!(
+            expensive.lookup(&[30.into()], true).unwrap(),
+            vec![vec![3.into(), 30.into()]]
+        );
+    })
+    .await
+    .unwrap();
+
+    drop(g);
+    done.await;
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn it_works_with_sql_recipe() {
     let mut g = start_simple("it_works_with_sql_recipe").await;
@@ -840,6 +1876,294 @@ async fn it_works_with_reads_before_writes() {
     assert_eq!(result[0], vec![aid.into(), uid.into()]);
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_range_lookups() {
+    use std::ops::Bound;
+
+    let mut g = start_simple_unsharded("it_works_with_range_lookups").await;
+    let sql = "
+        CREATE TABLE Story (id int, created_at int, PRIMARY KEY(id));
+        QUERY StoriesSince: SELECT id, created_at FROM Story WHERE created_at > ?;
+        QUERY StoryById: SELECT id, created_at FROM Story WHERE id = ?;
+    ";
+
+    g.install_recipe(sql).await.unwrap();
+    let mut story = g.table("Story").await.unwrap();
+    let mut getter = g.view("StoriesSince").await.unwrap();
+    let mut by_id = g.view("StoryById").await.unwrap();
+
+    for i in 0..10 {
+        story
+            .insert(vec![i.into(), (100 + i).into()])
+            .await
+            .unwrap();
+    }
+    sleep().await;
+
+    let since = (Bound::Excluded(vec![DataType::from(105)]), Bound::Unbounded);
+    let rs = getter.lookup_range(since.clone(), true).await.unwrap();
+    assert_eq!(rs.len(), 4);
+    assert_eq!(rs[0], vec![6.into(), 106.into()]);
+    assert_eq!(rs[3], vec![9.into(), 109.into()]);
+
+    // new writes to a range that has been filled show up without another replay
+    story.insert(vec![10.into(), 110.into()]).await.unwrap();
+    sleep().await;
+    let rs = getter.lookup_range(since, true).await.unwrap();
+    assert_eq!(rs.len(), 5);
+    assert_eq!(rs[4], vec![10.into(), 110.into()]);
+
+    // ranges can also be bounded on both sides
+    let rs = getter
+        .lookup_range(vec![DataType::from(101)]..vec![DataType::from(103)], true)
+        .await
+        .unwrap();
+    assert_eq!(rs.len(), 2);
+
+    // and point lookups keep working alongside range lookups
+    let rs = getter.lookup(&[104.into()], true).await.unwrap();
+    assert_eq!(rs.len(), 1);
+
+    // a view that only looks up exact keys keeps no ordered index, so it refuses range lookups
+    match by_id.lookup_range(vec![DataType::from(1)].., true).await {
+        Err(noria::error::ViewError::RangesUnsupported) => {}
+        r => panic!("expected the range lookup to be refused, got {:?}", r),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_subscriptions() {
+    use futures_util::stream::StreamExt;
+    use noria::Delta;
+
+    let mut g = start_simple("it_works_with_subscriptions").await;
+    let sql = "
+        CREATE TABLE Article (aid int, author int, PRIMARY KEY(aid));
+        QUERY ByAuthor: SELECT aid, author FROM Article WHERE author = ?;
+    ";
+
+    g.install_recipe(sql).await.unwrap();
+    let mut article = g.table("Article").await.unwrap();
+    let mut getter = g.view("ByAuthor").await.unwrap();
+
+    article.insert(vec![1.into(), 7.into()]).await.unwrap();
+    sleep().await;
+
+    // make sure the key is materialized, so that it is all sent in the first batch
+    let rs = getter.lookup(&[7.into()], true).await.unwrap();
+    assert_eq!(rs.len(), 1);
+
+    let mut sub = getter.subscribe(vec![7.into()]).await.unwrap();
+    let deltas = sub.next().await.unwrap().unwrap();
+    assert_eq!(deltas, vec![Delta::Positive(vec![1.into(), 7.into()])]);
+
+    // writes to other keys are not sent to the subscriber
+    article.insert(vec![2.into(), 8.into()]).await.unwrap();
+    article.insert(vec![3.into(), 7.into()]).await.unwrap();
+    let deltas = sub.next().await.unwrap().unwrap();
+    assert_eq!(deltas, vec![Delta::Positive(vec![3.into(), 7.into()])]);
+
+    article.delete(vec![1.into()]).await.unwrap();
+    let deltas = sub.next().await.unwrap().unwrap();
+    assert_eq!(deltas, vec![Delta::Negative(vec![1.into(), 7.into()])]);
+
+    sub.unsubscribe().await.unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_times_out_blocked_lookups() {
+    use noria::error::ViewError;
+
+    let mut g = start_simple_unsharded("it_times_out_blocked_lookups").await;
+    let sql = "
+        CREATE TABLE Car (id int, brand varchar(255), PRIMARY KEY(id));
+        QUERY CarsById: SELECT id, brand FROM Car WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let base = g.inputs().await.unwrap()["Car"];
+    let mut mutator = g.table("Car").await.unwrap();
+    let mut getter = g.view("CarsById").await.unwrap();
+    getter.set_timeout(Some(Duration::from_millis(100)));
+
+    // waiting for a write that never happens blocks the read indefinitely
+    let never = noria::WriteToken::new(base, 0, u64::max_value(), 1);
+    match getter.lookup_after(&[1.into()], &never).await {
+        Err(ViewError::Timeout) => {}
+        r => panic!("expected the lookup to time out, got {:?}", r),
+    }
+
+    // the read that timed out must not hold up later reads
+    let token = mutator
+        .insert(vec![1.into(), "Volvo".into()])
+        .await
+        .unwrap();
+    assert_eq!(
+        getter.lookup_after(&[1.into()], &token).await.unwrap(),
+        vec![vec![1.into(), "Volvo".into()]]
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_paged_lookups() {
+    use futures_util::stream::TryStreamExt;
+
+    let mut g = start_simple("it_works_with_paged_lookups").await;
+    let sql = "
+        CREATE TABLE Comment (cid int, story int, PRIMARY KEY(cid));
+        QUERY ByStory: SELECT cid, story FROM Comment WHERE story = ?;
+    ";
+
+    g.install_recipe(sql).await.unwrap();
+    let mut comment = g.table("Comment").await.unwrap();
+    let mut getter = g.view("ByStory").await.unwrap();
+
+    let mut token = noria::WriteToken::default();
+    for cid in &[4, 2, 5, 1, 3] {
+        let t = comment.insert(vec![(*cid).into(), 1.into()]).await.unwrap();
+        token.merge(&t);
+    }
+    getter.lookup_after(&[1.into()], &token).await.unwrap();
+
+    let pages: Vec<_> = getter
+        .lookup_paged(&[1.into()], 2)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(pages.len(), 3);
+    let rows: Vec<Vec<DataType>> = pages
+        .into_iter()
+        .flat_map(Into::<Vec<Vec<DataType>>>::into)
+        .collect();
+    assert_eq!(
+        rows,
+        (1..=5)
+            .map(|cid| vec![cid.into(), 1.into()])
+            .collect::<Vec<Vec<DataType>>>()
+    );
+
+    // a key with no rows has no pages
+    let pages: Vec<_> = getter
+        .lookup_paged(&[2.into()], 2)
+        .try_collect()
+        .await
+        .unwrap();
+    assert!(pages.is_empty());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_write_tokens() {
+    let mut g = start_simple("it_works_with_write_tokens").await;
+    let sql = "
+        CREATE TABLE Article (aid int, author int, PRIMARY KEY(aid));
+        QUERY ByAuthor: SELECT aid, author FROM Article WHERE author = ?;
+    ";
+
+    g.install_recipe(sql).await.unwrap();
+    let mut article = g.table("Article").await.unwrap();
+    let mut getter = g.view("ByAuthor").await.unwrap();
+
+    // no sleep() needed -- the lookup waits for the write
+    let token = article.insert(vec![1.into(), 7.into()]).await.unwrap();
+    assert!(!token.is_empty());
+    let rs = getter.lookup_after(&[7.into()], &token).await.unwrap();
+    assert_eq!(rs.len(), 1);
+
+    // tokens cover all the writes they are merged from, even ones that end up in other shards
+    let mut token = article.insert(vec![2.into(), 7.into()]).await.unwrap();
+    token.merge(&article.insert(vec![3.into(), 8.into()]).await.unwrap());
+    token.merge(&article.delete(vec![1.into()]).await.unwrap());
+    let rs = getter.lookup_after(&[7.into()], &token).await.unwrap();
+    assert_eq!(rs, vec![vec![2.into(), 7.into()]]);
+    let rs = getter.lookup_after(&[8.into()], &token).await.unwrap();
+    assert_eq!(rs, vec![vec![3.into(), 8.into()]]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_transactions() {
+    let mut g = start_simple("it_works_with_transactions").await;
+    let sql = "
+        CREATE TABLE Story (sid int, title varchar(255), PRIMARY KEY(sid));
+        CREATE TABLE Comment (cid int, sid int, body varchar(255), PRIMARY KEY(cid));
+        QUERY StoryComments: SELECT Story.sid, title, body FROM Story \
+            JOIN Comment ON Story.sid = Comment.sid WHERE Story.sid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let story = g.table("Story").await.unwrap();
+    let comment = g.table("Comment").await.unwrap();
+    let mut getter = g.view("StoryComments").await.unwrap();
+
+    let mut txn = g.transaction();
+    txn.insert(&story, vec![1.into(), "hello".into()]);
+    txn.insert(&comment, vec![1.into(), 1.into(), "first".into()]);
+    txn.insert(&comment, vec![2.into(), 1.into(), "second".into()]);
+    let token = txn.commit().await.unwrap();
+
+    let rs = getter.lookup_after(&[1.into()], &token).await.unwrap();
+    assert_eq!(rs.len(), 2);
+
+    // malformed transactions are rejected before anything is written
+    let mut txn = g.transaction();
+    txn.insert(&story, vec![2.into(), "world".into()]);
+    txn.insert(&comment, vec![3.into(), 2.into()]);
+    assert!(txn.commit().await.is_err());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_typed_rows() {
+    #[derive(Clone, Debug, PartialEq, noria::FromRow, noria::IntoRow)]
+    struct Article {
+        title: String,
+        #[noria(rename = "aid")]
+        id: i32,
+        score: Option<i64>,
+    }
+
+    #[derive(noria::IntoRow)]
+    struct Misnamed {
+        aid: i32,
+        headline: String,
+    }
+
+    #[derive(Debug, noria::FromRow)]
+    struct Mistyped {
+        title: i32,
+    }
+
+    let mut g = start_simple("it_works_with_typed_rows").await;
+    let sql = "
+        CREATE TABLE Article (aid int, score bigint, title varchar(255), PRIMARY KEY(aid));
+        QUERY ArticleById: SELECT title, aid, score FROM Article WHERE aid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    let mut getter = g.view("ArticleById").await.unwrap();
+
+    let article = Article {
+        title: "hello".to_owned(),
+        id: 1,
+        score: None,
+    };
+    let token = mutator.insert_typed(article.clone()).await.unwrap();
+    getter.lookup_after(&[1.into()], &token).await.unwrap();
+
+    let rs: Vec<Article> = getter.lookup_typed(&[1.into()], true).await.unwrap();
+    assert_eq!(rs, vec![article]);
+
+    // fields are matched to columns by name, and mismatches are reported
+    let misnamed = Misnamed {
+        aid: 2,
+        headline: "world".to_owned(),
+    };
+    assert!(mutator.insert_typed(misnamed).await.is_err());
+    assert!(getter
+        .lookup_typed::<Mistyped>(&[1.into()], true)
+        .await
+        .is_err());
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn forced_shuffle_despite_same_shard() {
     // XXX: this test doesn't currently *fail* despite
@@ -999,6 +2323,57 @@ async fn it_recovers_persisted_bases() {
     done.await;
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn it_reconnects_after_controller_restart() {
+    let authority = Arc::new(LocalAuthority::new());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("it_reconnects_after_controller_restart");
+    let persistence_params = PersistenceParameters::new(
+        DurabilityMode::Permanent,
+        Duration::from_millis(1),
+        Some(path.to_string_lossy().into()),
+        1,
+    );
+
+    let mut g = Builder::default();
+    g.set_persistence(persistence_params.clone());
+    let (mut g, done) = g.start(authority.clone()).await.unwrap();
+    let sql = "
+        CREATE TABLE Car (id int, price int, PRIMARY KEY(id));
+        QUERY CarPrice: SELECT price FROM Car WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    // these outlive the instance that they were created from
+    let mut handle = (*g).clone();
+    let mut mutator = g.table("Car").await.unwrap();
+    let mut getter = g.view("CarPrice").await.unwrap();
+    mutator.insert(vec![1.into(), 10.into()]).await.unwrap();
+    sleep().await;
+    drop(g);
+    done.await;
+
+    let mut g = Builder::default();
+    g.set_persistence(persistence_params);
+    let (g, done) = g.start(authority.clone()).await.unwrap();
+
+    // the old handle finds the new controller
+    handle.ready().await.unwrap();
+    assert!(handle.outputs().await.unwrap().contains_key("CarPrice"));
+
+    // and the old view and table can find their new shards
+    getter.refresh().await.unwrap();
+    mutator.refresh().await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result, vec![vec![10.into(), 1.into()]]);
+    let token = mutator.insert(vec![2.into(), 20.into()]).await.unwrap();
+    let result = getter.lookup_after(&[2.into()], &token).await.unwrap();
+    assert_eq!(result, vec![vec![20.into(), 2.into()]]);
+
+    drop(g);
+    done.await;
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn mutator_churn() {
     let mut g = start_simple("mutator_churn").await;
@@ -9,14 +9,18 @@ use futures_util::{
     future::{FutureExt, TryFutureExt},
     stream::{StreamExt, TryStreamExt},
 };
-use noria::{ReadQuery, ReadReply, Tagged};
+use nom_sql::OrderType;
+use noria::filter::FilterCondition;
+use noria::{cmp_rows, Delta, KeyRange, ReadQuery, ReadReply, Tagged, WriteToken};
 use pin_project::pin_project;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::mem;
+use std::sync::{Arc, Mutex};
 use std::time;
 use std::{future::Future, task::Poll};
-use stream_cancel::Valve;
+use stream_cancel::{Trigger, Valve, Valved};
 use tokio::task_local;
 use tokio_tower::multiplex::server;
 use tower::service_fn;
@@ -29,6 +33,10 @@ const RETRY_TIMEOUT: time::Duration = time::Duration::from_micros(100);
 /// while, waiting readers will use exponential backoff on this delay if they continue to miss.
 const TRIGGER_TIMEOUT_MS: u64 = 20;
 
+/// The number of batches of deltas that may be buffered for a subscriber before it is considered
+/// to have fallen behind, and its subscription is ended.
+const SUBSCRIPTION_BUFFER: usize = 1024;
+
 task_local! {
     static READERS: RefCell<HashMap<
         (NodeIndex, usize),
@@ -55,6 +63,17 @@ impl From<Vec<u8>> for SerializedReadReplyBatch {
 
 type Ack = tokio::sync::oneshot::Sender<Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>>;
 
+/// A subscription to a key in a reader, held by a client connection.
+struct Subscription {
+    /// Dropped to end the subscription, even if a request for deltas is waiting on it.
+    _end: Trigger,
+    /// `None` while a request for deltas is waiting on it.
+    deltas: Option<Valved<tokio::sync::mpsc::Receiver<Vec<Record>>>>,
+}
+
+/// The subscriptions held by a single client connection.
+type Subscriptions = Arc<Mutex<slab::Slab<Subscription>>>;
+
 pub(super) async fn listen(
     alive: tokio::sync::mpsc::Sender<()>,
     valve: Valve,
@@ -70,6 +89,7 @@ pub(super) async fn listen(
 
         let stream = stream.unwrap();
         let readers = readers.clone();
+        let subscriptions = Subscriptions::default();
         stream.set_nodelay(true).expect("could not set TCP_NODELAY");
         let alive = alive.clone();
 
@@ -80,16 +100,63 @@ pub(super) async fn listen(
         let retries = READERS.scope(Default::default(), async move {
             use async_timer::Oneshot;
             let mut retry = async_timer::oneshot::Timer::new(RETRY_TIMEOUT);
-            let mut pending = None::<(BlockingRead, Ack)>;
+            let mut pending = VecDeque::<(BlockingRead, Ack)>::new();
+            let mut closed = false;
             loop {
-                if let Some((ref mut blocking, _)) = pending {
+                if pending.is_empty() {
+                    // no point in waiting for a timer if we've got nothing to wait for
+                    // so let's get another request
+                    if closed {
+                        break;
+                    }
+                    match rx.next().await {
+                        Some(read) => pending.push_back(read),
+                        None => break,
+                    }
+                }
+
+                // pick up any other requests that have arrived in the meantime, so that their
+                // deadlines are enforced even while they wait behind a read that is stuck
+                while !closed {
+                    match rx.next().now_or_never() {
+                        Some(Some(read)) => pending.push_back(read),
+                        Some(None) => closed = true,
+                        None => break,
+                    }
+                }
+
+                // drop every queued read whose client went away, and time out every queued read
+                // whose deadline has passed, not just the one at the head of the queue
+                let now = time::Instant::now();
+                future::poll_fn(|cx| {
+                    let mut i = 0;
+                    while i < pending.len() {
+                        let (ref blocking, ref mut ack) = pending[i];
+                        if ack.poll_closed(cx).is_ready() {
+                            // the client went away, so there's no point in retrying the read
+                            pending.remove(i);
+                        } else if blocking.expired(now) {
+                            let (blocking, ack) = pending.remove(i).expect("i < pending.len()");
+                            let _ = ack.send(Ok(Tagged {
+                                tag: blocking.tag,
+                                v: ReadReply::TimedOut,
+                            }));
+                        } else {
+                            i += 1;
+                        }
+                    }
+                    Poll::Ready(())
+                })
+                .await;
+
+                if let Some((blocking, _)) = pending.front_mut() {
                     // we have a pending read — see if it can complete
                     if let Poll::Ready(res) = blocking.check() {
                         // it did! let's tell the caller.
-                        let (_, ack) = pending.take().expect("we matched on Some above");
+                        let (_, ack) = pending.pop_front().expect("we matched on Some above");
                         // if this errors, the client just went away
                         let _ = ack.send(res);
-                    // the loop will take care of looking for the next request
+                    // the loop will take care of looking at the next request
                     } else {
                         // we have a pending request, but it is still blocked
                         // time for us to wait...
@@ -102,14 +169,6 @@ pub(super) async fn listen(
                         // we need `(&mut )` here so that we can re-use it
                         (&mut retry).await;
                     }
-                } else {
-                    // no point in waiting for a timer if we've got nothing to wait for
-                    // so let's get another request
-                    if let Some(read) = rx.next().await {
-                        pending = Some(read);
-                    } else {
-                        break;
-                    }
                 }
             }
         });
@@ -119,7 +178,7 @@ pub(super) async fn listen(
             Default::default(),
             server::Server::new(
                 AsyncBincodeStream::from(stream).for_async(),
-                service_fn(move |req| handle_message(req, &readers, &mut tx)),
+                service_fn(move |req| handle_message(req, &readers, &subscriptions, &mut tx)),
             ),
         );
         tokio::spawn(
@@ -176,134 +235,550 @@ where
     SerializedReadReplyBatch(v)
 }
 
+/// Sort `rows` by the `ORDER BY` columns in `order`, if there are any.
+fn ordered<'a, I>(rows: I, order: &[(usize, OrderType)]) -> Vec<&'a Vec<DataType>>
+where
+    I: IntoIterator<Item = &'a Vec<DataType>>,
+{
+    let mut rows: Vec<_> = rows.into_iter().collect();
+    if !order.is_empty() {
+        rows.sort_by(|a, b| cmp_rows(order, a, b));
+    }
+    rows
+}
+
+/// A row that compares according to the `ORDER BY` columns of the reader it came from.
+struct OrderedRow<'a> {
+    row: &'a Vec<DataType>,
+    order: &'a [(usize, OrderType)],
+}
+
+impl PartialEq for OrderedRow<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedRow<'_> {}
+
+impl PartialOrd for OrderedRow<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedRow<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_rows(self.order, self.row, other.row)
+    }
+}
+
+/// Select the first `limit` of `rows` that sort after the cursor `after`, in sorted order.
+///
+/// Rows are sorted by the `ORDER BY` columns in `order` first, and then as a whole. The cursor is
+/// the last row of the previous page, along with how many copies of that row have been returned
+/// so far, since a key may hold several identical rows.
+fn page<'a, I>(
+    rows: I,
+    order: &'a [(usize, OrderType)],
+    after: Option<&(Vec<DataType>, usize)>,
+    limit: usize,
+) -> Vec<&'a Vec<DataType>>
+where
+    I: IntoIterator<Item = &'a Vec<DataType>>,
+{
+    use std::collections::BinaryHeap;
+
+    let mut skip = after.map(|&(_, n)| n).unwrap_or(0);
+    let mut smallest = BinaryHeap::with_capacity(limit + 1);
+    for row in rows {
+        if let Some(&(ref last, _)) = after {
+            match cmp_rows(order, row, last) {
+                Ordering::Less => continue,
+                Ordering::Equal if skip > 0 => {
+                    skip -= 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        smallest.push(OrderedRow { row, order });
+        if smallest.len() > limit {
+            smallest.pop();
+        }
+    }
+    smallest
+        .into_sorted_vec()
+        .into_iter()
+        .map(|r| r.row)
+        .collect()
+}
+
+/// A single lookup into a reader: all rows for a key, all rows for every key in a range, the rows
+/// for a key that match a filter, or one page of the rows for a key that match a filter.
+#[derive(Debug)]
+enum Lookup {
+    Key(Vec<DataType>),
+    Range(KeyRange),
+    Filtered {
+        key: Vec<DataType>,
+        filter: Vec<(usize, FilterCondition)>,
+    },
+    Page {
+        key: Vec<DataType>,
+        after: Option<(Vec<DataType>, usize)>,
+        limit: usize,
+        filter: Vec<(usize, FilterCondition)>,
+    },
+}
+
+impl Lookup {
+    /// Turn a lookup of the query parameters in `key` into a lookup of all the rows of a reader
+    /// whose `parameter_filter` checks those parameters instead.
+    ///
+    /// Such readers are keyed on the bogokey that the SQL layer adds to queries without a key to
+    /// look up, so all their rows are under the same key. Every lookup therefore checks every row
+    /// of the view, and partial state does not help: the first miss fills in the whole view.
+    /// Ranges of parameters cannot be looked up this way, and give `None`.
+    fn bind_parameters(self, parameter_filter: &[(usize, FilterCondition)]) -> Option<Lookup> {
+        let bogokey = || vec![DataType::from(0 as i32)];
+        let bind = |key: &[DataType]| -> Vec<_> {
+            parameter_filter
+                .iter()
+                .map(|(col, cond)| (*col, cond.bind(key)))
+                .collect()
+        };
+        match self {
+            Lookup::Key(key) => Some(Lookup::Filtered {
+                filter: bind(&key),
+                key: bogokey(),
+            }),
+            Lookup::Range(_) => None,
+            Lookup::Filtered { key, filter } => Some(Lookup::Filtered {
+                filter: bind(&key).into_iter().chain(filter).collect(),
+                key: bogokey(),
+            }),
+            Lookup::Page {
+                key,
+                after,
+                limit,
+                filter,
+            } => Some(Lookup::Page {
+                filter: bind(&key).into_iter().chain(filter).collect(),
+                key: bogokey(),
+                after,
+                limit,
+            }),
+        }
+    }
+
+    fn try_find(&self, reader: &SingleReadHandle) -> Result<Option<SerializedReadReplyBatch>, ()> {
+        match *self {
+            Lookup::Key(ref key) if reader.order().is_empty() => {
+                reader.try_find_and(key, |rs| serialize(rs)).map(|r| r.0)
+            }
+            Lookup::Key(ref key) => reader
+                .try_find_and(key, |rs| serialize(ordered(rs, reader.order())))
+                .map(|r| r.0),
+            Lookup::Range(ref range) => reader
+                .try_find_range_and(range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+                .map(|r| {
+                    r.0.map(|rows| serialize(ordered(&rows.concat(), reader.order())))
+                }),
+            Lookup::Filtered {
+                ref key,
+                ref filter,
+            } => reader
+                .try_find_and(key, |rs| {
+                    let matching = rs
+                        .iter()
+                        .filter(|r| filter.iter().all(|(col, cond)| cond.matches(r, *col)));
+                    serialize(ordered(matching, reader.order()))
+                })
+                .map(|r| r.0),
+            Lookup::Page {
+                ref key,
+                ref after,
+                limit,
+                ref filter,
+            } => reader
+                .try_find_and(key, |rs| {
+                    let matching = rs
+                        .iter()
+                        .filter(|r| filter.iter().all(|(col, cond)| cond.matches(r, *col)));
+                    serialize(page(matching, reader.order(), after.as_ref(), limit))
+                })
+                .map(|r| r.0),
+        }
+    }
+}
+
+/// Trigger backfills for all the given lookups, returning false if the reader is going away.
+fn trigger_backfills(reader: &SingleReadHandle, lookups: &[Lookup]) -> bool {
+    let keys = lookups.iter().filter_map(|l| match *l {
+        Lookup::Key(ref key) | Lookup::Filtered { ref key, .. } | Lookup::Page { ref key, .. } => {
+            Some(&key[..])
+        }
+        Lookup::Range(_) => None,
+    });
+    let mut ok = reader.trigger(keys);
+    for l in lookups {
+        if let Lookup::Range(ref range) = *l {
+            ok = reader.trigger_range(range) && ok;
+        }
+    }
+    ok
+}
+
 fn handle_message(
     m: Tagged<ReadQuery>,
     s: &Readers,
+    subscriptions: &Subscriptions,
     wait: &mut tokio::sync::mpsc::UnboundedSender<(BlockingRead, Ack)>,
 ) -> impl Future<Output = Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>> + Send {
     let tag = m.tag;
     match m.v {
         ReadQuery::Normal {
             target,
-            mut keys,
+            keys,
+            block,
+            timeout,
+        } => Either::Left(handle_lookups(
+            tag,
+            target,
+            keys.into_iter().map(Lookup::Key).collect(),
+            block,
+            None,
+            timeout,
+            s,
+            wait,
+        )),
+        ReadQuery::After {
+            target,
+            keys,
+            token,
+            timeout,
+        } => Either::Left(handle_lookups(
+            tag,
+            target,
+            keys.into_iter().map(Lookup::Key).collect(),
+            true,
+            Some(token),
+            timeout,
+            s,
+            wait,
+        )),
+        ReadQuery::Range {
+            target,
+            ranges,
+            block,
+            timeout,
+        } => Either::Left(handle_lookups(
+            tag,
+            target,
+            ranges.into_iter().map(Lookup::Range).collect(),
+            block,
+            None,
+            timeout,
+            s,
+            wait,
+        )),
+        ReadQuery::Page {
+            target,
+            key,
+            after,
+            limit,
+            timeout,
+        } => Either::Left(handle_lookups(
+            tag,
+            target,
+            vec![Lookup::Page {
+                key,
+                after,
+                limit,
+                filter: Vec::new(),
+            }],
+            true,
+            None,
+            timeout,
+            s,
+            wait,
+        )),
+        ReadQuery::Filtered {
+            target,
+            key,
+            filter,
+            block,
+            timeout,
+        } => Either::Left(handle_lookups(
+            tag,
+            target,
+            vec![Lookup::Filtered { key, filter }],
             block,
-        } => {
-            let immediate = READERS.with(|readers_cache| {
+            None,
+            timeout,
+            s,
+            wait,
+        )),
+        ReadQuery::Size { target } => {
+            let size = READERS.with(|readers_cache| {
                 let mut readers_cache = readers_cache.borrow_mut();
                 let reader = readers_cache.entry(target).or_insert_with(|| {
                     let readers = s.lock().unwrap();
                     readers.get(&target).unwrap().clone()
                 });
 
-                let mut ret = Vec::with_capacity(keys.len());
-
-                // first do non-blocking reads for all keys to see if we can return immediately
-                let mut i = -1;
-                let mut ready = true;
-                let mut pending = Vec::new();
-                keys.retain(|key| {
-                    i += 1;
-                    if !ready {
-                        ret.push(SerializedReadReplyBatch::empty());
-                        return false;
-                    }
-                    let rs = reader.try_find_and(key, |rs| serialize(rs)).map(|r| r.0);
-                    match rs {
-                        Ok(Some(rs)) => {
-                            // immediate hit!
-                            ret.push(rs);
-                            false
+                reader.len()
+            });
+
+            Either::Right(Either::Left(future::ready(Ok(Tagged {
+                tag,
+                v: ReadReply::Size(size),
+            }))))
+        }
+        ReadQuery::Subscribe { target, key } => {
+            let (tx, rx) = tokio::sync::mpsc::channel(SUBSCRIPTION_BUFFER);
+            let ready = READERS.with(|readers_cache| {
+                let mut readers_cache = readers_cache.borrow_mut();
+                let reader = readers_cache.entry(target).or_insert_with(|| {
+                    let readers = s.lock().unwrap();
+                    readers.get(&target).unwrap().clone()
+                });
+
+                if !reader.parameter_filter().is_empty() {
+                    // all rows of the reader are under the same key, whatever parameters they
+                    // match, so a subscriber would be sent the changes to all of them
+                    return false;
+                }
+                match reader.try_find_and(&key[..], |_| ()) {
+                    Err(()) => false,
+                    Ok((hit, _)) => {
+                        if hit.is_none() {
+                            // the subscriber will be sent the rows once they have been replayed
+                            reader.trigger(std::iter::once(&key[..]));
                         }
-                        Err(()) => {
-                            // map not yet ready
-                            ready = false;
-                            ret.push(SerializedReadReplyBatch::empty());
-                            false
+                        reader.subscribe(key, tx);
+                        true
+                    }
+                }
+            });
+
+            let v = if ready {
+                let (end, valve) = Valve::new();
+                let id = subscriptions.lock().unwrap().insert(Subscription {
+                    _end: end,
+                    deltas: Some(valve.wrap(rx)),
+                });
+                ReadReply::Subscribed(Ok(id))
+            } else {
+                ReadReply::Subscribed(Err(()))
+            };
+            Either::Right(Either::Left(future::ready(Ok(Tagged { tag, v }))))
+        }
+        ReadQuery::Deltas { subscription } => {
+            let deltas = subscriptions
+                .lock()
+                .unwrap()
+                .get_mut(subscription)
+                .and_then(|sub| sub.deltas.take());
+            let subscriptions = Arc::clone(subscriptions);
+            Either::Right(Either::Right(async move {
+                let mut rx = match deltas {
+                    Some(rx) => rx,
+                    None => {
+                        // unknown subscription, or one that is already being waited on
+                        return Ok(Tagged {
+                            tag,
+                            v: ReadReply::Deltas(Err(())),
+                        });
+                    }
+                };
+
+                let v = match rx.next().await {
+                    Some(records) => {
+                        if let Some(sub) = subscriptions.lock().unwrap().get_mut(subscription) {
+                            sub.deltas = Some(rx);
                         }
-                        Ok(None) => {
-                            // need to trigger partial replay for this key
-                            pending.push(i as usize);
-                            ret.push(SerializedReadReplyBatch::empty());
-                            true
+                        ReadReply::Deltas(Ok(records
+                            .into_iter()
+                            .map(|r| match r {
+                                Record::Positive(r) => Delta::Positive(r),
+                                Record::Negative(r) => Delta::Negative(r),
+                            })
+                            .collect()))
+                    }
+                    None => {
+                        // the subscription was ended, either by the client or by the reader
+                        let mut subscriptions = subscriptions.lock().unwrap();
+                        if subscriptions.contains(subscription) {
+                            subscriptions.remove(subscription);
                         }
+                        ReadReply::Deltas(Err(()))
                     }
-                });
+                };
+                Ok(Tagged { tag, v })
+            }))
+        }
+        ReadQuery::Unsubscribe { subscription } => {
+            let mut subscriptions = subscriptions.lock().unwrap();
+            if subscriptions.contains(subscription) {
+                subscriptions.remove(subscription);
+            }
+            Either::Right(Either::Left(future::ready(Ok(Tagged {
+                tag,
+                v: ReadReply::Unsubscribed,
+            }))))
+        }
+    }
+}
 
-                if !ready {
-                    return Ok(Tagged {
-                        tag,
-                        v: ReadReply::Normal(Err(())),
-                    });
-                }
+fn handle_lookups(
+    tag: u32,
+    target: (NodeIndex, usize),
+    mut keys: Vec<Lookup>,
+    block: bool,
+    after: Option<WriteToken>,
+    timeout: Option<time::Duration>,
+    s: &Readers,
+    wait: &mut tokio::sync::mpsc::UnboundedSender<(BlockingRead, Ack)>,
+) -> impl Future<Output = Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>> + Send {
+    let immediate = READERS.with(|readers_cache| {
+        let mut readers_cache = readers_cache.borrow_mut();
+        let reader = readers_cache.entry(target).or_insert_with(|| {
+            let readers = s.lock().unwrap();
+            readers.get(&target).unwrap().clone()
+        });
+
+        if !reader.serves_ranges() && keys.iter().any(|l| matches!(*l, Lookup::Range(_))) {
+            // the view refuses range lookups if its reader can't serve them, so a client that
+            // asks anyway would otherwise keep waiting for a range that no replay will fill
+            return Ok(Tagged {
+                tag,
+                v: ReadReply::Normal(Err(())),
+            });
+        }
 
-                if keys.is_empty() {
-                    // we hit on all the keys!
-                    assert!(pending.is_empty());
+        if !reader.parameter_filter().is_empty() {
+            let bound = keys
+                .into_iter()
+                .map(|l| l.bind_parameters(reader.parameter_filter()))
+                .collect();
+            keys = match bound {
+                Some(bound) => bound,
+                None => {
                     return Ok(Tagged {
                         tag,
-                        v: ReadReply::Normal(Ok(ret)),
-                    });
+                        v: ReadReply::Normal(Err(())),
+                    })
                 }
+            };
+        }
 
-                // trigger backfills for all the keys we missed on
-                reader.trigger(keys.iter().map(Vec::as_slice));
+        if let Some(ref token) = after {
+            if !reader.has_seen(token) {
+                // the writes have yet to reach the reader, so any results we read now may be stale
+                let ret = keys
+                    .iter()
+                    .map(|_| SerializedReadReplyBatch::empty())
+                    .collect();
+                let pending = (0..keys.len()).collect();
+                return Err((keys, ret, pending));
+            }
+        }
 
-                Err((keys, ret, pending))
-            });
+        let mut ret = Vec::with_capacity(keys.len());
 
-            match immediate {
-                Ok(reply) => Either::Left(Either::Left(future::ready(Ok(reply)))),
-                Err((keys, ret, pending)) => {
-                    if !block {
-                        Either::Left(Either::Left(future::ready(Ok(Tagged {
-                            tag,
-                            v: ReadReply::Normal(Ok(ret)),
-                        }))))
-                    } else {
-                        let (tx, rx) = tokio::sync::oneshot::channel();
-                        let trigger = time::Duration::from_millis(TRIGGER_TIMEOUT_MS);
-                        let now = time::Instant::now();
-                        let r = wait.send((
-                            BlockingRead {
-                                tag,
-                                target,
-                                keys,
-                                pending,
-                                read: ret,
-                                truth: s.clone(),
-                                trigger_timeout: trigger,
-                                next_trigger: now,
-                                first: now,
-                            },
-                            tx,
-                        ));
-                        if r.is_err() {
-                            // we're shutting down
-                            return Either::Left(Either::Left(future::ready(Err(()))));
-                        }
-                        Either::Left(Either::Right(rx.map(|r| match r {
-                            Err(_) => Err(()),
-                            Ok(r) => r,
-                        })))
-                    }
+        // first do non-blocking reads for all keys to see if we can return immediately
+        let mut i = -1;
+        let mut ready = true;
+        let mut pending = Vec::new();
+        keys.retain(|key| {
+            i += 1;
+            if !ready {
+                ret.push(SerializedReadReplyBatch::empty());
+                return false;
+            }
+            match key.try_find(reader) {
+                Ok(Some(rs)) => {
+                    // immediate hit!
+                    ret.push(rs);
+                    false
+                }
+                Err(()) => {
+                    // map not yet ready
+                    ready = false;
+                    ret.push(SerializedReadReplyBatch::empty());
+                    false
+                }
+                Ok(None) => {
+                    // need to trigger partial replay for this key
+                    pending.push(i as usize);
+                    ret.push(SerializedReadReplyBatch::empty());
+                    true
                 }
             }
-        }
-        ReadQuery::Size { target } => {
-            let size = READERS.with(|readers_cache| {
-                let mut readers_cache = readers_cache.borrow_mut();
-                let reader = readers_cache.entry(target).or_insert_with(|| {
-                    let readers = s.lock().unwrap();
-                    readers.get(&target).unwrap().clone()
-                });
+        });
 
-                reader.len()
+        if !ready {
+            return Ok(Tagged {
+                tag,
+                v: ReadReply::Normal(Err(())),
             });
+        }
 
-            Either::Right(future::ready(Ok(Tagged {
+        if keys.is_empty() {
+            // we hit on all the keys!
+            assert!(pending.is_empty());
+            return Ok(Tagged {
                 tag,
-                v: ReadReply::Size(size),
-            })))
+                v: ReadReply::Normal(Ok(ret)),
+            });
+        }
+
+        // trigger backfills for all the keys we missed on
+        trigger_backfills(reader, &keys[..]);
+
+        Err((keys, ret, pending))
+    });
+
+    match immediate {
+        Ok(reply) => Either::Left(future::ready(Ok(reply))),
+        Err((keys, ret, pending)) => {
+            if !block {
+                Either::Left(future::ready(Ok(Tagged {
+                    tag,
+                    v: ReadReply::Normal(Ok(ret)),
+                })))
+            } else {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let trigger = time::Duration::from_millis(TRIGGER_TIMEOUT_MS);
+                let now = time::Instant::now();
+                let r = wait.send((
+                    BlockingRead {
+                        tag,
+                        target,
+                        keys,
+                        pending,
+                        read: ret,
+                        after,
+                        truth: s.clone(),
+                        trigger_timeout: trigger,
+                        next_trigger: now,
+                        first: now,
+                        deadline: timeout.map(|timeout| now + timeout),
+                    },
+                    tx,
+                ));
+                if r.is_err() {
+                    // we're shutting down
+                    return Either::Left(future::ready(Err(())));
+                }
+                Either::Right(rx.map(|r| match r {
+                    Err(_) => Err(()),
+                    Ok(r) => r,
+                }))
+            }
         }
     }
 }
@@ -314,15 +789,19 @@ struct BlockingRead {
     target: (NodeIndex, usize),
     // serialized records for keys we have already read
     read: Vec<SerializedReadReplyBatch>,
-    // keys we have yet to read
-    keys: Vec<Vec<DataType>>,
+    // keys (or ranges) we have yet to read
+    keys: Vec<Lookup>,
     // index in self.read that each entyr in keys corresponds to
     pending: Vec<usize>,
+    // writes the reader must reflect before we can read any keys
+    after: Option<WriteToken>,
     truth: Readers,
 
     trigger_timeout: time::Duration,
     next_trigger: time::Instant,
     first: time::Instant,
+    // when to give up on the read, if ever
+    deadline: Option<time::Instant>,
 }
 
 impl std::fmt::Debug for BlockingRead {
@@ -333,14 +812,22 @@ impl std::fmt::Debug for BlockingRead {
             .field("read", &self.read)
             .field("keys", &self.keys)
             .field("pending", &self.pending)
+            .field("after", &self.after)
             .field("trigger_timeout", &self.trigger_timeout)
             .field("next_trigger", &self.next_trigger)
             .field("first", &self.first)
+            .field("deadline", &self.deadline)
             .finish()
     }
 }
 
 impl BlockingRead {
+    fn expired(&self, now: time::Instant) -> bool {
+        self.deadline
+            .map(|deadline| now >= deadline)
+            .unwrap_or(false)
+    }
+
     fn check(&mut self) -> Poll<Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>> {
         READERS.with(|readers_cache| {
             let mut readers_cache = readers_cache.borrow_mut();
@@ -351,6 +838,14 @@ impl BlockingRead {
                 readers.get(target).unwrap().clone()
             });
 
+            if let Some(ref token) = self.after {
+                if !reader.has_seen(token) {
+                    return Ok(());
+                }
+                // the writes have made it to the reader, so we can start reading
+                self.after = None;
+            }
+
             let now = time::Instant::now();
             let read = &mut self.read;
             let next_trigger = self.next_trigger;
@@ -364,7 +859,7 @@ impl BlockingRead {
 
             while let Some(read_i) = self.pending.pop() {
                 let key = self.keys.pop().expect("pending.len() == keys.len()");
-                match reader.try_find_and(&key, |rs| serialize(rs)).map(|r| r.0) {
+                match key.try_find(reader) {
                     Ok(Some(rs)) => {
                         read[read_i] = rs;
                     }
@@ -386,7 +881,7 @@ impl BlockingRead {
 
             if !self.keys.is_empty() && now > next_trigger {
                 // maybe the key got filled, then evicted, and we missed it?
-                if !reader.trigger(self.keys.iter().map(Vec::as_slice)) {
+                if !trigger_backfills(reader, &self.keys[..]) {
                     // server is shutting down and won't do the backfill
                     return Err(());
                 }
@@ -409,7 +904,7 @@ impl BlockingRead {
             Ok(())
         })?;
 
-        if self.keys.is_empty() {
+        if self.keys.is_empty() && self.after.is_none() {
             Poll::Ready(Ok(Tagged {
                 tag: self.tag,
                 v: ReadReply::Normal(Ok(mem::take(&mut self.read))),
@@ -626,3 +1121,74 @@ mod readreply {
         .await;
     }
 }
+
+#[cfg(test)]
+mod paging {
+    use super::page;
+    use noria::DataType;
+
+    fn rows(vs: &[i32]) -> Vec<Vec<DataType>> {
+        vs.iter().map(|&v| vec![DataType::from(v)]).collect()
+    }
+
+    #[test]
+    fn pages_are_sorted() {
+        let all = rows(&[5, 1, 4, 2, 3]);
+        let got: Vec<_> = page(&all, &[], None, 2).into_iter().cloned().collect();
+        assert_eq!(got, rows(&[1, 2]));
+
+        let after = (vec![DataType::from(2)], 1);
+        let got: Vec<_> = page(&all, &[], Some(&after), 2)
+            .into_iter()
+            .cloned()
+            .collect();
+        assert_eq!(got, rows(&[3, 4]));
+
+        let after = (vec![DataType::from(5)], 1);
+        assert!(page(&all, &[], Some(&after), 2).is_empty());
+    }
+
+    #[test]
+    fn pages_split_duplicates() {
+        let all = rows(&[2, 1, 2, 2, 3]);
+        let got: Vec<_> = page(&all, &[], None, 2).into_iter().cloned().collect();
+        assert_eq!(got, rows(&[1, 2]));
+
+        let after = (vec![DataType::from(2)], 1);
+        let got: Vec<_> = page(&all, &[], Some(&after), 2)
+            .into_iter()
+            .cloned()
+            .collect();
+        assert_eq!(got, rows(&[2, 2]));
+
+        let after = (vec![DataType::from(2)], 3);
+        let got: Vec<_> = page(&all, &[], Some(&after), 2)
+            .into_iter()
+            .cloned()
+            .collect();
+        assert_eq!(got, rows(&[3]));
+    }
+
+    #[test]
+    fn pages_follow_order() {
+        use nom_sql::OrderType;
+
+        // ordered by the second column descending, with ties broken by the whole row
+        let order = [(1, OrderType::OrderDescending)];
+        let all: Vec<Vec<DataType>> = vec![
+            vec![1.into(), 10.into()],
+            vec![2.into(), 30.into()],
+            vec![3.into(), 20.into()],
+            vec![4.into(), 30.into()],
+        ];
+        let got: Vec<_> = page(&all, &order, None, 3).into_iter().cloned().collect();
+        assert_eq!(got, vec![all[1].clone(), all[3].clone(), all[2].clone()]);
+
+        let after = (all[3].clone(), 1);
+        let got: Vec<_> = page(&all, &order, Some(&after), 3)
+            .into_iter()
+            .cloned()
+            .collect();
+        assert_eq!(got, vec![all[2].clone(), all[0].clone()]);
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use mysql::prelude::Queryable;
 use mysql::OptsBuilder;
 use mysql::Params;
@@ -20,6 +21,17 @@ use noria_server::{Builder, DataType};
 
 const DIRECTORY_PREFIX: &str = "tests/mysql_comparison_tests";
 
+/// How `NULL` is written in table data and query parameters.
+const NULL: &str = "NULL";
+
+fn mysql_value(value: &str) -> mysql::Value {
+    if value == NULL {
+        mysql::Value::NULL
+    } else {
+        value.into()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 enum Type {
     Int,
@@ -31,16 +43,51 @@ enum Type {
 
 impl Type {
     pub fn make_datatype(&self, value: &str) -> DataType {
+        if value == NULL {
+            return DataType::None;
+        }
         match *self {
             Type::Int => i64::from_str(value).unwrap().into(),
             Type::Text => value.into(),
             Type::Real => f64::from_str(value).unwrap().into(),
-            Type::Date => value.into(),
-            Type::Timestamp => value.into(),
+            Type::Date => parse_time(value, |v| {
+                NaiveDate::parse_from_str(v, DATE_FORMAT).or_else(|_| {
+                    NaiveDateTime::parse_from_str(v, TIMESTAMP_FORMAT).map(|ts| ts.date())
+                })
+            }),
+            Type::Timestamp => parse_time(value, |v| {
+                NaiveDateTime::parse_from_str(v, TIMESTAMP_FORMAT)
+            }),
         }
     }
 }
 
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Parse a date or timestamp from the data files.
+///
+/// Some files leave these fields empty, which we load as `NULL`, and some quote them. Anything
+/// else that does not parse is a broken fixture, so we panic rather than load it as `NULL`.
+fn parse_time<T, F>(value: &str, parse: F) -> DataType
+where
+    T: Into<DataType>,
+    F: FnOnce(&str) -> chrono::ParseResult<T>,
+{
+    let unquoted = value.trim_matches('\'');
+    if unquoted.is_empty() {
+        return DataType::None;
+    }
+    parse(unquoted)
+        .unwrap_or_else(|e| {
+            panic!(
+                "malformed date or timestamp {:?} in test data: {}",
+                value, e
+            )
+        })
+        .into()
+}
+
 #[derive(Debug, Deserialize)]
 struct Table {
     create_query: String,
@@ -173,7 +220,8 @@ fn generate_target_results(schemas: &BTreeMap<String, Schema>) {
             );
             let insert = conn.prep(query).unwrap();
             for row in table.data.as_ref().unwrap().iter() {
-                if let Err(msg) = conn.exec_drop(&insert, row.clone()) {
+                let values: Vec<_> = row.iter().map(|v| mysql_value(v)).collect();
+                if let Err(msg) = conn.exec_drop(&insert, values) {
                     println!(
                         "MySQL insert query failed for table: {}, values: {:?}",
                         table_name, row
@@ -197,7 +245,7 @@ fn generate_target_results(schemas: &BTreeMap<String, Schema>) {
                     .unwrap()
                     .insert(i.to_string(), Vec::new());
 
-                let values = Params::Positional(values.iter().map(|v| v.into()).collect());
+                let values = Params::Positional(values.iter().map(|v| mysql_value(v)).collect());
                 for row in pool
                     .get_conn()
                     .unwrap()
@@ -309,12 +357,20 @@ async fn check_query(
                         DataType::UnsignedInt(i) => i.to_string(),
                         DataType::BigInt(i) => i.to_string(),
                         DataType::UnsignedBigInt(i) => i.to_string(),
+                        DataType::Bool(b) => (b as u8).to_string(),
                         DataType::Real(i, f) => ((i as f64) + (f as f64) * 1.0e-9).to_string(),
+                        DataType::Double(d) => d.to_string(),
+                        DataType::Decimal(ref d) => d.to_string(),
                         DataType::Text(_) | DataType::TinyText(_) => {
                             let s: &str = (&v).into();
                             s.to_string()
                         }
-                        DataType::Timestamp(_) => unimplemented!(),
+                        DataType::Date(d) => d.to_string(),
+                        DataType::Timestamp(ts) => ts.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        DataType::Time(_)
+                        | DataType::Interval(..)
+                        | DataType::Json(_)
+                        | DataType::ByteArray(_) => unimplemented!(),
                     })
                     .collect()
             })